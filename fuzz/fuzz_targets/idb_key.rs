@@ -0,0 +1,26 @@
+#![no_main]
+
+use element_desktop_leveldb::indexeddb::key::{self, IdbKey};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = key::decode_entry_key(data);
+
+    // Whatever decodes must re-encode to the bytes it was decoded from
+    if let Some((decoded, used)) = IdbKey::decode(data) {
+        let mut encoded = Vec::new();
+        decoded.encode(&mut encoded);
+        if !has_nan(&decoded) {
+            assert_eq!(IdbKey::decode(&encoded).map(|(k, _)| k), Some(decoded));
+        }
+        assert!(used <= data.len());
+    }
+});
+
+fn has_nan(key: &IdbKey) -> bool {
+    match key {
+        IdbKey::Date(n) | IdbKey::Number(n) => n.is_nan(),
+        IdbKey::Array(items) => items.iter().any(has_nan),
+        _ => false,
+    }
+}
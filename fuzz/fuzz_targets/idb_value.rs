@@ -0,0 +1,9 @@
+#![no_main]
+
+use element_desktop_leveldb::indexeddb::value;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = value::decode_record_value(data);
+    let _ = value::decode_v8(data);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use element_desktop_leveldb::raw::table;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = table::read_table(data);
+    let _ = table::block_entries(data);
+});
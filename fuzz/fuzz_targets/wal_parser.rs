@@ -0,0 +1,9 @@
+#![no_main]
+
+use element_desktop_leveldb::raw::log;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = log::read_write_batches(data);
+    let _ = log::parse_write_batch(data);
+});
@@ -0,0 +1,167 @@
+# CLI-Meldungen und Berichtsüberschriften, Deutsch
+
+warning = Warnung: { $message }
+datetime-format = %d.%m.%Y %H:%M:%S %:z
+
+## apply
+apply-summary = { $dry_run ->
+    [true] Würde { $written } Schreibvorgänge und { $deleted } Löschungen auf { $path } anwenden
+   *[false] { $written } Schreibvorgänge und { $deleted } Löschungen auf { $path } angewendet
+}
+apply-forced-conflict = Konflikt erzwungen überschrieben: { $conflict }
+
+## diff
+diff-summary = { $added } hinzugefügt, { $removed } entfernt, { $changed } geändert
+
+## progress
+progress-reading = Lese { $entries } Einträge ({ $bytes })
+progress-extracting = { $entries } von { $total } Einträgen ({ $bytes })
+
+## repl
+repl-welcome = Untersuche { $path }; help listet die Befehle, Tab ergänzt
+repl-help =
+    get <key>             Wert eines Eintrags
+    scan [prefix]         Größe, Typ und Schlüssel der Einträge, deren Name mit prefix beginnt
+    classify <key>        Klassifizierung, Element-Schlüssel und Kategorie eines Eintrags
+    export json [file]    Metadaten und alle Einträge als JSON, auf stdout oder in file
+    help                  diese Liste
+    quit                  beenden (auch exit oder Strg-D)
+
+## assess
+assess-exposure = Datenschutz-Exposition: { $score }/100 ({ $level })
+assess-level-low = niedrig
+assess-level-moderate = mittel
+assess-level-high = hoch
+assess-not-assessed = nicht bewertet: { $reason }
+
+## analyze-crypto
+yes = ja
+no = nein
+crypto-recovery = Lässt sich der Nachrichtenverlauf wiederherstellen?
+crypto-no-recovery = Nein: keine lokalen Sitzungen, kein zwischengespeicherter Backup-Schlüssel und kein Schlüssel-Backup im Secret Storage
+crypto-rooms = Verschlüsselte Räume
+crypto-room-events = { $decryptable } von { $encrypted } zwischengespeicherten verschlüsselten Ereignissen entschlüsselbar ({ $sessions } Sitzungen)
+crypto-devices = Geräte (* = dieses Profil)
+crypto-key-requests = Offene Raumschlüssel-Anfragen (Nachrichten, die dieses Gerät nicht entschlüsseln konnte)
+crypto-key-request-to = angefragt bei: { $devices }
+crypto-to-device-queued = { $event_type } in der Warteschlange für { $devices }
+crypto-key-history = Verlauf der Raumschlüssel-Anfragen
+crypto-backup = Schlüssel-Backup und Secret Storage
+crypto-backup-in-ssss = Backup-Schlüssel im Secret Storage
+crypto-backup-cached = Backup-Schlüssel lokal zwischengespeichert
+crypto-backup-pending = Sitzungen, die auf das Backup warten
+crypto-ssss-key = Secret-Storage-Schlüssel
+crypto-ssss-secrets = Geheimnisse im Secret Storage
+crypto-pickle-key = Pickle-Schlüssel im Schlüsselbund des Systems
+
+## correlate
+correlate-accounts = Konten in mehreren Profilen
+correlate-rooms = Räume in mehreren Profilen
+correlate-devices = Geräte in mehreren Profilen
+correlate-timeline = Gemeinsame Zeitleiste
+
+## timeline
+timeline-partial = Unvollständiger lokaler Cache: nur die neuesten Ereignisse jedes Raums aus der letzten Synchronisierung, nicht der gesamte Verlauf
+timeline-room = { $events } zwischengespeicherte Ereignisse{ $limited ->
+    [true] , ältere Ereignisse nicht zwischengespeichert
+   *[false] {""}
+}
+timeline-encrypted = [verschlüsselt]
+
+## stats
+stats-entries = Einträge
+stats-key-bytes = Schlüssel-Bytes
+stats-value-bytes = Wert-Bytes
+stats-total-bytes = Bytes insgesamt
+stats-binary = Binär
+stats-categories = Kategorien
+stats-origins = Ursprünge
+stats-namespaces = Namensräume
+stats-value-sizes = Wertgrößen
+stats-largest = Größte Werte
+
+## bench
+bench-title = { $entries } Einträge, { $bytes } Bytes, schnellster von { $runs } Durchläufen
+bench-phase = Phase
+bench-ms = ms
+bench-entries-per-sec = Einträge/s
+bench-mb-per-sec = MB/s
+
+## report
+report-title = Element-Profilbericht
+report-database = Datenbank
+report-search = Suchen…
+report-crypto = Kryptografie
+report-crypto-stores = Krypto-Speicher
+report-pickle-key = Pickle-Schlüssel
+report-secret-storage = Schlüssel des geheimen Speichers
+report-passphrase = Passphrase
+report-backup-key = Backup-Schlüssel zwischengespeichert
+report-megolm-sessions = Megolm-Sitzungen
+report-recovery = Wiederherstellung
+report-no-indexeddb = Keine IndexedDB neben der Datenbank; --indexeddb angeben
+report-crypto-items = Krypto-Einträge
+report-findings = Auffällige Befunde
+report-level = Stufe
+report-rule = Regel
+report-location = Ort
+report-message = Meldung
+report-raw-entries = Rohe Einträge
+report-key = Schlüssel
+report-category = Kategorie
+report-size = Größe
+report-value = Wert
+
+## list-keys, dump
+page-more = Weitere Einträge folgen; weiter mit --after { $cursor }
+page-more-offset = Weitere Einträge folgen; weiter mit --offset { $offset }
+
+## parse tables
+table-account = Konto
+table-devices = Geräte
+table-rooms = Räume
+table-settings = Einstellungen
+table-empty = (keine)
+table-yes = ja
+table-no = nein
+table-user-id = Benutzer-ID
+table-display-name = Anzeigename
+table-avatar = Avatar
+table-device = Gerät
+table-app = App
+table-sessions = Anmeldesitzungen
+table-device-id = Geräte-ID
+table-name = Name
+table-current = Aktuell
+table-first-seen = Zuerst gesehen
+table-last-seen = Zuletzt gesehen
+table-sources = Quellen
+table-room-id = Raum-ID
+table-alias = Alias
+table-membership = Mitgliedschaft
+table-encrypted = Verschlüsselt
+table-tags = Tags
+table-theme = Design
+table-language = Sprache
+table-notifications = Benachrichtigungen
+table-layout = Layout
+table-font-size = Schriftgröße
+table-high-contrast = Hoher Kontrast
+table-url-previews = URL-Vorschauen
+table-url-previews-e2ee = URL-Vorschauen (verschlüsselte Räume)
+table-autoplay-gifs = GIFs automatisch abspielen
+table-autoplay-videos = Videos automatisch abspielen
+table-show-images = Bilder anzeigen
+
+## fetch-media, clone, verify-keys, watch
+fetch-summary = { $downloaded } von { $total } Mediendateien nach { $out } heruntergeladen (Manifest: { $manifest })
+clone-repaired = { $entries } Einträge aus { $files } Dateien nach { $dest } wiederhergestellt ({ $skipped } Probleme übersprungen)
+clone-resumed = Unterbrochener Scan ab dem Checkpoint fortgesetzt
+clone-copied = { $entries } Einträge nach { $dest } kopiert
+decode-store = { $database }/{ $store } dekodiert: { $records } Datensätze
+decode-resumed = Setze die unterbrochene Dekodierung nach { $stores } fertigen Stores fort
+decode-finished = { $records } Datensätze aus { $stores } Object Stores nach { $dest } dekodiert
+verify-query-failed = Homeserver konnte die Schlüssel von { $user } nicht abfragen
+watching = Beobachte { $path } ({ $keys } aktuelle Schlüssel)
+watching-metadata = Beobachte die Metadaten von { $path } ({ $rooms } Räume)
+monitoring = Überwache { $path } ({ $devices } Geräte, { $rooms } beigetretene Räume)
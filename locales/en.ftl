@@ -0,0 +1,168 @@
+# CLI messages and report headings, English (fallback for every other locale)
+
+warning = warning: { $message }
+# chrono strftime pattern of timestamps in reports
+datetime-format = %Y-%m-%d %H:%M:%S %:z
+
+## apply
+apply-summary = { $dry_run ->
+    [true] Would apply
+   *[false] Applied
+} { $written } writes and { $deleted } deletes to { $path }
+apply-forced-conflict = forced over conflict: { $conflict }
+
+## diff
+diff-summary = { $added } added, { $removed } removed, { $changed } changed
+
+## progress
+progress-reading = Reading { $entries } entries ({ $bytes })
+progress-extracting = { $entries } of { $total } entries ({ $bytes })
+
+## repl
+repl-welcome = Exploring { $path }; type help for commands, Tab to complete
+repl-help =
+    get <key>             value of an item
+    scan [prefix]         size, type and key of items whose name starts with prefix
+    classify <key>        classification, Element key and category of an item
+    export json [file]    metadata and every entry as JSON, to stdout or file
+    help                  this list
+    quit                  leave (also exit or Ctrl-D)
+
+## assess
+assess-exposure = Privacy exposure: { $score }/100 ({ $level })
+assess-level-low = low
+assess-level-moderate = moderate
+assess-level-high = high
+assess-not-assessed = not assessed: { $reason }
+
+## analyze-crypto
+yes = yes
+no = no
+crypto-recovery = Can message history be recovered?
+crypto-no-recovery = No: no local sessions, no cached backup key and no key backup in secret storage
+crypto-rooms = Encrypted rooms
+crypto-room-events = { $decryptable } of { $encrypted } cached encrypted events decryptable ({ $sessions } sessions)
+crypto-devices = Devices (* = this profile)
+crypto-key-requests = Pending room key requests (messages this device could not decrypt)
+crypto-key-request-to = asked: { $devices }
+crypto-to-device-queued = queued { $event_type } for { $devices }
+crypto-key-history = Room key request history
+crypto-backup = Key backup and secret storage
+crypto-backup-in-ssss = Backup key in secret storage
+crypto-backup-cached = Backup key cached locally
+crypto-backup-pending = Sessions waiting for backup
+crypto-ssss-key = Secret storage key
+crypto-ssss-secrets = Secrets in secret storage
+crypto-pickle-key = Pickle key in OS keychain
+
+## correlate
+correlate-accounts = Accounts in several profiles
+correlate-rooms = Rooms in several profiles
+correlate-devices = Devices in several profiles
+correlate-timeline = Combined timeline
+
+## timeline
+timeline-partial = Partial local cache: only the most recent events of each room as last synced, not the full history
+timeline-room = { $events } cached events{ $limited ->
+    [true] , older events not cached
+   *[false] {""}
+}
+timeline-encrypted = [encrypted]
+
+## stats
+stats-entries = Entries
+stats-key-bytes = Key bytes
+stats-value-bytes = Value bytes
+stats-total-bytes = Total bytes
+stats-binary = Binary
+stats-categories = Categories
+stats-origins = Origins
+stats-namespaces = Namespaces
+stats-value-sizes = Value sizes
+stats-largest = Largest values
+
+## bench
+bench-title = { $entries } entries, { $bytes } bytes, fastest of { $runs } runs
+bench-phase = Phase
+bench-ms = ms
+bench-entries-per-sec = Entries/s
+bench-mb-per-sec = MB/s
+
+## report
+report-title = Element profile report
+report-database = Database
+report-search = Search…
+report-crypto = Crypto
+report-crypto-stores = Crypto stores
+report-pickle-key = Pickle key
+report-secret-storage = Secret storage key
+report-passphrase = Passphrase
+report-backup-key = Backup key cached
+report-megolm-sessions = Megolm sessions
+report-recovery = Recovery
+report-no-indexeddb = No IndexedDB next to the database; pass --indexeddb
+report-crypto-items = Crypto items
+report-findings = Notable findings
+report-level = Level
+report-rule = Rule
+report-location = Location
+report-message = Message
+report-raw-entries = Raw entries
+report-key = Key
+report-category = Category
+report-size = Size
+report-value = Value
+
+## list-keys, dump
+page-more = More entries follow; continue with --after { $cursor }
+page-more-offset = More entries follow; continue with --offset { $offset }
+
+## parse tables
+table-account = Account
+table-devices = Devices
+table-rooms = Rooms
+table-settings = Settings
+table-empty = (none)
+table-yes = yes
+table-no = no
+table-user-id = User ID
+table-display-name = Display name
+table-avatar = Avatar
+table-device = Device
+table-app = App
+table-sessions = Login sessions
+table-device-id = Device ID
+table-name = Name
+table-current = Current
+table-first-seen = First seen
+table-last-seen = Last seen
+table-sources = Sources
+table-room-id = Room ID
+table-alias = Alias
+table-membership = Membership
+table-encrypted = Encrypted
+table-tags = Tags
+table-theme = Theme
+table-language = Language
+table-notifications = Notifications
+table-layout = Layout
+table-font-size = Font size
+table-high-contrast = High contrast
+table-url-previews = URL previews
+table-url-previews-e2ee = URL previews (encrypted rooms)
+table-autoplay-gifs = Autoplay GIFs
+table-autoplay-videos = Autoplay videos
+table-show-images = Show images
+
+## fetch-media, clone, verify-keys, watch
+fetch-summary = Downloaded { $downloaded } of { $total } media files into { $out } (manifest: { $manifest })
+clone-repaired = Recovered { $entries } entries from { $files } files into { $dest } ({ $skipped } problems skipped)
+clone-resumed = Continued the interrupted scan from its checkpoint
+clone-copied = Copied { $entries } entries into { $dest }
+decode-store = Decoded { $database }/{ $store }: { $records } records
+decode-resumed = Continuing the interrupted decode after { $stores } finished stores
+decode-finished = Decoded { $records } records of { $stores } object stores into { $dest }
+verify-query-failed = homeserver could not query keys for { $user }
+watching = Watching { $path } ({ $keys } live keys)
+watching-metadata = Watching the metadata of { $path } ({ $rooms } rooms)
+monitoring = Monitoring { $path } ({ $devices } devices, { $rooms } joined rooms)
@@ -0,0 +1,167 @@
+# Messages de la CLI et titres des rapports, français
+
+warning = avertissement : { $message }
+datetime-format = %d/%m/%Y %H:%M:%S %:z
+
+## apply
+apply-summary = { $dry_run ->
+    [true] { $written } écritures et { $deleted } suppressions seraient appliquées à { $path }
+   *[false] { $written } écritures et { $deleted } suppressions appliquées à { $path }
+}
+apply-forced-conflict = conflit écrasé de force : { $conflict }
+
+## diff
+diff-summary = { $added } ajoutées, { $removed } supprimées, { $changed } modifiées
+
+## progress
+progress-reading = Lecture de { $entries } entrées ({ $bytes })
+progress-extracting = { $entries } entrées sur { $total } ({ $bytes })
+
+## repl
+repl-welcome = Exploration de { $path } ; help liste les commandes, Tab complète
+repl-help =
+    get <key>             valeur d'une entrée
+    scan [prefix]         taille, type et clé des entrées dont le nom commence par prefix
+    classify <key>        classification, clé Element et catégorie d'une entrée
+    export json [file]    métadonnées et toutes les entrées en JSON, sur stdout ou dans file
+    help                  cette liste
+    quit                  quitter (aussi exit ou Ctrl-D)
+
+## assess
+assess-exposure = Exposition de la vie privée : { $score }/100 ({ $level })
+assess-level-low = faible
+assess-level-moderate = modérée
+assess-level-high = élevée
+assess-not-assessed = non évalué : { $reason }
+
+## analyze-crypto
+yes = oui
+no = non
+crypto-recovery = L'historique des messages peut-il être récupéré ?
+crypto-no-recovery = Non : aucune session locale, aucune clé de sauvegarde en cache et aucune sauvegarde de clés dans le stockage des secrets
+crypto-rooms = Salons chiffrés
+crypto-room-events = { $decryptable } événements chiffrés en cache sur { $encrypted } déchiffrables ({ $sessions } sessions)
+crypto-devices = Appareils (* = ce profil)
+crypto-key-requests = Demandes de clés de salon en attente (messages que cet appareil n'a pas pu déchiffrer)
+crypto-key-request-to = demandé à : { $devices }
+crypto-to-device-queued = { $event_type } en file d'attente pour { $devices }
+crypto-key-history = Historique des demandes de clés de salon
+crypto-backup = Sauvegarde des clés et stockage des secrets
+crypto-backup-in-ssss = Clé de sauvegarde dans le stockage des secrets
+crypto-backup-cached = Clé de sauvegarde en cache local
+crypto-backup-pending = Sessions en attente de sauvegarde
+crypto-ssss-key = Clé du stockage des secrets
+crypto-ssss-secrets = Secrets dans le stockage des secrets
+crypto-pickle-key = Clé pickle dans le trousseau du système
+
+## correlate
+correlate-accounts = Comptes présents dans plusieurs profils
+correlate-rooms = Salons présents dans plusieurs profils
+correlate-devices = Appareils présents dans plusieurs profils
+correlate-timeline = Chronologie combinée
+
+## timeline
+timeline-partial = Cache local partiel : seuls les événements les plus récents de chaque salon lors de la dernière synchronisation, pas l'historique complet
+timeline-room = { $events } événements en cache{ $limited ->
+    [true] , événements plus anciens absents du cache
+   *[false] {""}
+}
+timeline-encrypted = [chiffré]
+
+## stats
+stats-entries = Entrées
+stats-key-bytes = Octets de clés
+stats-value-bytes = Octets de valeurs
+stats-total-bytes = Octets au total
+stats-binary = Binaires
+stats-categories = Catégories
+stats-origins = Origines
+stats-namespaces = Espaces de noms
+stats-value-sizes = Tailles des valeurs
+stats-largest = Plus grandes valeurs
+
+## bench
+bench-title = { $entries } entrées, { $bytes } octets, plus rapide de { $runs } exécutions
+bench-phase = Phase
+bench-ms = ms
+bench-entries-per-sec = Entrées/s
+bench-mb-per-sec = Mo/s
+
+## report
+report-title = Rapport de profil Element
+report-database = Base de données
+report-search = Rechercher…
+report-crypto = Chiffrement
+report-crypto-stores = Magasins de chiffrement
+report-pickle-key = Clé de pickle
+report-secret-storage = Clé du stockage secret
+report-passphrase = Phrase de passe
+report-backup-key = Clé de sauvegarde en cache
+report-megolm-sessions = Sessions Megolm
+report-recovery = Récupération
+report-no-indexeddb = Pas d’IndexedDB à côté de la base ; passez --indexeddb
+report-crypto-items = Éléments de chiffrement
+report-findings = Constats notables
+report-level = Niveau
+report-rule = Règle
+report-location = Emplacement
+report-message = Message
+report-raw-entries = Entrées brutes
+report-key = Clé
+report-category = Catégorie
+report-size = Taille
+report-value = Valeur
+
+## list-keys, dump
+page-more = D'autres entrées suivent ; continuez avec --after { $cursor }
+page-more-offset = D'autres entrées suivent ; continuez avec --offset { $offset }
+
+## parse tables
+table-account = Compte
+table-devices = Appareils
+table-rooms = Salons
+table-settings = Paramètres
+table-empty = (aucun)
+table-yes = oui
+table-no = non
+table-user-id = Identifiant
+table-display-name = Nom affiché
+table-avatar = Avatar
+table-device = Appareil
+table-app = Application
+table-sessions = Sessions de connexion
+table-device-id = Identifiant d'appareil
+table-name = Nom
+table-current = Actuel
+table-first-seen = Vu pour la première fois
+table-last-seen = Vu pour la dernière fois
+table-sources = Sources
+table-room-id = Identifiant du salon
+table-alias = Alias
+table-membership = Appartenance
+table-encrypted = Chiffré
+table-tags = Étiquettes
+table-theme = Thème
+table-language = Langue
+table-notifications = Notifications
+table-layout = Disposition
+table-font-size = Taille de police
+table-high-contrast = Contraste élevé
+table-url-previews = Aperçus d'URL
+table-url-previews-e2ee = Aperçus d'URL (salons chiffrés)
+table-autoplay-gifs = Lecture automatique des GIF
+table-autoplay-videos = Lecture automatique des vidéos
+table-show-images = Afficher les images
+
+## fetch-media, clone, verify-keys, watch
+fetch-summary = { $downloaded } fichiers média sur { $total } téléchargés dans { $out } (manifeste : { $manifest })
+clone-repaired = { $entries } entrées récupérées depuis { $files } fichiers dans { $dest } ({ $skipped } problèmes ignorés)
+clone-resumed = Analyse interrompue reprise depuis son point de contrôle
+clone-copied = { $entries } entrées copiées dans { $dest }
+decode-store = { $database }/{ $store } décodé : { $records } enregistrements
+decode-resumed = Reprise du décodage interrompu après { $stores } stores terminés
+decode-finished = { $records } enregistrements de { $stores } object stores décodés dans { $dest }
+verify-query-failed = le serveur n'a pas pu interroger les clés de { $user }
+watching = Surveillance de { $path } ({ $keys } clés actives)
+watching-metadata = Surveillance des métadonnées de { $path } ({ $rooms } salons)
+monitoring = Surveillance de { $path } ({ $devices } appareils, { $rooms } salons rejoints)
@@ -0,0 +1,167 @@
+# Сообщения CLI и заголовки отчётов, русский
+
+warning = предупреждение: { $message }
+datetime-format = %d.%m.%Y %H:%M:%S %:z
+
+## apply
+apply-summary = { $dry_run ->
+    [true] Будет применено к { $path }: записей { $written }, удалений { $deleted }
+   *[false] Применено к { $path }: записей { $written }, удалений { $deleted }
+}
+apply-forced-conflict = конфликт перезаписан принудительно: { $conflict }
+
+## diff
+diff-summary = добавлено: { $added }, удалено: { $removed }, изменено: { $changed }
+
+## progress
+progress-reading = Чтение записей: { $entries } ({ $bytes })
+progress-extracting = Записей: { $entries } из { $total } ({ $bytes })
+
+## repl
+repl-welcome = Исследование { $path }; help — список команд, Tab — дополнение
+repl-help =
+    get <key>             значение элемента
+    scan [prefix]         размер, тип и ключ элементов, имя которых начинается с prefix
+    classify <key>        классификация, ключ Element и категория элемента
+    export json [file]    метаданные и все записи в JSON, в stdout или в file
+    help                  этот список
+    quit                  выход (также exit или Ctrl-D)
+
+## assess
+assess-exposure = Уровень раскрытия данных: { $score }/100 ({ $level })
+assess-level-low = низкий
+assess-level-moderate = средний
+assess-level-high = высокий
+assess-not-assessed = не оценено: { $reason }
+
+## analyze-crypto
+yes = да
+no = нет
+crypto-recovery = Можно ли восстановить историю сообщений?
+crypto-no-recovery = Нет: нет локальных сессий, кэшированного ключа резервной копии и резервной копии ключей в хранилище секретов
+crypto-rooms = Зашифрованные комнаты
+crypto-room-events = Расшифровывается { $decryptable } из { $encrypted } кэшированных зашифрованных событий (сессий: { $sessions })
+crypto-devices = Устройства (* = этот профиль)
+crypto-key-requests = Ожидающие запросы ключей комнат (сообщения, которые это устройство не смогло расшифровать)
+crypto-key-request-to = запрошено у: { $devices }
+crypto-to-device-queued = { $event_type } в очереди для { $devices }
+crypto-key-history = История запросов ключей комнат
+crypto-backup = Резервная копия ключей и хранилище секретов
+crypto-backup-in-ssss = Ключ резервной копии в хранилище секретов
+crypto-backup-cached = Ключ резервной копии в локальном кэше
+crypto-backup-pending = Сессии, ожидающие резервного копирования
+crypto-ssss-key = Ключ хранилища секретов
+crypto-ssss-secrets = Секреты в хранилище секретов
+crypto-pickle-key = Ключ pickle в системной связке ключей
+
+## correlate
+correlate-accounts = Учётные записи в нескольких профилях
+correlate-rooms = Комнаты в нескольких профилях
+correlate-devices = Устройства в нескольких профилях
+correlate-timeline = Общая хронология
+
+## timeline
+timeline-partial = Неполный локальный кэш: только последние события каждой комнаты на момент последней синхронизации, а не вся история
+timeline-room = Событий в кэше: { $events }{ $limited ->
+    [true] , более ранние события не кэшированы
+   *[false] {""}
+}
+timeline-encrypted = [зашифровано]
+
+## stats
+stats-entries = Записи
+stats-key-bytes = Байты ключей
+stats-value-bytes = Байты значений
+stats-total-bytes = Всего байт
+stats-binary = Двоичные
+stats-categories = Категории
+stats-origins = Источники
+stats-namespaces = Пространства имён
+stats-value-sizes = Размеры значений
+stats-largest = Самые большие значения
+
+## bench
+bench-title = { $entries } записей, { $bytes } байт, лучший из { $runs } прогонов
+bench-phase = Этап
+bench-ms = ms
+bench-entries-per-sec = Записей/с
+bench-mb-per-sec = МБ/с
+
+## report
+report-title = Отчёт по профилю Element
+report-database = База данных
+report-search = Поиск…
+report-crypto = Шифрование
+report-crypto-stores = Хранилища ключей
+report-pickle-key = Ключ pickle
+report-secret-storage = Ключ секретного хранилища
+report-passphrase = Парольная фраза
+report-backup-key = Ключ резервной копии в кэше
+report-megolm-sessions = Сессии Megolm
+report-recovery = Восстановление
+report-no-indexeddb = Нет IndexedDB рядом с базой; укажите --indexeddb
+report-crypto-items = Элементы шифрования
+report-findings = Важные находки
+report-level = Уровень
+report-rule = Правило
+report-location = Место
+report-message = Сообщение
+report-raw-entries = Исходные записи
+report-key = Ключ
+report-category = Категория
+report-size = Размер
+report-value = Значение
+
+## list-keys, dump
+page-more = Есть ещё записи; продолжите с --after { $cursor }
+page-more-offset = Есть ещё записи; продолжите с --offset { $offset }
+
+## parse tables
+table-account = Учётная запись
+table-devices = Устройства
+table-rooms = Комнаты
+table-settings = Настройки
+table-empty = (нет)
+table-yes = да
+table-no = нет
+table-user-id = ID пользователя
+table-display-name = Отображаемое имя
+table-avatar = Аватар
+table-device = Устройство
+table-app = Приложение
+table-sessions = Сеансы входа
+table-device-id = ID устройства
+table-name = Название
+table-current = Текущее
+table-first-seen = Впервые замечено
+table-last-seen = Последний раз замечено
+table-sources = Источники
+table-room-id = ID комнаты
+table-alias = Псевдоним
+table-membership = Участие
+table-encrypted = Зашифрована
+table-tags = Теги
+table-theme = Тема
+table-language = Язык
+table-notifications = Уведомления
+table-layout = Макет
+table-font-size = Размер шрифта
+table-high-contrast = Высокий контраст
+table-url-previews = Предпросмотр ссылок
+table-url-previews-e2ee = Предпросмотр ссылок (зашифрованные комнаты)
+table-autoplay-gifs = Автовоспроизведение GIF
+table-autoplay-videos = Автовоспроизведение видео
+table-show-images = Показывать изображения
+
+## fetch-media, clone, verify-keys, watch
+fetch-summary = Загружено медиафайлов: { $downloaded } из { $total } в { $out } (манифест: { $manifest })
+clone-repaired = Восстановлено записей: { $entries } из файлов: { $files } в { $dest } (пропущено проблем: { $skipped })
+clone-resumed = Прерванное сканирование продолжено с контрольной точки
+clone-copied = Скопировано записей в { $dest }: { $entries }
+decode-store = Декодировано { $database }/{ $store }: записей { $records }
+decode-resumed = Продолжение прерванного декодирования после { $stores } готовых хранилищ
+decode-finished = Декодировано записей: { $records } из { $stores } хранилищ объектов в { $dest }
+verify-query-failed = сервер не смог запросить ключи для { $user }
+watching = Наблюдение за { $path } (актуальных ключей: { $keys })
+watching-metadata = Наблюдение за метаданными { $path } (комнат: { $rooms })
+monitoring = Мониторинг { $path } (устройств: { $devices }, комнат: { $rooms })
@@ -0,0 +1,252 @@
+//! Local privacy exposure scoring
+//!
+//! Scores how much an attacker with a copy of the profile directory could
+//! learn or reuse, and explains every factor that contributed. The score is a
+//! sum of fixed weights capped at 100; higher means more exposed.
+
+#[cfg(feature = "crypto")]
+use crate::crypto;
+use crate::findings::{self, RuleSpec, Scanner, Severity};
+use crate::indexeddb::reader::Origin;
+use crate::localstorage;
+use crate::raw::{RawScan, ValueType};
+use crate::SYNC_DATABASE;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Local Storage keys holding reusable credentials
+const CREDENTIAL_KEYS: &[&str] = &[
+    "mx_access_token",
+    "mx_refresh_token",
+    "mx_oidc_refresh_token",
+];
+/// Local Storage key prefixes of composer drafts
+const DRAFT_PREFIXES: &[&str] = &["mx_cider_state_", "mx_cider_history_", "mx_draft"];
+/// matrix-js-sdk `DeviceVerification.Verified`
+#[cfg(feature = "crypto")]
+const DEVICE_VERIFIED: i64 = 1;
+
+/// Overall exposure band
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ExposureLevel {
+    Low,
+    Moderate,
+    High,
+}
+
+/// One reason the score went up
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Factor {
+    pub id: &'static str,
+    pub points: u32,
+    pub explanation: String,
+    /// Keys or identifiers backing the factor; never secret values
+    pub evidence: Vec<String>,
+}
+
+/// Result of an assessment
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Assessment {
+    pub score: u32,
+    pub level: ExposureLevel,
+    pub factors: Vec<Factor>,
+    /// Checks that could not run, e.g. without the IndexedDB directory
+    pub not_assessed: Vec<String>,
+}
+
+/// Scores a profile from a raw scan of its Local Storage and, when available,
+/// its IndexedDB origin
+pub fn assess(local_storage: &RawScan, indexeddb: Option<&Origin>) -> Assessment {
+    let live: BTreeMap<String, String> = local_storage
+        .latest()
+        .iter()
+        .filter_map(|(key, value)| {
+            let (_, name) = localstorage::decode_key(key)?;
+            Some((name, localstorage::decode_string(value)?))
+        })
+        .collect();
+
+    let mut factors = Vec::new();
+    let mut not_assessed = Vec::new();
+
+    let stored: Vec<String> = CREDENTIAL_KEYS
+        .iter()
+        .filter(|k| live.contains_key(**k))
+        .map(|k| k.to_string())
+        .collect();
+    if !stored.is_empty() {
+        factors.push(Factor {
+            id: "plaintext-tokens",
+            points: 30,
+            explanation: "Credentials are stored unencrypted in Local Storage; anyone with the \
+                          profile can act as this session"
+                .to_string(),
+            evidence: stored,
+        });
+    }
+
+    let superseded = superseded_credentials(local_storage, &live);
+    if !superseded.is_empty() {
+        factors.push(Factor {
+            id: "recoverable-old-tokens",
+            points: 10,
+            explanation: "Earlier credential values survive in the write-ahead log or tables \
+                          and can be recovered with a raw scan; they stay usable until the \
+                          server expires them"
+                .to_string(),
+            evidence: superseded,
+        });
+    }
+
+    if live.get("mx_has_pickle_key").map(String::as_str) != Some("true") {
+        factors.push(Factor {
+            id: "no-pickle-key",
+            points: 20,
+            explanation: "No pickle key is recorded, so the crypto store and tokens are not \
+                          protected by a key held in the OS keychain"
+                .to_string(),
+            evidence: vec!["mx_has_pickle_key".to_string()],
+        });
+    }
+
+    match indexeddb {
+        Some(origin) => {
+            let backup = origin
+                .records(SYNC_DATABASE, "accountData")
+                .iter()
+                .any(|r| r.value["type"] == "m.megolm_backup.v1");
+            if !backup {
+                factors.push(Factor {
+                    id: "key-backup-off",
+                    points: 15,
+                    explanation: "No server-side key backup is configured; message keys exist \
+                                  only on local devices, which makes this profile a primary \
+                                  copy worth stealing"
+                        .to_string(),
+                    evidence: vec![format!("{}/accountData", SYNC_DATABASE)],
+                });
+            }
+
+            #[cfg(feature = "crypto")]
+            {
+                let user_id = live.get("mx_user_id");
+                let device_id = live.get("mx_device_id");
+                let unverified = unverified_devices(origin, user_id, device_id);
+                if !unverified.is_empty() {
+                    factors.push(Factor {
+                        id: "unverified-sessions",
+                        points: 10,
+                        explanation: "Other sessions of this account are not verified; keys \
+                                      may be shared with a device nobody confirmed"
+                            .to_string(),
+                        evidence: unverified,
+                    });
+                }
+            }
+            #[cfg(not(feature = "crypto"))]
+            not_assessed.push("unverified-sessions: built without the crypto feature".to_string());
+        }
+        None => {
+            not_assessed.push("key-backup-off: IndexedDB not available".to_string());
+            not_assessed.push("unverified-sessions: IndexedDB not available".to_string());
+        }
+    }
+
+    let drafts = draft_secrets(&live);
+    if !drafts.is_empty() {
+        factors.push(Factor {
+            id: "secrets-in-drafts",
+            points: 15,
+            explanation: "Unsent composer drafts contain secrets; drafts are stored in \
+                          plaintext even for encrypted rooms"
+                .to_string(),
+            evidence: drafts,
+        });
+    }
+
+    let score = factors.iter().map(|f| f.points).sum::<u32>().min(100);
+    let level = match score {
+        0..=19 => ExposureLevel::Low,
+        20..=49 => ExposureLevel::Moderate,
+        _ => ExposureLevel::High,
+    };
+    Assessment {
+        score,
+        level,
+        factors,
+        not_assessed,
+    }
+}
+
+/// Credential keys with values other than the live one in older records
+fn superseded_credentials(scan: &RawScan, live: &BTreeMap<String, String>) -> Vec<String> {
+    let mut keys = BTreeSet::new();
+    for record in &scan.records {
+        if record.value_type != ValueType::Value {
+            continue;
+        }
+        let Some((_, name)) = localstorage::decode_key(&record.key) else {
+            continue;
+        };
+        if !CREDENTIAL_KEYS.contains(&name.as_str()) {
+            continue;
+        }
+        let value = localstorage::decode_string(&record.value);
+        if value.is_some() && value.as_ref() != live.get(&name) {
+            keys.insert(format!("{} (sequence {})", name, record.sequence));
+        }
+    }
+    keys.into_iter().collect()
+}
+
+/// Own devices, other than the current one, that are not verified
+#[cfg(feature = "crypto")]
+fn unverified_devices(
+    origin: &Origin,
+    user_id: Option<&String>,
+    device_id: Option<&String>,
+) -> Vec<String> {
+    let Some(user_id) = user_id else {
+        return Vec::new();
+    };
+    crypto::cached_devices(origin)
+        .into_iter()
+        .filter(|d| &d.user_id == user_id && Some(&d.device_id) != device_id)
+        .filter(|d| d.verified != Some(DEVICE_VERIFIED))
+        .map(|d| d.device_id)
+        .collect()
+}
+
+/// Draft keys matching a built-in secret rule or mentioning one
+fn draft_secrets(live: &BTreeMap<String, String>) -> Vec<String> {
+    let mut rules = findings::builtin_rules();
+    rules.push(RuleSpec {
+        id: "draft-secret-mention".to_string(),
+        description: "Draft mentions a secret".to_string(),
+        severity: Severity::Medium,
+        pattern: Some(r"(?i)\b(?:recovery|security) key\b|\bpass(?:word|phrase)\b".to_string()),
+        key_pattern: None,
+    });
+    let Ok(scanner) = Scanner::new(rules) else {
+        return Vec::new();
+    };
+
+    let drafts: Vec<(Vec<u8>, Vec<u8>)> = live
+        .iter()
+        .filter(|(name, _)| DRAFT_PREFIXES.iter().any(|p| name.starts_with(p)))
+        .map(|(name, value)| (name.clone().into_bytes(), value.clone().into_bytes()))
+        .collect();
+    let mut keys: Vec<String> = scanner
+        .scan(drafts.iter().map(|(k, v)| (k.as_slice(), v.as_slice())))
+        .into_iter()
+        .map(|f| f.key)
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
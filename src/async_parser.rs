@@ -0,0 +1,145 @@
+//! Async parser API for tokio services
+//!
+//! Built only with the `async` feature. LevelDB handles aren't `Send`, so the
+//! parser lives on one blocking task for as long as the async handle exists,
+//! and the async methods send it requests; reading never blocks the executor.
+//! Requests are served one at a time. Entries stream through a bounded
+//! channel, so a slow consumer holds the reader back instead of letting
+//! entries pile up in memory.
+
+use crate::entries::Entry;
+use crate::error::{ParserError, Result};
+use crate::parser::{ElementLevelDBParser, ElementMetadata, ParserOptions};
+use futures_core::Stream;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::{mpsc, oneshot};
+
+/// Entries read ahead of a stream consumer
+const STREAM_BUFFER: usize = 256;
+
+enum Request {
+    ParseMetadata(oneshot::Sender<Result<ElementMetadata>>),
+    GetValue(Vec<u8>, oneshot::Sender<Result<Option<Vec<u8>>>>),
+    Entries(mpsc::Sender<Entry>, oneshot::Sender<Result<()>>),
+}
+
+/// `ElementLevelDBParser` for async code
+pub struct AsyncElementLevelDBParser {
+    requests: mpsc::Sender<Request>,
+}
+
+impl AsyncElementLevelDBParser {
+    /// Opens Element's LevelDB database with the default options
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        Self::open_with(path, ParserOptions::default()).await
+    }
+
+    /// Opens the database with the given options
+    pub async fn open_with(path: impl Into<PathBuf>, options: ParserOptions) -> Result<Self> {
+        let path = path.into();
+        let (requests, rx) = mpsc::channel(1);
+        let (opened_tx, opened) = oneshot::channel();
+        tokio::task::spawn_blocking(move || {
+            match ElementLevelDBParser::builder().options(options).open(&path) {
+                Ok(parser) => {
+                    let _ = opened_tx.send(Ok(()));
+                    serve(&parser, rx);
+                }
+                Err(e) => {
+                    let _ = opened_tx.send(Err(e));
+                }
+            }
+        });
+        opened.await.map_err(|_| ParserError::Poisoned)??;
+        Ok(AsyncElementLevelDBParser { requests })
+    }
+
+    /// Extracts metadata from the LevelDB database
+    pub async fn parse_metadata(&self) -> Result<ElementMetadata> {
+        self.request(Request::ParseMetadata).await
+    }
+
+    /// Gets a single value by key, exactly as stored
+    pub async fn get_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let key = key.to_vec();
+        self.request(|reply| Request::GetValue(key, reply)).await
+    }
+
+    /// Gets a single value by key as text, with invalid UTF-8 replaced by U+FFFD
+    pub async fn get_value_lossy(&self, key: &str) -> Result<Option<String>> {
+        let value = self.get_value_bytes(key.as_bytes()).await?;
+        Ok(value.map(|value| String::from_utf8_lossy(&value).into_owned()))
+    }
+
+    /// Gets a single value by key
+    #[deprecated(note = "lossy for binary values; use get_value_bytes or get_value_lossy")]
+    pub async fn get_value(&self, key: &str) -> Result<Option<String>> {
+        self.get_value_lossy(key).await
+    }
+
+    /// Streams the entries in key order
+    ///
+    /// Other requests wait until the stream is finished or dropped.
+    pub async fn entries(&self) -> Result<EntryStream> {
+        let (tx, entries) = mpsc::channel(STREAM_BUFFER);
+        self.request(|started| Request::Entries(tx, started))
+            .await?;
+        Ok(EntryStream { entries })
+    }
+
+    async fn request<T>(
+        &self,
+        request: impl FnOnce(oneshot::Sender<Result<T>>) -> Request,
+    ) -> Result<T> {
+        let (reply, rx) = oneshot::channel();
+        // The worker only goes away by panicking
+        self.requests
+            .send(request(reply))
+            .await
+            .map_err(|_| ParserError::Poisoned)?;
+        rx.await.map_err(|_| ParserError::Poisoned)?
+    }
+}
+
+/// Stream of the entries of a database, from `AsyncElementLevelDBParser::entries`
+pub struct EntryStream {
+    entries: mpsc::Receiver<Entry>,
+}
+
+impl Stream for EntryStream {
+    type Item = Entry;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Entry>> {
+        self.entries.poll_recv(cx)
+    }
+}
+
+/// Answers requests until the async handle is dropped
+fn serve(parser: &ElementLevelDBParser, mut requests: mpsc::Receiver<Request>) {
+    while let Some(request) = requests.blocking_recv() {
+        match request {
+            Request::ParseMetadata(reply) => {
+                let _ = reply.send(parser.parse_metadata());
+            }
+            Request::GetValue(key, reply) => {
+                let _ = reply.send(parser.get_value_bytes(&key));
+            }
+            Request::Entries(tx, started) => match parser.entries() {
+                Ok(entries) => {
+                    let _ = started.send(Ok(()));
+                    for entry in entries {
+                        // The stream was dropped
+                        if tx.blocking_send(entry).is_err() {
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    let _ = started.send(Err(e));
+                }
+            },
+        }
+    }
+}
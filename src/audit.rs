@@ -0,0 +1,36 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Append-only JSON Lines record of what a command did to which evidence
+pub struct AuditLog {
+    file: File,
+}
+
+impl AuditLog {
+    /// Opens `path` for appending, creating it if needed
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.as_ref())
+            .with_context(|| format!("Failed to open audit log {}", path.as_ref().display()))?;
+        Ok(AuditLog { file })
+    }
+
+    /// Appends `{"time", "event", ...details}` and flushes it to disk
+    pub fn record(&mut self, event: &str, details: Value) -> Result<()> {
+        let mut line = json!({
+            "time": chrono::Utc::now().to_rfc3339(),
+            "event": event,
+        });
+        if let (Some(line), Value::Object(details)) = (line.as_object_mut(), details) {
+            line.extend(details);
+        }
+        writeln!(self.file, "{}", line)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+//! `bench`: how fast a database opens, iterates, classifies and exports
+//!
+//! Each phase runs `--runs` times over the same database and the fastest run
+//! counts, so a busy disk cache or scheduler hiccup doesn't skew a comparison
+//! between releases or `--threads` settings. Throughput is over the entries
+//! and the key and value bytes of the whole database.
+
+use crate::i18n::tr;
+use crate::table::Table;
+use anyhow::Result;
+use element_desktop_leveldb::export::EntryRecord;
+use element_desktop_leveldb::keys::classify_key;
+use element_desktop_leveldb::layout::Layout;
+use element_desktop_leveldb::{Classification, ElementLevelDBParser};
+use serde::Serialize;
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// What the phases measure
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub runs: usize,
+    pub threads: Option<usize>,
+    pub entries: usize,
+    /// Key and value bytes of every entry
+    pub bytes: u64,
+    pub phases: Vec<Phase>,
+}
+
+/// The fastest run of one phase
+#[derive(Debug, Serialize)]
+pub struct Phase {
+    /// `open`, `iterate`, `classify` or `export`
+    pub name: &'static str,
+    pub seconds: f64,
+    /// `None` for `open`, which reads the log and manifest only
+    pub entries_per_sec: Option<f64>,
+    pub mb_per_sec: Option<f64>,
+}
+
+/// Times every phase `runs` times on the database at `path`
+pub fn run(path: &Path, runs: usize, threads: Option<usize>) -> Result<Report> {
+    let open = || {
+        let mut builder = ElementLevelDBParser::builder();
+        if let Some(threads) = threads {
+            builder = builder.threads(threads);
+        }
+        builder.open(path)
+    };
+
+    let open_time = fastest(runs, || {
+        open()?;
+        Ok(())
+    })?;
+    let parser = open()?;
+
+    let mut entries = Vec::new();
+    let iterate_time = fastest(runs, || {
+        entries = parser.entries()?.collect();
+        Ok(())
+    })?;
+    let bytes = entries
+        .iter()
+        .map(|entry| (entry.key.len() + entry.value.len()) as u64)
+        .sum();
+
+    let classify_time = fastest(runs, || {
+        let layout = Layout::detect(
+            entries
+                .iter()
+                .map(|entry| (entry.key.as_ref(), entry.value.as_ref())),
+        )
+        .unwrap_or_default();
+        for entry in &entries {
+            if let Classification::Element { name } = Classification::of(&entry.key, &layout.origin)
+            {
+                black_box(classify_key(&name).category());
+            }
+        }
+        Ok(())
+    })?;
+
+    let export_time = fastest(runs, || {
+        let metadata = parser.parse_metadata()?;
+        let records: Vec<_> = parser.entries()?.map(|e| EntryRecord::from(&e)).collect();
+        serde_json::to_writer(std::io::sink(), &(metadata, records))?;
+        Ok(())
+    })?;
+
+    let phase = |name, time: Duration, throughput: bool| {
+        let seconds = time.as_secs_f64();
+        let rate = |amount: f64| (throughput && seconds > 0.0).then(|| amount / seconds);
+        Phase {
+            name,
+            seconds,
+            entries_per_sec: rate(entries.len() as f64),
+            mb_per_sec: rate(bytes as f64 / 1e6),
+        }
+    };
+    Ok(Report {
+        runs,
+        threads,
+        entries: entries.len(),
+        bytes,
+        phases: vec![
+            phase("open", open_time, false),
+            phase("iterate", iterate_time, true),
+            phase("classify", classify_time, true),
+            phase("export", export_time, true),
+        ],
+    })
+}
+
+/// The shortest of `runs` runs of `phase`
+fn fastest(runs: usize, mut phase: impl FnMut() -> Result<()>) -> Result<Duration> {
+    let mut best = Duration::MAX;
+    for _ in 0..runs {
+        let start = Instant::now();
+        phase()?;
+        best = best.min(start.elapsed());
+    }
+    Ok(best)
+}
+
+/// The report as a table of phases
+pub fn render(report: &Report) -> String {
+    let mut table = Table::new(
+        tr!(
+            "bench-title",
+            entries = report.entries,
+            bytes = report.bytes,
+            runs = report.runs
+        ),
+        vec![
+            tr!("bench-phase"),
+            tr!("bench-ms"),
+            tr!("bench-entries-per-sec"),
+            tr!("bench-mb-per-sec"),
+        ],
+    );
+    let rate = |rate: Option<f64>| rate.map_or_else(|| "-".to_string(), |r| format!("{:.1}", r));
+    for phase in &report.phases {
+        table.row(vec![
+            phase.name.to_string(),
+            format!("{:.3}", phase.seconds * 1e3),
+            rate(phase.entries_per_sec),
+            rate(phase.mb_per_sec),
+        ]);
+    }
+    table.render()
+}
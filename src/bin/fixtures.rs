@@ -0,0 +1,50 @@
+use anyhow::{bail, Context, Result};
+use element_desktop_leveldb::fixtures::{self, FixtureOptions};
+
+const USAGE: &str =
+    "Usage: fixtures <out-dir> [--profiles N] [--rooms N] [--records N] [--seed N] [--corrupt]";
+
+/// Generates synthetic Element Desktop profiles for tests, benchmarks and demos
+fn main() -> Result<()> {
+    let mut options = FixtureOptions::default();
+    let mut out = None;
+    let mut seeds = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut number = |name: &str| -> Result<u64> {
+            let value = args
+                .next()
+                .with_context(|| format!("{} needs a value", name))?;
+            value
+                .parse()
+                .with_context(|| format!("{} expects a number, got {:?}", name, value))
+        };
+        match arg.as_str() {
+            "--profiles" => options.profiles = number("--profiles")? as usize,
+            "--rooms" => options.rooms = number("--rooms")? as usize,
+            "--records" => options.records = number("--records")? as usize,
+            "--seed" => options.seed = number("--seed")?,
+            "--corrupt" => options.corrupt = true,
+            "--seeds" => seeds = Some(args.next().context("--seeds needs a directory")?),
+            flag if flag.starts_with("--") => bail!("Unknown flag {}\n{}", flag, USAGE),
+            path if out.is_none() => out = Some(path.to_string()),
+            _ => bail!(USAGE),
+        }
+    }
+    if let Some(corpus) = seeds {
+        let written = fixtures::write_fuzz_seeds(&corpus, options.seed)?;
+        println!("Wrote {} fuzz seeds to {}", written, corpus);
+        return Ok(());
+    }
+    let Some(out) = out else {
+        bail!(USAGE);
+    };
+
+    for profile in fixtures::generate(&out, &options)? {
+        println!("{}  {}", profile.root.display(), profile.user_id);
+        println!("  {}", profile.local_storage.display());
+        println!("  {}", profile.indexeddb.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! `export --format cbor|msgpack`: the export for programs, values as bytes
+//!
+//! The document has the shape of `export --format json`, `metadata` and
+//! `entries`, but each entry holds its key and value as byte strings exactly
+//! as stored, next to the key label, classification and category. Binary
+//! values survive the round trip without the `0x` hex of the JSON export, and
+//! the document is much smaller. MessagePack maps are keyed by field name, as
+//! CBOR maps are. `entries` is left out when `--fields` drops the entries.
+
+use crate::export::{self, key_label};
+use crate::{Classification, Entry};
+use anyhow::Result;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Document<'a, M> {
+    metadata: &'a M,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entries: Option<Vec<Record<'a>>>,
+}
+
+/// An entry with its stored bytes
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(with = "serde_bytes")]
+    key: &'a [u8],
+    label: String,
+    classification: &'a Classification,
+    category: &'static str,
+    #[serde(with = "serde_bytes")]
+    value: &'a [u8],
+}
+
+fn document<'a, M>(metadata: &'a M, entries: Option<&'a [Entry]>) -> Document<'a, M> {
+    Document {
+        metadata,
+        entries: entries.map(|entries| {
+            entries
+                .iter()
+                .map(|entry| Record {
+                    key: &entry.key,
+                    label: key_label(&entry.key),
+                    classification: &entry.classification,
+                    category: export::category(&entry.classification),
+                    value: &entry.value,
+                })
+                .collect()
+        }),
+    }
+}
+
+/// The export as CBOR
+pub fn cbor(metadata: &impl Serialize, entries: Option<&[Entry]>) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    ciborium::into_writer(&document(metadata, entries), &mut out)?;
+    Ok(out)
+}
+
+/// The export as MessagePack
+pub fn msgpack(metadata: &impl Serialize, entries: Option<&[Entry]>) -> Result<Vec<u8>> {
+    Ok(rmp_serde::to_vec_named(&document(metadata, entries))?)
+}
@@ -0,0 +1,272 @@
+//! `browse`: terminal key browser for examiners
+//!
+//! Built only with the `tui` feature. Keys are shown as a tree, by origin and
+//! then by namespace (see `stats::key_namespace`), next to a preview of the
+//! selected value, decoded the way `preview` does, and its classification.
+
+use anyhow::Result;
+use element_desktop_leveldb::export;
+use element_desktop_leveldb::preview;
+use element_desktop_leveldb::stats::key_namespace;
+use element_desktop_leveldb::{ElementLevelDBParser, Entry};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Label of the group of keys that belong to no origin
+const NO_ORIGIN: &str = "(database)";
+
+const HELP: &str =
+    " ↑↓ select  ←→ collapse/expand  Enter toggle  PgUp/PgDn scroll preview  q quit ";
+
+struct Namespace {
+    name: String,
+    /// Indices into `Browser::entries`
+    entries: Vec<usize>,
+    open: bool,
+}
+
+struct Group {
+    origin: String,
+    namespaces: Vec<Namespace>,
+    open: bool,
+}
+
+/// A visible line of the tree
+#[derive(Clone, Copy)]
+enum Row {
+    Group(usize),
+    Namespace(usize, usize),
+    Key(usize, usize, usize),
+}
+
+struct Browser {
+    path: String,
+    entries: Vec<Entry>,
+    groups: Vec<Group>,
+    rows: Vec<Row>,
+    list: ListState,
+    scroll: u16,
+}
+
+/// Opens the browser on the database at `path` until the user quits
+pub fn run(path: &Path) -> Result<()> {
+    let parser = ElementLevelDBParser::open(path)?;
+    let mut browser = Browser::new(path.display().to_string(), parser.entries()?.collect());
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl Browser {
+    fn new(path: String, entries: Vec<Entry>) -> Self {
+        let mut tree: BTreeMap<String, BTreeMap<String, Vec<usize>>> = BTreeMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            let origin = export::origin(entry).unwrap_or_else(|| NO_ORIGIN.to_string());
+            tree.entry(origin)
+                .or_default()
+                .entry(key_namespace(&entry.key))
+                .or_default()
+                .push(i);
+        }
+        let groups = tree
+            .into_iter()
+            .map(|(origin, namespaces)| Group {
+                origin,
+                namespaces: namespaces
+                    .into_iter()
+                    .map(|(name, entries)| Namespace {
+                        name,
+                        entries,
+                        open: false,
+                    })
+                    .collect(),
+                open: true,
+            })
+            .collect();
+        let mut browser = Browser {
+            path,
+            entries,
+            groups,
+            rows: Vec::new(),
+            list: ListState::default().with_selected(Some(0)),
+            scroll: 0,
+        };
+        browser.update_rows();
+        browser
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.select(1),
+                KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+                KeyCode::Home => self.select(isize::MIN),
+                KeyCode::End => self.select(isize::MAX),
+                KeyCode::Right | KeyCode::Char('l') => self.set_open(Some(true)),
+                KeyCode::Left | KeyCode::Char('h') => self.set_open(Some(false)),
+                KeyCode::Enter | KeyCode::Char(' ') => self.set_open(None),
+                KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+                KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+                _ => {}
+            }
+        }
+    }
+
+    /// Rebuilds the visible rows from what is expanded
+    fn update_rows(&mut self) {
+        self.rows.clear();
+        for (g, group) in self.groups.iter().enumerate() {
+            self.rows.push(Row::Group(g));
+            if !group.open {
+                continue;
+            }
+            for (n, namespace) in group.namespaces.iter().enumerate() {
+                self.rows.push(Row::Namespace(g, n));
+                if namespace.open {
+                    self.rows
+                        .extend((0..namespace.entries.len()).map(|k| Row::Key(g, n, k)));
+                }
+            }
+        }
+    }
+
+    /// Moves the selection by `delta` rows, stopping at either end
+    fn select(&mut self, delta: isize) {
+        let current = self.list.selected().unwrap_or(0) as isize;
+        let last = self.rows.len().saturating_sub(1) as isize;
+        let next = current.saturating_add(delta).clamp(0, last);
+        self.list.select(Some(next as usize));
+        self.scroll = 0;
+    }
+
+    /// Expands, collapses or, with `None`, toggles the selected group or
+    /// namespace; collapsing on a key collapses its namespace
+    fn set_open(&mut self, open: Option<bool>) {
+        let Some(&row) = self.list.selected().and_then(|i| self.rows.get(i)) else {
+            return;
+        };
+        let (flag, parent) = match row {
+            Row::Group(g) => (&mut self.groups[g].open, None),
+            Row::Namespace(g, n) => (&mut self.groups[g].namespaces[n].open, None),
+            Row::Key(g, n, _) if open != Some(true) => (
+                &mut self.groups[g].namespaces[n].open,
+                Some(Row::Namespace(g, n)),
+            ),
+            Row::Key(..) => return,
+        };
+        *flag = open.unwrap_or(!*flag);
+        self.update_rows();
+        if let Some(Row::Namespace(g, n)) = parent {
+            let position = self
+                .rows
+                .iter()
+                .position(|r| matches!(r, Row::Namespace(rg, rn) if *rg == g && *rn == n));
+            self.list.select(position);
+        }
+    }
+
+    fn entry(&self, g: usize, n: usize, k: usize) -> &Entry {
+        &self.entries[self.groups[g].namespaces[n].entries[k]]
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [tree, preview] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|&row| ListItem::new(self.row_label(row)))
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(format!(" {} ", self.path)))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, tree, &mut self.list);
+
+        let lines = match self.list.selected().and_then(|i| self.rows.get(i)) {
+            Some(&row) => self.preview(row),
+            None => Vec::new(),
+        };
+        let paragraph = Paragraph::new(lines)
+            .block(Block::bordered().title(" Value "))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(paragraph, preview);
+        frame.render_widget(Line::from(HELP).reversed(), status);
+    }
+
+    fn row_label(&self, row: Row) -> String {
+        let marker = |open: bool| if open { "▾" } else { "▸" };
+        match row {
+            Row::Group(g) => {
+                let group = &self.groups[g];
+                let keys: usize = group.namespaces.iter().map(|n| n.entries.len()).sum();
+                format!("{} {} ({})", marker(group.open), group.origin, keys)
+            }
+            Row::Namespace(g, n) => {
+                let namespace = &self.groups[g].namespaces[n];
+                format!(
+                    "  {} {} ({})",
+                    marker(namespace.open),
+                    namespace.name,
+                    namespace.entries.len()
+                )
+            }
+            Row::Key(g, n, k) => format!("      {}", export::item_name(self.entry(g, n, k))),
+        }
+    }
+
+    /// The selected key's label, classification, size, encoding and value, or
+    /// a summary of the selected group or namespace
+    fn preview(&self, row: Row) -> Vec<Line<'static>> {
+        let summary = |entries: &mut dyn Iterator<Item = usize>| {
+            let (count, bytes) = entries.fold((0, 0), |(count, bytes), i| {
+                (count + 1, bytes + self.entries[i].value.len())
+            });
+            vec![Line::from(format!("{} keys, {} bytes", count, bytes))]
+        };
+        match row {
+            Row::Group(g) => summary(
+                &mut self.groups[g]
+                    .namespaces
+                    .iter()
+                    .flat_map(|n| n.entries.iter().copied()),
+            ),
+            Row::Namespace(g, n) => {
+                summary(&mut self.groups[g].namespaces[n].entries.iter().copied())
+            }
+            Row::Key(g, n, k) => {
+                let entry = self.entry(g, n, k);
+                let decoded = preview::decode(entry);
+                let classification =
+                    serde_json::to_string(&entry.classification).unwrap_or_default();
+                let mut lines = vec![
+                    Line::from(export::key_label(&entry.key)).bold(),
+                    Line::from(classification),
+                    Line::from(format!("{} bytes, {}", entry.value.len(), decoded.label())),
+                    Line::default(),
+                ];
+                lines.extend(decoded.body.lines().map(|l| Line::from(l.to_string())));
+                lines
+            }
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! `--canonical`: JSON output in the RFC 8785 canonical form
+//!
+//! The command's output is captured, like for `--query`, and every JSON
+//! document in it is written again without whitespace, with object members
+//! sorted by the UTF-16 code units of their names, strings escaped only where
+//! JSON requires it, and numbers as IEEE 754 doubles in the shortest form
+//! ECMAScript would print. Two runs over the same database then produce the
+//! same bytes, which can be hashed or signed. Integers above 2^53 lose
+//! precision, as the RFC prescribes. Documents are separated by newlines,
+//! with none after the last, so a single report is exactly its canonical form.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Every JSON document in `output`, canonicalized
+pub fn run(output: &[u8]) -> Result<Vec<u8>> {
+    let mut documents = Vec::new();
+    for document in serde_json::Deserializer::from_slice(output).into_iter::<Value>() {
+        let document =
+            document.map_err(|_| anyhow!("--canonical needs JSON output; try --format json"))?;
+        let mut text = String::new();
+        write(&mut text, &document);
+        documents.push(text);
+    }
+    Ok(documents.join("\n").into_bytes())
+}
+
+fn write(out: &mut String, value: &Value) {
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => out.push_str(&value.to_string()),
+        Value::Number(n) => out.push_str(&number(n.as_f64().unwrap_or_default())),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write(out, item);
+            }
+            out.push(']');
+        }
+        Value::Object(members) => {
+            let mut members: Vec<_> = members.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (name, value)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(name.clone()).to_string());
+                out.push(':');
+                write(out, value);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// `x` as ECMAScript's `Number.prototype.toString` prints it
+fn number(x: f64) -> String {
+    if x == 0.0 {
+        return "0".to_string();
+    }
+    if x < 0.0 {
+        return format!("-{}", number(-x));
+    }
+    // Shortest round-trip digits and the exponent of the first one
+    let scientific = format!("{:e}", x);
+    let (mantissa, exponent) = scientific.split_once('e').expect("{:e} has an exponent");
+    let mut digits = mantissa.replace('.', "");
+    if let Some(even) = even_digits(x, &digits, exponent) {
+        digits = even;
+    }
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().expect("{:e} exponent") + 1;
+    if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        let (first, rest) = digits.split_at(1);
+        let fraction = if rest.is_empty() {
+            String::new()
+        } else {
+            format!(".{}", rest)
+        };
+        format!("{}{}e{}{}", first, fraction, sign, (n - 1).abs())
+    }
+}
+
+/// Shortest digits of `x` ending in an even digit, when they are as close to
+/// `x` as `digits`: ECMAScript takes the even of two, Rust rounds the tie up
+fn even_digits(x: f64, digits: &str, exponent: &str) -> Option<String> {
+    // A tie rounded up ends in an odd digit, one above the even one
+    let (head, last) = digits.split_at(digits.len() - 1);
+    let last = last.parse::<u8>().ok().filter(|digit| digit % 2 == 1)?;
+    let prefix = format!("{}{}", head, last - 1);
+    // when `x` is exactly halfway: the even digits followed by a 5
+    let halfway = format!("{}5", prefix).parse::<u64>().ok()?;
+    let scale = exponent.parse::<i32>().ok()? - digits.len() as i32;
+    if !is_decimal(x, halfway, scale) {
+        return None;
+    }
+    let (first, fraction) = prefix.split_at(1);
+    let candidate = format!("{}.{}0e{}", first, fraction, exponent);
+    (candidate.parse::<f64>().ok()? == x).then_some(prefix)
+}
+
+/// Whether the positive `x` is exactly `m` × 10^`scale`, for an odd `m`
+fn is_decimal(x: f64, m: u64, scale: i32) -> bool {
+    // Equal numbers have the same power of two: 2^`scale` in `m` × 10^`scale`
+    // and in `x` that of its mantissa made odd
+    let bits = x.to_bits();
+    let biased = (bits >> 52 & 0x7ff) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+    let (mantissa, power) = match biased {
+        0 => (fraction, -1074),
+        _ => (fraction | 1 << 52, biased - 1075),
+    };
+    if mantissa == 0 || scale != power + mantissa.trailing_zeros() as i32 {
+        return false;
+    }
+    let mantissa = u128::from(mantissa >> mantissa.trailing_zeros());
+    let m = u128::from(m);
+    // What is left are powers of five, on the side of `m` when `scale` is
+    // negative and on the side of the mantissa when it is not
+    let fives = 5u128.checked_pow(scale.unsigned_abs());
+    if scale < 0 {
+        fives.and_then(|fives| fives.checked_mul(mantissa)) == Some(m)
+    } else {
+        fives.and_then(|fives| fives.checked_mul(m)) == Some(mantissa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(json: &str) -> String {
+        String::from_utf8(run(json.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn numbers_of_rfc_8785_appendix_b() {
+        for (bits, expected) in [
+            (0x0000000000000000u64, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ] {
+            assert_eq!(number(f64::from_bits(bits)), expected, "{:#x}", bits);
+        }
+    }
+
+    #[test]
+    fn ties_take_the_even_digit() {
+        // Halfway between 17-digit neighbours, which Rust rounds up
+        assert_eq!(
+            number(f64::from_bits(0x431a1c764dfa9c11)),
+            "1837410958616324.2"
+        );
+        assert_eq!(
+            number(f64::from_bits(0x42705f6aa410b680)),
+            "1125124817163.4062"
+        );
+        // Not halfway, for all the 5 after the digits
+        assert_eq!(number(0.1), "0.1");
+        assert_eq!(number(f64::from_bits(1)), "5e-324");
+    }
+
+    #[test]
+    fn numbers_in_documents() {
+        assert_eq!(canonical("1e21"), "1e+21");
+        assert_eq!(canonical("-0"), "0");
+        assert_eq!(canonical("-0.0"), "0");
+        assert_eq!(canonical("5e-324"), "5e-324");
+        assert_eq!(
+            canonical("[1.0, 100, 1e20]"),
+            "[1,100,100000000000000000000]"
+        );
+    }
+
+    #[test]
+    fn members_sorted_by_utf16_code_units() {
+        // RFC 8785 section 3.2.3: the emoji's surrogates sort before U+FB33,
+        // though its code point is larger
+        let input = r#"{
+            "\u20ac": "Euro Sign",
+            "\r": "Carriage Return",
+            "\ufb33": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "\ud83d\ude00": "Emoji: Grinning Face",
+            "\u0080": "Control",
+            "\u00f6": "Latin Small Letter O With Diaeresis"
+        }"#;
+        assert_eq!(
+            canonical(input),
+            "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\
+             \"\u{f6}\":\"Latin Small Letter O With Diaeresis\",\"\u{20ac}\":\"Euro Sign\",\
+             \"\u{1f600}\":\"Emoji: Grinning Face\",\
+             \"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}"
+        );
+    }
+
+    #[test]
+    fn example_of_rfc_8785_section_3_2_2() {
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        assert_eq!(
+            canonical(input),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    #[test]
+    fn documents_on_lines() {
+        assert_eq!(
+            canonical("{\"b\":1,\"a\":2}\n[ ]\n"),
+            "{\"a\":2,\"b\":1}\n[]"
+        );
+        assert!(run(b"not json").is_err());
+    }
+}
@@ -0,0 +1,340 @@
+use anyhow::{anyhow, bail, Context, Result};
+use rusty_leveldb::{WriteBatch, DB};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Current change-set format version
+pub const CHANGESET_VERSION: u32 = 1;
+
+/// Kind of change recorded for a single key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Single key change; keys and values are UTF-8 text, or `0x`-prefixed hex
+/// for binary data and for text that itself starts with `0x`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_value: Option<String>,
+    /// Hash of the new value, for reports; `apply` doesn't check it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_sha256: Option<String>,
+}
+
+/// Set of changes between two database states
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeSet {
+    pub version: u32,
+    pub changes: Vec<Change>,
+}
+
+/// Outcome of applying a change set
+#[derive(Debug, Default)]
+pub struct ApplyReport {
+    pub written: usize,
+    pub deleted: usize,
+    pub conflicts: Vec<String>,
+}
+
+impl ChangeSet {
+    /// Loads a change set from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read change set {}", path.display()))?;
+        let set: ChangeSet = serde_json::from_str(&text)?;
+        if set.version != CHANGESET_VERSION {
+            bail!("Unsupported change set version {}", set.version);
+        }
+        Ok(set)
+    }
+}
+
+/// Computes the change set that turns `old` into `new`
+pub fn diff(old: &BTreeMap<Vec<u8>, Vec<u8>>, new: &BTreeMap<Vec<u8>, Vec<u8>>) -> ChangeSet {
+    let mut keys: Vec<&Vec<u8>> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    let changes = keys
+        .into_iter()
+        .filter_map(|key| {
+            let kind = match (old.get(key), new.get(key)) {
+                (None, Some(_)) => ChangeKind::Added,
+                (Some(_), None) => ChangeKind::Removed,
+                (Some(a), Some(b)) if a != b => ChangeKind::Changed,
+                _ => return None,
+            };
+            Some(Change {
+                kind,
+                key: encode_bytes(key),
+                old_value: old.get(key).map(|v| encode_bytes(v)),
+                old_sha256: old.get(key).map(|v| sha256_hex(v)),
+                new_value: new.get(key).map(|v| encode_bytes(v)),
+                new_sha256: new.get(key).map(|v| sha256_hex(v)),
+            })
+        })
+        .collect();
+
+    ChangeSet {
+        version: CHANGESET_VERSION,
+        changes,
+    }
+}
+
+impl ChangeSet {
+    /// Drops the values, keeping keys and hashes, for reports that must not
+    /// repeat secrets
+    pub fn without_values(mut self) -> Self {
+        for change in &mut self.changes {
+            change.old_value = None;
+            change.new_value = None;
+        }
+        self
+    }
+}
+
+/// Encodes bytes as UTF-8 text, or as `0x`-prefixed hex when they aren't
+/// UTF-8 or are text starting with `0x`, so that `decode_bytes` can tell
+/// the two apart
+pub fn encode_bytes(data: &[u8]) -> String {
+    match std::str::from_utf8(data) {
+        Ok(text) if !text.starts_with("0x") => text.to_string(),
+        _ => format!("0x{}", hex::encode(data)),
+    }
+}
+
+/// Decodes a value of `encode_bytes`: `0x`-prefixed hex or plain UTF-8 text
+pub fn decode_bytes(value: &str) -> Result<Vec<u8>> {
+    match value.strip_prefix("0x") {
+        Some(h) => hex::decode(h).map_err(|e| anyhow!("Invalid hex value {:?}: {}", value, e)),
+        None => Ok(value.as_bytes().to_vec()),
+    }
+}
+
+/// Hex-encoded SHA-256 of a value
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Checks a change's precondition against the value currently stored
+fn check_precondition(change: &Change, current: Option<&[u8]>) -> Result<Option<String>> {
+    let expected_old = change.old_value.as_deref().map(decode_bytes).transpose()?;
+    let conflict = match (change.kind, current) {
+        (ChangeKind::Added, Some(cur)) => {
+            let new = change.new_value.as_deref().map(decode_bytes).transpose()?;
+            (new.as_deref() != Some(cur)).then(|| "key already exists".to_string())
+        }
+        (ChangeKind::Added, None) => None,
+        (_, None) => Some("key is missing".to_string()),
+        (_, Some(cur)) => {
+            if expected_old.as_deref().is_some_and(|old| old != cur) {
+                Some("current value differs from old_value".to_string())
+            } else if change
+                .old_sha256
+                .as_deref()
+                .is_some_and(|h| !h.eq_ignore_ascii_case(&sha256_hex(cur)))
+            {
+                Some("current value hash differs from old_sha256".to_string())
+            } else {
+                None
+            }
+        }
+    };
+    Ok(conflict)
+}
+
+/// Applies a change set to an open database as a single atomic batch
+///
+/// Each change is checked against the value the changes before it in the
+/// set leave, so a second change of a key must start where the first ended.
+/// Conflicting changes abort the whole apply unless `force` is set.
+pub fn apply(db: &mut DB, set: &ChangeSet, force: bool, dry_run: bool) -> Result<ApplyReport> {
+    let mut report = ApplyReport::default();
+    let mut batch = WriteBatch::default();
+    // Values the batch will leave, `None` for deleted keys
+    let mut pending: HashMap<Vec<u8>, Option<Vec<u8>>> = HashMap::new();
+
+    for change in &set.changes {
+        let key = decode_bytes(&change.key)?;
+        let current = match pending.get(&key) {
+            Some(value) => value.clone(),
+            None => db.get(&key).map(|value| value.to_vec()),
+        };
+        if let Some(reason) = check_precondition(change, current.as_deref())? {
+            report.conflicts.push(format!("{}: {}", change.key, reason));
+        }
+
+        match change.kind {
+            ChangeKind::Added | ChangeKind::Changed => {
+                let value = change
+                    .new_value
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("Change for {:?} has no new_value", change.key))?;
+                let value = decode_bytes(value)?;
+                batch.put(&key, &value);
+                pending.insert(key, Some(value));
+                report.written += 1;
+            }
+            ChangeKind::Removed => {
+                batch.delete(&key);
+                pending.insert(key, None);
+                report.deleted += 1;
+            }
+        }
+    }
+
+    if !report.conflicts.is_empty() && !force {
+        bail!(
+            "Change set conflicts with target database:\n  {}",
+            report.conflicts.join("\n  ")
+        );
+    }
+
+    if !dry_run {
+        db.write(batch, true)?;
+        db.flush()?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(pairs: &[(&[u8], &[u8])]) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_vec(), v.to_vec()))
+            .collect()
+    }
+
+    fn database(contents: &BTreeMap<Vec<u8>, Vec<u8>>) -> DB {
+        let mut db = DB::open("changeset", rusty_leveldb::in_memory()).unwrap();
+        for (key, value) in contents {
+            db.put(key, value).unwrap();
+        }
+        db
+    }
+
+    fn change(kind: ChangeKind, key: &str, old: Option<&str>, new: Option<&str>) -> Change {
+        Change {
+            kind,
+            key: key.to_string(),
+            old_value: old.map(str::to_string),
+            old_sha256: None,
+            new_value: new.map(str::to_string),
+            new_sha256: None,
+        }
+    }
+
+    #[test]
+    fn diff_then_apply_round_trip() {
+        let old = map(&[
+            (b"kept", b"same"),
+            (b"changed", b"before"),
+            (b"removed", b"gone"),
+            (b"binary", &[0xff, 0x00]),
+        ]);
+        let new = map(&[
+            (b"kept", b"same"),
+            (b"changed", b"after"),
+            (b"added", b"new"),
+            (b"binary", &[0xfe, 0x01]),
+        ]);
+        let set = diff(&old, &new);
+        assert_eq!(set.changes.len(), 4);
+
+        // Through JSON, as `diff --output` and `apply` pass it
+        let set: ChangeSet = serde_json::from_str(&serde_json::to_string(&set).unwrap()).unwrap();
+        let mut db = database(&old);
+        let report = apply(&mut db, &set, false, false).unwrap();
+        assert_eq!((report.written, report.deleted), (3, 1));
+        assert!(report.conflicts.is_empty());
+        for (key, value) in &new {
+            assert_eq!(db.get(key).as_deref(), Some(&value[..]));
+        }
+        assert_eq!(db.get(b"removed"), None);
+
+        // Applied again, every change but the addition, already made, conflicts
+        let error = apply(&mut db, &set, false, true).unwrap_err().to_string();
+        assert_eq!(error.matches("\n  ").count(), 3, "{}", error);
+    }
+
+    #[test]
+    fn text_starting_with_0x_round_trips() {
+        let old = map(&[(b"0x-key", b"0xdeadbeef")]);
+        let new = map(&[(b"0x-key", b"0x-foo"), (b"0x", b"")]);
+        let set = diff(&old, &new);
+        let change = &set.changes[1];
+        assert_eq!(change.key, "0x30782d6b6579");
+        assert_eq!(change.old_value.as_deref(), Some("0x30786465616462656566"));
+
+        let mut db = database(&old);
+        let report = apply(&mut db, &set, false, false).unwrap();
+        assert!(report.conflicts.is_empty());
+        assert_eq!(db.get(b"0x-key").as_deref(), Some(&b"0x-foo"[..]));
+        assert_eq!(db.get(b"0x").as_deref(), Some(&b""[..]));
+        assert_eq!(decode_bytes("plain").unwrap(), b"plain");
+    }
+
+    #[test]
+    fn later_changes_are_checked_against_earlier_ones() {
+        let mut db = database(&map(&[(b"k", b"a")]));
+
+        // Both start from the stored value; the second would overwrite the first
+        let set = ChangeSet {
+            version: CHANGESET_VERSION,
+            changes: vec![
+                change(ChangeKind::Changed, "k", Some("a"), Some("b")),
+                change(ChangeKind::Changed, "k", Some("a"), Some("c")),
+            ],
+        };
+        let error = apply(&mut db, &set, false, false).unwrap_err().to_string();
+        assert!(
+            error.contains("k: current value differs from old_value"),
+            "{}",
+            error
+        );
+        assert_eq!(db.get(b"k").as_deref(), Some(&b"a"[..]));
+
+        // A chain that starts where the previous change ended applies
+        let set = ChangeSet {
+            version: CHANGESET_VERSION,
+            changes: vec![
+                change(ChangeKind::Changed, "k", Some("a"), Some("b")),
+                change(ChangeKind::Changed, "k", Some("b"), Some("c")),
+                change(ChangeKind::Removed, "k", Some("c"), None),
+                change(ChangeKind::Added, "k", None, Some("d")),
+            ],
+        };
+        let report = apply(&mut db, &set, false, false).unwrap();
+        assert!(report.conflicts.is_empty());
+        assert_eq!(db.get(b"k").as_deref(), Some(&b"d"[..]));
+
+        // Removing a key twice: the second finds it missing
+        let set = ChangeSet {
+            version: CHANGESET_VERSION,
+            changes: vec![
+                change(ChangeKind::Removed, "k", Some("d"), None),
+                change(ChangeKind::Removed, "k", Some("d"), None),
+            ],
+        };
+        let error = apply(&mut db, &set, false, false).unwrap_err().to_string();
+        assert!(error.contains("k: key is missing"), "{}", error);
+    }
+}
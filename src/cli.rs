@@ -0,0 +1,922 @@
+//! Command line of the `element-desktop-leveldb` binary
+//!
+//! Every subcommand is an `Args` struct here and a function in one of the
+//! `commands` modules, which `main.rs` calls. Commands reading one database
+//! take it as a positional `DB` or as `--db <PATH>`.
+
+use crate::timerange::{Instant, TimeRange};
+use anyhow::{bail, Result};
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand, ValueEnum};
+pub use element_desktop_leveldb::export::{Encoding, SortOrder};
+use regex::Regex;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+const LOCATIONS: &str = "\
+Element Desktop keeps its Local Storage database at:
+  Windows  %APPDATA%\\Element\\Local Storage\\leveldb
+  Linux    ~/.config/Element/Local Storage/leveldb
+  macOS    ~/Library/Application Support/Element/Local Storage/leveldb";
+
+/// Reads account, room, device and settings metadata from Element Desktop's
+/// Local Storage LevelDB
+#[derive(Debug, Parser)]
+#[command(
+    name = "element-desktop-leveldb",
+    version,
+    arg_required_else_help = true,
+    after_help = LOCATIONS
+)]
+pub struct Cli {
+    /// Defaults for every command, instead of $ELEMENT_LEVELDB_CONFIG or the
+    /// config.toml in the configuration directory
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Language of messages and report headings, such as de or ru
+    #[arg(long, global = true, value_name = "LANG")]
+    pub lang: Option<String>,
+
+    /// Zone of report timestamps: UTC, local or an offset such as +02:00
+    #[arg(long, global = true, value_name = "ZONE")]
+    pub timezone: Option<String>,
+
+    /// Write the report to FILE, atomically, gzip-compressed when it ends in .gz
+    #[arg(short, long, global = true, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// More log output on standard error: -v info, -vv debug, -vvv trace
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Only errors on standard error, and no progress bar
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Log format
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub log_format: LogFormat,
+
+    /// No colors in reports, as with NO_COLOR
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Tables without borders or colors, columns separated by spaces
+    #[arg(long, global = true)]
+    pub plain: bool,
+
+    /// Print only what a jq-style expression picks from the JSON output,
+    /// such as .user_id or '.rooms | length'
+    #[arg(long, global = true, value_name = "EXPR")]
+    pub query: Option<String>,
+
+    /// JSON output in the RFC 8785 canonical form, byte for byte reproducible
+    /// for hashing and signing
+    #[arg(long, global = true, conflicts_with = "query")]
+    pub canonical: bool,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print the extracted metadata: tables on a terminal, JSON otherwise
+    Parse(ParseArgs),
+    /// Print the value of one item
+    Get(GetArgs),
+    /// Print one value decoded, or as an offset/hex/ASCII dump, with its guessed encoding
+    Show(ShowArgs),
+    /// List the keys of a database
+    ListKeys(ListKeysArgs),
+    /// Write the metadata together with every entry
+    Export(ExportArgs),
+    /// Print raw keys and values, hex or base64 encoded, for other tools
+    Dump(DumpArgs),
+    /// Find where a text or pattern appears in the keys and values
+    Search(SearchArgs),
+    /// Entry counts and sizes, largest values and counts per category, origin and namespace
+    Stats(StatsArgs),
+    /// Score local privacy exposure and explain each contributing factor
+    Assess(AssessArgs),
+    /// Which rooms can be decrypted, which devices are trusted, can history be recovered
+    AnalyzeCrypto(AnalyzeCryptoArgs),
+    /// Apply a change set, the format `diff` writes, to a database
+    Apply(ApplyArgs),
+    /// Time opening, iterating, classifying and exporting a database
+    Bench(BenchArgs),
+    /// Copy a profile into a fresh database, optionally salvaging a damaged one
+    Clone(CloneArgs),
+    /// Print the completion script for a shell
+    Completions(CompletionsArgs),
+    /// Direct-message contacts for case management tools
+    Contacts(ContactsArgs),
+    /// Shared accounts, rooms and devices of several profiles, and one timeline
+    Correlate(CorrelateArgs),
+    /// Decode a large IndexedDB store by store into JSON lines
+    DecodeIndexeddb(DecodeIndexeddbArgs),
+    /// Added, removed and changed keys of two databases, with value hashes and diffs
+    Diff(DiffArgs),
+    /// Resolve cached room IDs to names, aliases and public directory entries
+    EnrichRooms(EnrichRoomsArgs),
+    /// Resolve bare user IDs to current display names and avatars
+    EnrichUsers(EnrichUsersArgs),
+    /// Download avatars and files referenced by mxc:// URIs, with a hash manifest
+    FetchMedia(FetchMediaArgs),
+    /// Dump storage from a running Element Desktop over the DevTools protocol
+    Live(LiveArgs),
+    /// Print the man page, or write pages for every command into a directory
+    Man(ManArgs),
+    /// OpenMetrics summary of one or more profiles
+    Metrics(MetricsArgs),
+    /// Report new devices, token changes and joined rooms as they happen
+    Monitor(MonitorArgs),
+    /// Explore a database interactively: get, scan, classify and export
+    Repl(ReplArgs),
+    /// A single-file HTML report, or a Markdown summary for tickets and wikis
+    Report(ReportArgs),
+    /// Secret findings, as JSON or as SARIF with the crypto posture issues
+    Scan(ScanArgs),
+    /// JSON Schema of the metadata report, the export document or an entry
+    Schema(SchemaArgs),
+    /// Recently synced events of each room, from the partial IndexedDB cache
+    Timeline(TimelineArgs),
+    /// Cross-check stored device keys against the homeserver's /keys/query
+    VerifyKeys(VerifyKeysArgs),
+    /// Print a JSON line for every key, or metadata field, that changes
+    Watch(WatchArgs),
+    /// Desktop viewer with search, filtering and JSON export
+    #[cfg(feature = "gui")]
+    Gui(GuiArgs),
+    /// Terminal key browser with a decoded preview of each value
+    #[cfg(feature = "tui")]
+    Browse(BrowseArgs),
+}
+
+impl Command {
+    /// Whether the command runs until stopped, so it never has a finished
+    /// report for `--output`
+    pub fn runs_until_stopped(&self) -> bool {
+        match self {
+            Command::Monitor(_) | Command::Repl(_) | Command::Watch(_) => true,
+            #[cfg(feature = "gui")]
+            Command::Gui(_) => true,
+            #[cfg(feature = "tui")]
+            Command::Browse(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the command writes its results to files of its own, given
+    /// with `--out` or `--dir`, and nothing to standard output
+    pub fn writes_own_files(&self) -> bool {
+        match self {
+            Command::Export(args) => args.out.is_some() || args.dir.is_some(),
+            Command::Metrics(args) => args.out.is_some(),
+            Command::Man(args) => args.dir.is_some(),
+            _ => false,
+        }
+    }
+}
+
+/// The database a command reads
+#[derive(Debug, Args)]
+pub struct Db {
+    /// `<profile>/Local Storage/leveldb` directory
+    #[arg(value_name = "DB")]
+    db: Option<PathBuf>,
+
+    /// The database directory, instead of DB
+    #[arg(
+        long = "db",
+        id = "db_flag",
+        value_name = "PATH",
+        conflicts_with = "db"
+    )]
+    db_flag: Option<PathBuf>,
+}
+
+impl Db {
+    pub fn path(&self) -> Result<&Path> {
+        match self.db.as_deref().or(self.db_flag.as_deref()) {
+            Some(path) => Ok(path),
+            None => bail!("No database given: pass DB or --db <PATH>"),
+        }
+    }
+}
+
+/// One page of a long listing
+#[derive(Debug, Args)]
+pub struct PageArgs {
+    /// At most N entries
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Skip the first N entries
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub offset: usize,
+
+    /// Start after the entry whose raw key, hex encoded, is CURSOR; printed when
+    /// --limit leaves entries out
+    #[arg(long, value_name = "CURSOR")]
+    pub after: Option<String>,
+}
+
+/// The incident window of a report
+#[derive(Debug, Args)]
+pub struct TimeRangeArgs {
+    /// Only what was seen at or after WHEN: a date, a time such as
+    /// 2024-05-01T09:30 in --timezone or with an offset, milliseconds since the
+    /// epoch (12 digits or more; 20240501 is not a date), or #N for a LevelDB
+    /// sequence number
+    #[arg(long, value_name = "WHEN")]
+    pub since: Option<Instant>,
+
+    /// Only what was seen at or before WHEN; a date includes the whole day
+    #[arg(long, value_name = "WHEN")]
+    pub until: Option<Instant>,
+}
+
+impl TimeRangeArgs {
+    /// The window, in the `--timezone` of the run
+    pub fn range(&self) -> Result<TimeRange> {
+        TimeRange::new(self.since, self.until)
+    }
+}
+
+impl PageArgs {
+    pub fn is_set(&self) -> bool {
+        self.limit.is_some() || self.offset > 0 || self.after.is_some()
+    }
+}
+
+/// Homeserver to query and the token to query it with
+#[derive(Debug, Args)]
+pub struct HomeserverArgs {
+    /// Homeserver URL, instead of the profile's mx_hs_url
+    #[arg(long, value_name = "URL")]
+    pub homeserver: Option<String>,
+
+    /// Access token; the environment keeps it out of shell history
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        env = "MATRIX_ACCESS_TOKEN",
+        hide_env_values = true
+    )]
+    pub access_token: Option<String>,
+}
+
+/// Log lines as text or as JSON objects
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Human-readable text or JSON
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+/// What `parse` prints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ParseFormat {
+    /// Tables of the account, devices, rooms and settings
+    Text,
+    Json,
+    Yaml,
+}
+
+#[derive(Debug, Args)]
+pub struct ParseArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Only some fields, such as identity,rooms.name,devices.*
+    #[arg(long, value_name = "SPEC", allow_hyphen_values = true)]
+    pub fields: Option<String>,
+
+    /// Record the file, level and offset every raw entry was read from
+    #[arg(long)]
+    pub provenance: bool,
+
+    /// Threads reading the table files, 0 for one per CPU
+    #[arg(long, value_name = "N")]
+    pub threads: Option<usize>,
+
+    #[command(flatten)]
+    pub time_range: TimeRangeArgs,
+
+    /// Output format: tables of the account, devices, rooms and settings,
+    /// JSON or YAML; tables only on a terminal unless given
+    #[arg(long, value_enum)]
+    pub format: Option<ParseFormat>,
+
+    /// Render the metadata, or the selected fields, with a Tera template
+    #[arg(long, value_name = "FILE", conflicts_with = "format")]
+    pub template: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct GetArgs {
+    /// Item name such as mx_user_id, or a raw key such as VERSION
+    pub key: String,
+
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+#[derive(Debug, Args)]
+pub struct ShowArgs {
+    /// Item name such as mx_user_id, or a raw key such as VERSION
+    pub key: String,
+
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Dump the raw value as offset, hex and ASCII columns
+    #[arg(long)]
+    pub hex: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ListKeysArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Only keys whose item name starts with PREFIX, such as mx_setting_
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Only keys whose item name matches PATTERN
+    #[arg(long, value_name = "PATTERN")]
+    pub regex: Option<Regex>,
+
+    /// Only items and records of ORIGIN, such as vector://vector
+    #[arg(long)]
+    pub origin: Option<String>,
+
+    /// Also print each value's size in bytes and type (json, text or binary)
+    #[arg(short, long)]
+    pub long: bool,
+
+    /// Order of the keys
+    #[arg(long, value_enum, default_value_t)]
+    pub sort: SortOrder,
+
+    #[command(flatten)]
+    pub page: PageArgs,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+/// What `export` writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// One document with the metadata and the entries
+    #[default]
+    Json,
+    /// A row per entry; with --dir also rooms.csv and devices.csv
+    Csv,
+    /// The JSON document as YAML
+    Yaml,
+    /// A JSON line per entry, written as it is read; no metadata
+    Ndjson,
+    /// The metadata and the entries as XML, in a versioned structure
+    Xml,
+    /// The metadata and the entries as CBOR, keys and values as bytes
+    Cbor,
+    /// The metadata and the entries as MessagePack, keys and values as bytes
+    Msgpack,
+    /// Timed artifacts as log2timeline CSV, for a master timeline
+    L2tcsv,
+    /// Timed artifacts in the Sleuth Kit body format, for `mactime`
+    Bodyfile,
+    /// Timed artifacts as Timesketch JSON lines, ready to upload
+    Timesketch,
+    /// An SQLite database of tables for entries, rooms, devices, sessions,
+    /// settings and crypto material, written to --out
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    /// The entries as a zstd-compressed Parquet table, written to --out
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl ExportFormat {
+    /// Whether the format is a timeline of the artifacts with a time
+    pub fn is_timeline(self) -> bool {
+        matches!(
+            self,
+            ExportFormat::L2tcsv | ExportFormat::Bodyfile | ExportFormat::Timesketch
+        )
+    }
+
+    /// Whether the format is written to --out rather than standard output
+    pub fn writes_file(self) -> bool {
+        match self {
+            #[cfg(feature = "sqlite")]
+            ExportFormat::Sqlite => true,
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: ExportFormat,
+
+    /// Order of the entries; --format ndjson only streams in key order
+    #[arg(long, value_enum, default_value_t)]
+    pub sort: SortOrder,
+
+    /// Only some fields, such as identity,rooms.name,entries.key
+    #[arg(long, value_name = "SPEC", allow_hyphen_values = true)]
+    pub fields: Option<String>,
+
+    #[command(flatten)]
+    pub time_range: TimeRangeArgs,
+
+    /// Render the metadata and entries with a Tera template
+    #[arg(long, value_name = "FILE", conflicts_with = "format")]
+    pub template: Option<PathBuf>,
+
+    /// Write entries.csv, rooms.csv and devices.csv into DIR instead of
+    /// printing the entries; needs --format csv
+    #[arg(long, value_name = "DIR", conflicts_with = "template")]
+    pub dir: Option<PathBuf>,
+
+    /// The file --format sqlite or parquet writes, replaced if it exists
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["template", "dir"])]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+#[command(group(ArgGroup::new("entries").required(true).args(["key", "all"])))]
+pub struct DumpArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Only the entry with this item name or raw key
+    #[arg(long)]
+    pub key: Option<String>,
+
+    /// Every entry, in key order
+    #[arg(long)]
+    pub all: bool,
+
+    /// Entries written out at a time with --all
+    #[arg(long, value_name = "N", default_value = "1000")]
+    pub chunk: NonZeroUsize,
+
+    /// Order of --all
+    #[arg(long, value_enum, default_value_t)]
+    pub sort: SortOrder,
+
+    /// A page of --all
+    #[command(flatten)]
+    pub page: PageArgs,
+
+    /// Encoding of keys and values
+    #[arg(long, value_enum, default_value_t)]
+    pub encoding: Encoding,
+
+    /// Output format: KEY<TAB>VALUE lines, or JSON lines
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+#[derive(Debug, Args)]
+pub struct SearchArgs {
+    /// Text to look for, or with --regex a pattern
+    pub pattern: String,
+
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Take PATTERN as a regular expression
+    #[arg(long)]
+    pub regex: bool,
+
+    /// Ignore case
+    #[arg(short, long)]
+    pub ignore_case: bool,
+
+    /// Also search values as UTF-8 and UTF-16LE
+    #[arg(long)]
+    pub binary: bool,
+
+    /// Characters of context shown around each occurrence in a value
+    #[arg(short = 'C', long, value_name = "N", default_value_t = 40)]
+    pub context: usize,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+#[derive(Debug, Args)]
+pub struct AssessArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// IndexedDB directory, instead of the one next to the database
+    #[arg(long, value_name = "DIR")]
+    pub indexeddb: Option<PathBuf>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+#[derive(Debug, Args)]
+pub struct AnalyzeCryptoArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// IndexedDB directory, instead of the one next to the database
+    #[arg(long, value_name = "DIR")]
+    pub indexeddb: Option<PathBuf>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+#[derive(Debug, Args)]
+pub struct ApplyArgs {
+    /// Database to change
+    pub db: PathBuf,
+
+    /// Change set written by `diff`
+    pub changes: PathBuf,
+
+    /// Check the changes without writing them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Write changes whose old value doesn't match
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct BenchArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Runs of each phase; the fastest counts
+    #[arg(long, value_name = "N", default_value = "3")]
+    pub runs: NonZeroUsize,
+
+    /// Threads reading the table files, 0 for one per CPU
+    #[arg(long, value_name = "N")]
+    pub threads: Option<usize>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+#[derive(Debug, Args)]
+pub struct CloneArgs {
+    pub src: PathBuf,
+    pub dest: PathBuf,
+
+    /// Salvage what can be read from a damaged profile
+    #[arg(long)]
+    pub repair: bool,
+
+    /// Continue an interrupted repair
+    #[arg(long, requires = "repair")]
+    pub resume: bool,
+
+    /// Log what was done as JSON lines
+    #[arg(long, value_name = "FILE")]
+    pub audit_log: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    pub shell: clap_complete::Shell,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ContactsFormat {
+    #[default]
+    Vcard,
+    Csv,
+}
+
+#[derive(Debug, Args)]
+pub struct ContactsArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: ContactsFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct CorrelateArgs {
+    /// Databases of the profiles, at least two
+    #[arg(value_name = "DB", num_args = 2.., required = true)]
+    pub dbs: Vec<PathBuf>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeIndexeddbArgs {
+    /// `<profile>/IndexedDB/<origin>.indexeddb.leveldb` directory
+    pub src: PathBuf,
+
+    /// Directory for the JSON lines and the checkpoint
+    pub out: PathBuf,
+
+    /// Continue after the last finished store
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Records decoded between flushes of the output
+    #[arg(long, value_name = "N")]
+    pub chunk: Option<NonZeroUsize>,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    pub old: PathBuf,
+    pub new: PathBuf,
+
+    /// A unified diff of the values instead of a change set
+    #[arg(long, conflicts_with_all = ["summary", "no_values"])]
+    pub unified: bool,
+
+    /// Only the changed keys with the hashes of their values, and the counts
+    #[arg(long, conflicts_with = "no_values")]
+    pub summary: bool,
+
+    /// A change set with hashes but without the values
+    #[arg(long)]
+    pub no_values: bool,
+
+    /// Lines of context around unified diff changes
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub context: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct EnrichRoomsArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    #[command(flatten)]
+    pub homeserver: HomeserverArgs,
+
+    /// Also look up rooms whose name is already known
+    #[arg(long)]
+    pub all: bool,
+
+    /// Pages of the public room directory to read
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub directory_pages: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct EnrichUsersArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    #[command(flatten)]
+    pub homeserver: HomeserverArgs,
+
+    /// Also look up users whose display name is already known
+    #[arg(long)]
+    pub all: bool,
+
+    /// Profile lookups per second
+    #[arg(long, value_name = "N", value_parser = positive_rate)]
+    pub rate: Option<f64>,
+
+    /// File caching profiles between runs
+    #[arg(long, value_name = "FILE")]
+    pub cache: Option<PathBuf>,
+}
+
+fn positive_rate(value: &str) -> Result<f64, String> {
+    match value.parse() {
+        Ok(rate) if rate > 0.0 => Ok(rate),
+        _ => Err("needs a positive number of lookups per second".to_string()),
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct FetchMediaArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    #[command(flatten)]
+    pub homeserver: HomeserverArgs,
+
+    /// Directory for the files and the manifest
+    #[arg(long, value_name = "DIR", default_value = "media")]
+    pub out: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct LiveArgs {
+    /// Element's --remote-debugging-port
+    #[arg(long, value_name = "N", default_value_t = 9222)]
+    pub port: u16,
+
+    /// Origin whose storage is dumped
+    #[arg(long, value_name = "URL", default_value = element_desktop_leveldb::ELEMENT_DESKTOP_ORIGIN)]
+    pub origin: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ManArgs {
+    /// Write element-desktop-leveldb.1 and a page per command into DIR
+    #[arg(long, value_name = "DIR")]
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct MetricsArgs {
+    /// Databases of the profiles
+    #[arg(value_name = "DB", required = true)]
+    pub dbs: Vec<PathBuf>,
+
+    /// Write to FILE, atomically, instead of standard output
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct MonitorArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Also POST every notification to URL
+    #[arg(long, value_name = "URL")]
+    pub webhook: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ScanFormat {
+    #[default]
+    Json,
+    Sarif,
+}
+
+#[derive(Debug, Args)]
+pub struct ScanArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// YAML file of additional rules; may be repeated
+    #[arg(long, value_name = "FILE")]
+    pub rules: Vec<PathBuf>,
+
+    /// Only the rules given with --rules
+    #[arg(long)]
+    pub no_builtin: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: ScanFormat,
+
+    /// IndexedDB directory, instead of the one next to the database
+    #[arg(long, value_name = "DIR")]
+    pub indexeddb: Option<PathBuf>,
+}
+
+/// Which output `schema` describes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SchemaDocument {
+    /// `parse --format json`
+    #[default]
+    Metadata,
+    /// `export --format json`: the metadata and the entries
+    Export,
+    /// One entry of the export; a line of `export --format ndjson`
+    Entry,
+}
+
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    #[arg(value_enum, default_value_t)]
+    pub document: SchemaDocument,
+}
+
+#[derive(Debug, Args)]
+pub struct TimelineArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// IndexedDB directory, instead of the one next to the database
+    #[arg(long, value_name = "DIR")]
+    pub indexeddb: Option<PathBuf>,
+
+    /// Only this room
+    #[arg(long, value_name = "ROOM_ID")]
+    pub room: Option<String>,
+
+    #[command(flatten)]
+    pub time_range: TimeRangeArgs,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+#[derive(Debug, Args)]
+pub struct VerifyKeysArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    #[command(flatten)]
+    pub homeserver: HomeserverArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplArgs {
+    #[command(flatten)]
+    pub db: Db,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    #[default]
+    Html,
+    Markdown,
+}
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: ReportFormat,
+
+    /// IndexedDB directory, instead of the one next to the database
+    #[arg(long, value_name = "DIR")]
+    pub indexeddb: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    #[command(flatten)]
+    pub db: Db,
+
+    /// Re-parse on every change and print the metadata fields that differ
+    #[arg(long)]
+    pub metadata: bool,
+}
+
+#[cfg(feature = "gui")]
+#[derive(Debug, Args)]
+pub struct GuiArgs {
+    /// Database to open at start
+    pub db: Option<PathBuf>,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Debug, Args)]
+pub struct BrowseArgs {
+    #[command(flatten)]
+    pub db: Db,
+}
@@ -0,0 +1,175 @@
+//! `clone`: copying a database's live entries into a new one
+//!
+//! The copy is a fresh, compacted LevelDB holding only what a read of the
+//! source sees, built beside the destination as `<dest>.partial` and renamed
+//! into place once written, so a failed or interrupted clone never leaves a
+//! half-written database at the destination. With `repair` the source is read
+//! through the raw file readers, checkpointed for `resume`. An `AuditLog`
+//! records each step for the chain of custody.
+
+use crate::audit::AuditLog;
+use crate::raw::checkpoint;
+use anyhow::{bail, Context, Result};
+use rusty_leveldb::{LdbIterator, Options, WriteBatch, DB};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Number of entries written per batch to the new database
+const BATCH_SIZE: usize = 1000;
+
+/// Outcome of cloning a database
+#[derive(Debug, Default)]
+pub struct CloneReport {
+    pub entries: usize,
+    pub files_read: usize,
+    pub issues: Vec<String>,
+    /// The repair scan continued from a checkpoint
+    pub resumed: bool,
+}
+
+/// Checkpoint directory of a repair into `dest`
+pub fn checkpoint_dir(dest: &Path) -> PathBuf {
+    suffixed(dest, ".checkpoint")
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Copies every live entry of `src` into a brand-new database at `dest`
+///
+/// With `repair`, the source is read through the raw WAL/table readers instead
+/// of being opened as a database, so profiles LevelDB refuses to open can still
+/// be salvaged into something Element will accept. The repair scan is
+/// checkpointed into `<dest>.checkpoint` after every file so that, with
+/// `resume`, an interrupted run continues where it stopped; the database is
+/// built as `<dest>.partial` and only renamed to `dest` once complete.
+pub fn clone_database<P: AsRef<Path>, Q: AsRef<Path>>(
+    src: P,
+    dest: Q,
+    repair: bool,
+    resume: bool,
+    mut audit: Option<&mut AuditLog>,
+) -> Result<CloneReport> {
+    let (src, dest) = (src.as_ref(), dest.as_ref());
+    let mut report = CloneReport::default();
+    if resume && !repair {
+        bail!("--resume only applies to --repair");
+    }
+    if dest.exists() {
+        bail!("{} already exists", dest.display());
+    }
+    if let Some(audit) = audit.as_deref_mut() {
+        audit.record(
+            "clone-started",
+            json!({ "source": src, "destination": dest, "repair": repair, "resume": resume }),
+        )?;
+    }
+
+    let checkpoints = checkpoint_dir(dest);
+    let entries = if repair {
+        let scan = checkpoint::scan_directory(src, &checkpoints, resume, |state, file| {
+            let Some(audit) = audit.as_deref_mut() else {
+                return Ok(());
+            };
+            match file {
+                None => {
+                    report.resumed = state.resumes > 0;
+                    let event = if report.resumed {
+                        "scan-resumed"
+                    } else {
+                        "scan-started"
+                    };
+                    audit.record(
+                        event,
+                        json!({
+                            "checkpoint": checkpoints.join(checkpoint::CHECKPOINT_FILE),
+                            "started_at": state.started_at,
+                            "files_done": state.files.len(),
+                            "spool_offset": state.spool_offset,
+                            "resumes": state.resumes,
+                        }),
+                    )
+                }
+                Some(file) => audit.record("file-checkpointed", json!({ "file": file })),
+            }
+        })?;
+        report.files_read = scan.files_read;
+        report.issues = scan.issues.clone();
+        scan.latest()
+    } else {
+        read_live_entries(src)?
+    };
+
+    // Left behind only by a run interrupted while writing; it is ours to replace
+    let partial = suffixed(dest, ".partial");
+    if partial.exists() {
+        std::fs::remove_dir_all(&partial)
+            .with_context(|| format!("Failed to remove the unfinished {}", partial.display()))?;
+        tracing::warn!(path = %partial.display(), "removed an unfinished earlier clone");
+        if let Some(audit) = audit.as_deref_mut() {
+            audit.record("partial-removed", json!({ "path": partial }))?;
+        }
+    }
+    let options = Options {
+        create_if_missing: true,
+        error_if_exists: true,
+        ..Options::default()
+    };
+    let mut db = DB::open(&partial, options)
+        .with_context(|| format!("Failed to create {}", partial.display()))?;
+
+    let mut batch = WriteBatch::default();
+    for (i, (key, value)) in entries.iter().enumerate() {
+        batch.put(key, value);
+        if (i + 1) % BATCH_SIZE == 0 {
+            db.write(std::mem::take(&mut batch), false)?;
+        }
+    }
+    db.write(batch, true)?;
+    db.flush()?;
+    drop(db);
+    std::fs::rename(&partial, dest)?;
+    if repair {
+        std::fs::remove_dir_all(&checkpoints)?;
+    }
+
+    report.entries = entries.len();
+    if let Some(audit) = audit {
+        audit.record(
+            "clone-finished",
+            json!({
+                "destination": dest,
+                "entries": report.entries,
+                "files_read": report.files_read,
+                "issues": report.issues.len(),
+                "resumed": report.resumed,
+            }),
+        )?;
+    }
+    Ok(report)
+}
+
+/// Reads all entries of a database that opens cleanly
+pub fn read_live_entries(path: &Path) -> Result<BTreeMap<Vec<u8>, Vec<u8>>> {
+    let options = Options {
+        create_if_missing: false,
+        ..Options::default()
+    };
+    let mut db = DB::open(path, options)
+        .with_context(|| format!("Failed to open {} (try --repair)", path.display()))?;
+
+    let mut entries = BTreeMap::new();
+    let mut iter = db.new_iter()?;
+    iter.seek_to_first();
+    while iter.valid() {
+        if let Some((key, value)) = iter.current() {
+            entries.insert(key.to_vec(), value.to_vec());
+        }
+        iter.advance();
+    }
+    Ok(entries)
+}
@@ -0,0 +1,339 @@
+//! Commands that write the database out for other tools: `export`,
+//! `timeline` and `contacts`
+
+use super::{progress_bar, sibling_indexeddb, sort_entries, write_document};
+use crate::cli::{self, ContactsFormat, ExportFormat, Format, SortOrder};
+use crate::i18n::tr;
+use crate::output::{self, out, outln};
+use crate::{i18n, status, template};
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
+use element_desktop_leveldb::export::{EntryRecord, Export};
+use element_desktop_leveldb::fields::FieldSelection;
+use element_desktop_leveldb::indexeddb::reader::Origin;
+#[cfg(feature = "parquet")]
+use element_desktop_leveldb::parquet;
+use element_desktop_leveldb::raw::provenance;
+#[cfg(feature = "sqlite")]
+use element_desktop_leveldb::sqlite;
+use element_desktop_leveldb::{binary, csv, export, timeline, xml};
+use element_desktop_leveldb::{contacts, sync, ElementLevelDBParser, Entry};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+
+/// `contacts <db> [--format vcard|csv]`: direct-message contacts for case tools
+pub fn contacts(args: cli::ContactsArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+
+    let parser = ElementLevelDBParser::open(db_path)?;
+    let dir = sibling_indexeddb(&parser)
+        .ok_or_else(|| anyhow!("No IndexedDB directory next to {}", db_path.display()))?;
+    let contacts = contacts::dm_contacts(&Origin::read_directory(dir)?);
+    match args.format {
+        ContactsFormat::Vcard => out!("{}", contacts::to_vcard(&contacts))?,
+        ContactsFormat::Csv => out!("{}", contacts::to_csv(&contacts))?,
+    }
+    Ok(())
+}
+
+/// `export <db> [--format json|csv|yaml|ndjson|sqlite] [--dir DIR] [--out FILE] [--sort key|size|category|timestamp]
+/// [--since WHEN] [--until WHEN] [--fields SPEC]`: the metadata together with every entry
+///
+/// `--fields` selects the metadata as for `parse`, and the entries with
+/// `entries` or `entries.FIELD`.
+///
+/// `ndjson` writes each entry as it is read and leaves the metadata out, so
+/// memory use stays bounded however large the profile: the database is opened
+/// without reading the raw files for the login history, and entries stay in
+/// key order. `--since` and `--until` still read the sequence number of every
+/// key, but no values.
+pub fn export(args: cli::ExportArgs) -> Result<()> {
+    let _span = tracing::info_span!("export").entered();
+    let db_path = args.db.path()?;
+    if args.dir.is_some() && args.format != ExportFormat::Csv {
+        bail!("--dir writes the files of --format csv");
+    }
+    if args.format.writes_file() && args.out.is_none() && args.template.is_none() {
+        let format = args
+            .format
+            .to_possible_value()
+            .expect("no variant is skipped");
+        bail!(
+            "--format {} writes the file given with --out FILE",
+            format.get_name()
+        );
+    }
+    if args.out.is_some() && !args.format.writes_file() {
+        bail!(
+            "--out needs --format sqlite or parquet; builds without them need --features sqlite \
+             or --features parquet"
+        );
+    }
+    if args.format == ExportFormat::Ndjson && args.template.is_none() && args.sort != SortOrder::Key
+    {
+        bail!("--format ndjson streams in key order; --sort would hold every entry in memory");
+    }
+    let binary = matches!(args.format, ExportFormat::Cbor | ExportFormat::Msgpack);
+    let fields = args
+        .fields
+        .as_deref()
+        .map(FieldSelection::parse)
+        .transpose()?;
+    let entry_fields = fields.as_ref().and_then(|fields| fields.below("entries"));
+    if fields.is_some() && (args.format.writes_file() || args.format.is_timeline()) {
+        bail!("--fields selects fields of json, yaml, xml, csv, ndjson, cbor and msgpack");
+    }
+    if entry_fields.is_some()
+        && args.template.is_none()
+        && (binary || args.format == ExportFormat::Xml)
+    {
+        bail!("--fields selects fields of entries only in json, yaml, csv and ndjson");
+    }
+    if binary && args.template.is_none() && output::is_terminal() {
+        bail!("--format cbor and msgpack are binary; redirect them or pass --output FILE");
+    }
+    let range = args.time_range.range()?;
+    let sequences = if range.has_sequence() || args.format.is_timeline() {
+        let (live, issues) = provenance::live_provenance(db_path)?;
+        for issue in &issues {
+            eprintln!("{}", tr!("warning", message = issue.to_string()));
+        }
+        status::damaged(&issues);
+        live
+    } else {
+        Default::default()
+    };
+    // Read before opening, which may compact the log into a new table
+    let file_times = if args.format.is_timeline() {
+        timeline::file_times(db_path, &sequences)
+    } else {
+        Default::default()
+    };
+    let streamed = args.format == ExportFormat::Ndjson && args.template.is_none();
+    let parser = ElementLevelDBParser::builder()
+        .login_history(!streamed)
+        .progress(progress_bar())
+        .open(db_path)?;
+    let in_range = |entry: &Entry| {
+        range.contains_sequence(sequences.get(entry.key.as_ref()).map(|p| p.sequence))
+    };
+
+    if streamed {
+        let entries = parser.entries()?.filter(in_range);
+        let mut out = std::io::BufWriter::new(output::Writer);
+        let mut written = 0;
+        for entry in entries {
+            let record = EntryRecord::from(&entry);
+            match &entry_fields {
+                Some(fields) => serde_json::to_writer(&mut out, &fields.select(&record)?)?,
+                None => serde_json::to_writer(&mut out, &record)?,
+            }
+            out.write_all(b"\n")?;
+            written += 1;
+        }
+        out.flush()?;
+        status::damaged(parser.issues());
+        tracing::info!(entries = written, "exported");
+        return Ok(());
+    }
+
+    let mut metadata = parser.parse_metadata()?;
+    status::found(&metadata);
+    if range.is_set() {
+        range.retain(&mut metadata);
+    }
+    if let Some(fields) = &fields {
+        fields.prune(&mut metadata);
+    }
+    let with_entries = fields.as_ref().is_none_or(|fields| fields.keeps("entries"));
+    let entries: Vec<Entry> = if with_entries {
+        sort_entries(parser.entries()?.filter(in_range), args.sort, db_path)?.collect()
+    } else {
+        Vec::new()
+    };
+    let export = export::Export {
+        metadata,
+        entries: entries.iter().map(EntryRecord::from).collect(),
+    };
+    status::damaged(parser.issues());
+    tracing::info!(entries = export.entries.len(), "exported");
+    if let Some(template) = &args.template {
+        match &fields {
+            Some(fields) => out!(
+                "{}",
+                template::render(template, &selected(&export, fields)?)?
+            )?,
+            None => out!("{}", template::render(template, &export)?)?,
+        }
+        return Ok(());
+    }
+    if let Some(dir) = &args.dir {
+        return csv::write_dir(dir, &export, fields.as_ref());
+    }
+    match args.format {
+        ExportFormat::Json | ExportFormat::Yaml => {
+            let yaml = args.format == ExportFormat::Yaml;
+            match &fields {
+                Some(fields) => write_document(&selected(&export, fields)?, yaml)?,
+                None => write_document(&export, yaml)?,
+            }
+        }
+        ExportFormat::Csv => {
+            let columns = |field: &str| entry_fields.as_ref().is_none_or(|f| f.keeps(field));
+            out!("{}", csv::entries(&export.entries, columns))?
+        }
+        ExportFormat::Ndjson => unreachable!("streamed above"),
+        ExportFormat::Xml => {
+            let metadata = match &fields {
+                Some(fields) => fields.select(&export.metadata)?,
+                None => serde_json::to_value(&export.metadata)?,
+            };
+            let records = with_entries.then_some(export.entries.as_slice());
+            out!("{}", xml::document(&metadata, records))?
+        }
+        ExportFormat::L2tcsv | ExportFormat::Bodyfile | ExportFormat::Timesketch => {
+            let indexeddb = sibling_indexeddb(&parser);
+            let mut events = timeline::events(
+                db_path,
+                &export.metadata,
+                &entries,
+                &sequences,
+                &file_times,
+                indexeddb.as_deref().zip(parser.indexeddb()),
+            );
+            range.retain_events(&mut events);
+            let text = match args.format {
+                ExportFormat::L2tcsv => timeline::l2tcsv(&events),
+                ExportFormat::Bodyfile => timeline::bodyfile(&events),
+                _ => timeline::timesketch(&events),
+            };
+            out!("{}", text)?;
+        }
+        ExportFormat::Cbor | ExportFormat::Msgpack => {
+            let records = with_entries.then_some(entries.as_slice());
+            let document = match &fields {
+                Some(fields) => {
+                    binary_document(args.format, &fields.select(&export.metadata)?, records)?
+                }
+                None => binary_document(args.format, &export.metadata, records)?,
+            };
+            output::Writer.write_all(&document)?;
+        }
+        #[cfg(feature = "sqlite")]
+        ExportFormat::Sqlite => {
+            let out = args.out.as_deref().expect("checked above");
+            sqlite::write(out, &export, parser.indexeddb())?;
+        }
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => {
+            parquet::write(args.out.as_deref().expect("checked above"), &export)?
+        }
+    }
+    Ok(())
+}
+
+/// The export with only the fields `fields` selects
+fn selected(export: &Export, fields: &FieldSelection) -> Result<Value> {
+    let mut document = serde_json::Map::new();
+    document.insert("metadata".into(), fields.select(&export.metadata)?);
+    if fields.keeps("entries") {
+        let entry_fields = fields.below("entries");
+        let entries = export
+            .entries
+            .iter()
+            .map(|record| match &entry_fields {
+                Some(fields) => fields.select(record),
+                None => Ok(serde_json::to_value(record)?),
+            })
+            .collect::<Result<_>>()?;
+        document.insert("entries".into(), Value::Array(entries));
+    }
+    Ok(Value::Object(document))
+}
+
+/// `export --format cbor` or `msgpack`
+fn binary_document(
+    format: ExportFormat,
+    metadata: &impl Serialize,
+    entries: Option<&[Entry]>,
+) -> Result<Vec<u8>> {
+    if format == ExportFormat::Cbor {
+        binary::cbor(metadata, entries)
+    } else {
+        binary::msgpack(metadata, entries)
+    }
+}
+
+/// `timeline <db> [--indexeddb DIR] [--room ROOM_ID] [--since WHEN] [--until WHEN] [--format text|json]`:
+/// cached timeline events
+pub fn timeline(args: cli::TimelineArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+
+    let dir = match args.indexeddb {
+        Some(dir) => Some(dir),
+        None => sibling_indexeddb(&ElementLevelDBParser::open(db_path)?),
+    };
+    let dir = dir.ok_or_else(|| {
+        anyhow!(
+            "No IndexedDB directory next to {}; pass --indexeddb",
+            db_path.display()
+        )
+    })?;
+    let range = args.time_range.range()?;
+    let mut timelines: Vec<_> = sync::cached_timelines(&Origin::read_directory(dir)?)
+        .into_iter()
+        .filter(|t| args.room.as_ref().is_none_or(|room| &t.room_id == room))
+        .collect();
+    for timeline in &mut timelines {
+        timeline
+            .events
+            .retain(|event| range.contains_time(event.origin_server_ts.map(|ts| ts as i64)));
+    }
+
+    if args.format == Format::Json {
+        let report = serde_json::json!({
+            "note": sync::PARTIAL_CACHE_NOTE,
+            "rooms": timelines,
+        });
+        outln!("{}", serde_json::to_string_pretty(&report)?)?;
+        return Ok(());
+    }
+
+    outln!("{}", tr!("timeline-partial"))?;
+    let placeholder = i18n::datetime(0).map_or(19, |t| t.chars().count());
+    for timeline in &timelines {
+        outln!(
+            "\n{}  {}",
+            timeline.name.as_deref().unwrap_or(&timeline.room_id),
+            tr!(
+                "timeline-room",
+                events = timeline.events.len(),
+                limited = timeline.limited.to_string(),
+            )
+        )?;
+        for event in &timeline.events {
+            let time = event
+                .origin_server_ts
+                .and_then(|ts| i18n::datetime(ts as i64))
+                .unwrap_or_else(|| "-".repeat(placeholder));
+            let text = if event.encrypted {
+                tr!("timeline-encrypted")
+            } else {
+                event
+                    .content
+                    .as_ref()
+                    .and_then(|c| c["body"].as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("[{}]", event.event_type))
+            };
+            outln!(
+                "  {}  {}  {}",
+                time,
+                event.sender.as_deref().unwrap_or("?"),
+                text
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,464 @@
+//! Commands that read the database and print what is in it
+
+use super::{progress_bar, sort_entries, write_document};
+use crate::cli::{self, Format, ParseFormat, SchemaDocument, SortOrder};
+use crate::i18n::tr;
+use crate::output::{self, out, outln};
+use crate::{status, table, template, unified};
+use anyhow::{anyhow, bail, Result};
+use element_desktop_leveldb::export::{EntryRecord, KeyRecord};
+use element_desktop_leveldb::fields::FieldSelection;
+use element_desktop_leveldb::indexeddb::checkpoint;
+use element_desktop_leveldb::raw::provenance;
+use element_desktop_leveldb::stats::Statistics;
+use element_desktop_leveldb::{changeset, clone, export, preview, search};
+use element_desktop_leveldb::{ElementLevelDBParser, ElementMetadata, Entries, Entry};
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::ops::Bound;
+
+/// `decode-indexeddb <indexeddb-dir> <out-dir> [--resume] [--chunk N]`
+///
+/// Decodes every object store into JSON lines, checkpointing after each store
+pub fn decode_indexeddb(args: cli::DecodeIndexeddbArgs) -> Result<()> {
+    let chunk = args
+        .chunk
+        .map_or(checkpoint::DEFAULT_CHUNK_RECORDS, NonZeroUsize::get);
+    let result =
+        checkpoint::decode_directory(&args.src, &args.out, args.resume, chunk, |state, store| {
+            match store {
+                None if state.resumes > 0 => {
+                    eprintln!("{}", tr!("decode-resumed", stores = state.stores.len()))
+                }
+                None => {}
+                Some(store) => eprintln!(
+                    "{}",
+                    tr!(
+                        "decode-store",
+                        database = store.database.as_str(),
+                        store = store.store.as_str(),
+                        records = store.records,
+                    )
+                ),
+            }
+            Ok(())
+        })?;
+    for issue in &result.issues {
+        eprintln!("{}", tr!("warning", message = issue.to_string()));
+    }
+    outln!(
+        "{}",
+        tr!(
+            "decode-finished",
+            stores = result.stores.len(),
+            records = result.stores.iter().map(|s| s.records).sum::<usize>(),
+            dest = args.out.display().to_string(),
+        )
+    )?;
+    Ok(())
+}
+
+/// `diff <old-db> <new-db> [--unified [--context N] | --summary | --no-values]`
+pub fn diff(args: cli::DiffArgs) -> Result<()> {
+    let old = clone::read_live_entries(&args.old)?;
+    let new = clone::read_live_entries(&args.new)?;
+    let set = changeset::diff(&old, &new);
+    if args.unified {
+        out!("{}", unified::render(&set, args.context))?;
+    } else if args.summary {
+        out!("{}", unified::summary(&set))?;
+    } else if args.no_values {
+        outln!("{}", serde_json::to_string_pretty(&set.without_values())?)?;
+    } else {
+        outln!("{}", serde_json::to_string_pretty(&set)?)?;
+    }
+    Ok(())
+}
+
+/// `parse <db> [--fields SPEC] [--provenance] [--threads N] [--since WHEN] [--until WHEN]
+/// [--format text|json|yaml] [--template FILE]`:
+/// prints the extracted metadata as tables at a terminal, and otherwise as
+/// JSON (or YAML) and nothing else
+pub fn parse(args: cli::ParseArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+    let fields = args
+        .fields
+        .as_deref()
+        .map(FieldSelection::parse)
+        .transpose()?;
+    if fields.is_some() && args.format == Some(ParseFormat::Text) {
+        bail!("--fields selects fields of the JSON or YAML report; pass --format json or yaml");
+    }
+    // Selected fields and provenance are never tables
+    let tables = args.template.is_none()
+        && fields.is_none()
+        && !args.provenance
+        && args
+            .format
+            .map_or_else(output::is_terminal, |format| format == ParseFormat::Text);
+    let yaml = args.format == Some(ParseFormat::Yaml);
+
+    let range = args.time_range.range()?;
+
+    // Located before opening, which may compact the log into a new table
+    let provenance = if args.provenance || range.has_sequence() {
+        let (live, issues) = provenance::live_provenance(db_path)?;
+        for issue in &issues {
+            eprintln!("{}", tr!("warning", message = issue.to_string()));
+        }
+        status::damaged(&issues);
+        Some(live)
+    } else {
+        None
+    };
+    let mut builder = ElementLevelDBParser::builder()
+        .login_history(true)
+        .progress(progress_bar());
+    if let Some(threads) = args.threads {
+        builder = builder.threads(threads);
+    }
+    let parser = builder.open(db_path)?;
+    let mut metadata = parser.parse_metadata()?;
+    status::found(&metadata);
+    status::damaged(parser.issues());
+    if range.is_set() {
+        range.retain(&mut metadata);
+    }
+    if let Some(live) = &provenance
+        && range.has_sequence()
+    {
+        metadata
+            .raw_entries
+            .retain(|key, _| range.contains_sequence(live.get(key.as_bytes()).map(|p| p.sequence)));
+    }
+    metadata.provenance = provenance.filter(|_| args.provenance).map(|live| {
+        live.into_iter()
+            .filter_map(|(key, p)| Some((String::from_utf8(key).ok()?, p)))
+            .filter(|(key, _)| metadata.raw_entries.contains_key(key))
+            .collect()
+    });
+    if let Some(fields) = &fields {
+        fields.prune(&mut metadata);
+    }
+    if let Some(template) = &args.template {
+        let report = match &fields {
+            Some(fields) => template::render(template, &fields.select(&metadata)?)?,
+            None => template::render(template, &metadata)?,
+        };
+        out!("{}", report)?;
+        return Ok(());
+    }
+    match fields {
+        _ if tables => {
+            let tables: Vec<String> = table::metadata(&metadata)
+                .iter()
+                .map(table::Table::render)
+                .collect();
+            out!("{}", tables.join("\n"))?;
+        }
+        Some(fields) => write_document(&fields.select(&metadata)?, yaml)?,
+        None => write_document(&metadata, yaml)?,
+    }
+    Ok(())
+}
+
+/// `get <key> <db> [--format text|json]`: the value of one item
+pub fn get(args: cli::GetArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+    let parser = ElementLevelDBParser::open(db_path)?;
+    let Some(entry) = parser.entries()?.find(|e| export::is_key(e, &args.key)) else {
+        bail!("No item {} in {}", args.key, db_path.display());
+    };
+    let record = EntryRecord::from(&entry);
+    match args.format {
+        Format::Text => outln!("{}", record.value)?,
+        Format::Json => outln!("{}", serde_json::to_string_pretty(&record)?)?,
+    }
+    Ok(())
+}
+
+/// `show <key> <db> [--hex]`: the value decoded, or with `--hex` the raw
+/// bytes as offset, hex and ASCII columns, after a line with the key, size
+/// and guessed encoding
+pub fn show(args: cli::ShowArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+    let parser = ElementLevelDBParser::open(db_path)?;
+    let Some(entry) = parser.entries()?.find(|e| export::is_key(e, &args.key)) else {
+        bail!("No item {} in {}", args.key, db_path.display());
+    };
+    let decoded = preview::decode(&entry);
+    outln!(
+        "{}  {} bytes, {}",
+        export::key_label(&entry.key),
+        entry.value.len(),
+        decoded.label()
+    )?;
+    if args.hex {
+        out!("{}", preview::hexdump(&entry.value))?;
+    } else {
+        outln!("{}", decoded.body.trim_end_matches('\n'))?;
+    }
+    Ok(())
+}
+
+/// `list-keys <db> [--prefix P] [--regex PATTERN] [--origin O] [--long] [--format text|json]
+/// [--sort key|size|category|timestamp] [--limit N] [--offset N] [--after CURSOR]`
+///
+/// Every key, in key order unless `--sort` says otherwise. Filters apply to the item name of Local Storage
+/// items and to the whole key of other entries.
+pub fn list_keys(args: cli::ListKeysArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+    let parser = ElementLevelDBParser::open(db_path)?;
+    let keys = page_entries(&parser, &args.page, args.sort)?.filter(|entry| {
+        let name = export::item_name(entry);
+        args.prefix
+            .as_ref()
+            .is_none_or(|p| name.starts_with(p.as_str()))
+            && args.regex.as_ref().is_none_or(|r| r.is_match(&name))
+            && args
+                .origin
+                .as_ref()
+                .is_none_or(|o| export::origin(entry).as_ref() == Some(o))
+    });
+    let keys = sort_entries(keys, args.sort, db_path)?;
+    let mut keys = Page::new(keys, &args.page, args.sort);
+    match args.format {
+        Format::Text => {
+            for entry in keys.by_ref() {
+                let key = export::key_label(&entry.key);
+                if args.long {
+                    let value_type = export::value_type(&entry);
+                    outln!("{:>9}  {:<6}  {}", entry.value.len(), value_type, key)?;
+                } else {
+                    outln!("{}", key)?;
+                }
+            }
+        }
+        Format::Json => {
+            let records: Vec<_> = keys.by_ref().map(|entry| KeyRecord::from(&entry)).collect();
+            outln!("{}", serde_json::to_string_pretty(&records)?)?;
+        }
+    }
+    keys.finish();
+    Ok(())
+}
+
+/// The entries of `parser` after the `--after` cursor, or all of them
+///
+/// The cursor is a raw key, so the listing seeks straight to it instead of
+/// reading the pages before. That only works in key order.
+fn page_entries(
+    parser: &ElementLevelDBParser,
+    page: &cli::PageArgs,
+    sort: SortOrder,
+) -> Result<Entries> {
+    match &page.after {
+        Some(_) if sort != SortOrder::Key => {
+            bail!("--after pages in key order; use --offset with --sort")
+        }
+        Some(cursor) => {
+            let key = hex::decode(cursor)
+                .map_err(|_| anyhow!("--after takes a hex-encoded raw key, not {}", cursor))?;
+            Ok(parser.range((Bound::Excluded(key), Bound::Unbounded))?)
+        }
+        None => Ok(parser.entries()?),
+    }
+}
+
+/// `--offset` and `--limit` over a listing
+struct Page<I: Iterator<Item = Entry>> {
+    entries: std::iter::Peekable<std::iter::Skip<I>>,
+    remaining: Option<usize>,
+    last: Option<Vec<u8>>,
+    /// Offset of the next page
+    next_offset: usize,
+    sort: SortOrder,
+}
+
+impl<I: Iterator<Item = Entry>> Page<I> {
+    fn new(entries: I, page: &cli::PageArgs, sort: SortOrder) -> Self {
+        Page {
+            entries: entries.skip(page.offset).peekable(),
+            remaining: page.limit,
+            last: None,
+            next_offset: page.offset,
+            sort,
+        }
+    }
+
+    /// Tells how to continue when `--limit` left entries out: after the last
+    /// key in key order, else at the next offset
+    fn finish(mut self) {
+        if self.remaining != Some(0) || self.entries.peek().is_none() {
+            return;
+        }
+        if self.sort != SortOrder::Key {
+            eprintln!("{}", tr!("page-more-offset", offset = self.next_offset));
+        } else if let Some(last) = &self.last {
+            eprintln!("{}", tr!("page-more", cursor = hex::encode(last)));
+        }
+    }
+}
+
+impl<I: Iterator<Item = Entry>> Iterator for Page<I> {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Entry> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+        let entry = self.entries.next()?;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+        }
+        self.last = Some(entry.key.to_vec());
+        self.next_offset += 1;
+        Some(entry)
+    }
+}
+
+/// `dump <db> (--key K | --all [--chunk N] [--sort key|size|category|timestamp] [--limit N]
+/// [--offset N] [--after CURSOR]) [--encoding hex|base64] [--format text|json]`
+///
+/// Raw keys and values, both encoded, one entry per line. `--all` streams the
+/// database and writes `--chunk` entries at a time, so memory use stays
+/// bounded however large the profile, unless `--sort` needs every entry.
+pub fn dump(args: cli::DumpArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+    let parser = ElementLevelDBParser::open(db_path)?;
+    let mut out = std::io::BufWriter::new(output::Writer);
+    if let Some(key) = &args.key {
+        if args.page.is_set() || args.sort != SortOrder::Key {
+            bail!("--sort, --limit, --offset and --after apply to --all");
+        }
+        let Some(entry) = parser.entries()?.find(|e| export::is_key(e, key)) else {
+            bail!("No item {} in {}", key, db_path.display());
+        };
+        dump_entry(&mut out, &entry, &args)?;
+    } else {
+        let entries = page_entries(&parser, &args.page, args.sort)?;
+        let entries = sort_entries(entries, args.sort, db_path)?;
+        let mut entries = Page::new(entries, &args.page, args.sort);
+        loop {
+            let mut written = 0;
+            for entry in entries.by_ref().take(args.chunk.get()) {
+                dump_entry(&mut out, &entry, &args)?;
+                written += 1;
+            }
+            out.flush()?;
+            if written < args.chunk.get() {
+                break;
+            }
+        }
+        entries.finish();
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn dump_entry(out: &mut impl Write, entry: &Entry, args: &cli::DumpArgs) -> Result<()> {
+    let key = export::encode(&entry.key, args.encoding);
+    let value = export::encode(&entry.value, args.encoding);
+    match args.format {
+        Format::Text => writeln!(out, "{}\t{}", key, value)?,
+        Format::Json => writeln!(out, "{}", serde_json::json!({ "key": key, "value": value }))?,
+    }
+    Ok(())
+}
+
+/// `search <pattern> <db> [--regex] [--ignore-case] [--binary] [--context N] [--format text|json]`
+///
+/// Occurrences in the key labels and value texts, with context; values
+/// without text are only found by key unless `--binary`
+pub fn search(args: cli::SearchArgs) -> Result<()> {
+    let matcher = search::Matcher::new(&args.pattern, args.regex, args.ignore_case, args.binary)?;
+
+    let parser = ElementLevelDBParser::open(args.db.path()?)?;
+    let hits = parser
+        .entries()?
+        .filter_map(|entry| search::search(&entry, &matcher, args.context));
+    match args.format {
+        Format::Text => {
+            let color = output::color();
+            for hit in hits {
+                outln!("{}", search::render(&hit, color))?;
+            }
+        }
+        Format::Json => {
+            let hits: Vec<_> = hits.collect();
+            outln!("{}", serde_json::to_string_pretty(&hits)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// `schema [metadata|export|entry]`: the JSON Schema downstream parsers validate against
+pub fn schema(args: cli::SchemaArgs) -> Result<()> {
+    let schema = match args.document {
+        SchemaDocument::Metadata => schemars::schema_for!(ElementMetadata),
+        SchemaDocument::Export => schemars::schema_for!(export::Export),
+        SchemaDocument::Entry => schemars::schema_for!(EntryRecord),
+    };
+    outln!("{}", serde_json::to_string_pretty(&schema)?)?;
+    Ok(())
+}
+
+/// `stats <db> [--format text|json]`
+pub fn stats(args: cli::StatsArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+    let entries = clone::read_live_entries(db_path)?;
+    let stats = Statistics::compute(entries.iter().map(|(k, v)| (k.as_slice(), v.as_slice())));
+
+    if args.format == Format::Json {
+        outln!("{}", serde_json::to_string_pretty(&stats)?)?;
+        return Ok(());
+    }
+
+    let rows = [
+        (tr!("stats-entries"), stats.entries.to_string()),
+        (tr!("stats-key-bytes"), stats.key_bytes.to_string()),
+        (tr!("stats-value-bytes"), stats.value_bytes.to_string()),
+        (tr!("stats-total-bytes"), stats.total_bytes().to_string()),
+        (
+            tr!("stats-binary"),
+            format!(
+                "{} ({:.1}%)",
+                stats.binary_values,
+                stats.binary_ratio() * 100.0
+            ),
+        ),
+    ];
+    let width = rows.iter().map(|(label, _)| label.chars().count()).max();
+    for (label, value) in &rows {
+        let label = format!("{}:", label);
+        outln!(
+            "{:<w$}  {}",
+            label,
+            value,
+            w = width.unwrap_or_default() + 1
+        )?;
+    }
+    outln!("\n{}:", tr!("stats-categories"))?;
+    for (category, count) in &stats.categories {
+        outln!("  {:>6}  {}", count, category.as_str())?;
+    }
+    outln!("\n{}:", tr!("stats-origins"))?;
+    for (origin, count) in &stats.origins {
+        outln!("  {:>6}  {}", count, origin)?;
+    }
+    outln!("\n{}:", tr!("stats-namespaces"))?;
+    let mut namespaces: Vec<_> = stats.namespaces.iter().collect();
+    namespaces.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    for (namespace, count) in namespaces {
+        outln!("  {:>6}  {}", count, namespace)?;
+    }
+    outln!("\n{}:", tr!("stats-value-sizes"))?;
+    for (bound, count) in &stats.value_sizes {
+        outln!("  <= {:>8} B  {}", bound, count)?;
+    }
+    outln!("\n{}:", tr!("stats-largest"))?;
+    for value in &stats.largest_values {
+        outln!("  {:>8} B  {}", value.bytes, value.key.escape_debug())?;
+    }
+    Ok(())
+}
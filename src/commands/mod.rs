@@ -0,0 +1,155 @@
+//! The subcommands of the binary, one function per command
+//!
+//! `main` parses the command line and calls the function of the subcommand;
+//! each takes its `cli` arguments and writes with `out!` and `outln!`.
+
+pub mod extract;
+pub mod inspect;
+pub mod modify;
+pub mod monitoring;
+pub mod online;
+pub mod security;
+pub mod tooling;
+
+use crate::cli::SortOrder;
+use crate::i18n::tr;
+use crate::logging;
+use crate::output::{self, out, outln};
+use anyhow::Result;
+use element_desktop_leveldb::progress::{Progress, Stage};
+use element_desktop_leveldb::query::Query;
+use element_desktop_leveldb::raw::provenance;
+use element_desktop_leveldb::{canonical, export};
+use element_desktop_leveldb::{ElementLevelDBParser, Entry};
+use indicatif::{HumanBytes, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// What happens to the output of a command once it has run: printed as is,
+/// or captured and passed through `--query` or `--canonical`
+pub struct Capture {
+    query: Option<Query>,
+    canonical: bool,
+}
+
+impl Capture {
+    /// Starts capturing the output when there is a query or `--canonical`
+    pub fn start(query: Option<Query>, canonical: bool) -> Self {
+        if query.is_some() || canonical {
+            output::capture();
+        }
+        Capture { query, canonical }
+    }
+
+    /// Writes the results of the command and commits the output file, or
+    /// abandons it when the command failed
+    pub fn finish(self, result: Result<()>) -> Result<()> {
+        let result = result.and_then(|()| match &self.query {
+            Some(query) => {
+                let results = query.run(&output::take_captured())?;
+                Ok(out!("{}", results)?)
+            }
+            None if self.canonical => {
+                let document = canonical::run(&output::take_captured())?;
+                Ok(output::Writer.write_all(&document)?)
+            }
+            None => Ok(()),
+        });
+        match result {
+            Ok(()) => output::finish(),
+            Err(e) => {
+                output::abandon();
+                Err(e)
+            }
+        }
+    }
+}
+
+/// `document` as pretty JSON, or as YAML
+pub fn write_document(document: &impl serde::Serialize, yaml: bool) -> Result<()> {
+    if yaml {
+        out!("{}", serde_yaml::to_string(document)?)?;
+    } else {
+        outln!("{}", serde_json::to_string_pretty(document)?)?;
+    }
+    Ok(())
+}
+
+/// A progress bar for `parse_metadata` on standard error, drawn only when
+/// that is a terminal and not `--quiet`
+pub fn progress_bar() -> impl Fn(&Progress) + Send + Sync + 'static {
+    let target = if logging::quiet() {
+        ProgressDrawTarget::hidden()
+    } else {
+        ProgressDrawTarget::stderr()
+    };
+    let bar = ProgressBar::with_draw_target(None, target);
+    move |progress: &Progress| {
+        let bytes = HumanBytes(progress.bytes).to_string();
+        match (progress.stage, progress.total_entries) {
+            (Stage::Extracting, Some(total)) => {
+                bar.set_message(tr!(
+                    "progress-extracting",
+                    entries = progress.entries,
+                    total = total,
+                    bytes = bytes
+                ));
+                if bar.length() != Some(total) {
+                    bar.set_style(ProgressStyle::with_template("{bar:40} {msg}").unwrap());
+                    bar.set_length(total);
+                }
+                bar.set_position(progress.entries);
+                if progress.entries == total {
+                    bar.finish_and_clear();
+                }
+            }
+            _ => {
+                bar.set_style(ProgressStyle::with_template("{spinner} {msg}").unwrap());
+                bar.set_message(tr!(
+                    "progress-reading",
+                    entries = progress.entries,
+                    bytes = bytes
+                ));
+                bar.tick();
+            }
+        }
+    }
+}
+
+/// `entries` in `--sort` order
+///
+/// Key order is the database's own and streams; the other orders hold every
+/// entry in memory, and `timestamp` also reads the sequence numbers from the
+/// database files.
+pub fn sort_entries<'a>(
+    entries: impl Iterator<Item = Entry> + 'a,
+    sort: SortOrder,
+    db_path: &Path,
+) -> Result<Box<dyn Iterator<Item = Entry> + 'a>> {
+    if sort == SortOrder::Key {
+        return Ok(Box::new(entries));
+    }
+    let provenance = if sort == SortOrder::Timestamp {
+        let (live, issues) = provenance::live_provenance(db_path)?;
+        for issue in &issues {
+            tracing::warn!(%issue, "reading sequence numbers");
+        }
+        live
+    } else {
+        Default::default()
+    };
+    let mut entries: Vec<Entry> = entries.collect();
+    export::sort(&mut entries, sort, &provenance);
+    Ok(Box::new(entries.into_iter()))
+}
+
+/// Element's IndexedDB directory next to the `<profile>/Local Storage/leveldb`
+/// directory `parser` reads, for the Desktop or Web origin found in it
+pub fn sibling_indexeddb(parser: &ElementLevelDBParser) -> Option<PathBuf> {
+    parser.layout().indexeddb_dir(parser.path())
+}
+
+/// Homeserver URL stored in the database `parser` reads (`mx_hs_url`)
+pub fn stored_homeserver(parser: &ElementLevelDBParser) -> Option<String> {
+    parser.get_as("mx_hs_url").ok().flatten()
+}
@@ -0,0 +1,76 @@
+//! Commands that write to a database: `apply` and `clone`
+
+use crate::cli;
+use crate::i18n::tr;
+use crate::output::outln;
+use crate::status;
+use anyhow::Result;
+use element_desktop_leveldb::{audit, changeset, clone};
+use rusty_leveldb::{Options, DB};
+
+/// `apply <db> <changes.json> [--dry-run] [--force]`
+pub fn apply(args: cli::ApplyArgs) -> Result<()> {
+    let dry_run = args.dry_run;
+    let db_path = args.db.display().to_string();
+    let set = changeset::ChangeSet::load(&args.changes)?;
+    let options = Options {
+        create_if_missing: false,
+        ..Options::default()
+    };
+    let mut db = DB::open(&args.db, options)?;
+    let report = changeset::apply(&mut db, &set, args.force, dry_run)?;
+
+    for conflict in &report.conflicts {
+        let message = tr!("apply-forced-conflict", conflict = conflict.to_string());
+        eprintln!("{}", tr!("warning", message = message));
+    }
+    outln!(
+        "{}",
+        tr!(
+            "apply-summary",
+            dry_run = dry_run.to_string(),
+            written = report.written,
+            deleted = report.deleted,
+            path = db_path.as_str(),
+        )
+    )?;
+    Ok(())
+}
+
+/// `clone <src> <dest> [--repair [--resume]] [--audit-log FILE]`
+pub fn clone(args: cli::CloneArgs) -> Result<()> {
+    let repair = args.repair;
+    let dest = args.dest.display().to_string();
+    let mut audit = args.audit_log.map(audit::AuditLog::open).transpose()?;
+
+    let report = clone::clone_database(&args.src, &args.dest, repair, args.resume, audit.as_mut())?;
+    if report.resumed {
+        eprintln!("{}", tr!("clone-resumed"));
+    }
+    for issue in &report.issues {
+        eprintln!("{}", tr!("warning", message = issue.to_string()));
+    }
+    status::damaged(&report.issues);
+    if repair {
+        outln!(
+            "{}",
+            tr!(
+                "clone-repaired",
+                entries = report.entries,
+                files = report.files_read,
+                dest = dest.as_str(),
+                skipped = report.issues.len(),
+            )
+        )?;
+    } else {
+        outln!(
+            "{}",
+            tr!(
+                "clone-copied",
+                entries = report.entries,
+                dest = dest.as_str()
+            )
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! Commands that follow a database as Element writes to it
+
+use crate::cli;
+use crate::i18n::tr;
+use crate::output::{out, outln};
+use anyhow::Result;
+use element_desktop_leveldb::findings::{self, Scanner};
+use element_desktop_leveldb::{clone, live, monitor, watch};
+use element_desktop_leveldb::{openmetrics, ElementLevelDBParser};
+
+/// `live [--port N] [--origin URL]`: dumps storage from a running client over CDP
+pub fn live(args: cli::LiveArgs) -> Result<()> {
+    let dump = live::dump(args.port, &args.origin)?;
+    outln!("{}", serde_json::to_string_pretty(&dump)?)?;
+    Ok(())
+}
+
+/// `watch <db> [--metadata]`: prints one JSON change event per line as the database
+/// changes, or with `--metadata` one line per metadata field that differs
+pub fn watch(args: cli::WatchArgs) -> Result<()> {
+    let dir = args.db.path()?;
+    if args.metadata {
+        let state = watch::metadata(dir)?;
+        eprintln!(
+            "{}",
+            tr!(
+                "watching-metadata",
+                path = dir.display().to_string(),
+                rooms = state.rooms.len()
+            )
+        );
+        return watch::watch_metadata(
+            dir,
+            state,
+            |deltas| {
+                for delta in deltas {
+                    outln!("{}", serde_json::to_string(&delta)?)?;
+                }
+                Ok(())
+            },
+            |issue| eprintln!("{}", tr!("warning", message = issue.to_string())),
+        );
+    }
+
+    let (state, issues) = watch::IncrementalState::baseline(dir)?;
+    for issue in &issues {
+        eprintln!("{}", tr!("warning", message = issue.to_string()));
+    }
+    eprintln!(
+        "{}",
+        tr!(
+            "watching",
+            path = dir.display().to_string(),
+            keys = state.live_keys()
+        )
+    );
+
+    watch::watch(
+        dir,
+        state,
+        |event| {
+            outln!("{}", serde_json::to_string(&event)?)?;
+            Ok(())
+        },
+        |issue| eprintln!("{}", tr!("warning", message = issue.to_string())),
+    )
+}
+
+/// `monitor <db> [--webhook URL]`: reports device, token and room changes as they happen
+///
+/// Notifications are printed as JSON lines and, with `--webhook`, POSTed there
+pub fn monitor(args: cli::MonitorArgs) -> Result<()> {
+    let dir = args.db.path()?;
+
+    let state = monitor::Snapshot::read(dir)?;
+    eprintln!(
+        "{}",
+        tr!(
+            "monitoring",
+            path = dir.display().to_string(),
+            devices = state.devices.len(),
+            rooms = state.joined_rooms.len(),
+        )
+    );
+
+    monitor::monitor(
+        dir,
+        state,
+        |notifications| {
+            for notification in &notifications {
+                outln!("{}", serde_json::to_string(notification)?)?;
+                // An unreachable webhook must not stop monitoring
+                if let Some(url) = &args.webhook
+                    && let Err(e) = monitor::post(url, notification)
+                {
+                    eprintln!("{}", tr!("warning", message = format!("{}: {}", url, e)));
+                }
+            }
+            Ok(())
+        },
+        |issue| eprintln!("{}", tr!("warning", message = issue)),
+    )
+}
+
+/// `metrics <db>... [--out FILE]`: OpenMetrics summary of one or more profiles
+pub fn metrics(args: cli::MetricsArgs) -> Result<()> {
+    let scanner = Scanner::new(findings::builtin_rules())?;
+    let mut summaries = Vec::new();
+    for db_path in &args.dbs {
+        let metadata = ElementLevelDBParser::builder()
+            .login_history(true)
+            .open(db_path)?
+            .parse_metadata()?;
+        let entries = clone::read_live_entries(db_path)?;
+        let mut by_severity = std::collections::BTreeMap::new();
+        for finding in scanner.scan(entries.iter().map(|(k, v)| (k.as_slice(), v.as_slice()))) {
+            *by_severity.entry(finding.severity).or_default() += 1;
+        }
+        summaries.push(openmetrics::ProfileSummary {
+            profile: db_path.display().to_string(),
+            layout: metadata.layout,
+            entries: entries.len(),
+            rooms: metadata.rooms.len(),
+            encrypted_rooms: metadata
+                .rooms
+                .iter()
+                .filter(|r| r.encrypted == Some(true))
+                .count(),
+            devices: metadata.devices.len(),
+            login_sessions: metadata.login_history.len(),
+            findings: by_severity,
+        });
+    }
+
+    let text = openmetrics::render(&summaries);
+    match args.out {
+        // Written aside and renamed so a textfile collector never reads half a file
+        Some(path) => {
+            let mut tmp = path.clone().into_os_string();
+            tmp.push(".tmp");
+            std::fs::write(&tmp, text)?;
+            std::fs::rename(&tmp, path)?;
+        }
+        None => out!("{}", text)?,
+    }
+    Ok(())
+}
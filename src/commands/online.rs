@@ -0,0 +1,137 @@
+//! Commands that ask the homeserver about what the database mentions
+
+use super::{sibling_indexeddb, stored_homeserver};
+use crate::cli;
+use crate::i18n::tr;
+use crate::output::outln;
+use anyhow::{anyhow, Result};
+use element_desktop_leveldb::indexeddb::reader::Origin;
+use element_desktop_leveldb::{contacts, media, sync, ElementLevelDBParser};
+use element_desktop_leveldb::{enrich, fetch, homeserver};
+
+/// `fetch-media <db> [--out DIR] [--homeserver URL] [--access-token TOKEN]`
+///
+/// The token may also come from `MATRIX_ACCESS_TOKEN`, keeping it out of shell history.
+pub fn fetch_media(args: cli::FetchMediaArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+    let out = args.out;
+
+    let parser = ElementLevelDBParser::open(db_path)?;
+    let mut uris = std::collections::BTreeSet::new();
+    for entry in parser.entries()? {
+        let text = entry
+            .text()
+            .unwrap_or_else(|| String::from_utf8_lossy(&entry.value).into_owned());
+        media::collect_from_text(&text, &mut uris);
+    }
+    if let Some(dir) = sibling_indexeddb(&parser) {
+        for db in Origin::read_directory(dir)?.databases {
+            for record in db.object_stores.values().flatten() {
+                media::collect_from_json(&record.value, &mut uris);
+            }
+        }
+    }
+
+    let homeserver = args
+        .homeserver
+        .homeserver
+        .or_else(|| stored_homeserver(&parser))
+        .ok_or_else(|| anyhow!("No mx_hs_url in the profile; pass --homeserver"))?;
+    let homeserver = homeserver::Homeserver::new(&homeserver, args.homeserver.access_token);
+    let entries = fetch::fetch_all(&uris, &homeserver, &out)?;
+    let failed = entries.iter().filter(|e| e.error.is_some()).count();
+    for entry in entries.iter().filter(|e| e.error.is_some()) {
+        let message = format!(
+            "{}: {}",
+            entry.mxc,
+            entry.error.as_deref().unwrap_or_default()
+        );
+        eprintln!("{}", tr!("warning", message = message));
+    }
+    outln!(
+        "{}",
+        tr!(
+            "fetch-summary",
+            downloaded = entries.len() - failed,
+            total = entries.len(),
+            out = out.display().to_string(),
+            manifest = fetch::MANIFEST_NAME,
+        )
+    )?;
+    Ok(())
+}
+
+/// `enrich-rooms <db> [--homeserver URL] [--access-token TOKEN] [--all] [--directory-pages N]`
+pub fn enrich_rooms(args: cli::EnrichRoomsArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+
+    let parser = ElementLevelDBParser::open(db_path)?;
+    let homeserver = args
+        .homeserver
+        .homeserver
+        .or_else(|| stored_homeserver(&parser))
+        .ok_or_else(|| anyhow!("No mx_hs_url in the profile; pass --homeserver"))?;
+    let dir = sibling_indexeddb(&parser)
+        .ok_or_else(|| anyhow!("No IndexedDB directory next to {}", db_path.display()))?;
+    let rooms = element_desktop_leveldb::sync::cached_rooms(&Origin::read_directory(dir)?);
+
+    let homeserver = homeserver::Homeserver::new(&homeserver, args.homeserver.access_token);
+    let (enriched, issues) =
+        enrich::enrich_rooms(&homeserver, &rooms, args.all, args.directory_pages);
+    for issue in &issues {
+        eprintln!("{}", tr!("warning", message = issue.to_string()));
+    }
+    outln!("{}", serde_json::to_string_pretty(&enriched)?)?;
+    Ok(())
+}
+
+/// `enrich-users <db> [--homeserver URL] [--access-token TOKEN] [--all] [--rate N] [--cache FILE]`
+///
+/// Looks up the current profile of every user ID the caches name without a display name
+pub fn enrich_users(args: cli::EnrichUsersArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+
+    let parser = ElementLevelDBParser::open(db_path)?;
+    let homeserver = args
+        .homeserver
+        .homeserver
+        .or_else(|| stored_homeserver(&parser))
+        .ok_or_else(|| anyhow!("No mx_hs_url in the profile; pass --homeserver"))?;
+    let dir = sibling_indexeddb(&parser)
+        .ok_or_else(|| anyhow!("No IndexedDB directory next to {}", db_path.display()))?;
+    let origin = Origin::read_directory(dir)?;
+
+    // User ID to the display name the caches hold for it, if any
+    let mut users: std::collections::BTreeMap<String, Option<String>> = Default::default();
+    for contact in contacts::dm_contacts(&origin) {
+        users.insert(contact.user_id, contact.display_name);
+    }
+    for timeline in sync::cached_timelines(&origin) {
+        for event in timeline.events {
+            if let Some(sender) = event.sender {
+                users.entry(sender).or_default();
+            }
+            let name = event
+                .content
+                .as_ref()
+                .and_then(|c| c["displayname"].as_str().map(str::to_string));
+            if event.event_type == "m.room.member"
+                && let Some(member) = event.state_key
+            {
+                let known = users.entry(member).or_default();
+                *known = known.take().or(name);
+            }
+        }
+    }
+    for device in element_desktop_leveldb::crypto::cached_devices(&origin) {
+        users.entry(device.user_id).or_default();
+    }
+
+    let mut cache = enrich::ProfileCache::load(args.cache.as_deref())?;
+    let homeserver = homeserver::Homeserver::new(&homeserver, args.homeserver.access_token);
+    let rate = args.rate.unwrap_or(enrich::DEFAULT_PROFILE_RATE);
+    let enriched = enrich::enrich_users(&homeserver, &users, args.all, &mut cache, rate);
+    cache.save()?;
+    outln!("{}", serde_json::to_string_pretty(&enriched)?)?;
+    Ok(())
+}
@@ -0,0 +1,387 @@
+//! Commands that judge a profile: its risk, its crypto store, secrets in
+//! its values and what it shares with other profiles
+
+use super::{sibling_indexeddb, stored_homeserver};
+use crate::cli::{self, Format, ReportFormat, ScanFormat};
+use crate::i18n::tr;
+use crate::output::{out, outln};
+use crate::{i18n, report};
+use anyhow::{anyhow, Result};
+use element_desktop_leveldb::assessment::{self, ExposureLevel};
+use element_desktop_leveldb::export::EntryRecord;
+use element_desktop_leveldb::findings::{self, Scanner};
+use element_desktop_leveldb::indexeddb::reader::Origin;
+use element_desktop_leveldb::layout::Layout;
+use element_desktop_leveldb::posture;
+use element_desktop_leveldb::raw;
+use element_desktop_leveldb::settings::Settings;
+use element_desktop_leveldb::{clone, homeserver, verify};
+use element_desktop_leveldb::{correlation, sarif, sync, ElementLevelDBParser};
+
+/// `correlate <db> <db>... [--format text|json]`: what several profiles have in common
+pub fn correlate(args: cli::CorrelateArgs) -> Result<()> {
+    let mut profiles = Vec::new();
+    for db_path in &args.dbs {
+        let parser = ElementLevelDBParser::builder()
+            .login_history(true)
+            .open(db_path)?;
+        let metadata = parser.parse_metadata()?;
+        profiles.push(correlation::ProfileData {
+            profile: db_path.display().to_string(),
+            user_id: metadata.user_id,
+            rooms: metadata.rooms,
+            devices: metadata.devices,
+            timelines: parser
+                .indexeddb()
+                .map(sync::cached_timelines)
+                .unwrap_or_default(),
+        });
+    }
+    let report = correlation::correlate(&profiles);
+
+    if args.format == Format::Json {
+        outln!("{}", serde_json::to_string_pretty(&report)?)?;
+        return Ok(());
+    }
+
+    // Profiles are numbered in the order given, which keeps the lists short
+    for (i, profile) in report.profiles.iter().enumerate() {
+        outln!("[{}] {}", i + 1, profile)?;
+    }
+    let numbers = |profiles: &std::collections::BTreeSet<String>| {
+        report
+            .profiles
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| profiles.contains(*p))
+            .map(|(i, _)| (i + 1).to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let sections = [
+        (tr!("correlate-accounts"), &report.shared_accounts),
+        (tr!("correlate-rooms"), &report.shared_rooms),
+        (tr!("correlate-devices"), &report.shared_devices),
+    ];
+    for (heading, shared) in sections {
+        outln!("\n{} ({})", heading, shared.len())?;
+        for entry in shared {
+            match &entry.name {
+                Some(name) => outln!("  [{}]  {}  {}", numbers(&entry.profiles), entry.id, name)?,
+                None => outln!("  [{}]  {}", numbers(&entry.profiles), entry.id)?,
+            }
+        }
+    }
+
+    outln!(
+        "\n{} ({})",
+        tr!("correlate-timeline"),
+        report.timeline.len()
+    )?;
+    outln!("{}", tr!("timeline-partial"))?;
+    let placeholder = i18n::datetime(0).map_or(19, |t| t.chars().count());
+    for entry in &report.timeline {
+        let time = entry
+            .origin_server_ts
+            .and_then(|ts| i18n::datetime(ts as i64))
+            .unwrap_or_else(|| "-".repeat(placeholder));
+        let text = if entry.encrypted {
+            tr!("timeline-encrypted")
+        } else {
+            entry
+                .body
+                .clone()
+                .unwrap_or_else(|| format!("[{}]", entry.event_type))
+        };
+        outln!(
+            "  {}  [{}]  {}  {}  {}",
+            time,
+            numbers(&entry.profiles),
+            entry.room_name.as_deref().unwrap_or(&entry.room_id),
+            entry.sender.as_deref().unwrap_or("?"),
+            text
+        )?;
+    }
+    Ok(())
+}
+
+/// `assess <db> [--indexeddb DIR] [--format text|json]`: privacy exposure score
+pub fn assess(args: cli::AssessArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+
+    let scan = raw::scan_directory(db_path)?;
+    let latest = scan.latest();
+    let layout = Layout::detect(latest.iter().map(|(k, v)| (&k[..], &v[..]))).unwrap_or_default();
+    let indexeddb = args.indexeddb.or_else(|| layout.indexeddb_dir(db_path));
+    let origin = indexeddb.map(Origin::read_directory).transpose()?;
+    let report = assessment::assess(&scan, origin.as_ref());
+
+    if args.format == Format::Json {
+        outln!("{}", serde_json::to_string_pretty(&report)?)?;
+        return Ok(());
+    }
+    let level = match report.level {
+        ExposureLevel::Low => tr!("assess-level-low"),
+        ExposureLevel::Moderate => tr!("assess-level-moderate"),
+        ExposureLevel::High => tr!("assess-level-high"),
+    };
+    outln!(
+        "{}",
+        tr!("assess-exposure", score = report.score, level = level)
+    )?;
+    for factor in &report.factors {
+        outln!("\n+{:>3}  {}", factor.points, factor.id)?;
+        outln!("      {}", factor.explanation)?;
+        for evidence in &factor.evidence {
+            outln!("      - {}", evidence)?;
+        }
+    }
+    for skipped in &report.not_assessed {
+        outln!(
+            "\n{}",
+            tr!("assess-not-assessed", reason = skipped.to_string())
+        )?;
+    }
+    Ok(())
+}
+
+/// `analyze-crypto <db> [--indexeddb DIR] [--format text|json]`: end-to-end encryption posture
+pub fn analyze_crypto(args: cli::AnalyzeCryptoArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+
+    let parser = ElementLevelDBParser::open(db_path)?;
+    let dir = args
+        .indexeddb
+        .or_else(|| sibling_indexeddb(&parser))
+        .ok_or_else(|| {
+            anyhow!(
+                "No IndexedDB directory next to {}; pass --indexeddb",
+                db_path.display()
+            )
+        })?;
+    let origin = Origin::read_directory(dir)?;
+    let entries: Vec<_> = parser.entries()?.collect();
+    let settings = Settings::from_entries(
+        entries
+            .iter()
+            .map(|entry| (entry.key.as_ref(), entry.value.as_ref())),
+    );
+    let report = posture::analyze(&origin, Some(&settings));
+
+    if args.format == Format::Json {
+        outln!("{}", serde_json::to_string_pretty(&report)?)?;
+        return Ok(());
+    }
+    let yes_no = |value: bool| if value { tr!("yes") } else { tr!("no") };
+
+    outln!("{}", tr!("crypto-recovery"))?;
+    if report.recovery.paths.is_empty() {
+        outln!("  {}", tr!("crypto-no-recovery"))?;
+    }
+    for path in &report.recovery.paths {
+        outln!("  - {}", path)?;
+    }
+
+    outln!("\n{}", tr!("crypto-rooms"))?;
+    for room in &report.rooms {
+        outln!(
+            "  {}  {}",
+            room.name.as_deref().unwrap_or(&room.room_id),
+            tr!(
+                "crypto-room-events",
+                decryptable = room.decryptable_events,
+                encrypted = room.encrypted_events,
+                sessions = room.sessions,
+            )
+        )?;
+    }
+
+    outln!("\n{}", tr!("crypto-devices"))?;
+    for device in &report.devices {
+        let marker = if device.current { "*" } else { " " };
+        outln!(
+            " {}{:<12} {:<10} {} {}",
+            marker,
+            device.device_id,
+            device.trust,
+            device.user_id,
+            device.display_name.as_deref().unwrap_or_default()
+        )?;
+    }
+
+    outln!("\n{}", tr!("crypto-key-requests"))?;
+    if report.pending_key_requests.is_empty() {
+        outln!("  -")?;
+    }
+    for request in &report.pending_key_requests {
+        outln!(
+            "  {:<38} {:<12} {}",
+            request.room_id.as_deref().unwrap_or("?"),
+            request.state,
+            request.session_id.as_deref().unwrap_or("?")
+        )?;
+        outln!(
+            "    {}",
+            tr!(
+                "crypto-key-request-to",
+                devices = request.recipients.join(", ")
+            )
+        )?;
+    }
+    for batch in &report.to_device_queue {
+        outln!(
+            "  {}",
+            tr!(
+                "crypto-to-device-queued",
+                event_type = batch.event_type.as_str(),
+                devices = batch.targets.join(", "),
+            )
+        )?;
+    }
+
+    outln!("\n{}", tr!("crypto-key-history"))?;
+    if report.key_request_history.is_empty() {
+        outln!("  -")?;
+    }
+    for entry in &report.key_request_history {
+        outln!(
+            "  {:<10} {:<38} {} {}",
+            entry.outcome,
+            entry.room_id.as_deref().unwrap_or("?"),
+            entry.session_id,
+            entry.detail.as_deref().unwrap_or_default()
+        )?;
+    }
+
+    outln!("\n{}", tr!("crypto-backup"))?;
+    let backup = &report.key_backup;
+    outln!(
+        "  {}: {}",
+        tr!("crypto-backup-in-ssss"),
+        yes_no(backup.key_in_secret_storage)
+    )?;
+    outln!(
+        "  {}: {}",
+        tr!("crypto-backup-cached"),
+        yes_no(backup.key_cached_locally)
+    )?;
+    outln!(
+        "  {}: {}",
+        tr!("crypto-backup-pending"),
+        backup.sessions_pending
+    )?;
+    let ssss = &report.secret_storage;
+    outln!(
+        "  {}: {}",
+        tr!("crypto-ssss-key"),
+        ssss.default_key.as_deref().unwrap_or("-")
+    )?;
+    outln!(
+        "  {}: {}",
+        tr!("crypto-ssss-secrets"),
+        ssss.secrets.join(", ")
+    )?;
+    if let Some(pickle_key) = report.pickle_key {
+        outln!("  {}: {}", tr!("crypto-pickle-key"), yes_no(pickle_key))?;
+    }
+    for limitation in &report.limitations {
+        eprintln!("{}", tr!("warning", message = limitation.to_string()));
+    }
+    Ok(())
+}
+
+/// `scan <db> [--rules rules.yaml]... [--no-builtin] [--format json|sarif] [--indexeddb DIR]`
+///
+/// Prints findings as JSON, or as SARIF together with the crypto posture issues
+pub fn scan(args: cli::ScanArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+    let mut rules = findings::builtin_rules();
+    if args.no_builtin {
+        rules.clear();
+    }
+    for path in &args.rules {
+        rules.extend(Scanner::load_rules(path)?);
+    }
+
+    let scanner = Scanner::new(rules.clone())?;
+    let entries = clone::read_live_entries(db_path)?;
+    let found = scanner.scan(entries.iter().map(|(k, v)| (k.as_slice(), v.as_slice())));
+    match args.format {
+        ScanFormat::Json => outln!("{}", serde_json::to_string_pretty(&found)?)?,
+        ScanFormat::Sarif => {
+            let mut issues = sarif::finding_issues(&found);
+            // The crypto posture joins in when the profile's IndexedDB is available
+            let layout = Layout::detect(entries.iter().map(|(k, v)| (k.as_slice(), v.as_slice())))
+                .unwrap_or_default();
+            if let Some(dir) = args.indexeddb.or_else(|| layout.indexeddb_dir(db_path)) {
+                let settings = Settings::from_entries(
+                    entries.iter().map(|(k, v)| (k.as_slice(), v.as_slice())),
+                );
+                let posture = posture::analyze(&Origin::read_directory(dir)?, Some(&settings));
+                issues.extend(sarif::posture_issues(&posture));
+            }
+            let log = sarif::log(&db_path.display().to_string(), &rules, &issues);
+            outln!("{}", serde_json::to_string_pretty(&log)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// `report <db> [--format html|markdown] [--indexeddb DIR]`: one document for a case file
+pub fn report(args: cli::ReportArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+    let parser = ElementLevelDBParser::builder()
+        .login_history(true)
+        .open(db_path)?;
+    let metadata = parser.parse_metadata()?;
+    let entries: Vec<_> = parser.entries()?.collect();
+    let raw = || entries.iter().map(|e| (e.key.as_ref(), e.value.as_ref()));
+
+    let mut issues = sarif::finding_issues(&Scanner::new(findings::builtin_rules())?.scan(raw()));
+    let origin = match args.indexeddb {
+        Some(dir) => Some(Origin::read_directory(dir)?),
+        None => None,
+    };
+    let posture = origin
+        .as_ref()
+        .or(parser.indexeddb())
+        .map(|origin| posture::analyze(origin, Some(&Settings::from_entries(raw()))));
+    if let Some(posture) = &posture {
+        issues.extend(sarif::posture_issues(posture));
+    }
+
+    let records: Vec<EntryRecord> = entries.iter().map(EntryRecord::from).collect();
+    let report = report::build(db_path, &metadata, &records, posture.as_ref(), &issues);
+    match args.format {
+        ReportFormat::Html => out!("{}", report::html(&report)?)?,
+        ReportFormat::Markdown => out!("{}", report::markdown(&report))?,
+    }
+    Ok(())
+}
+
+/// `verify-keys <db> [--homeserver URL] [--access-token TOKEN]`
+pub fn verify_keys(args: cli::VerifyKeysArgs) -> Result<()> {
+    let db_path = args.db.path()?;
+    let access_token = args.homeserver.access_token.ok_or_else(|| {
+        anyhow!("/keys/query needs an access token: set MATRIX_ACCESS_TOKEN or pass --access-token")
+    })?;
+
+    let parser = ElementLevelDBParser::open(db_path)?;
+    let homeserver = args
+        .homeserver
+        .homeserver
+        .or_else(|| stored_homeserver(&parser))
+        .ok_or_else(|| anyhow!("No mx_hs_url in the profile; pass --homeserver"))?;
+    let dir = sibling_indexeddb(&parser)
+        .ok_or_else(|| anyhow!("No IndexedDB directory next to {}", db_path.display()))?;
+    let devices = element_desktop_leveldb::crypto::cached_devices(&Origin::read_directory(dir)?);
+
+    let homeserver = homeserver::Homeserver::new(&homeserver, Some(access_token));
+    let (checks, failures) = verify::verify_keys(&homeserver, &devices)?;
+    for failure in &failures {
+        let message = tr!("verify-query-failed", user = failure.to_string());
+        eprintln!("{}", tr!("warning", message = message));
+    }
+    outln!("{}", serde_json::to_string_pretty(&checks)?)?;
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! Commands about the tool itself: `bench`, `man` and `completions`
+
+use crate::bench;
+use crate::cli::{self, Cli, Format};
+use crate::output::{self, out, outln};
+use anyhow::Result;
+use clap::CommandFactory;
+use std::io::Write;
+
+/// `bench <db> [--runs N] [--threads N] [--format text|json]`: time and
+/// throughput of each phase of a parse
+pub fn bench(args: cli::BenchArgs) -> Result<()> {
+    let report = bench::run(args.db.path()?, args.runs.get(), args.threads)?;
+    match args.format {
+        Format::Text => out!("{}", bench::render(&report))?,
+        Format::Json => outln!("{}", serde_json::to_string_pretty(&report)?)?,
+    }
+    Ok(())
+}
+
+/// `man [--dir DIR]`: pages without the config file's defaults, so packaged
+/// pages show the built-in ones
+pub fn man(args: cli::ManArgs) -> Result<()> {
+    match args.dir {
+        Some(dir) => {
+            std::fs::create_dir_all(&dir)?;
+            clap_mangen::generate_to(Cli::command(), &dir)?;
+        }
+        None => clap_mangen::Man::new(Cli::command()).render(&mut output::Writer)?,
+    }
+    Ok(())
+}
+
+pub fn completions(args: cli::CompletionsArgs) -> Result<()> {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    // Generated into memory, as clap_complete panics when a write fails
+    let mut script = Vec::new();
+    clap_complete::generate(args.shell, &mut command, name, &mut script);
+    output::Writer.write_all(&script)?;
+    Ok(())
+}
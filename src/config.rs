@@ -0,0 +1,207 @@
+//! `config.toml` and environment variables: defaults for the command line
+//!
+//! The file named by `--config` or `ELEMENT_LEVELDB_CONFIG`, or else
+//! `element-desktop-leveldb/config.toml` in the user's configuration directory
+//! when it exists, supplies the database, output format, language, redaction
+//! and scan rules a command uses when its own flags don't say. Relative paths
+//! are relative to the file. Environment variables such as
+//! `ELEMENT_LEVELDB_PATH` replace settings of the file, for pipelines that
+//! can't pass flags. Settings become clap default values, so a flag given on
+//! the command line always wins.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Name of the file in the configuration directory
+const FILE_NAME: &str = "config.toml";
+
+/// Config file, when `--config` isn't given
+const CONFIG_VAR: &str = "ELEMENT_LEVELDB_CONFIG";
+/// Replaces `db`
+const PATH_VAR: &str = "ELEMENT_LEVELDB_PATH";
+/// Replaces `format`
+const FORMAT_VAR: &str = "ELEMENT_PARSER_FORMAT";
+/// Replaces `lang`
+const LANG_VAR: &str = "ELEMENT_LEVELDB_LANG";
+/// Replaces `timezone`
+const TIMEZONE_VAR: &str = "ELEMENT_LEVELDB_TIMEZONE";
+/// Replaces `scan.rules`: a list of files, separated like `PATH`
+const RULES_VAR: &str = "ELEMENT_LEVELDB_RULES";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Database of commands given no `DB` or `--db`
+    pub db: Option<PathBuf>,
+    /// `--format` of every command that has this format
+    pub format: Option<String>,
+    pub lang: Option<String>,
+    pub timezone: Option<String>,
+    pub redaction: Redaction,
+    pub scan: ScanConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Redaction {
+    /// Leave values out of `diff` change sets, as `--no-values` does
+    pub no_values: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanConfig {
+    /// Rule files of `scan` when no `--rules` is given
+    pub rules: Vec<PathBuf>,
+    /// Only the configured rules, as `--no-builtin` does
+    pub no_builtin: bool,
+}
+
+/// `element-desktop-leveldb/config.toml` in the configuration directory
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("element-desktop-leveldb").join(FILE_NAME))
+}
+
+/// The value of `--config` in `args`, read ahead of parsing them
+pub fn requested_path(args: &[OsString]) -> Option<PathBuf> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|a| a.strip_prefix("--config=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// A non-empty environment variable
+fn var(name: &str) -> Option<OsString> {
+    std::env::var_os(name).filter(|value| !value.is_empty())
+}
+
+/// Reads the file `--config` or `ELEMENT_LEVELDB_CONFIG` names, or the default
+/// file if there is one, and applies the environment variables over it
+pub fn load(requested: Option<&Path>) -> Result<Config> {
+    let path = match requested {
+        Some(path) => Some(path.to_path_buf()),
+        None => var(CONFIG_VAR)
+            .map(PathBuf::from)
+            .or_else(|| default_path().filter(|path| path.is_file())),
+    };
+    let mut config = match path {
+        Some(path) => read(&path)?,
+        None => Config::default(),
+    };
+    config.apply_env()?;
+    Ok(config)
+}
+
+fn read(path: &Path) -> Result<Config> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut config: Config =
+        toml::from_str(&text).with_context(|| format!("Invalid config {}", path.display()))?;
+    if let Some(dir) = path.parent() {
+        config.resolve(dir);
+    }
+    Ok(config)
+}
+
+impl Config {
+    /// Makes relative paths relative to `dir`, the file's directory
+    fn resolve(&mut self, dir: &Path) {
+        if let Some(db) = &mut self.db {
+            *db = dir.join(&*db);
+        }
+        for rules in &mut self.scan.rules {
+            *rules = dir.join(&*rules);
+        }
+    }
+
+    /// Replaces settings with the environment variables that are set
+    fn apply_env(&mut self) -> Result<()> {
+        let text = |name: &str| match var(name) {
+            Some(value) => match value.into_string() {
+                Ok(value) => Ok(Some(value)),
+                Err(_) => bail!("{} is not valid UTF-8", name),
+            },
+            None => Ok(None),
+        };
+        if let Some(db) = var(PATH_VAR) {
+            self.db = Some(PathBuf::from(db));
+        }
+        if let Some(format) = text(FORMAT_VAR)? {
+            self.format = Some(format);
+        }
+        if let Some(lang) = text(LANG_VAR)? {
+            self.lang = Some(lang);
+        }
+        if let Some(timezone) = text(TIMEZONE_VAR)? {
+            self.timezone = Some(timezone);
+        }
+        if let Some(rules) = var(RULES_VAR) {
+            self.scan.rules = std::env::split_paths(&rules).collect();
+        }
+        Ok(())
+    }
+
+    /// Sets the configured values as defaults of the matching arguments
+    pub fn apply(&self, mut command: clap::Command) -> clap::Command {
+        if let Some(lang) = &self.lang {
+            command = command.mut_arg("lang", |arg| arg.default_value(lang.clone()));
+        }
+        if let Some(timezone) = &self.timezone {
+            command = command.mut_arg("timezone", |arg| arg.default_value(timezone.clone()));
+        }
+        command.mut_subcommands(|subcommand| self.apply_subcommand(subcommand))
+    }
+
+    fn apply_subcommand(&self, mut command: clap::Command) -> clap::Command {
+        if let Some(db) = &self.db
+            && command.get_arguments().any(|arg| arg.get_id() == "db_flag")
+        {
+            command = command.mut_arg("db_flag", |arg| {
+                arg.default_value(db.clone().into_os_string())
+            });
+        }
+        if let Some(format) = &self.format
+            && command.get_arguments().any(|arg| {
+                arg.get_id() == "format"
+                    && arg
+                        .get_possible_values()
+                        .iter()
+                        .any(|v| v.matches(format, false))
+            })
+        {
+            command = command.mut_arg("format", |arg| arg.default_value(format.clone()));
+        }
+        match command.get_name() {
+            "diff" if self.redaction.no_values => {
+                command.mut_arg("no_values", |arg| arg.default_value("true"))
+            }
+            "scan" => {
+                if !self.scan.rules.is_empty() {
+                    let rules: Vec<_> = self
+                        .scan
+                        .rules
+                        .iter()
+                        .map(|r| r.clone().into_os_string())
+                        .collect();
+                    command = command.mut_arg("rules", |arg| arg.default_values(rules));
+                }
+                if self.scan.no_builtin {
+                    command = command.mut_arg("no_builtin", |arg| arg.default_value("true"));
+                }
+                command
+            }
+            _ => command,
+        }
+    }
+}
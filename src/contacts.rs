@@ -0,0 +1,154 @@
+//! Direct-message contacts and their vCard / CSV export
+//!
+//! Contacts come from the `m.direct` account data event (user ID to DM rooms);
+//! display names and avatars from the cached presence events in the `users`
+//! store, falling back to member events in the cached rooms.
+
+use crate::indexeddb::reader::Origin;
+use crate::{sync, SYNC_DATABASE};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// One direct-message contact
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Contact {
+    pub user_id: String,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub direct_rooms: Vec<String>,
+}
+
+/// Direct-message contacts of the account, sorted by user ID
+pub fn dm_contacts(origin: &Origin) -> Vec<Contact> {
+    let direct = origin
+        .records(SYNC_DATABASE, "accountData")
+        .iter()
+        .find(|r| r.value["type"] == "m.direct")
+        .and_then(|r| r.value["content"].as_object().cloned())
+        .unwrap_or_default();
+
+    let mut contacts: BTreeMap<String, Contact> = direct
+        .into_iter()
+        .map(|(user_id, rooms)| {
+            let direct_rooms = rooms
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|r| r.as_str().map(str::to_string))
+                .collect();
+            let contact = Contact {
+                user_id: user_id.clone(),
+                direct_rooms,
+                ..Contact::default()
+            };
+            (user_id, contact)
+        })
+        .collect();
+
+    for record in origin.records(SYNC_DATABASE, "users") {
+        let Some(contact) = record.value["userId"]
+            .as_str()
+            .and_then(|u| contacts.get_mut(u))
+        else {
+            continue;
+        };
+        let content = &record.value["event"]["content"];
+        contact.display_name = content["displayname"].as_str().map(str::to_string);
+        contact.avatar_url = content["avatar_url"].as_str().map(str::to_string);
+    }
+
+    if let Some(snapshot) = sync::snapshot(origin) {
+        for membership in sync::MEMBERSHIPS {
+            for room in snapshot["roomsData"][membership]
+                .as_object()
+                .into_iter()
+                .flat_map(|rooms| rooms.values())
+            {
+                let events = room["state"]["events"].as_array().into_iter().flatten();
+                for event in events.filter(|e| e["type"] == "m.room.member") {
+                    let Some(contact) = event["state_key"]
+                        .as_str()
+                        .and_then(|u| contacts.get_mut(u))
+                    else {
+                        continue;
+                    };
+                    let content = &event["content"];
+                    if contact.display_name.is_none() {
+                        contact.display_name = content["displayname"].as_str().map(str::to_string);
+                    }
+                    if contact.avatar_url.is_none() {
+                        contact.avatar_url = content["avatar_url"].as_str().map(str::to_string);
+                    }
+                }
+            }
+        }
+    }
+
+    contacts.into_values().collect()
+}
+
+/// vCard 4.0 cards, one per contact
+pub fn to_vcard(contacts: &[Contact]) -> String {
+    let mut out = String::new();
+    for contact in contacts {
+        let name = contact.display_name.as_deref().unwrap_or(&contact.user_id);
+        out.push_str("BEGIN:VCARD\r\nVERSION:4.0\r\n");
+        out.push_str(&format!("FN:{}\r\n", vcard_escape(name)));
+        if let Some(uri) = matrix_uri(&contact.user_id) {
+            out.push_str(&format!("IMPP:{}\r\n", uri));
+        }
+        out.push_str(&format!(
+            "X-MATRIX-ID:{}\r\n",
+            vcard_escape(&contact.user_id)
+        ));
+        if let Some(avatar) = &contact.avatar_url {
+            out.push_str(&format!("PHOTO:{}\r\n", avatar));
+        }
+        if !contact.direct_rooms.is_empty() {
+            let note = format!("Direct rooms: {}", contact.direct_rooms.join(" "));
+            out.push_str(&format!("NOTE:{}\r\n", vcard_escape(&note)));
+        }
+        out.push_str("END:VCARD\r\n");
+    }
+    out
+}
+
+/// CSV with a header row; direct rooms are separated by `;`
+pub fn to_csv(contacts: &[Contact]) -> String {
+    let mut out = String::from("matrix_id,display_name,avatar_url,direct_rooms\n");
+    for contact in contacts {
+        let fields = [
+            contact.user_id.as_str(),
+            contact.display_name.as_deref().unwrap_or_default(),
+            contact.avatar_url.as_deref().unwrap_or_default(),
+            &contact.direct_rooms.join(";"),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_escape(f)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// `matrix:u/<localpart>:<server>` URI (MSC2312) for a user ID
+fn matrix_uri(user_id: &str) -> Option<String> {
+    let rest = user_id.strip_prefix('@')?;
+    Some(format!("matrix:u/{}", rest))
+}
+
+fn vcard_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace('\n', "\\n")
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,126 @@
+//! Conversions of extracted data into ruma types
+//!
+//! Built only with the `ruma` feature. Each value is rebuilt as the JSON the
+//! Matrix specification defines and deserialized into the ruma type, so the
+//! same validation applies as to data received from a homeserver; invalid
+//! identifiers in a damaged profile become errors rather than panics.
+
+use crate::crypto::{CachedDevice, InboundSession, KeyRequest};
+use crate::indexeddb::reader::Origin;
+use crate::rooms::RoomInfo;
+use crate::sync;
+use crate::SYNC_DATABASE;
+use anyhow::{anyhow, Context, Result};
+use ruma::encryption::DeviceKeys;
+use ruma::events::room_key_request::RequestedKeyInfo;
+use ruma::events::{AnyGlobalAccountDataEvent, AnyRoomAccountDataEvent};
+use ruma::serde::Raw;
+use ruma::{OwnedRoomId, OwnedUserId};
+use serde_json::{json, Value};
+
+/// Megolm algorithm every stored session uses
+const MEGOLM: &str = "m.megolm.v1.aes-sha2";
+
+/// Device keys of a cached device, without signatures
+pub fn device_keys(device: &CachedDevice) -> Result<DeviceKeys> {
+    serde_json::from_value(json!({
+        "user_id": device.user_id,
+        "device_id": device.device_id,
+        "algorithms": ["m.olm.v1.curve25519-aes-sha2", MEGOLM],
+        "keys": device.keys,
+        "signatures": {},
+        "unsigned": { "device_display_name": device.display_name },
+    }))
+    .with_context(|| format!("Invalid device {} of {}", device.device_id, device.user_id))
+}
+
+/// Identity of a stored Megolm session, as a key request would name it
+pub fn room_key_info(session: &InboundSession) -> Result<RequestedKeyInfo> {
+    let room_id = session
+        .room_id
+        .as_deref()
+        .ok_or_else(|| anyhow!("Session {} has no room ID", session.session_id))?;
+    requested_key_info(room_id, &session.sender_key, &session.session_id)
+}
+
+/// The session an outgoing key request asks for
+pub fn key_request_info(request: &KeyRequest) -> Result<RequestedKeyInfo> {
+    let field = |value: &Option<String>, name| {
+        value
+            .clone()
+            .ok_or_else(|| anyhow!("Key request {} has no {}", request.request_id, name))
+    };
+    requested_key_info(
+        &field(&request.room_id, "room ID")?,
+        &field(&request.sender_key, "sender key")?,
+        &field(&request.session_id, "session ID")?,
+    )
+}
+
+fn requested_key_info(
+    room_id: &str,
+    sender_key: &str,
+    session_id: &str,
+) -> Result<RequestedKeyInfo> {
+    serde_json::from_value(json!({
+        "algorithm": MEGOLM,
+        "room_id": room_id,
+        "sender_key": sender_key,
+        "session_id": session_id,
+    }))
+    .with_context(|| format!("Invalid room key {} in {}", session_id, room_id))
+}
+
+/// Room ID of a consolidated room
+pub fn room_id(room: &RoomInfo) -> Result<OwnedRoomId> {
+    OwnedRoomId::try_from(room.room_id.as_str())
+        .with_context(|| format!("Invalid room ID {}", room.room_id))
+}
+
+/// User ID, e.g. `mx_user_id` from Local Storage
+pub fn user_id(user_id: &str) -> Result<OwnedUserId> {
+    OwnedUserId::try_from(user_id).with_context(|| format!("Invalid user ID {}", user_id))
+}
+
+/// Global account data events of the sync store
+///
+/// Events stay `Raw`; `deserialize()` on each gives the typed event, with
+/// unknown types as `_Custom`.
+pub fn global_account_data(origin: &Origin) -> Result<Vec<Raw<AnyGlobalAccountDataEvent>>> {
+    origin
+        .records(SYNC_DATABASE, "accountData")
+        .iter()
+        .map(|r| raw(&r.value))
+        .collect()
+}
+
+/// Room account data events of each cached room, by room ID
+pub fn room_account_data(
+    origin: &Origin,
+) -> Result<Vec<(OwnedRoomId, Vec<Raw<AnyRoomAccountDataEvent>>)>> {
+    let Some(snapshot) = sync::snapshot(origin) else {
+        return Ok(Vec::new());
+    };
+    let mut rooms = Vec::new();
+    for membership in sync::MEMBERSHIPS {
+        let Some(section) = snapshot["roomsData"][membership].as_object() else {
+            continue;
+        };
+        for (room_id, room) in section {
+            let events = room["account_data"]["events"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(raw)
+                .collect::<Result<_>>()?;
+            let room_id = OwnedRoomId::try_from(room_id.as_str())
+                .with_context(|| format!("Invalid room ID {}", room_id))?;
+            rooms.push((room_id, events));
+        }
+    }
+    Ok(rooms)
+}
+
+fn raw<T>(value: &Value) -> Result<Raw<T>> {
+    Ok(Raw::from_json(serde_json::value::to_raw_value(value)?))
+}
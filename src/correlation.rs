@@ -0,0 +1,152 @@
+//! Correlation of several profiles of one subject
+//!
+//! Profiles from different machines, or several named profiles on one
+//! machine, are compared by the identifiers Matrix keeps globally unique:
+//! user IDs, room IDs, device IDs and event IDs. Anything seen in more than
+//! one profile is listed with the profiles it appears in, and the cached
+//! timelines are merged into one, with events cached by several profiles
+//! appearing once.
+
+use crate::devices::DeviceInfo;
+use crate::rooms::RoomInfo;
+use crate::sync::CachedTimeline;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// What one profile contributes to the correlation
+#[derive(Debug, Clone, Default)]
+pub struct ProfileData {
+    /// Label of the profile in the report, usually its path
+    pub profile: String,
+    pub user_id: Option<String>,
+    pub rooms: Vec<RoomInfo>,
+    pub devices: Vec<DeviceInfo>,
+    pub timelines: Vec<CachedTimeline>,
+}
+
+/// An identifier and the profiles it was found in
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Shared {
+    pub id: String,
+    /// Room or device name, when any profile knows it
+    pub name: Option<String>,
+    pub profiles: BTreeSet<String>,
+}
+
+/// One event of the combined timeline
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TimelineEntry {
+    pub origin_server_ts: Option<u64>,
+    pub room_id: String,
+    pub room_name: Option<String>,
+    pub event_id: Option<String>,
+    pub sender: Option<String>,
+    pub event_type: String,
+    pub encrypted: bool,
+    /// Text of unencrypted messages
+    pub body: Option<String>,
+    /// Profiles whose cache holds the event
+    pub profiles: BTreeSet<String>,
+}
+
+/// The merged view of several profiles
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Correlation {
+    pub profiles: Vec<String>,
+    /// Accounts logged in on more than one profile
+    pub shared_accounts: Vec<Shared>,
+    /// Rooms known to more than one profile
+    pub shared_rooms: Vec<Shared>,
+    /// Device IDs found in more than one profile: the same account's device
+    /// list synced to both, or a profile copied from one machine to another
+    pub shared_devices: Vec<Shared>,
+    /// Cached events of all profiles, oldest first
+    pub timeline: Vec<TimelineEntry>,
+}
+
+/// Correlates the given profiles
+pub fn correlate(profiles: &[ProfileData]) -> Correlation {
+    let mut accounts: BTreeMap<String, Shared> = BTreeMap::new();
+    let mut rooms: BTreeMap<String, Shared> = BTreeMap::new();
+    let mut devices: BTreeMap<String, Shared> = BTreeMap::new();
+    // Events without an ID can't be matched and are kept per profile
+    let mut events: BTreeMap<(String, String), TimelineEntry> = BTreeMap::new();
+
+    for data in profiles {
+        let label = &data.profile;
+        if let Some(user_id) = &data.user_id {
+            record(&mut accounts, user_id, None, label);
+        }
+        for room in &data.rooms {
+            record(&mut rooms, &room.room_id, room.name.as_deref(), label);
+        }
+        for device in &data.devices {
+            record(
+                &mut devices,
+                &device.device_id,
+                device.display_name.as_deref(),
+                label,
+            );
+        }
+        for timeline in &data.timelines {
+            for (i, event) in timeline.events.iter().enumerate() {
+                let key = match &event.event_id {
+                    Some(event_id) => (event_id.clone(), String::new()),
+                    None => (format!("{}#{}", timeline.room_id, i), label.clone()),
+                };
+                events
+                    .entry(key)
+                    .or_insert_with(|| TimelineEntry {
+                        origin_server_ts: event.origin_server_ts,
+                        room_id: timeline.room_id.clone(),
+                        room_name: timeline.name.clone(),
+                        event_id: event.event_id.clone(),
+                        sender: event.sender.clone(),
+                        event_type: event.event_type.clone(),
+                        encrypted: event.encrypted,
+                        body: event
+                            .content
+                            .as_ref()
+                            .and_then(|c| c["body"].as_str())
+                            .map(str::to_string),
+                        profiles: BTreeSet::new(),
+                    })
+                    .profiles
+                    .insert(label.clone());
+            }
+        }
+    }
+
+    let shared = |map: BTreeMap<String, Shared>| -> Vec<Shared> {
+        map.into_values().filter(|s| s.profiles.len() > 1).collect()
+    };
+    let mut timeline: Vec<TimelineEntry> = events.into_values().collect();
+    timeline.sort_by(|a, b| {
+        a.origin_server_ts
+            .cmp(&b.origin_server_ts)
+            .then_with(|| a.room_id.cmp(&b.room_id))
+    });
+    Correlation {
+        profiles: profiles.iter().map(|p| p.profile.clone()).collect(),
+        shared_accounts: shared(accounts),
+        shared_rooms: shared(rooms),
+        shared_devices: shared(devices),
+        timeline,
+    }
+}
+
+fn record(map: &mut BTreeMap<String, Shared>, id: &str, name: Option<&str>, profile: &str) {
+    let entry = map.entry(id.to_string()).or_insert_with(|| Shared {
+        id: id.to_string(),
+        name: None,
+        profiles: BTreeSet::new(),
+    });
+    if entry.name.is_none() {
+        entry.name = name.map(str::to_string);
+    }
+    entry.profiles.insert(profile.to_string());
+}
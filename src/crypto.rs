@@ -0,0 +1,191 @@
+//! Accessors for the legacy matrix-js-sdk crypto store
+//!
+//! `matrix-js-sdk:crypto` keeps the Olm account pickle and cached private keys
+//! in `account`, the device list of every tracked user in `device_data`, and
+//! the Megolm sessions able to decrypt room messages in
+//! `inbound_group_sessions`. `outgoingRoomKeyRequests` holds the requests for
+//! missing Megolm sessions that are still outstanding, and
+//! `inbound_group_sessions_withheld` the sessions senders refused to share.
+
+use crate::indexeddb::key::IdbKey;
+use crate::indexeddb::reader::Origin;
+use crate::CRYPTO_DATABASE;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Device as recorded in the local device list
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CachedDevice {
+    pub user_id: String,
+    pub device_id: String,
+    /// `<algorithm>:<device id>` to public key, e.g. `ed25519:ABCDEF`
+    pub keys: BTreeMap<String, String>,
+    pub display_name: Option<String>,
+    /// matrix-js-sdk `DeviceVerification`: -1 blocked, 0 unverified, 1 verified
+    pub verified: Option<i64>,
+}
+
+/// Megolm session able to decrypt messages in one room
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct InboundSession {
+    pub room_id: Option<String>,
+    pub session_id: String,
+    pub sender_key: String,
+    /// Received by key forwarding rather than from the sender directly
+    pub forwarded: bool,
+    /// Curve25519 keys of the devices that forwarded the session, in order
+    pub forwarding_chain: Vec<String>,
+}
+
+/// Session whose key a sender refused to share (`m.room_key.withheld`)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct WithheldSession {
+    pub room_id: Option<String>,
+    pub session_id: String,
+    pub sender_key: String,
+    /// e.g. `m.unverified`, `m.blacklisted` or `m.unauthorised`
+    pub code: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// Outstanding request for a Megolm session this device could not decrypt with
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct KeyRequest {
+    pub request_id: String,
+    pub room_id: Option<String>,
+    pub session_id: Option<String>,
+    pub sender_key: Option<String>,
+    /// matrix-js-sdk `RoomKeyRequestState`: `unsent`, `sent`,
+    /// `cancellation_pending` or `cancellation_pending_and_will_resend`
+    pub state: String,
+    /// Devices asked for the key, as `<user id>/<device id>`
+    pub recipients: Vec<String>,
+}
+
+/// Named entries of the `account` store other than the account pickle,
+/// e.g. `crossSigningKeys` or `ssss_cache:m.megolm_backup.v1`
+pub fn account_entries(origin: &Origin) -> Vec<String> {
+    origin
+        .records(CRYPTO_DATABASE, "account")
+        .iter()
+        .filter_map(|r| match &r.key {
+            IdbKey::String(name) if name != "-" => Some(name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Every stored inbound Megolm session
+pub fn inbound_sessions(origin: &Origin) -> Vec<InboundSession> {
+    origin
+        .records(CRYPTO_DATABASE, "inbound_group_sessions")
+        .iter()
+        .filter_map(|r| {
+            let data = &r.value["session"];
+            let forwarding_chain: Vec<String> = data["forwardingCurve25519KeyChain"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|k| k.as_str().map(str::to_string))
+                .collect();
+            Some(InboundSession {
+                room_id: data["room_id"].as_str().map(str::to_string),
+                session_id: r.value["sessionId"].as_str()?.to_string(),
+                sender_key: r.value["senderCurve25519Key"].as_str()?.to_string(),
+                forwarded: !forwarding_chain.is_empty(),
+                forwarding_chain,
+            })
+        })
+        .collect()
+}
+
+/// Every device in the cached device lists
+pub fn cached_devices(origin: &Origin) -> Vec<CachedDevice> {
+    let mut devices = Vec::new();
+    for record in origin.records(CRYPTO_DATABASE, "device_data") {
+        let Some(users) = record.value["devices"].as_object() else {
+            continue;
+        };
+        for (user_id, user_devices) in users {
+            for (device_id, device) in user_devices.as_object().into_iter().flatten() {
+                devices.push(CachedDevice {
+                    user_id: user_id.clone(),
+                    device_id: device_id.clone(),
+                    keys: device["keys"]
+                        .as_object()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                        .collect(),
+                    display_name: device["unsigned"]["device_display_name"]
+                        .as_str()
+                        .map(str::to_string),
+                    verified: device["verified"].as_i64(),
+                });
+            }
+        }
+    }
+    devices
+}
+
+/// Every session recorded as withheld by its sender
+pub fn withheld_sessions(origin: &Origin) -> Vec<WithheldSession> {
+    origin
+        .records(CRYPTO_DATABASE, "inbound_group_sessions_withheld")
+        .iter()
+        .filter_map(|r| {
+            let data = &r.value["session"];
+            let text = |value: &serde_json::Value| value.as_str().map(str::to_string);
+            Some(WithheldSession {
+                room_id: text(&data["room_id"]),
+                session_id: text(&r.value["sessionId"])?,
+                sender_key: text(&r.value["senderCurve25519Key"])?,
+                code: text(&data["code"]),
+                reason: text(&data["reason"]),
+            })
+        })
+        .collect()
+}
+
+/// Every outstanding outgoing room key request
+pub fn key_requests(origin: &Origin) -> Vec<KeyRequest> {
+    origin
+        .records(CRYPTO_DATABASE, "outgoingRoomKeyRequests")
+        .iter()
+        .filter_map(|r| {
+            let body = &r.value["requestBody"];
+            let text = |value: &serde_json::Value| value.as_str().map(str::to_string);
+            Some(KeyRequest {
+                request_id: text(&r.value["requestId"])?,
+                room_id: text(&body["room_id"]),
+                session_id: text(&body["session_id"]),
+                sender_key: text(&body["sender_key"]),
+                state: match r.value["state"].as_i64() {
+                    Some(0) => "unsent",
+                    Some(1) => "sent",
+                    Some(2) => "cancellation_pending",
+                    Some(3) => "cancellation_pending_and_will_resend",
+                    _ => "unknown",
+                }
+                .to_string(),
+                recipients: r.value["recipients"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|t| {
+                        Some(format!(
+                            "{}/{}",
+                            t["userId"].as_str()?,
+                            t["deviceId"].as_str()?
+                        ))
+                    })
+                    .collect(),
+            })
+        })
+        .collect()
+}
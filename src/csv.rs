@@ -0,0 +1,185 @@
+//! `export --format csv`: flat tables for e-discovery review platforms
+//!
+//! `entries.csv` has a row per entry: its key label, category (see
+//! `export::category`), value as text (or `0x` and hex) and size. `rooms.csv`
+//! and `devices.csv` hold the parsed rooms and devices. Lists within a cell
+//! are separated by `;` and times are RFC 3339 in UTC. Every file starts with
+//! a header row; fields with commas, quotes or line breaks are quoted.
+
+use crate::devices::Seen;
+use crate::export::{self, EntryRecord, Export};
+use crate::fields::FieldSelection;
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat};
+use std::path::Path;
+
+/// A CSV document being written
+pub struct Csv {
+    pub text: String,
+}
+
+impl Csv {
+    pub fn new(header: &[&str]) -> Self {
+        let mut csv = Csv {
+            text: String::new(),
+        };
+        csv.row(header.iter().map(|field| field.to_string()));
+        csv
+    }
+
+    pub fn row(&mut self, fields: impl IntoIterator<Item = String>) {
+        let fields: Vec<String> = fields.into_iter().map(|field| escape(&field)).collect();
+        self.text.push_str(&fields.join(","));
+        self.text.push('\n');
+    }
+}
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn text(value: Option<&str>) -> String {
+    value.unwrap_or_default().to_string()
+}
+
+fn flag(value: Option<bool>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// The time of a sighting, empty when none was recorded
+fn time(seen: Option<&Seen>) -> String {
+    seen.and_then(|seen| seen.timestamp)
+        .and_then(|ts| DateTime::from_timestamp_millis(ts as i64))
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, true))
+        .unwrap_or_default()
+}
+
+fn sequence(seen: Option<&Seen>) -> String {
+    seen.map(|seen| seen.sequence.to_string())
+        .unwrap_or_default()
+}
+
+/// The `EntryRecord` field behind each column of `entries.csv`
+const ENTRY_COLUMNS: [(&str, &str); 4] = [
+    ("key", "key"),
+    ("classification", "category"),
+    ("value", "value"),
+    ("size", "size"),
+];
+
+/// `entries.csv`, with the columns of the fields `keep` accepts
+pub fn entries(entries: &[EntryRecord], keep: impl Fn(&str) -> bool) -> String {
+    let kept: Vec<bool> = ENTRY_COLUMNS.iter().map(|(field, _)| keep(field)).collect();
+    let header: Vec<&str> = ENTRY_COLUMNS
+        .iter()
+        .zip(&kept)
+        .filter(|(_, kept)| **kept)
+        .map(|((_, column), _)| *column)
+        .collect();
+    let mut csv = Csv::new(&header);
+    for entry in entries {
+        let row = [
+            entry.key.clone(),
+            export::category(&entry.classification).to_string(),
+            entry.value.clone(),
+            entry.size.to_string(),
+        ];
+        csv.row(
+            row.into_iter()
+                .zip(&kept)
+                .filter(|(_, kept)| **kept)
+                .map(|(field, _)| field),
+        );
+    }
+    csv.text
+}
+
+/// `rooms.csv`
+fn rooms(export: &Export) -> String {
+    let mut csv = Csv::new(&[
+        "room_id",
+        "name",
+        "canonical_alias",
+        "membership",
+        "encrypted",
+        "tags",
+        "sources",
+    ]);
+    for room in &export.metadata.rooms {
+        csv.row([
+            room.room_id.clone(),
+            text(room.name.as_deref()),
+            text(room.canonical_alias.as_deref()),
+            text(room.membership.as_deref()),
+            flag(room.encrypted),
+            room.tags.join(";"),
+            room.sources.iter().cloned().collect::<Vec<_>>().join(";"),
+        ]);
+    }
+    csv.text
+}
+
+/// `devices.csv`
+fn devices(export: &Export) -> String {
+    let mut csv = Csv::new(&[
+        "device_id",
+        "user_id",
+        "display_name",
+        "current",
+        "first_seen",
+        "first_seen_sequence",
+        "last_seen",
+        "last_seen_sequence",
+        "curve25519_key",
+        "ed25519_key",
+        "sources",
+    ]);
+    for device in &export.metadata.devices {
+        csv.row([
+            device.device_id.clone(),
+            text(device.user_id.as_deref()),
+            text(device.display_name.as_deref()),
+            device.current.to_string(),
+            time(device.first_seen.as_ref()),
+            sequence(device.first_seen.as_ref()),
+            time(device.last_seen.as_ref()),
+            sequence(device.last_seen.as_ref()),
+            text(device.curve25519_key.as_deref()),
+            text(device.ed25519_key.as_deref()),
+            device.sources.iter().cloned().collect::<Vec<_>>().join(";"),
+        ]);
+    }
+    csv.text
+}
+
+/// Writes `entries.csv`, `rooms.csv` and `devices.csv` into `dir`, each
+/// through a `.tmp` file so no half-written file is left behind; `fields`
+/// picks the files and the columns of `entries.csv`
+pub fn write_dir(dir: &Path, export: &Export, fields: Option<&FieldSelection>) -> Result<()> {
+    let keeps = |name: &str| fields.is_none_or(|fields| fields.keeps(name));
+    let columns = fields.and_then(|fields| fields.below("entries"));
+    std::fs::create_dir_all(dir)?;
+    for (name, text) in [
+        (
+            "entries",
+            entries(&export.entries, |field| {
+                columns.as_ref().is_none_or(|columns| columns.keeps(field))
+            }),
+        ),
+        ("rooms", rooms(export)),
+        ("devices", devices(export)),
+    ] {
+        if !keeps(name) {
+            continue;
+        }
+        let path = dir.join(name).with_extension("csv");
+        let tmp = path.with_extension("csv.tmp");
+        std::fs::write(&tmp, text)?;
+        std::fs::rename(&tmp, &path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Pluggable decoding of stored values
+//!
+//! Values come in many encodings. A `ValueDecoder` registered on the
+//! `ParserBuilder` for a key pattern turns the matching values into text
+//! before `parse_metadata` sees them, so the Element fields are read from the
+//! decoded text and `raw_entries` holds it. Decoders are tried in the order
+//! they were registered; the first whose pattern matches and which accepts
+//! the value wins, and values no decoder accepts are handled as before.
+
+use crate::localstorage;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use regex::Regex;
+use std::fmt;
+use std::sync::Arc;
+
+/// Turns a stored value into text
+///
+/// Closures taking the same arguments are decoders too.
+pub trait ValueDecoder: Send + Sync {
+    /// The value of the item `name` as text, or `None` to leave it to the
+    /// next decoder; `name` is the decoded Local Storage item name, or the
+    /// key itself for other entries
+    fn decode(&self, name: &str, value: &[u8]) -> Option<String>;
+}
+
+impl<F> ValueDecoder for F
+where
+    F: Fn(&str, &[u8]) -> Option<String> + Send + Sync,
+{
+    fn decode(&self, name: &str, value: &[u8]) -> Option<String> {
+        self(name, value)
+    }
+}
+
+/// UTF-8 text
+pub struct Utf8;
+
+impl ValueDecoder for Utf8 {
+    fn decode(&self, _name: &str, value: &[u8]) -> Option<String> {
+        std::str::from_utf8(value).ok().map(str::to_string)
+    }
+}
+
+/// UTF-16LE text without a prefix
+pub struct Utf16Le;
+
+impl ValueDecoder for Utf16Le {
+    fn decode(&self, _name: &str, value: &[u8]) -> Option<String> {
+        if !value.len().is_multiple_of(2) {
+            return None;
+        }
+        let units: Vec<u16> = value
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16(&units).ok()
+    }
+}
+
+/// Chromium Local Storage strings, Latin-1 or UTF-16 by their first byte
+pub struct LocalStorageString;
+
+impl ValueDecoder for LocalStorageString {
+    fn decode(&self, _name: &str, value: &[u8]) -> Option<String> {
+        localstorage::decode_string(value)
+    }
+}
+
+/// JSON, written back compactly; anything else is left alone
+#[cfg(feature = "serde")]
+pub struct Json;
+
+#[cfg(feature = "serde")]
+impl ValueDecoder for Json {
+    fn decode(&self, _name: &str, value: &[u8]) -> Option<String> {
+        let json: serde_json::Value = serde_json::from_str(&text(value)?).ok()?;
+        Some(json.to_string())
+    }
+}
+
+/// Base64, such as a pickle, as `0x` and the hex of the decoded bytes
+pub struct Base64;
+
+impl ValueDecoder for Base64 {
+    fn decode(&self, _name: &str, value: &[u8]) -> Option<String> {
+        let bytes = STANDARD.decode(text(value)?.trim()).ok()?;
+        Some(format!("0x{}", hex::encode(bytes)))
+    }
+}
+
+/// Text of a Local Storage string or plain UTF-8 value
+fn text(value: &[u8]) -> Option<String> {
+    localstorage::decode_string(value).or_else(|| Utf8.decode("", value))
+}
+
+/// Decoders by key pattern, in the order they were registered
+#[derive(Clone, Default)]
+pub struct DecoderRegistry {
+    decoders: Vec<(Regex, Arc<dyn ValueDecoder>)>,
+}
+
+impl DecoderRegistry {
+    /// Decodes the values of the items whose name matches `pattern`
+    pub fn register(&mut self, pattern: Regex, decoder: impl ValueDecoder + 'static) {
+        self.decoders.push((pattern, Arc::new(decoder)));
+    }
+
+    /// The value of `name` as decoded by the first decoder that accepts it
+    pub fn decode(&self, name: &str, value: &[u8]) -> Option<String> {
+        self.decoders
+            .iter()
+            .filter(|(pattern, _)| pattern.is_match(name))
+            .find_map(|(_, decoder)| decoder.decode(name, value))
+    }
+}
+
+impl fmt::Debug for DecoderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.decoders.iter().map(|(pattern, _)| pattern.as_str()))
+            .finish()
+    }
+}
@@ -0,0 +1,141 @@
+//! The profile's own devices, current and historical
+//!
+//! Local Storage names the device currently logged in (`mx_device_id`) and,
+//! through superseded records, the devices of earlier logins; the crypto
+//! store's device list adds display names and identity keys for every device
+//! of the account. All of them are merged into one `DeviceInfo` per device ID.
+
+#[cfg(feature = "crypto")]
+use crate::crypto::CachedDevice;
+use crate::history::Session;
+use crate::settings::Settings;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Everything known about one of the account's devices
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DeviceInfo {
+    pub device_id: String,
+    pub user_id: Option<String>,
+    pub display_name: Option<String>,
+    pub curve25519_key: Option<String>,
+    pub ed25519_key: Option<String>,
+    /// The device the profile is logged in as
+    pub current: bool,
+    /// Earliest and latest evidence of the device being used from this profile
+    pub first_seen: Option<Seen>,
+    pub last_seen: Option<Seen>,
+    /// Where the device was seen: `mx_device_id`, `login_history` or `device_list`
+    pub sources: BTreeSet<String>,
+}
+
+/// A point in the Local Storage history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Seen {
+    /// LevelDB sequence number of the record
+    pub sequence: u64,
+    /// `mx_last_login_ts` at that point (milliseconds), when recorded
+    pub timestamp: Option<u64>,
+}
+
+/// Devices being collected, by device ID
+#[derive(Debug, Clone, Default)]
+pub struct DeviceIndex {
+    devices: BTreeMap<String, DeviceInfo>,
+}
+
+impl DeviceIndex {
+    /// The device with `device_id`, created if new, with `source` recorded
+    pub fn device(&mut self, device_id: &str, source: &str) -> &mut DeviceInfo {
+        let device = self
+            .devices
+            .entry(device_id.to_string())
+            .or_insert_with(|| DeviceInfo {
+                device_id: device_id.to_string(),
+                ..DeviceInfo::default()
+            });
+        device.sources.insert(source.to_string());
+        device
+    }
+
+    /// Records the current device and any keys or name stored next to it
+    pub fn add_local_storage(&mut self, settings: &Settings) {
+        let Some(device_id) = settings.value("mx_device_id") else {
+            return;
+        };
+        let user_id = settings.value("mx_user_id").map(str::to_string);
+        let device = self.device(device_id, "mx_device_id");
+        device.current = true;
+        device.user_id = device.user_id.take().or(user_id);
+        for (name, value) in settings.entries() {
+            if name.contains("device_name") || name.contains("deviceName") {
+                device.display_name = Some(value.to_string());
+            } else if name.contains("curve25519") {
+                device.curve25519_key = Some(value.to_string());
+            } else if name.contains("ed25519") {
+                device.ed25519_key = Some(value.to_string());
+            }
+        }
+    }
+
+    /// Records the device of every login session with when it was in use
+    pub fn add_sessions(&mut self, sessions: &[Session]) {
+        for session in sessions {
+            let Some(device_id) = &session.device_id else {
+                continue;
+            };
+            let first = Seen {
+                sequence: session.first_sequence,
+                timestamp: session.login_timestamps.iter().min().copied(),
+            };
+            let last = Seen {
+                sequence: session.last_sequence,
+                timestamp: session.login_timestamps.iter().max().copied(),
+            };
+            let device = self.device(device_id, "login_history");
+            device.user_id = device.user_id.take().or_else(|| session.user_id.clone());
+            if device
+                .first_seen
+                .is_none_or(|seen| first.sequence < seen.sequence)
+            {
+                device.first_seen = Some(first);
+            }
+            if device
+                .last_seen
+                .is_none_or(|seen| last.sequence > seen.sequence)
+            {
+                device.last_seen = Some(last);
+            }
+        }
+    }
+
+    /// Merges `user_id`'s devices from the crypto store's device list
+    #[cfg(feature = "crypto")]
+    pub fn add_cached(&mut self, devices: &[CachedDevice], user_id: &str) {
+        for cached in devices.iter().filter(|d| d.user_id == user_id) {
+            let key = |algorithm: &str| {
+                cached
+                    .keys
+                    .get(&format!("{}:{}", algorithm, cached.device_id))
+                    .cloned()
+            };
+            let device = self.device(&cached.device_id, "device_list");
+            device.user_id = Some(cached.user_id.clone());
+            device.display_name = device
+                .display_name
+                .take()
+                .or_else(|| cached.display_name.clone());
+            device.curve25519_key = device.curve25519_key.take().or_else(|| key("curve25519"));
+            device.ed25519_key = device.ed25519_key.take().or_else(|| key("ed25519"));
+        }
+    }
+
+    pub fn into_devices(self) -> Vec<DeviceInfo> {
+        self.devices.into_values().collect()
+    }
+}
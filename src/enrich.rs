@@ -0,0 +1,330 @@
+use crate::homeserver::{encode_path_segment, Homeserver};
+use crate::sync::CachedRoom;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Rooms requested per public directory page
+const DIRECTORY_PAGE_SIZE: usize = 100;
+
+/// Profile lookups per second unless `--rate` says otherwise
+pub const DEFAULT_PROFILE_RATE: f64 = 2.0;
+
+/// Cached profiles older than this (milliseconds) are looked up again
+const PROFILE_CACHE_TTL_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Retries of a lookup the homeserver rate-limited (429)
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// A room with the locally cached details and whatever the homeserver added
+#[derive(Debug, Serialize)]
+pub struct EnrichedRoom {
+    pub room_id: String,
+    pub membership: Option<String>,
+    pub name: Option<String>,
+    pub canonical_alias: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alt_aliases: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub joined_members: Option<u64>,
+    pub in_public_directory: bool,
+    /// Where the fields came from: `cache`, `state` and/or `directory`
+    pub sources: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+/// Fills in names and aliases the local cache lacks
+///
+/// Rooms without a cached name (or every room with `all`) are looked up via
+/// room state, which needs a token of a member; the public room directory is
+/// read for up to `directory_pages` pages and covers rooms the token can't see.
+/// A directory that can't be read is reported as an issue, not an error.
+pub fn enrich_rooms(
+    homeserver: &Homeserver,
+    rooms: &[CachedRoom],
+    all: bool,
+    directory_pages: usize,
+) -> (Vec<EnrichedRoom>, Vec<String>) {
+    let mut issues = Vec::new();
+    let directory = public_directory(homeserver, directory_pages).unwrap_or_else(|e| {
+        issues.push(format!("public room directory: {}", e));
+        HashMap::new()
+    });
+
+    let mut enriched = Vec::new();
+    for room in rooms {
+        let mut entry = EnrichedRoom {
+            room_id: room.room_id.clone(),
+            membership: Some(room.membership.clone()),
+            name: room.name.clone(),
+            canonical_alias: room.canonical_alias.clone(),
+            alt_aliases: Vec::new(),
+            topic: None,
+            joined_members: None,
+            in_public_directory: false,
+            sources: vec!["cache"],
+            errors: Vec::new(),
+        };
+
+        if (all || entry.name.is_none()) && homeserver.is_authenticated() {
+            lookup_state(homeserver, &mut entry);
+        }
+
+        if let Some(public) = directory.get(&room.room_id) {
+            entry.in_public_directory = true;
+            entry.sources.push("directory");
+            let text = |field: &str| public[field].as_str().map(str::to_string);
+            entry.name = entry.name.or_else(|| text("name"));
+            entry.canonical_alias = entry.canonical_alias.or_else(|| text("canonical_alias"));
+            entry.topic = entry.topic.or_else(|| text("topic"));
+            entry.joined_members = public["num_joined_members"].as_u64();
+        }
+        enriched.push(entry);
+    }
+    (enriched, issues)
+}
+
+fn lookup_state(homeserver: &Homeserver, entry: &mut EnrichedRoom) {
+    let room = encode_path_segment(&entry.room_id);
+    let state = |event_type: &str| {
+        homeserver.get_json(&format!(
+            "/_matrix/client/v3/rooms/{}/state/{}/",
+            room, event_type
+        ))
+    };
+
+    let mut found = false;
+    match state("m.room.name") {
+        Ok(content) => {
+            if let Some(name) = content["name"].as_str().filter(|n| !n.is_empty()) {
+                entry.name = Some(name.to_string());
+                found = true;
+            }
+        }
+        Err(e) => entry.errors.push(format!("m.room.name: {}", e)),
+    }
+    match state("m.room.canonical_alias") {
+        Ok(content) => {
+            if let Some(alias) = content["alias"].as_str() {
+                entry.canonical_alias = Some(alias.to_string());
+                found = true;
+            }
+            entry.alt_aliases = content["alt_aliases"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|a| a.as_str().map(str::to_string))
+                .collect();
+        }
+        Err(e) => entry.errors.push(format!("m.room.canonical_alias: {}", e)),
+    }
+    if let Ok(content) = state("m.room.topic") {
+        entry.topic = content["topic"].as_str().map(str::to_string);
+    }
+    if found {
+        entry.sources.push("state");
+    }
+}
+
+/// Reads up to `pages` pages of the public room directory, keyed by room ID
+fn public_directory(homeserver: &Homeserver, pages: usize) -> Result<HashMap<String, Value>> {
+    let mut rooms = HashMap::new();
+    let mut since: Option<String> = None;
+    for _ in 0..pages {
+        let mut path = format!(
+            "/_matrix/client/v3/publicRooms?limit={}",
+            DIRECTORY_PAGE_SIZE
+        );
+        if let Some(token) = &since {
+            path.push_str(&format!("&since={}", encode_path_segment(token)));
+        }
+        let page = homeserver.get_json(&path)?;
+        for room in page["chunk"].as_array().into_iter().flatten() {
+            if let Some(room_id) = room["room_id"].as_str() {
+                rooms.insert(room_id.to_string(), room.clone());
+            }
+        }
+        match page["next_batch"].as_str() {
+            Some(next) => since = Some(next.to_string()),
+            None => break,
+        }
+    }
+    Ok(rooms)
+}
+
+/// A user ID with its locally known name and the current profile
+#[derive(Debug, Serialize)]
+pub struct EnrichedUser {
+    pub user_id: String,
+    /// Display name cached in the profile, if any
+    pub local_display_name: Option<String>,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    /// `homeserver` for a lookup made now, `cache` for one from the cache file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A profile as the homeserver returned it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachedProfile {
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    /// When the lookup was made, in milliseconds since the epoch
+    pub fetched_at: i64,
+}
+
+/// Profiles looked up earlier, kept in a JSON file between runs
+#[derive(Debug, Default)]
+pub struct ProfileCache {
+    path: Option<PathBuf>,
+    profiles: BTreeMap<String, CachedProfile>,
+}
+
+impl ProfileCache {
+    /// Reads the cache at `path`; a missing file is an empty cache
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let profiles = match path {
+            Some(path) if path.exists() => serde_json::from_slice(&std::fs::read(path)?)?,
+            _ => BTreeMap::new(),
+        };
+        Ok(ProfileCache {
+            path: path.map(Path::to_path_buf),
+            profiles,
+        })
+    }
+
+    fn fresh(&self, user_id: &str, now: i64) -> Option<&CachedProfile> {
+        self.profiles
+            .get(user_id)
+            .filter(|p| now - p.fetched_at < PROFILE_CACHE_TTL_MS)
+    }
+
+    /// Writes the cache back, aside first so an interrupted run keeps the old one
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&self.profiles)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Spaces requests evenly at no more than a given rate
+struct RateLimiter {
+    interval: Duration,
+    next: Instant,
+}
+
+impl RateLimiter {
+    fn new(per_second: f64) -> Self {
+        RateLimiter {
+            interval: Duration::from_secs_f64(1.0 / per_second),
+            next: Instant::now(),
+        }
+    }
+
+    fn wait(&mut self) {
+        let now = Instant::now();
+        if self.next > now {
+            thread::sleep(self.next - now);
+        }
+        self.next = self.next.max(now) + self.interval;
+    }
+}
+
+/// Resolves user IDs to their current display names and avatars
+///
+/// `users` maps each user ID to the display name cached locally; only users
+/// without one are looked up, or every user with `all`. Lookups go through
+/// `cache` first and are made at most `rate` per second; a 429 answer backs off
+/// and retries a few times. Users whose profile can't be read keep the error.
+pub fn enrich_users(
+    homeserver: &Homeserver,
+    users: &BTreeMap<String, Option<String>>,
+    all: bool,
+    cache: &mut ProfileCache,
+    rate: f64,
+) -> Vec<EnrichedUser> {
+    let mut limiter = RateLimiter::new(rate);
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut enriched = Vec::new();
+    for (user_id, local) in users {
+        let mut entry = EnrichedUser {
+            user_id: user_id.clone(),
+            local_display_name: local.clone(),
+            display_name: local.clone(),
+            avatar_url: None,
+            source: None,
+            error: None,
+        };
+        if local.is_some() && !all {
+            enriched.push(entry);
+            continue;
+        }
+
+        let profile = match cache.fresh(user_id, now) {
+            Some(profile) => {
+                entry.source = Some("cache");
+                Some(profile.clone())
+            }
+            None => match lookup_profile(homeserver, user_id, &mut limiter) {
+                Ok(profile) => {
+                    let profile = CachedProfile {
+                        display_name: profile["displayname"].as_str().map(str::to_string),
+                        avatar_url: profile["avatar_url"].as_str().map(str::to_string),
+                        fetched_at: now,
+                    };
+                    cache.profiles.insert(user_id.clone(), profile.clone());
+                    entry.source = Some("homeserver");
+                    Some(profile)
+                }
+                Err(e) => {
+                    entry.error = Some(e.to_string());
+                    None
+                }
+            },
+        };
+        if let Some(profile) = profile {
+            entry.display_name = profile.display_name.or(entry.display_name);
+            entry.avatar_url = profile.avatar_url;
+        }
+        enriched.push(entry);
+    }
+    enriched
+}
+
+fn lookup_profile(
+    homeserver: &Homeserver,
+    user_id: &str,
+    limiter: &mut RateLimiter,
+) -> Result<Value> {
+    let path = format!(
+        "/_matrix/client/v3/profile/{}",
+        encode_path_segment(user_id)
+    );
+    let mut backoff = limiter.interval.max(Duration::from_secs(1));
+    for _ in 0..MAX_RATE_LIMIT_RETRIES {
+        limiter.wait();
+        match homeserver.get_json(&path) {
+            Err(e) if matches!(e.downcast_ref(), Some(ureq::Error::StatusCode(429))) => {
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+    limiter.wait();
+    homeserver.get_json(&path)
+}
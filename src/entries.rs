@@ -0,0 +1,404 @@
+//! Streaming access to the entries of a Local Storage database
+//!
+//! `ElementLevelDBParser::entries` walks the database in key order and yields
+//! one `Entry` at a time, so a consumer filtering a very large profile never
+//! holds more than the entry at hand. `parse_with_visitor` hands the same
+//! entries to an `EntryVisitor` without copying them, and stops as soon as the
+//! visitor breaks.
+
+use crate::localstorage;
+use bytes::Bytes;
+use rusty_leveldb::{DBIterator, LdbIterator};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::ops::{Bound, ControlFlow};
+
+/// What a Local Storage entry is
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
+pub enum Classification {
+    /// An item of the Element origin, by name
+    Element { name: String },
+    /// An item of another site sharing the browser profile
+    OtherOrigin { origin: String, name: String },
+    /// Chromium's `META:<origin>` record of an origin's last use and size
+    Meta { origin: String },
+    /// Chromium's schema `VERSION`
+    Version,
+    /// Anything else
+    Unknown,
+}
+
+impl Classification {
+    /// Classifies `key` of a database whose Element origin is `origin`
+    pub fn of(key: &[u8], origin: &str) -> Self {
+        if let Some((key_origin, name)) = localstorage::decode_key(key) {
+            return if key_origin == origin {
+                Classification::Element { name }
+            } else {
+                Classification::OtherOrigin {
+                    origin: key_origin,
+                    name,
+                }
+            };
+        }
+        if key == b"VERSION" {
+            return Classification::Version;
+        }
+        match key.strip_prefix(b"META:").map(std::str::from_utf8) {
+            Some(Ok(origin)) => Classification::Meta {
+                origin: origin.to_string(),
+            },
+            _ => Classification::Unknown,
+        }
+    }
+}
+
+/// One key and value of the database
+///
+/// Key and value are `Bytes` shared with LevelDB's read buffers: cloning an
+/// entry or slicing a value doesn't copy it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub key: Bytes,
+    pub value: Bytes,
+    pub classification: Classification,
+}
+
+impl Entry {
+    /// The value as text: decoded for Local Storage items, else when UTF-8
+    pub fn text(&self) -> Option<String> {
+        match self.classification {
+            Classification::Element { .. } | Classification::OtherOrigin { .. } => {
+                localstorage::decode_string(&self.value)
+            }
+            _ => std::str::from_utf8(&self.value).ok().map(str::to_string),
+        }
+    }
+}
+
+/// Callback for each entry of `parse_with_visitor`
+///
+/// Returning `ControlFlow::Break` stops the walk. Closures taking the same
+/// arguments are visitors too.
+pub trait EntryVisitor {
+    fn visit(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        classification: &Classification,
+    ) -> ControlFlow<()>;
+}
+
+impl<F> EntryVisitor for F
+where
+    F: FnMut(&[u8], &[u8], &Classification) -> ControlFlow<()>,
+{
+    fn visit(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        classification: &Classification,
+    ) -> ControlFlow<()> {
+        self(key, value, classification)
+    }
+}
+
+/// Lazy iterator over the entries of a database, in key order or, after
+/// `backwards`, in reverse
+pub struct Entries {
+    iter: DBIterator,
+    started: bool,
+    finished: bool,
+    backwards: bool,
+    /// Only keys starting with this; empty for all
+    prefix: Vec<u8>,
+    lower: Bound<Vec<u8>>,
+    upper: Bound<Vec<u8>>,
+    origin: String,
+    max_value_size: Option<usize>,
+}
+
+impl Entries {
+    pub(crate) fn new(iter: DBIterator, origin: String, max_value_size: Option<usize>) -> Self {
+        Entries {
+            iter,
+            started: false,
+            finished: false,
+            backwards: false,
+            prefix: Vec::new(),
+            lower: Bound::Unbounded,
+            upper: Bound::Unbounded,
+            origin,
+            max_value_size,
+        }
+    }
+
+    /// Limits the iterator to keys starting with `prefix`
+    pub(crate) fn with_prefix(mut self, prefix: &[u8]) -> Self {
+        self.prefix = prefix.to_vec();
+        self
+    }
+
+    /// Limits the iterator to keys between `lower` and `upper`
+    pub(crate) fn with_bounds(mut self, lower: Bound<Vec<u8>>, upper: Bound<Vec<u8>>) -> Self {
+        self.lower = lower;
+        self.upper = upper;
+        self
+    }
+
+    /// Walks from the last key to the first instead
+    ///
+    /// Prefix and range limits still apply. LevelDB can only seek forward, so
+    /// without an upper bound (or past the last key) the start is found by
+    /// one pass over the keys; values are only read on the way back.
+    /// Has no effect once iteration has started.
+    pub fn backwards(mut self) -> Self {
+        if !self.started {
+            self.backwards = true;
+        }
+        self
+    }
+
+    /// Positions at the first key that can be in range
+    fn seek_start(&mut self) {
+        // Keys are sorted, so the matching ones are contiguous
+        match &self.lower {
+            Bound::Included(start) | Bound::Excluded(start) if *start > self.prefix => {
+                self.iter.seek(start)
+            }
+            _ if !self.prefix.is_empty() => self.iter.seek(&self.prefix),
+            _ => self.iter.seek_to_first(),
+        }
+    }
+
+    /// Positions at or just past the last key that can be in range
+    fn seek_end(&mut self) {
+        let end = match &self.upper {
+            Bound::Included(end) | Bound::Excluded(end) => Some(end.clone()),
+            Bound::Unbounded => successor(&self.prefix),
+        };
+        if let Some(end) = end {
+            self.iter.seek(&end);
+            if self.iter.valid() {
+                return;
+            }
+        }
+        // Nothing at or after the end, so start from the last key there is
+        self.seek_start();
+        let mut last = None;
+        while self.iter.valid() {
+            last = self.iter.current().map(|(key, _)| key);
+            self.iter.advance();
+        }
+        if let Some(last) = last {
+            self.iter.seek(&last);
+        }
+    }
+
+    fn before_start(&self, key: &[u8]) -> bool {
+        let before = match &self.lower {
+            Bound::Included(start) => key < &start[..],
+            Bound::Excluded(start) => key <= &start[..],
+            Bound::Unbounded => false,
+        };
+        before || key < &self.prefix[..]
+    }
+
+    fn past_end(&self, key: &[u8]) -> bool {
+        let past = match &self.upper {
+            Bound::Included(end) => key > &end[..],
+            Bound::Excluded(end) => key >= &end[..],
+            Bound::Unbounded => false,
+        };
+        past || (key > &self.prefix[..] && !key.starts_with(&self.prefix))
+    }
+}
+
+/// The smallest key greater than every key starting with `prefix`, if any
+fn successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < 0xff {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+impl Iterator for Entries {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Entry> {
+        while !self.finished {
+            match (self.started, self.backwards) {
+                (false, false) => self.seek_start(),
+                (false, true) => self.seek_end(),
+                (true, false) => {
+                    self.iter.advance();
+                }
+                (true, true) => {
+                    self.iter.prev();
+                }
+            }
+            self.started = true;
+            let Some((key, value)) = self.iter.current().filter(|_| self.iter.valid()) else {
+                self.finished = true;
+                break;
+            };
+            // Leaving the range ends the walk; the key the walk started at
+            // may lie just outside it
+            let (ended, skipped) = if self.backwards {
+                (self.before_start(&key), self.past_end(&key))
+            } else {
+                (self.past_end(&key), self.before_start(&key))
+            };
+            if ended {
+                self.finished = true;
+                break;
+            }
+            if skipped {
+                continue;
+            }
+            if self.max_value_size.is_some_and(|max| value.len() > max) {
+                tracing::debug!(
+                    key = %String::from_utf8_lossy(&key),
+                    size = value.len(),
+                    "skipped value over max_value_size"
+                );
+                continue;
+            }
+            return Some(Entry {
+                classification: Classification::of(&key, &self.origin),
+                key,
+                value,
+            });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ElementLevelDBParser;
+    use std::collections::HashMap;
+
+    const ORIGIN: &str = "vector://vector";
+
+    fn parser() -> ElementLevelDBParser {
+        let mut entries: HashMap<Vec<u8>, Vec<u8>> = ["a", "ab", "abc", "b", "c"]
+            .into_iter()
+            .map(|key| (key.as_bytes().to_vec(), b"value".to_vec()))
+            .collect();
+        entries.insert(
+            localstorage::encode_key(ORIGIN, "mx_user_id"),
+            localstorage::encode_string("@alice:example.org"),
+        );
+        ElementLevelDBParser::in_memory(entries).unwrap()
+    }
+
+    fn keys(entries: Entries) -> Vec<String> {
+        entries
+            .map(|entry| String::from_utf8_lossy(&entry.key).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn prefixes_stop_at_the_first_key_past_them() {
+        let parser = parser();
+        assert_eq!(keys(parser.scan_prefix(b"ab").unwrap()), ["ab", "abc"]);
+        assert_eq!(
+            keys(parser.scan_prefix(b"ab").unwrap().backwards()),
+            ["abc", "ab"]
+        );
+        assert_eq!(keys(parser.scan_prefix(b"c").unwrap()), ["c"]);
+        assert!(keys(parser.scan_prefix(b"d").unwrap()).is_empty());
+        assert!(keys(parser.scan_prefix(b"aa").unwrap().backwards()).is_empty());
+    }
+
+    #[test]
+    fn ranges_honor_each_kind_of_bound() {
+        let parser = parser();
+        let range = |lower: Bound<&[u8]>, upper: Bound<&[u8]>| {
+            keys(parser.range::<&[u8]>((lower, upper)).unwrap())
+        };
+        assert_eq!(
+            range(Bound::Included(b"ab"), Bound::Excluded(b"b")),
+            ["ab", "abc"]
+        );
+        assert_eq!(
+            range(Bound::Excluded(b"ab"), Bound::Included(b"b")),
+            ["abc", "b"]
+        );
+        assert_eq!(range(Bound::Included(b"b"), Bound::Unbounded), ["b", "c"]);
+        assert_eq!(
+            range(Bound::Excluded(b"c"), Bound::Unbounded),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            keys(parser.range(b"a".as_slice()..b"abc".as_slice()).unwrap()),
+            ["a", "ab"]
+        );
+        assert_eq!(
+            keys(
+                parser
+                    .range(b"ab".as_slice()..=b"b".as_slice())
+                    .unwrap()
+                    .backwards()
+            ),
+            ["b", "abc", "ab"]
+        );
+        assert_eq!(
+            keys(parser.range(..b"a".as_slice()).unwrap())
+                .iter()
+                .filter(|key| key.starts_with('_'))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn reverse_iteration_mirrors_forward() {
+        let parser = parser();
+        let mut forward = keys(parser.entries().unwrap());
+        forward.reverse();
+        assert_eq!(keys(parser.iter_rev().unwrap()), forward);
+        assert_eq!(
+            keys(parser.range(b"abc".as_slice()..).unwrap().backwards()),
+            ["c", "b", "abc"]
+        );
+    }
+
+    #[test]
+    fn keys_are_classified_by_origin() {
+        let key = localstorage::encode_key(ORIGIN, "mx_user_id");
+        assert_eq!(
+            Classification::of(&key, ORIGIN),
+            Classification::Element {
+                name: "mx_user_id".to_string()
+            }
+        );
+        assert_eq!(
+            Classification::of(&key, "https://app.element.io"),
+            Classification::OtherOrigin {
+                origin: ORIGIN.to_string(),
+                name: "mx_user_id".to_string()
+            }
+        );
+        assert_eq!(
+            Classification::of(b"VERSION", ORIGIN),
+            Classification::Version
+        );
+        assert_eq!(
+            Classification::of(b"META:vector://vector", ORIGIN),
+            Classification::Meta {
+                origin: ORIGIN.to_string()
+            }
+        );
+        assert_eq!(Classification::of(b"a", ORIGIN), Classification::Unknown);
+    }
+}
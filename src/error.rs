@@ -0,0 +1,58 @@
+//! Errors of the parser API
+//!
+//! `ElementLevelDBParser` reports failures as a `ParserError`, so callers can
+//! tell a database held open by Element from a damaged or unrelated one
+//! without matching on message text.
+
+use rusty_leveldb::{Status, StatusCode};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Why opening or reading a database failed
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ParserError {
+    /// Another process, usually a running Element, holds the LevelDB lock
+    #[error("Database {0} is locked; close Element or work on a copy")]
+    DatabaseLocked(PathBuf),
+    /// A table or log of the database failed to read
+    #[error("Corrupt table in {path}: {message}")]
+    CorruptTable { path: PathBuf, message: String },
+    /// Not a LevelDB directory, or a database without any Element keys
+    #[error("{0} is not an Element database")]
+    NotElementDatabase(PathBuf),
+    /// A key that isn't UTF-8, with `fail_on_decode_error` set
+    #[error("Key 0x{} could not be decoded", hex::encode(.key))]
+    KeyDecode { key: Vec<u8> },
+    /// An earlier call panicked while holding the database
+    #[error("Database state poisoned by an earlier panic")]
+    Poisoned,
+    /// Any other LevelDB failure
+    #[error("LevelDB error in {path}: {status}")]
+    LevelDb { path: PathBuf, status: Status },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+impl ParserError {
+    /// Classifies a LevelDB status of the database at `path`
+    pub(crate) fn leveldb(path: &Path, status: Status) -> Self {
+        match status.code {
+            StatusCode::LockError => ParserError::DatabaseLocked(path.to_path_buf()),
+            StatusCode::Corruption | StatusCode::CompressionError => ParserError::CorruptTable {
+                path: path.to_path_buf(),
+                message: status.err,
+            },
+            _ => ParserError::LevelDb {
+                path: path.to_path_buf(),
+                status,
+            },
+        }
+    }
+}
+
+/// Result of the parser API
+pub type Result<T, E = ParserError> = std::result::Result<T, E>;
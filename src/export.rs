@@ -0,0 +1,212 @@
+//! Entries as records for `get`, `list-keys`, `search`, `dump` and `export`
+//!
+//! A record labels its key the way `diff --unified` does, `origin/name` for
+//! Local Storage items, and holds the value as text or, when it has none, as
+//! `0x` and hex like `raw_entries`.
+
+use crate::keys::classify_key;
+use crate::raw::provenance::ProvenanceMap;
+use crate::{localstorage, Classification, ElementMetadata, Entry};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Serialize;
+use std::cmp::Reverse;
+
+/// One entry of the database
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EntryRecord {
+    pub key: String,
+    pub classification: Classification,
+    pub value: String,
+    /// Size of the stored value in bytes
+    pub size: usize,
+}
+
+impl From<&Entry> for EntryRecord {
+    fn from(entry: &Entry) -> Self {
+        EntryRecord {
+            key: key_label(&entry.key),
+            classification: entry.classification.clone(),
+            value: entry
+                .text()
+                .unwrap_or_else(|| format!("0x{}", hex::encode(&entry.value))),
+            size: entry.value.len(),
+        }
+    }
+}
+
+/// One key of `list-keys --format json`
+#[derive(Debug, Serialize)]
+pub struct KeyRecord {
+    pub key: String,
+    /// The raw key, hex encoded; the cursor of `--after`
+    pub raw_key: String,
+    pub classification: Classification,
+    pub size: usize,
+    #[serde(rename = "type")]
+    pub value_type: &'static str,
+}
+
+impl From<&Entry> for KeyRecord {
+    fn from(entry: &Entry) -> Self {
+        KeyRecord {
+            key: key_label(&entry.key),
+            raw_key: hex::encode(&entry.key),
+            classification: entry.classification.clone(),
+            size: entry.value.len(),
+            value_type: value_type(entry),
+        }
+    }
+}
+
+/// What `export --format json` writes
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Export {
+    pub metadata: ElementMetadata,
+    pub entries: Vec<EntryRecord>,
+}
+
+/// `origin/name` for Local Storage items, else the key with anything
+/// unprintable escaped
+pub fn key_label(key: &[u8]) -> String {
+    match localstorage::decode_key(key) {
+        Some((origin, name)) => format!("{}/{}", origin, name),
+        None => String::from_utf8_lossy(key).escape_debug().to_string(),
+    }
+}
+
+/// The item name of a Local Storage entry, else its key label
+pub fn item_name(entry: &Entry) -> String {
+    match &entry.classification {
+        Classification::Element { name } | Classification::OtherOrigin { name, .. } => name.clone(),
+        _ => key_label(&entry.key),
+    }
+}
+
+/// The origin a Local Storage item or `META:` record belongs to
+pub fn origin(entry: &Entry) -> Option<String> {
+    match &entry.classification {
+        Classification::Meta { origin } | Classification::OtherOrigin { origin, .. } => {
+            Some(origin.clone())
+        }
+        Classification::Element { .. } => {
+            localstorage::decode_key(&entry.key).map(|(origin, _)| origin)
+        }
+        _ => None,
+    }
+}
+
+/// What a value holds: `json`, `text` or `binary`
+pub fn value_type(entry: &Entry) -> &'static str {
+    match entry.text() {
+        Some(text)
+            if serde_json::from_str::<serde_json::Value>(&text)
+                .is_ok_and(|json| json.is_object() || json.is_array()) =>
+        {
+            "json"
+        }
+        Some(_) => "text",
+        None => "binary",
+    }
+}
+
+/// Kind, origin and item name of an entry for flat exports; Element items
+/// carry `element_origin`, the origin of the detected layout
+#[cfg(any(feature = "sqlite", feature = "parquet"))]
+pub fn parts<'a>(
+    classification: &'a Classification,
+    element_origin: &'a str,
+) -> (&'static str, Option<&'a str>, Option<&'a str>) {
+    match classification {
+        Classification::Element { name } => ("element", Some(element_origin), Some(name)),
+        Classification::OtherOrigin { origin, name } => ("other_origin", Some(origin), Some(name)),
+        Classification::Meta { origin } => ("meta", Some(origin), None),
+        Classification::Version => ("version", None, None),
+        Classification::Unknown => ("unknown", None, None),
+    }
+}
+
+/// Group of an entry for flat exports: for Element items the group of the
+/// item name (`user`, `rooms`, `crypto`, `settings` or `other`), for other
+/// entries `other_origin`, `meta`, `version` or `unknown`
+pub fn category(classification: &Classification) -> &'static str {
+    match classification {
+        Classification::Element { name } => classify_key(name).category().as_str(),
+        Classification::OtherOrigin { .. } => "other_origin",
+        Classification::Meta { .. } => "meta",
+        Classification::Version => "version",
+        Classification::Unknown => "unknown",
+    }
+}
+
+/// How `dump` writes bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Encoding {
+    /// Lowercase hex without a prefix, for `xxd -r -p`
+    #[default]
+    Hex,
+    /// Padded standard base64
+    Base64,
+}
+
+/// `bytes` in `encoding`
+pub fn encode(bytes: &[u8], encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Hex => hex::encode(bytes),
+        Encoding::Base64 => STANDARD.encode(bytes),
+    }
+}
+
+/// Whether the Element item name or raw key of `entry` is `key`
+pub fn is_key(entry: &Entry, key: &str) -> bool {
+    match &entry.classification {
+        Classification::Element { name } => name == key,
+        _ => entry.key == key.as_bytes(),
+    }
+}
+
+/// Order of listed and exported entries; ties are broken by key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum SortOrder {
+    /// Raw key, as stored
+    #[default]
+    Key,
+    /// Largest value first
+    Size,
+    /// Element items, other sites' items, META records, VERSION, then the rest
+    Category,
+    /// Oldest write first, by LevelDB sequence number
+    Timestamp,
+}
+
+/// Puts `entries` in `order`, ties in key order, so that runs over the same
+/// database list them the same way; `provenance` holds the sequence numbers
+/// of `SortOrder::Timestamp`
+pub fn sort(entries: &mut [Entry], order: SortOrder, provenance: &ProvenanceMap) {
+    match order {
+        SortOrder::Key => entries.sort_by(|a, b| a.key.cmp(&b.key)),
+        SortOrder::Size => entries.sort_by_key(|e| (Reverse(e.value.len()), e.key.clone())),
+        SortOrder::Category => {
+            entries.sort_by_key(|e| (category_rank(&e.classification), e.key.clone()))
+        }
+        // Keys whose record wasn't found go last
+        SortOrder::Timestamp => entries.sort_by_key(|e| {
+            let sequence = provenance.get(e.key.as_ref()).map(|p| p.sequence);
+            (sequence.is_none(), sequence, e.key.clone())
+        }),
+    }
+}
+
+fn category_rank(classification: &Classification) -> u8 {
+    match classification {
+        Classification::Element { .. } => 0,
+        Classification::OtherOrigin { .. } => 1,
+        Classification::Meta { .. } => 2,
+        Classification::Version => 3,
+        Classification::Unknown => 4,
+    }
+}
@@ -0,0 +1,79 @@
+//! `fetch-media`: downloading the `mxc://` media a profile mentions
+//!
+//! Each URI is fetched through the homeserver's media API and written into
+//! the output directory as `<server>_<media id>`, next to a `media.json`
+//! manifest recording the source URL, SHA-256, size and content type of every
+//! file, or why it couldn't be downloaded.
+
+use crate::changeset::sha256_hex;
+use crate::homeserver::Homeserver;
+use crate::media;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Manifest written next to the downloaded files
+pub const MANIFEST_NAME: &str = "media.json";
+
+/// Outcome of downloading one `mxc://` URI
+#[derive(Debug, Serialize)]
+pub struct MediaEntry {
+    pub mxc: String,
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Downloads every URI into `out` and writes a manifest with hashes
+///
+/// Failures are recorded per entry so one missing file doesn't lose the rest.
+pub fn fetch_all(
+    uris: &BTreeSet<String>,
+    homeserver: &Homeserver,
+    out: &Path,
+) -> Result<Vec<MediaEntry>> {
+    std::fs::create_dir_all(out).with_context(|| format!("Failed to create {}", out.display()))?;
+
+    let mut entries = Vec::new();
+    for uri in uris {
+        let url = media::download_url(homeserver.base(), uri, homeserver.is_authenticated());
+        let mut entry = MediaEntry {
+            mxc: uri.clone(),
+            url: url.clone(),
+            file: None,
+            sha256: None,
+            size: None,
+            content_type: None,
+            error: None,
+        };
+        match (url, media::file_name(uri)) {
+            (Some(url), Some(name)) => match homeserver.download(&url) {
+                Ok((data, content_type)) => {
+                    std::fs::write(out.join(&name), &data)?;
+                    entry.sha256 = Some(sha256_hex(&data));
+                    entry.size = Some(data.len());
+                    entry.file = Some(name);
+                    entry.content_type = content_type;
+                }
+                Err(e) => entry.error = Some(e.to_string()),
+            },
+            _ => entry.error = Some("not a valid mxc:// URI".to_string()),
+        }
+        entries.push(entry);
+    }
+
+    std::fs::write(
+        out.join(MANIFEST_NAME),
+        serde_json::to_string_pretty(&entries)?,
+    )?;
+    Ok(entries)
+}
@@ -0,0 +1,281 @@
+//! Selecting the fields of a report before it is written
+//!
+//! A selection is a comma-separated list of dotted paths such as
+//! `identity,rooms.name,devices.*`. Paths descend into objects by key and
+//! apply to every element of an array, so `rooms.name` keeps the name of each
+//! room. A path prefixed with `-` removes that field instead; a selection of
+//! only removals keeps everything else, e.g. `-raw_entries`. A report's
+//! `schema_version` stays in every selection unless it is removed.
+//!
+//! `prune` clears the fields of `ElementMetadata` a selection drops whole
+//! before it is serialized, so `-raw_entries` never converts the raw entries
+//! to JSON only to throw them away.
+
+use crate::ElementMetadata;
+use anyhow::{bail, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Names standing for several top-level fields of the metadata report
+const GROUPS: &[(&str, &[&str])] = &[
+    ("identity", &["user_id", "display_name", "avatar_url"]),
+    (
+        "settings",
+        &[
+            "theme",
+            "language",
+            "notifications_enabled",
+            "appearance",
+            "media_settings",
+        ],
+    ),
+];
+
+/// Fields to keep below one level of a report
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    /// The whole value
+    All,
+    /// Only these keys, each with its own selection
+    Fields(BTreeMap<String, Node>),
+}
+
+impl Node {
+    fn insert(&mut self, path: &[&str]) {
+        let Node::Fields(fields) = self else {
+            return;
+        };
+        match path {
+            [] | ["*"] => *self = Node::All,
+            [first, rest @ ..] => fields
+                .entry(first.to_string())
+                .or_insert_with(|| Node::Fields(BTreeMap::new()))
+                .insert(rest),
+        }
+    }
+
+    fn select(&self, value: Value) -> Value {
+        let Node::Fields(fields) = self else {
+            return value;
+        };
+        match value {
+            Value::Object(object) => Value::Object(
+                object
+                    .into_iter()
+                    .filter_map(|(key, value)| {
+                        let node = fields.get(&key)?;
+                        Some((key, node.select(value)))
+                    })
+                    .collect::<Map<_, _>>(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.into_iter().map(|item| self.select(item)).collect())
+            }
+            other => other,
+        }
+    }
+}
+
+/// A parsed `--fields` selection
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSelection {
+    include: Option<Node>,
+    exclude: Vec<Vec<String>>,
+}
+
+impl FieldSelection {
+    /// Parses a comma-separated list of paths; see the module documentation
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut include = None;
+        let mut exclude = Vec::new();
+        for item in spec.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (removed, path) = match item.strip_prefix('-') {
+                Some(path) => (true, path),
+                None => (false, item),
+            };
+            let path: Vec<&str> = path.split('.').collect();
+            if path.iter().any(|segment| segment.is_empty()) {
+                bail!("Invalid field path {:?}", item);
+            }
+            let expanded: Vec<Vec<&str>> = match GROUPS.iter().find(|(name, _)| *name == path[0]) {
+                Some((_, members)) if path.len() == 1 => {
+                    members.iter().map(|member| vec![*member]).collect()
+                }
+                _ => vec![path],
+            };
+            for path in expanded {
+                if removed {
+                    exclude.push(path.iter().map(|s| s.to_string()).collect());
+                } else {
+                    include
+                        .get_or_insert_with(|| Node::Fields(BTreeMap::new()))
+                        .insert(&path);
+                }
+            }
+        }
+        if include.is_none() && exclude.is_empty() {
+            bail!("Empty field selection");
+        }
+        if let Some(node) = &mut include {
+            node.insert(&["schema_version"]);
+        }
+        Ok(FieldSelection { include, exclude })
+    }
+
+    /// Applies the selection to an already serialized report
+    pub fn apply(&self, value: Value) -> Value {
+        let mut value = match &self.include {
+            Some(node) => node.select(value),
+            None => value,
+        };
+        for path in &self.exclude {
+            remove(&mut value, path);
+        }
+        value
+    }
+
+    /// Serializes `report` to a JSON value with only the selected fields
+    pub fn select<T: Serialize>(&self, report: &T) -> Result<Value> {
+        Ok(self.apply(serde_json::to_value(report)?))
+    }
+
+    /// Whether any of the top-level field `name` is kept
+    pub fn keeps(&self, name: &str) -> bool {
+        let included = match &self.include {
+            Some(Node::Fields(fields)) => fields.contains_key(name),
+            _ => true,
+        };
+        included
+            && !self
+                .exclude
+                .iter()
+                .any(|path| path.len() == 1 && path[0] == name)
+    }
+
+    /// The selection within the top-level field `name`, such as `name` of
+    /// `rooms.name` for `rooms`; `None` when the field is kept whole
+    pub fn below(&self, name: &str) -> Option<FieldSelection> {
+        let include = match &self.include {
+            Some(Node::Fields(fields)) => fields.get(name).filter(|node| **node != Node::All),
+            _ => None,
+        };
+        let exclude: Vec<Vec<String>> = self
+            .exclude
+            .iter()
+            .filter(|path| path.len() > 1 && path[0] == name)
+            .map(|path| path[1..].to_vec())
+            .collect();
+        (include.is_some() || !exclude.is_empty()).then(|| FieldSelection {
+            include: include.cloned(),
+            exclude,
+        })
+    }
+
+    /// Empties the fields of `metadata` the selection drops whole
+    pub fn prune(&self, metadata: &mut ElementMetadata) {
+        let ElementMetadata {
+            schema_version: _,
+            user_id,
+            display_name,
+            avatar_url,
+            theme,
+            language,
+            notifications_enabled,
+            layout,
+            rooms,
+            devices,
+            login_history,
+            ui_state,
+            media_devices,
+            appearance,
+            media_settings,
+            raw_entries,
+            provenance,
+            extensions,
+        } = metadata;
+        self.clear("user_id", user_id);
+        self.clear("display_name", display_name);
+        self.clear("avatar_url", avatar_url);
+        self.clear("theme", theme);
+        self.clear("language", language);
+        self.clear("notifications_enabled", notifications_enabled);
+        self.clear("layout", layout);
+        self.clear("rooms", rooms);
+        self.clear("devices", devices);
+        self.clear("login_history", login_history);
+        self.clear("ui_state", ui_state);
+        self.clear("media_devices", media_devices);
+        self.clear("appearance", appearance);
+        self.clear("media_settings", media_settings);
+        self.clear("raw_entries", raw_entries);
+        self.clear("provenance", provenance);
+        self.clear("extensions", extensions);
+    }
+
+    fn clear<T: Default>(&self, name: &str, field: &mut T) {
+        if !self.keeps(name) {
+            *field = T::default();
+        }
+    }
+}
+
+fn remove(value: &mut Value, path: &[String]) {
+    match (value, path) {
+        (Value::Array(items), _) => items.iter_mut().for_each(|item| remove(item, path)),
+        (Value::Object(object), [last]) if last == "*" => object.clear(),
+        (Value::Object(object), [last]) => {
+            object.remove(last);
+        }
+        (Value::Object(object), [first, rest @ ..]) => {
+            if let Some(child) = object.get_mut(first) {
+                remove(child, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_fields_are_pruned_before_serializing() {
+        let mut metadata = ElementMetadata {
+            user_id: Some("@alice:example.org".into()),
+            theme: Some("dark".into()),
+            raw_entries: [("mx_theme".to_string(), "dark".to_string())].into(),
+            ..Default::default()
+        };
+        let fields = FieldSelection::parse("-raw_entries").unwrap();
+        fields.prune(&mut metadata);
+        assert!(metadata.raw_entries.is_empty());
+        assert_eq!(metadata.theme.as_deref(), Some("dark"));
+
+        let fields = FieldSelection::parse("identity").unwrap();
+        fields.prune(&mut metadata);
+        assert_eq!(metadata.user_id.as_deref(), Some("@alice:example.org"));
+        assert_eq!(metadata.theme, None);
+    }
+
+    #[test]
+    fn selections_below_a_field() {
+        let fields = FieldSelection::parse("settings,entries.key,-entries.size").unwrap();
+        assert!(fields.keeps("appearance") && fields.keeps("entries"));
+        assert!(!fields.keeps("rooms"));
+        let entries = fields.below("entries").unwrap();
+        let record = serde_json::json!({"key": "mx_theme", "value": "dark", "size": 4});
+        assert_eq!(
+            entries.apply(record),
+            serde_json::json!({"key": "mx_theme"})
+        );
+        assert_eq!(fields.below("theme"), None);
+        assert_eq!(
+            FieldSelection::parse("-raw_entries")
+                .unwrap()
+                .below("entries"),
+            None
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Secret-scanning rule engine
+//!
+//! Rules match a regex against the entry key, the decoded value, or both, and
+//! yield findings independent of the metadata extraction. Built-in rules cover
+//! the secrets Element is known to keep; more can be loaded from YAML:
+//!
+//! ```yaml
+//! rules:
+//!   - id: internal-api-key
+//!     description: Internal API key pasted into a draft
+//!     severity: high
+//!     pattern: "ik_[0-9a-f]{32}"
+//!     key_pattern: "^mx_cider_state_"   # optional
+//! ```
+
+use crate::localstorage;
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// Rule as written in YAML
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RuleSpec {
+    pub id: String,
+    pub description: String,
+    pub severity: Severity,
+    /// Regex matched against the decoded value
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pattern: Option<String>,
+    /// Regex matched against the key (the script key for Local Storage)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub key_pattern: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Debug, Deserialize)]
+struct RuleFile {
+    rules: Vec<RuleSpec>,
+}
+
+/// Single match of a rule
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Finding {
+    pub rule: String,
+    pub severity: Severity,
+    pub description: String,
+    /// Key the match was found under (script key for Local Storage)
+    pub key: String,
+    /// Redacted excerpt of the matched text
+    pub excerpt: String,
+}
+
+struct Rule {
+    spec: RuleSpec,
+    pattern: Option<Regex>,
+    key_pattern: Option<Regex>,
+}
+
+/// Compiled set of rules
+pub struct Scanner {
+    rules: Vec<Rule>,
+}
+
+/// Rules shipped with the crate
+pub fn builtin_rules() -> Vec<RuleSpec> {
+    vec![
+        rule(
+            "matrix-access-token",
+            Severity::Critical,
+            "Matrix access token",
+            Some(r"\bsyt_[A-Za-z0-9]+_[A-Za-z0-9]+(?:_[A-Za-z0-9]+)?\b"),
+            None,
+        ),
+        rule(
+            "stored-access-token",
+            Severity::Critical,
+            "Access token stored in plaintext",
+            Some(r"\S{16,}"),
+            Some(r"^mx_access_token$"),
+        ),
+        rule(
+            "oidc-refresh-token",
+            Severity::Critical,
+            "OIDC or Matrix refresh token",
+            Some(r"\S{16,}"),
+            Some(r"^mx_(?:oidc_)?refresh_token$"),
+        ),
+        rule(
+            "jwt",
+            Severity::Medium,
+            "JSON Web Token (OIDC ID token or similar)",
+            Some(r"\beyJ[A-Za-z0-9_-]+\.eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+"),
+            None,
+        ),
+        rule(
+            "recovery-key",
+            Severity::Critical,
+            "Secure backup recovery key",
+            Some(r"\bE[Ss][1-9A-HJ-NP-Za-km-z]{2}(?: [1-9A-HJ-NP-Za-km-z]{4}){11}\b"),
+            None,
+        ),
+        rule(
+            "private-key-pem",
+            Severity::Critical,
+            "PEM private key",
+            Some(r"-----BEGIN (?:[A-Z]+ )*PRIVATE KEY-----"),
+            None,
+        ),
+        rule(
+            "pickle-key",
+            Severity::High,
+            "Key protecting the crypto store pickles",
+            None,
+            Some(r"(?i)^(?:mx_)?pickle_?key$"),
+        ),
+    ]
+}
+
+fn rule(
+    id: &str,
+    severity: Severity,
+    description: &str,
+    pattern: Option<&str>,
+    key_pattern: Option<&str>,
+) -> RuleSpec {
+    RuleSpec {
+        id: id.to_string(),
+        description: description.to_string(),
+        severity,
+        pattern: pattern.map(str::to_string),
+        key_pattern: key_pattern.map(str::to_string),
+    }
+}
+
+impl Scanner {
+    /// Compiles rules, failing on the first invalid regex
+    pub fn new(specs: Vec<RuleSpec>) -> Result<Self> {
+        let compile = |id: &str, pattern: &Option<String>| -> Result<Option<Regex>> {
+            pattern
+                .as_deref()
+                .map(|p| Regex::new(p).with_context(|| format!("Invalid regex in rule {}", id)))
+                .transpose()
+        };
+        let rules = specs
+            .into_iter()
+            .map(|spec| {
+                if spec.pattern.is_none() && spec.key_pattern.is_none() {
+                    bail!("Rule {} needs a pattern or a key_pattern", spec.id);
+                }
+                Ok(Rule {
+                    pattern: compile(&spec.id, &spec.pattern)?,
+                    key_pattern: compile(&spec.id, &spec.key_pattern)?,
+                    spec,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Scanner { rules })
+    }
+
+    /// Reads additional rules from a YAML file
+    #[cfg(feature = "serde")]
+    pub fn load_rules<P: AsRef<Path>>(path: P) -> Result<Vec<RuleSpec>> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read rules {}", path.display()))?;
+        let file: RuleFile = serde_yaml::from_str(&text)
+            .with_context(|| format!("Invalid rules file {}", path.display()))?;
+        Ok(file.rules)
+    }
+
+    /// Runs every rule over the entries, most severe findings first
+    pub fn scan<'a, I>(&self, entries: I) -> Vec<Finding>
+    where
+        I: IntoIterator<Item = (&'a [u8], &'a [u8])>,
+    {
+        let mut findings = Vec::new();
+        for (key, value) in entries {
+            let (key, value) = match localstorage::decode_key(key) {
+                Some((_, name)) => (
+                    name,
+                    localstorage::decode_string(value)
+                        .unwrap_or_else(|| String::from_utf8_lossy(value).to_string()),
+                ),
+                None => (
+                    String::from_utf8_lossy(key).to_string(),
+                    String::from_utf8_lossy(value).to_string(),
+                ),
+            };
+
+            for rule in &self.rules {
+                if rule
+                    .key_pattern
+                    .as_ref()
+                    .is_some_and(|re| !re.is_match(&key))
+                {
+                    continue;
+                }
+                let matched = match &rule.pattern {
+                    Some(re) => match re.find(&value) {
+                        Some(m) => m.as_str(),
+                        None => continue,
+                    },
+                    None => value.as_str(),
+                };
+                findings.push(Finding {
+                    rule: rule.spec.id.clone(),
+                    severity: rule.spec.severity,
+                    description: rule.spec.description.clone(),
+                    key: key.clone(),
+                    excerpt: redact(matched),
+                });
+            }
+        }
+        findings.sort_by(|a, b| b.severity.cmp(&a.severity).then(a.key.cmp(&b.key)));
+        findings
+    }
+}
+
+/// Keeps the first four characters and the length of a secret
+fn redact(secret: &str) -> String {
+    let shown: String = secret.chars().take(4).collect();
+    format!("{}… ({} chars)", shown, secret.chars().count())
+}
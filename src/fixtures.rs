@@ -0,0 +1,804 @@
+//! Synthetic Element Desktop profile generator
+//!
+//! Real profiles can't be committed, so tests, benchmarks and demos use
+//! generated ones. Each profile gets a Chromium-style `Local Storage/leveldb`
+//! and an `IndexedDB/vector_vector_0.indexeddb.leveldb`, written directly in
+//! LevelDB's on-disk format: older writes are compacted into a level-0 table,
+//! newer ones (including superseded values and deletions) stay in the log.
+
+use crate::indexeddb::key::{self as idb_key, EntryKey, IdbKey};
+use crate::indexeddb::{self, value as idb_value};
+use crate::localstorage;
+use crate::raw::log::{encode_write_batch, LogWriter};
+use crate::raw::manifest::{FileMetaData, VersionEdit};
+use crate::raw::table::{internal_key, TableBuilder};
+use crate::raw::{self, ValueType};
+use crate::{CRYPTO_DATABASE, ELEMENT_DESKTOP_ORIGIN, ELEMENT_DESKTOP_ORIGIN_ID, SYNC_DATABASE};
+use anyhow::Result;
+use base64::engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const BYTEWISE_COMPARATOR: &str = "leveldb.BytewiseComparator";
+const WRITE_BATCH_SIZE: usize = 8;
+const BASE_TIMESTAMP_MS: u64 = 1_760_000_000_000;
+
+const FIRST_NAMES: &[&str] = &[
+    "alice", "bob", "carol", "dave", "erin", "frank", "grace", "heidi", "ivan", "judy", "mallory",
+    "niaj", "olivia", "peggy", "rupert", "sybil", "trent", "victor", "walter", "zoë",
+];
+const SERVERS: &[&str] = &["example.org", "matrix.example.net", "chat.example.com"];
+const ROOM_NAMES: &[&str] = &[
+    "General",
+    "Random",
+    "Ops",
+    "Incident Response",
+    "Book Club",
+    "Family",
+    "Project Αθήνα",
+    "Release Planning",
+    "Off-topic",
+    "Security",
+];
+
+/// What to generate
+#[derive(Debug, Clone)]
+pub struct FixtureOptions {
+    /// Number of independent profiles (one account each)
+    pub profiles: usize,
+    /// Rooms joined by each account
+    pub rooms: usize,
+    /// Cached timeline events per room
+    pub records: usize,
+    /// Seed for the deterministic generator
+    pub seed: u64,
+    /// Damage a table block and truncate the log of every database
+    pub corrupt: bool,
+}
+
+impl Default for FixtureOptions {
+    fn default() -> Self {
+        Self {
+            profiles: 1,
+            rooms: 5,
+            records: 20,
+            seed: 1,
+            corrupt: false,
+        }
+    }
+}
+
+/// Paths of a generated profile
+#[derive(Debug, Clone)]
+pub struct GeneratedProfile {
+    pub root: PathBuf,
+    pub local_storage: PathBuf,
+    pub indexeddb: PathBuf,
+    pub user_id: String,
+}
+
+/// Small deterministic PRNG (splitmix64) so fixtures are reproducible
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+
+    fn ident(&mut self, len: usize) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+        (0..len)
+            .map(|_| ALPHABET[self.below(ALPHABET.len())] as char)
+            .collect()
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+
+    /// Unpadded base64 of `len` random bytes, like Matrix keys and pickles
+    fn base64(&mut self, len: usize) -> String {
+        STANDARD_NO_PAD.encode(self.bytes(len))
+    }
+}
+
+/// One logical write destined for a generated database
+type Write = (ValueType, Vec<u8>, Vec<u8>);
+
+fn put(key: Vec<u8>, value: Vec<u8>) -> Write {
+    (ValueType::Value, key, value)
+}
+
+fn delete(key: Vec<u8>) -> Write {
+    (ValueType::Deletion, key, Vec::new())
+}
+
+/// Generates `options.profiles` profiles under `out`
+pub fn generate<P: AsRef<Path>>(out: P, options: &FixtureOptions) -> Result<Vec<GeneratedProfile>> {
+    let mut rng = Rng(options.seed);
+    let mut profiles = Vec::new();
+
+    for index in 0..options.profiles {
+        let root = out.as_ref().join(format!("profile-{}", index));
+        let account = Account::new(&mut rng, index, options);
+
+        let local_storage = root.join("Local Storage").join("leveldb");
+        let (compacted, recent) = local_storage_writes(&mut rng, &account);
+        write_database(&local_storage, BYTEWISE_COMPARATOR, &compacted, &recent)?;
+
+        let indexeddb = root
+            .join("IndexedDB")
+            .join(format!("{}.indexeddb.leveldb", ELEMENT_DESKTOP_ORIGIN_ID));
+        let (compacted, recent) = indexeddb_writes(&mut rng, &account, options.records);
+        write_database(&indexeddb, indexeddb::COMPARATOR_NAME, &compacted, &recent)?;
+
+        if options.corrupt {
+            corrupt_database(&local_storage, &mut rng)?;
+            corrupt_database(&indexeddb, &mut rng)?;
+        }
+
+        profiles.push(GeneratedProfile {
+            root,
+            local_storage,
+            indexeddb,
+            user_id: account.user_id.clone(),
+        });
+    }
+
+    Ok(profiles)
+}
+
+/// Writes fuzzing seed inputs for each decoder, one directory per fuzz target
+///
+/// Clean and corrupted profiles are generated into a scratch directory and
+/// split up into table files (`sst_reader`), log and manifest files
+/// (`wal_parser`), raw IndexedDB keys (`idb_key`) and record values
+/// (`idb_value`). Returns the number of seeds written.
+pub fn write_fuzz_seeds<P: AsRef<Path>>(dir: P, seed: u64) -> Result<usize> {
+    let dir = dir.as_ref();
+    let scratch = std::env::temp_dir().join(format!("element-fuzz-seeds-{}", std::process::id()));
+    let mut written = 0;
+
+    for corrupt in [false, true] {
+        let options = FixtureOptions {
+            profiles: 2,
+            rooms: 3,
+            records: 5,
+            seed,
+            corrupt,
+        };
+        let variant = if corrupt { "corrupt" } else { "clean" };
+        for (i, profile) in generate(scratch.join(variant), &options)?
+            .iter()
+            .enumerate()
+        {
+            let label = format!("{}-{}", variant, i);
+            for db in [&profile.local_storage, &profile.indexeddb] {
+                for entry in std::fs::read_dir(db)? {
+                    let path = entry?.path();
+                    let name = path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string();
+                    let target = match path.extension().and_then(|e| e.to_str()) {
+                        Some("ldb") => "sst_reader",
+                        Some("log") => "wal_parser",
+                        _ if name.starts_with("MANIFEST-") => "wal_parser",
+                        _ => continue,
+                    };
+                    let db_name = db.file_name().unwrap_or_default().to_string_lossy();
+                    let out = dir
+                        .join(target)
+                        .join(format!("{}-{}-{}", label, db_name, name));
+                    std::fs::create_dir_all(out.parent().unwrap_or(dir))?;
+                    std::fs::copy(&path, out)?;
+                    written += 1;
+                }
+            }
+
+            if corrupt {
+                continue;
+            }
+            let scan = raw::scan_directory(&profile.indexeddb)?;
+            for (n, record) in scan.records.iter().enumerate() {
+                let is_data = matches!(
+                    idb_key::decode_entry_key(&record.key),
+                    Some(EntryKey::ObjectStoreData { .. })
+                );
+                write_seed(dir, "idb_key", &format!("{}-{}", label, n), &record.key)?;
+                written += 1;
+                if is_data {
+                    write_seed(dir, "idb_value", &format!("{}-{}", label, n), &record.value)?;
+                    written += 1;
+                }
+            }
+        }
+    }
+
+    std::fs::remove_dir_all(&scratch)?;
+    Ok(written)
+}
+
+fn write_seed(dir: &Path, target: &str, name: &str, data: &[u8]) -> Result<()> {
+    let target_dir = dir.join(target);
+    std::fs::create_dir_all(&target_dir)?;
+    std::fs::write(target_dir.join(name), data)?;
+    Ok(())
+}
+
+/// Identity and rooms of a generated account
+struct Account {
+    user_id: String,
+    server: String,
+    display_name: String,
+    previous_device_id: String,
+    device_id: String,
+    rooms: Vec<(String, String, bool)>,
+    contacts: Vec<(String, String)>,
+    created_ms: u64,
+}
+
+impl Account {
+    fn new(rng: &mut Rng, index: usize, options: &FixtureOptions) -> Self {
+        let server = SERVERS[index % SERVERS.len()].to_string();
+        let name = FIRST_NAMES[index % FIRST_NAMES.len()];
+        let contacts = (0..(options.rooms + 3))
+            .map(|i| {
+                let contact = FIRST_NAMES[(index + i + 1) % FIRST_NAMES.len()];
+                let user_id = format!("@{}{}:{}", ascii_name(contact), i, rng.pick(SERVERS));
+                let mut display = contact.to_string();
+                display[..1].make_ascii_uppercase();
+                (user_id, display)
+            })
+            .collect();
+        let rooms = (0..options.rooms.max(1))
+            .map(|i| {
+                let room_id = format!("!{}:{}", rng.ident(18), server);
+                let name = ROOM_NAMES[i % ROOM_NAMES.len()].to_string();
+                (room_id, name, rng.below(3) != 0)
+            })
+            .collect();
+
+        let mut display_name = name.to_string();
+        display_name[..1].make_ascii_uppercase();
+        if index % 2 == 1 {
+            display_name.push_str(" 🔐");
+        }
+
+        Account {
+            user_id: format!("@{}:{}", ascii_name(name), server),
+            server,
+            display_name,
+            previous_device_id: rng.ident(10).to_uppercase(),
+            device_id: rng.ident(10).to_uppercase(),
+            rooms,
+            contacts,
+            created_ms: BASE_TIMESTAMP_MS + rng.below(1_000_000_000) as u64,
+        }
+    }
+}
+
+fn ascii_name(name: &str) -> String {
+    name.chars().filter(char::is_ascii_alphanumeric).collect()
+}
+
+fn local_storage_key(name: &str) -> Vec<u8> {
+    localstorage::encode_key(ELEMENT_DESKTOP_ORIGIN, name)
+}
+
+fn ls_put(name: &str, value: &str) -> Write {
+    put(local_storage_key(name), localstorage::encode_string(value))
+}
+
+/// Fake but well-formed JWT
+fn fake_jwt(rng: &mut Rng, account: &Account, issued_ms: u64) -> String {
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#);
+    let claims = json!({
+        "iss": format!("https://account.{}/", account.server),
+        "sub": rng.ident(26),
+        "aud": rng.ident(26),
+        "iat": issued_ms / 1000,
+        "exp": issued_ms / 1000 + 300,
+    });
+    let signature = URL_SAFE_NO_PAD.encode(rng.bytes(256));
+    format!(
+        "{}.{}.{}",
+        header,
+        URL_SAFE_NO_PAD.encode(claims.to_string()),
+        signature
+    )
+}
+
+/// Local Storage writes: an older session compacted into the table, and
+/// the current session (plus deletions of stale keys) in the log
+fn local_storage_writes(rng: &mut Rng, account: &Account) -> (Vec<Write>, Vec<Write>) {
+    let hs_url = format!("https://matrix.{}", account.server);
+    let first_login = account.created_ms;
+    let second_login = first_login + 86_400_000 * (3 + rng.below(30) as u64);
+
+    let meta_key = format!("META:{}", ELEMENT_DESKTOP_ORIGIN).into_bytes();
+    let mut meta = vec![0x08];
+    crate::raw::write_varint(&mut meta, first_login * 1000);
+
+    let old_token = format!("syt_{}_{}", rng.ident(12), rng.ident(20));
+    let compacted = vec![
+        put(b"VERSION".to_vec(), b"1".to_vec()),
+        put(meta_key, meta),
+        ls_put("mx_hs_url", &hs_url),
+        ls_put("mx_is_url", "https://vector.im"),
+        ls_put("mx_user_id", &account.user_id),
+        ls_put("mx_device_id", &account.previous_device_id),
+        ls_put("mx_is_guest", "false"),
+        ls_put("mx_access_token", &old_token),
+        ls_put("mx_has_pickle_key", "false"),
+        ls_put("mx_soft_logout", "true"),
+        ls_put("mx_profile_displayname", &account.display_name),
+        ls_put("mx_last_login_ts", &first_login.to_string()),
+    ];
+
+    let (last_room, _, _) = &account.rooms[rng.below(account.rooms.len())];
+    let settings = json!({
+        "language": "en",
+        "use_system_theme": false,
+        "theme": if rng.below(2) == 0 { "dark" } else { "light" },
+        "showMediaEventIds": {},
+    });
+    let mut recent = vec![
+        delete(local_storage_key("mx_access_token")),
+        delete(local_storage_key("mx_soft_logout")),
+        ls_put("mx_device_id", &account.device_id),
+        ls_put("mx_has_pickle_key", "true"),
+        ls_put("mx_has_access_token", "true"),
+        ls_put("mx_has_refresh_token", "true"),
+        ls_put("mx_crypto_initialised", "true"),
+        ls_put(
+            "mx_profile_avatar_url",
+            &format!("mxc://{}/{}", account.server, rng.ident(24)),
+        ),
+        ls_put("mx_last_room_id", last_room),
+        ls_put("mx_local_settings", &settings.to_string()),
+        ls_put("mx_oidc_client_id", &rng.ident(26)),
+        ls_put(
+            "mx_oidc_token_issuer",
+            &format!("https://account.{}/", account.server),
+        ),
+        ls_put("mx_oidc_id_token", &fake_jwt(rng, account, second_login)),
+        ls_put("mx_last_login_ts", &second_login.to_string()),
+        ls_put("mx_draft_cleanup", &(second_login + 3_600_000).to_string()),
+    ];
+
+    for (room_id, _, _) in &account.rooms {
+        let phases =
+            json!({ "history": [{ "phase": "RoomSummary" }], "isOpen": rng.below(2) == 0 });
+        recent.push(ls_put(
+            &format!("mx_setting_RightPanel.phases_{}", room_id),
+            &phases.to_string(),
+        ));
+    }
+    let (draft_room, _, _) = &account.rooms[0];
+    let draft =
+        json!({ "parts": [{ "type": "plain", "text": "the recovery key is EsTc 8Qn2 ..." }] });
+    recent.push(ls_put(
+        &format!("mx_cider_state_{}", draft_room),
+        &draft.to_string(),
+    ));
+
+    (compacted, recent)
+}
+
+/// Database and object store layout of the generated IndexedDB origin
+struct IdbLayout {
+    writes: Vec<Write>,
+}
+
+impl IdbLayout {
+    fn database(&mut self, id: u64, name: &str, stores: &[&str]) {
+        self.writes.push(put(
+            idb_key::database_name_key(ELEMENT_DESKTOP_ORIGIN_ID, name),
+            idb_key::encode_int(id),
+        ));
+        let mut origin = Vec::new();
+        idb_key::encode_string(ELEMENT_DESKTOP_ORIGIN_ID, &mut origin);
+        self.writes.push(put(
+            idb_key::database_meta_key(id, idb_key::DATABASE_ORIGIN_NAME),
+            origin,
+        ));
+        let mut db_name = Vec::new();
+        idb_key::encode_string(name, &mut db_name);
+        self.writes.push(put(
+            idb_key::database_meta_key(id, idb_key::DATABASE_NAME),
+            db_name,
+        ));
+        self.writes.push(put(
+            idb_key::database_meta_key(id, idb_key::DATABASE_MAX_OBJECT_STORE_ID),
+            idb_key::encode_int(stores.len() as u64),
+        ));
+
+        for (i, store) in stores.iter().enumerate() {
+            let store_id = i as u64 + 1;
+            let mut store_name = Vec::new();
+            idb_key::encode_string(store, &mut store_name);
+            self.writes.push(put(
+                idb_key::object_store_meta_key(id, store_id, idb_key::OBJECT_STORE_NAME),
+                store_name,
+            ));
+            self.writes.push(put(
+                idb_key::object_store_names_key(id, store),
+                idb_key::encode_int(store_id),
+            ));
+        }
+    }
+}
+
+fn idb_put(database_id: u64, store_id: u64, key: IdbKey, value: &Value) -> Write {
+    put(
+        idb_key::object_store_data_key(database_id, store_id, &key),
+        idb_value::encode_record_value(1, value),
+    )
+}
+
+const SYNC_STORES: &[&str] = &[
+    "users",
+    "accountData",
+    "sync",
+    "client_options",
+    "to_device_queue",
+];
+const CRYPTO_STORES: &[&str] = &[
+    "account",
+    "device_data",
+    "inbound_group_sessions",
+    "sessions_needing_backup",
+    "outgoingRoomKeyRequests",
+    "inbound_group_sessions_withheld",
+];
+
+/// IndexedDB writes: schema and an older sync snapshot compacted, the
+/// current sync snapshot and user cache in the log
+fn indexeddb_writes(
+    rng: &mut Rng,
+    account: &Account,
+    events_per_room: usize,
+) -> (Vec<Write>, Vec<Write>) {
+    let mut layout = IdbLayout { writes: Vec::new() };
+    layout.database(1, SYNC_DATABASE, SYNC_STORES);
+    layout.database(2, CRYPTO_DATABASE, CRYPTO_STORES);
+    let mut compacted = layout.writes;
+
+    let old_sync = sync_snapshot(rng, account, &account.rooms[..account.rooms.len() / 2], 1);
+    compacted.push(idb_put(1, 3, IdbKey::String("-".into()), &old_sync));
+
+    let mut recent = Vec::new();
+    for (user_id, display_name) in &account.contacts {
+        let user = json!({
+            "userId": user_id,
+            "event": {
+                "type": "m.presence",
+                "sender": user_id,
+                "content": {
+                    "displayname": display_name,
+                    "avatar_url": format!("mxc://{}/{}", account.server, rng.ident(24)),
+                    "presence": if rng.below(2) == 0 { "online" } else { "offline" },
+                    "last_active_ago": rng.below(10_000_000),
+                },
+            },
+        });
+        recent.push(idb_put(1, 1, IdbKey::String(user_id.clone()), &user));
+    }
+
+    let direct: serde_json::Map<String, Value> = account
+        .contacts
+        .iter()
+        .zip(&account.rooms)
+        .map(|((user_id, _), (room_id, _, _))| (user_id.clone(), json!([room_id])))
+        .collect();
+    // Secret storage with the key backup secret; derived values keep the
+    // random stream (and so every other fixture value) unchanged
+    let ssss_key = rng.ident(32);
+    let sealed = |name: &str| {
+        json!({ ssss_key.clone(): {
+            "iv": STANDARD_NO_PAD.encode(format!("iv {}", name)),
+            "ciphertext": STANDARD_NO_PAD.encode(format!("sealed {} with {}", name, ssss_key)),
+            "mac": STANDARD_NO_PAD.encode(format!("mac {}", name)),
+        }})
+    };
+    let account_data = [
+        json!({ "type": "m.direct", "content": direct }),
+        json!({
+            "type": "im.vector.setting.breadcrumbs",
+            "content": { "recent_rooms": account.rooms.iter().map(|r| &r.0).collect::<Vec<_>>() },
+        }),
+        json!({ "type": "m.secret_storage.default_key", "content": { "key": ssss_key } }),
+        json!({
+            "type": format!("m.secret_storage.key.{}", ssss_key),
+            "content": { "algorithm": "m.secret_storage.v1.aes-hmac-sha2", "name": "Recovery key" },
+        }),
+        json!({ "type": "m.megolm_backup.v1", "content": { "encrypted": sealed("m.megolm_backup.v1") } }),
+        push_rules(&account.rooms),
+    ];
+    for event in &account_data {
+        let event_type = event["type"].as_str().unwrap_or_default().to_string();
+        recent.push(idb_put(1, 2, IdbKey::String(event_type), event));
+    }
+
+    let sync = sync_snapshot(rng, account, &account.rooms, events_per_room);
+    recent.push(idb_put(1, 3, IdbKey::String("-".into()), &sync));
+
+    let account_pickle = json!(rng.base64(240));
+    recent.push(idb_put(2, 1, IdbKey::String("-".into()), &account_pickle));
+
+    let mut devices = serde_json::Map::new();
+    for device_id in [&account.previous_device_id, &account.device_id] {
+        devices.insert(
+            device_id.clone(),
+            json!({
+                "algorithms": ["m.olm.v1.curve25519-aes-sha2", "m.megolm.v1.aes-sha2"],
+                "keys": {
+                    format!("curve25519:{}", device_id): rng.base64(32),
+                    format!("ed25519:{}", device_id): rng.base64(32),
+                },
+                "verified": 1,
+                "known": true,
+                "unsigned": { "device_display_name": format!("Element Desktop: {}", rng.pick(&["Linux", "macOS", "Windows"])) },
+            }),
+        );
+    }
+    let device_data = json!({
+        "devices": { account.user_id.clone(): devices },
+        "trackingStatus": { account.user_id.clone(): 3 },
+        "syncToken": format!("s{}_{}", rng.below(1_000_000), rng.below(1_000)),
+    });
+    recent.push(idb_put(2, 2, IdbKey::String("-".into()), &device_data));
+
+    // Sessions for every other encrypted event, so rooms are partly decryptable;
+    // the other sessions were requested from the previous device
+    let sender_key =
+        devices[&account.device_id]["keys"][format!("curve25519:{}", account.device_id)].clone();
+    let previous_key = devices[&account.previous_device_id]["keys"]
+        [format!("curve25519:{}", account.previous_device_id)]
+    .clone();
+    let joined = sync["roomsData"]["join"].as_object().into_iter().flatten();
+    let mut queued = 0;
+    for (room_id, room) in joined {
+        let encrypted = room["timeline"]["events"].as_array().into_iter().flatten();
+        let session_ids = encrypted.filter_map(|e| e["content"]["session_id"].as_str());
+        for (i, session_id) in session_ids.enumerate() {
+            if i % 2 == 1 {
+                let request_id = format!("req{}", &session_id[..8]);
+                let body = json!({ "algorithm": "m.megolm.v1.aes-sha2", "room_id": room_id,
+                                   "session_id": session_id, "sender_key": sender_key });
+                let recipients =
+                    json!([{ "userId": account.user_id, "deviceId": account.previous_device_id }]);
+                // Sent on the first sync after the failure; the newest is still queued
+                let state = if i % 4 == 1 { 1 } else { 0 };
+                let request = json!({ "requestId": request_id, "requestTxnId": request_id, "cancellationTxnId": null,
+                                      "recipients": recipients, "requestBody": body, "state": state });
+                recent.push(idb_put(2, 5, IdbKey::String(request_id.clone()), &request));
+                if state == 1 {
+                    // The previous device refused: it does not trust this one
+                    let key = IdbKey::Array(vec![
+                        IdbKey::String(sender_key.as_str().unwrap_or_default().to_string()),
+                        IdbKey::String(session_id.to_string()),
+                    ]);
+                    let withheld = json!({ "senderCurve25519Key": sender_key, "sessionId": session_id,
+                        "session": { "algorithm": "m.megolm.v1.aes-sha2", "room_id": room_id, "session_id": session_id,
+                                     "sender_key": sender_key, "code": "m.unverified", "reason": "Device not verified" } });
+                    recent.push(idb_put(2, 6, key, &withheld));
+                }
+                if state == 0 {
+                    queued += 1;
+                    let batch = json!({ "id": queued, "eventType": "m.room_key_request", "txnId": request_id,
+                        "batch": [{ "userId": account.user_id, "deviceId": account.previous_device_id,
+                                    "payload": { "action": "request", "body": body, "request_id": request_id,
+                                                 "requesting_device_id": account.device_id } }] });
+                    recent.push(idb_put(1, 5, IdbKey::Number(queued as f64), &batch));
+                }
+                continue;
+            }
+            let key = IdbKey::Array(vec![
+                IdbKey::String(sender_key.as_str().unwrap_or_default().to_string()),
+                IdbKey::String(session_id.to_string()),
+            ]);
+            let session = json!({
+                "senderCurve25519Key": sender_key,
+                "sessionId": session_id,
+                "session": {
+                    "room_id": room_id,
+                    "session": STANDARD_NO_PAD.encode(format!("pickled {}", session_id)),
+                    "keysClaimed": { "ed25519": devices[&account.device_id]["keys"][format!("ed25519:{}", account.device_id)] },
+                    // The third session was forwarded by the previous device
+                    "forwardingCurve25519KeyChain": if i == 2 { json!([previous_key]) } else { json!([]) },
+                },
+            });
+            recent.push(idb_put(2, 3, key.clone(), &session));
+            if i % 4 == 0 {
+                let pending = json!({ "senderCurve25519Key": sender_key, "sessionId": session_id });
+                recent.push(idb_put(2, 4, key, &pending));
+            }
+        }
+    }
+
+    (compacted, recent)
+}
+
+/// `m.push_rules` muting the second room and setting the third to mentions only
+fn push_rules(rooms: &[(String, String, bool)]) -> Value {
+    let room_rule = |room: &(String, String, bool), actions: Value| {
+        json!({ "rule_id": room.0, "default": false, "enabled": true, "actions": actions,
+                "conditions": [{ "kind": "event_match", "key": "room_id", "pattern": room.0 }] })
+    };
+    json!({
+        "type": "m.push_rules",
+        "content": { "global": {
+            "override": rooms.get(1).map(|r| room_rule(r, json!([]))).into_iter().collect::<Vec<_>>(),
+            "room": rooms.get(2).map(|r| room_rule(r, json!(["dont_notify"]))).into_iter().collect::<Vec<_>>(),
+        }},
+    })
+}
+
+/// Room account data: the first room is a favourite with a custom notification sound
+fn room_account_data(index: usize) -> Value {
+    if index > 0 {
+        return json!([{ "type": "m.tag", "content": { "tags": {} } }]);
+    }
+    json!([
+        { "type": "m.tag", "content": { "tags": { "m.favourite": { "order": 0.5 } } } },
+        { "type": crate::sync::NOTIFICATION_SOUND_EVENT, "content": {
+            "url": "mxc://example.org/ding", "name": "ding.ogg", "type": "audio/ogg", "size": 5120 } },
+    ])
+}
+
+/// matrix-js-sdk sync accumulator snapshot as stored in the `sync` object store
+fn sync_snapshot(
+    rng: &mut Rng,
+    account: &Account,
+    rooms: &[(String, String, bool)],
+    events_per_room: usize,
+) -> Value {
+    let mut join = serde_json::Map::new();
+    for (i, (room_id, name, encrypted)) in rooms.iter().enumerate() {
+        let member = &account.contacts[i % account.contacts.len()].0;
+        let mut ts = account.created_ms + rng.below(1_000_000_000) as u64;
+        let mut state = vec![
+            json!({ "type": "m.room.name", "state_key": "", "sender": account.user_id, "content": { "name": name }, "origin_server_ts": ts, "event_id": format!("${}", rng.ident(43)) }),
+            json!({ "type": "m.room.member", "state_key": account.user_id, "sender": account.user_id, "content": { "membership": "join", "displayname": account.display_name }, "origin_server_ts": ts, "event_id": format!("${}", rng.ident(43)) }),
+            json!({ "type": "m.room.member", "state_key": member, "sender": member, "content": { "membership": "join" }, "origin_server_ts": ts, "event_id": format!("${}", rng.ident(43)) }),
+        ];
+        if *encrypted {
+            state.push(json!({ "type": "m.room.encryption", "state_key": "", "sender": account.user_id, "content": { "algorithm": "m.megolm.v1.aes-sha2" }, "origin_server_ts": ts, "event_id": format!("${}", rng.ident(43)) }));
+        }
+
+        let timeline: Vec<Value> = (0..events_per_room)
+            .map(|_| {
+                ts += rng.below(3_600_000) as u64;
+                let sender = if rng.below(2) == 0 { &account.user_id } else { member };
+                let event_id = format!("${}", rng.ident(43));
+                if *encrypted {
+                    json!({ "type": "m.room.encrypted", "sender": sender, "origin_server_ts": ts, "event_id": event_id,
+                            "content": { "algorithm": "m.megolm.v1.aes-sha2", "ciphertext": rng.base64(160), "session_id": rng.ident(43), "device_id": account.device_id } })
+                } else {
+                    json!({ "type": "m.room.message", "sender": sender, "origin_server_ts": ts, "event_id": event_id,
+                            "content": { "msgtype": "m.text", "body": format!("message {}", rng.ident(12)) } })
+                }
+            })
+            .collect();
+
+        join.insert(
+            room_id.clone(),
+            json!({
+                "state": { "events": state },
+                "timeline": { "events": timeline, "prev_batch": format!("t{}-{}", rng.below(100_000), rng.below(100_000)) },
+                "account_data": { "events": room_account_data(i) },
+                "ephemeral": { "events": [] },
+                "summary": { "m.joined_member_count": 2, "m.invited_member_count": 0 },
+                "unread_notifications": { "notification_count": rng.below(20), "highlight_count": rng.below(3) },
+            }),
+        );
+    }
+
+    json!({
+        "clobber": "-",
+        "nextBatch": format!("s{}_{}_{}", rng.below(10_000_000), rng.below(100_000), rng.below(1_000)),
+        "roomsData": { "join": join, "invite": {}, "leave": {} },
+        "accountData": [],
+    })
+}
+
+/// Writes a LevelDB directory: `compacted` as one level-0 table (keeping the
+/// last write per key), `recent` as write batches in the log
+fn write_database(
+    dir: &Path,
+    comparator: &str,
+    compacted: &[Write],
+    recent: &[Write],
+) -> Result<()> {
+    const MANIFEST_NUMBER: u64 = 2;
+    const TABLE_NUMBER: u64 = 5;
+    const LOG_NUMBER: u64 = 6;
+
+    std::fs::create_dir_all(dir)?;
+
+    let mut latest: BTreeMap<&[u8], (u64, &Write)> = BTreeMap::new();
+    for (i, write) in compacted.iter().enumerate() {
+        latest.insert(&write.1, (i as u64 + 1, write));
+    }
+    let mut table = TableBuilder::default();
+    for (key, (sequence, (value_type, _, value))) in &latest {
+        table.add(&internal_key(key, *sequence, *value_type), value);
+    }
+    let table = table.finish();
+    std::fs::write(dir.join(format!("{:06}.ldb", TABLE_NUMBER)), &table)?;
+
+    let mut log = LogWriter::default();
+    let mut sequence = compacted.len() as u64 + 1;
+    for chunk in recent.chunks(WRITE_BATCH_SIZE) {
+        let records: Vec<_> = chunk
+            .iter()
+            .map(|(t, k, v)| (*t, k.as_slice(), v.as_slice()))
+            .collect();
+        log.add_record(&encode_write_batch(sequence, &records));
+        sequence += chunk.len() as u64;
+    }
+    std::fs::write(dir.join(format!("{:06}.log", LOG_NUMBER)), log.finish())?;
+
+    let (first, last) = (latest.first_key_value(), latest.last_key_value());
+    let mut edit = VersionEdit {
+        comparator: Some(comparator.to_string()),
+        log_number: Some(LOG_NUMBER),
+        next_file_number: Some(LOG_NUMBER + 1),
+        last_sequence: Some(compacted.len() as u64),
+        new_files: Vec::new(),
+    };
+    if let (Some((smallest, (s_seq, s))), Some((largest, (l_seq, l)))) = (first, last) {
+        edit.new_files.push(FileMetaData {
+            level: 0,
+            number: TABLE_NUMBER,
+            size: table.len() as u64,
+            smallest: internal_key(smallest, *s_seq, s.0),
+            largest: internal_key(largest, *l_seq, l.0),
+        });
+    }
+    let mut manifest = LogWriter::default();
+    manifest.add_record(&edit.encode());
+    let manifest_name = format!("MANIFEST-{:06}", MANIFEST_NUMBER);
+    std::fs::write(dir.join(&manifest_name), manifest.finish())?;
+    std::fs::write(dir.join("CURRENT"), format!("{}\n", manifest_name))?;
+    std::fs::write(dir.join("LOCK"), b"")?;
+
+    Ok(())
+}
+
+/// Flips a byte inside the table's first data block and cuts the log short
+fn corrupt_database(dir: &Path, rng: &mut Rng) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let mut data = std::fs::read(&path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ldb") if data.len() > 64 => {
+                let at = 16 + rng.below(32);
+                data[at] ^= 0x5a;
+            }
+            Some("log") if data.len() > 16 => {
+                data.truncate(data.len() * 4 / 5);
+            }
+            _ => continue,
+        }
+        std::fs::write(&path, data)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+//! Desktop viewer for examiners who prefer not to use a terminal
+//!
+//! Built only with the `gui` feature. Opens a profile, shows the parsed
+//! metadata grouped into the same sections as the README, and supports text
+//! search, hiding empty fields and exporting the metadata as JSON.
+
+use anyhow::{anyhow, Result};
+use eframe::egui;
+use element_desktop_leveldb::{ElementLevelDBParser, ElementMetadata};
+use serde_json::Value;
+use std::path::Path;
+
+/// Metadata fields shown in each section, in display order
+const SECTIONS: &[(&str, &[&str])] = &[
+    ("Profile", &["user_id", "display_name", "avatar_url"]),
+    ("Settings", &["theme", "language", "notifications_enabled"]),
+    ("Rooms", &["rooms"]),
+    ("Device & encryption", &["devices"]),
+    ("Login history", &["login_history"]),
+    ("Raw entries", &["raw_entries"]),
+];
+
+/// Opens the viewer window, optionally with a profile already loaded
+pub fn run(path: Option<&Path>) -> Result<()> {
+    let mut viewer = Viewer::default();
+    if let Some(path) = path {
+        viewer.path = path.display().to_string();
+        viewer.open();
+    }
+    eframe::run_native(
+        "Element Desktop LevelDB Viewer",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Ok(Box::new(viewer))),
+    )
+    .map_err(|e| anyhow!("Viewer failed: {}", e))
+}
+
+#[derive(Default)]
+struct Viewer {
+    path: String,
+    export_path: String,
+    metadata: Option<ElementMetadata>,
+    /// Flattened `(field, value)` rows per section
+    rows: Vec<Vec<(String, String)>>,
+    section: usize,
+    search: String,
+    hide_empty: bool,
+    status: String,
+}
+
+impl Viewer {
+    fn open(&mut self) {
+        let result = ElementLevelDBParser::builder()
+            .login_history(true)
+            .open(&self.path)
+            .and_then(|p| p.parse_metadata());
+        match result {
+            Ok(metadata) => {
+                self.rows = section_rows(&metadata);
+                self.status = format!("Opened {}", self.path);
+                if self.export_path.is_empty() {
+                    self.export_path = "element-metadata.json".to_string();
+                }
+                self.metadata = Some(metadata);
+            }
+            Err(e) => {
+                self.metadata = None;
+                self.rows.clear();
+                self.status = format!("Error opening database: {}", e);
+            }
+        }
+    }
+
+    fn export(&mut self) {
+        let Some(metadata) = &self.metadata else {
+            return;
+        };
+        let result = serde_json::to_string_pretty(metadata)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(&self.export_path, json)?));
+        self.status = match result {
+            Ok(()) => format!("Exported to {}", self.export_path),
+            Err(e) => format!("Export failed: {}", e),
+        };
+    }
+
+    fn visible_rows(&self) -> Vec<&(String, String)> {
+        let needle = self.search.to_lowercase();
+        self.rows
+            .get(self.section)
+            .into_iter()
+            .flatten()
+            .filter(|(_, value)| !self.hide_empty || !value.is_empty())
+            .filter(|(field, value)| {
+                needle.is_empty()
+                    || field.to_lowercase().contains(&needle)
+                    || value.to_lowercase().contains(&needle)
+            })
+            .collect()
+    }
+}
+
+impl eframe::App for Viewer {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Profile:");
+                ui.text_edit_singleline(&mut self.path);
+                if ui.button("Open").clicked() {
+                    self.open();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Search:");
+                ui.text_edit_singleline(&mut self.search);
+                ui.checkbox(&mut self.hide_empty, "Hide empty fields");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Export to:");
+                ui.text_edit_singleline(&mut self.export_path);
+                let can_export = self.metadata.is_some() && !self.export_path.is_empty();
+                if ui
+                    .add_enabled(can_export, egui::Button::new("Export JSON"))
+                    .clicked()
+                {
+                    self.export();
+                }
+            });
+        });
+
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.label(&self.status);
+        });
+
+        egui::SidePanel::left("sections").show(ctx, |ui| {
+            for (i, (name, _)) in SECTIONS.iter().enumerate() {
+                let count = self.rows.get(i).map_or(0, Vec::len);
+                ui.selectable_value(&mut self.section, i, format!("{} ({})", name, count));
+            }
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if self.metadata.is_none() {
+                ui.label("Enter the path of a Local Storage leveldb directory and press Open.");
+                return;
+            }
+            let rows = self.visible_rows();
+            egui::ScrollArea::both().show(ui, |ui| {
+                egui::Grid::new("fields")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (field, value) in rows {
+                            ui.monospace(field);
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+    }
+}
+
+/// Flattens each section of the metadata into `(field, value)` rows
+fn section_rows(metadata: &ElementMetadata) -> Vec<Vec<(String, String)>> {
+    let json = serde_json::to_value(metadata).unwrap_or_default();
+    SECTIONS
+        .iter()
+        .map(|(_, fields)| {
+            let mut rows = Vec::new();
+            for field in *fields {
+                flatten(field, &json[field], &mut rows);
+            }
+            rows
+        })
+        .collect()
+}
+
+fn flatten(path: &str, value: &Value, rows: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            for (key, child) in entries {
+                flatten(&format!("{}.{}", path, key.escape_debug()), child, rows);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (i, child) in items.iter().enumerate() {
+                flatten(&format!("{}[{}]", path, i), child, rows);
+            }
+        }
+        Value::Null => rows.push((path.to_string(), String::new())),
+        Value::String(s) => rows.push((path.to_string(), s.clone())),
+        other => rows.push((path.to_string(), other.to_string())),
+    }
+}
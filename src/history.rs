@@ -0,0 +1,112 @@
+//! Login history reconstructed from every recorded version of the session keys
+//!
+//! The live database only holds the current session. Superseded values that
+//! are still in the write-ahead log or in uncompacted tables show earlier
+//! device IDs, access tokens and login timestamps, so the history is built
+//! from a raw scan rather than from the database view.
+
+use crate::localstorage;
+use crate::raw::{RawScan, ValueType};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Local Storage keys that describe a login session
+const SESSION_KEYS: &[&str] = &[
+    "mx_user_id",
+    "mx_device_id",
+    "mx_access_token",
+    "mx_last_login_ts",
+    "mx_soft_logout",
+];
+
+/// One login session, in the order the sessions were written
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Session {
+    pub user_id: Option<String>,
+    pub device_id: Option<String>,
+    /// Sequence numbers of the first and last record attributed to the session
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    /// `mx_last_login_ts` values seen during the session (milliseconds)
+    pub login_timestamps: Vec<u64>,
+    /// SHA-256 fingerprints (first 16 hex digits) of each access-token generation
+    pub access_tokens: Vec<String>,
+    /// `mx_soft_logout` was set during the session
+    pub soft_logout: bool,
+    /// The device ID was removed, i.e. the session was logged out
+    pub logged_out: bool,
+}
+
+/// Builds the ordered login history from a raw scan of a Local Storage database
+pub fn login_history(scan: &RawScan) -> Vec<Session> {
+    let mut records: Vec<_> = scan
+        .records
+        .iter()
+        .filter_map(|record| {
+            let (_, name) = localstorage::decode_key(&record.key)?;
+            SESSION_KEYS
+                .contains(&name.as_str())
+                .then_some((name, record))
+        })
+        .collect();
+    records.sort_by_key(|(_, record)| record.sequence);
+    records.dedup_by(|a, b| a.0 == b.0 && a.1.sequence == b.1.sequence);
+
+    let mut sessions: Vec<Session> = Vec::new();
+    for (name, record) in records {
+        let value = match record.value_type {
+            ValueType::Value => localstorage::decode_string(&record.value),
+            ValueType::Deletion => None,
+        };
+
+        // A different user or device starts a new session
+        let starts_session = match (name.as_str(), &value, sessions.last()) {
+            (_, _, None) => true,
+            ("mx_user_id", Some(v), Some(s)) => s.user_id.as_ref().is_some_and(|u| u != v),
+            ("mx_device_id", Some(v), Some(s)) => {
+                s.logged_out || s.device_id.as_ref().is_some_and(|d| d != v)
+            }
+            _ => false,
+        };
+        if starts_session {
+            let user_id = sessions.last().and_then(|s| s.user_id.clone());
+            sessions.push(Session {
+                user_id,
+                first_sequence: record.sequence,
+                ..Session::default()
+            });
+        }
+        let session = sessions
+            .last_mut()
+            .expect("a session exists after the first record");
+        session.last_sequence = record.sequence;
+
+        match (name.as_str(), value) {
+            ("mx_user_id", Some(v)) => session.user_id = Some(v),
+            ("mx_device_id", Some(v)) => session.device_id = Some(v),
+            ("mx_device_id", None) if record.value_type == ValueType::Deletion => {
+                session.logged_out = true;
+            }
+            ("mx_access_token", Some(v)) => {
+                let fingerprint = hex::encode(&Sha256::digest(v.as_bytes())[..8]);
+                if session.access_tokens.last() != Some(&fingerprint) {
+                    session.access_tokens.push(fingerprint);
+                }
+            }
+            ("mx_last_login_ts", Some(v)) => {
+                if let Ok(ts) = v.trim().parse::<u64>()
+                    && !session.login_timestamps.contains(&ts)
+                {
+                    session.login_timestamps.push(ts);
+                }
+            }
+            ("mx_soft_logout", Some(v)) if v == "true" => session.soft_logout = true,
+            _ => {}
+        }
+    }
+
+    sessions
+}
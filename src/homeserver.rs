@@ -0,0 +1,84 @@
+use anyhow::Result;
+use serde_json::Value;
+
+/// Largest response body that will be read
+const MAX_BODY_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Blocking client for the few Client-Server API calls the online steps need
+pub struct Homeserver {
+    base: String,
+    access_token: Option<String>,
+}
+
+impl Homeserver {
+    pub fn new(base: &str, access_token: Option<String>) -> Self {
+        Homeserver {
+            base: base.trim_end_matches('/').to_string(),
+            access_token,
+        }
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    pub fn is_authenticated(&self) -> bool {
+        self.access_token.is_some()
+    }
+
+    /// GETs a path below the homeserver base and parses the JSON body
+    pub fn get_json(&self, path: &str) -> Result<Value> {
+        let (body, _) = self.download(&format!("{}{}", self.base, path))?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// POSTs a JSON body to a path below the homeserver base and parses the reply
+    pub fn post_json(&self, path: &str, body: &Value) -> Result<Value> {
+        let mut request = ureq::post(&format!("{}{}", self.base, path))
+            .header("Content-Type", "application/json");
+        if let Some(token) = &self.access_token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let mut response = request.send(body.to_string())?;
+        let reply = response
+            .body_mut()
+            .with_config()
+            .limit(MAX_BODY_BYTES)
+            .read_to_vec()?;
+        Ok(serde_json::from_slice(&reply)?)
+    }
+
+    /// GETs an absolute URL, returning the body and its content type
+    pub fn download(&self, url: &str) -> Result<(Vec<u8>, Option<String>)> {
+        let mut request = ureq::get(url);
+        if let Some(token) = &self.access_token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let mut response = request.call()?;
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let body = response
+            .body_mut()
+            .with_config()
+            .limit(MAX_BODY_BYTES)
+            .read_to_vec()?;
+        Ok((body, content_type))
+    }
+}
+
+/// Percent-encodes a path segment such as a room or user ID
+pub fn encode_path_segment(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
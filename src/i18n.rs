@@ -0,0 +1,159 @@
+//! Fluent translations of CLI messages and report headings
+//!
+//! The locale is chosen once with `--lang`; messages missing from it fall back
+//! to English. JSON output and command usage lines are never translated.
+//! Timestamps in reports follow the locale's `datetime-format` in the zone
+//! chosen with `--timezone`.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
+use fluent::concurrent::FluentBundle;
+use fluent::{FluentArgs, FluentResource};
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+/// Bundled locales; the first is the fallback
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("fr", include_str!("../locales/fr.ftl")),
+    ("ru", include_str!("../locales/ru.ftl")),
+];
+
+struct Localizer {
+    selected: Option<FluentBundle<FluentResource>>,
+    fallback: FluentBundle<FluentResource>,
+}
+
+static LOCALIZER: OnceLock<Localizer> = OnceLock::new();
+
+/// Zone report timestamps are shown in
+#[derive(Debug, Clone, Copy)]
+enum Zone {
+    Utc,
+    /// The system zone, which follows `TZ`
+    Local,
+    Fixed(FixedOffset),
+}
+
+static ZONE: OnceLock<Zone> = OnceLock::new();
+
+fn bundle(lang: &str, source: &str) -> Result<FluentBundle<FluentResource>> {
+    let id: LanguageIdentifier = lang.parse()?;
+    let resource = FluentResource::try_new(source.to_string())
+        .map_err(|(_, errors)| anyhow!("Invalid {} translations: {:?}", lang, errors))?;
+    let mut bundle = FluentBundle::new_concurrent(vec![id]);
+    // Terminals and report files render the isolation marks as garbage
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .map_err(|errors| anyhow!("Duplicate {} messages: {:?}", lang, errors))?;
+    Ok(bundle)
+}
+
+/// Selects the output language; `de-AT` or `de_AT.UTF-8` select `de`
+pub fn init(lang: Option<&str>) -> Result<()> {
+    let (_, english) = LOCALES[0];
+    let selected = match lang {
+        None => None,
+        Some(lang) => {
+            let primary = lang
+                .split(['-', '_', '.'])
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            let Some((code, source)) = LOCALES.iter().find(|(code, _)| *code == primary) else {
+                let known: Vec<_> = LOCALES.iter().map(|(code, _)| *code).collect();
+                bail!(
+                    "Unsupported --lang {} (available: {})",
+                    lang,
+                    known.join(", ")
+                );
+            };
+            Some(bundle(code, source)?)
+        }
+    };
+    let localizer = Localizer {
+        selected,
+        fallback: bundle("en", english)?,
+    };
+    LOCALIZER
+        .set(localizer)
+        .map_err(|_| anyhow!("Language already selected"))
+}
+
+/// Selects the zone of report timestamps: `UTC` (default), `local` or an
+/// offset such as `+02:00`
+pub fn init_timezone(timezone: Option<&str>) -> Result<()> {
+    let zone = match timezone {
+        None => Zone::Utc,
+        Some(tz) if tz.eq_ignore_ascii_case("utc") || tz == "Z" => Zone::Utc,
+        Some(tz) if tz.eq_ignore_ascii_case("local") => Zone::Local,
+        Some(tz) => Zone::Fixed(tz.parse().map_err(|_| {
+            anyhow!(
+                "Unsupported --timezone {} (expected UTC, local or an offset such as +02:00)",
+                tz
+            )
+        })?),
+    };
+    ZONE.set(zone)
+        .map_err(|_| anyhow!("Time zone already selected"))
+}
+
+/// Formats a timestamp in milliseconds for a report in the selected zone
+pub fn datetime(millis: i64) -> Option<String> {
+    let utc = DateTime::<Utc>::from_timestamp_millis(millis)?;
+    let pattern = message("datetime-format", None);
+    let formatted = match ZONE.get().copied().unwrap_or(Zone::Utc) {
+        Zone::Utc => utc.fixed_offset().format(&pattern),
+        Zone::Local => utc.with_timezone(&Local).fixed_offset().format(&pattern),
+        Zone::Fixed(offset) => utc.with_timezone(&offset).format(&pattern),
+    };
+    Some(formatted.to_string())
+}
+
+/// Milliseconds since the epoch of `time` read in the selected zone; the
+/// earlier one when the local clock shows it twice
+pub fn timestamp(time: NaiveDateTime) -> Option<i64> {
+    let time = match ZONE.get().copied().unwrap_or(Zone::Utc) {
+        Zone::Utc => time.and_utc().timestamp_millis(),
+        Zone::Local => Local
+            .from_local_datetime(&time)
+            .earliest()?
+            .timestamp_millis(),
+        Zone::Fixed(offset) => offset
+            .from_local_datetime(&time)
+            .single()?
+            .timestamp_millis(),
+    };
+    Some(time)
+}
+
+/// Formats message `id` in the selected language
+pub fn message(id: &str, args: Option<&FluentArgs>) -> String {
+    let Some(localizer) = LOCALIZER.get() else {
+        return id.to_string();
+    };
+    for bundle in localizer.selected.iter().chain([&localizer.fallback]) {
+        if let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) {
+            let mut errors = Vec::new();
+            return bundle
+                .format_pattern(pattern, args, &mut errors)
+                .into_owned();
+        }
+    }
+    id.to_string()
+}
+
+/// `tr!("id")` or `tr!("id", name = value, ...)`: translated message
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::message($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::message($id, Some(&args))
+    }};
+}
+pub(crate) use tr;
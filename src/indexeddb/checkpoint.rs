@@ -0,0 +1,314 @@
+//! Decoding of large IndexedDB directories one object store at a time
+//!
+//! The crypto and sync stores of a heavy account can decode to far more
+//! memory than their LevelDB files take on disk. Here every record is written
+//! as a JSON line to `<database id>.<store id>.jsonl` as soon as it is decoded,
+//! and the file is flushed every `chunk_records` records, so decoded values
+//! never pile up; the live raw entries are the only thing kept for the whole
+//! run. The checkpoint lists the finished stores and is rewritten atomically
+//! after each one, so an interrupted run resumes with the first unfinished
+//! store.
+
+use super::key::{self, decode_entry_key, EntryKey, IdbKey};
+use super::reader::{Database, Origin, Record};
+use super::value::decode_record_value;
+use crate::raw;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Checkpoint metadata inside the output directory
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+/// Decoded records written between flushes unless told otherwise
+pub const DEFAULT_CHUNK_RECORDS: usize = 10_000;
+
+/// Progress of a checkpointed decode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub source: PathBuf,
+    /// RFC 3339 time the decode first started
+    pub started_at: String,
+    /// RFC 3339 time of the last update
+    pub updated_at: String,
+    /// Object stores fully decoded, in key order
+    pub stores: Vec<StoreProgress>,
+    /// Raw scan problems and records that could not be decoded
+    pub issues: Vec<String>,
+    /// How many times the decode was resumed
+    #[serde(default)]
+    pub resumes: u32,
+    /// Set once every store is done
+    #[serde(default)]
+    pub complete: bool,
+}
+
+/// One fully decoded object store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreProgress {
+    pub database_id: u64,
+    pub database: String,
+    pub object_store_id: u64,
+    pub store: String,
+    pub records: usize,
+    /// JSON lines file inside the output directory
+    pub file: String,
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint of `dir`, if there is one
+    pub fn load(dir: &Path) -> Result<Option<Checkpoint>> {
+        let path = dir.join(CHECKPOINT_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = std::fs::read(&path)?;
+        let checkpoint = serde_json::from_slice(&data)
+            .with_context(|| format!("Unreadable checkpoint {}", path.display()))?;
+        Ok(Some(checkpoint))
+    }
+
+    /// Writes the checkpoint through a temporary file and a rename
+    fn save(&self, dir: &Path) -> Result<()> {
+        let tmp = dir.join(format!("{}.tmp", CHECKPOINT_FILE));
+        let mut file = File::create(&tmp)?;
+        file.write_all(&serde_json::to_vec_pretty(self)?)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, dir.join(CHECKPOINT_FILE))?;
+        Ok(())
+    }
+
+    /// Reads the records of one decoded store back, one at a time
+    pub fn records(
+        &self,
+        dir: &Path,
+        database: &str,
+        store: &str,
+    ) -> Result<impl Iterator<Item = Result<Record>>> {
+        let progress = self
+            .stores
+            .iter()
+            .find(|s| s.database == database && s.store == store)
+            .ok_or_else(|| anyhow!("{}/{} was not decoded", database, store))?;
+        let file = File::open(dir.join(&progress.file))?;
+        Ok(BufReader::new(file).lines().map(|line| {
+            let line: Value = serde_json::from_str(&line?)?;
+            let bytes = hex::decode(line["key"].as_str().unwrap_or_default())?;
+            let (key, _) =
+                IdbKey::decode(&bytes).ok_or_else(|| anyhow!("Invalid key {}", line["key"]))?;
+            Ok(Record {
+                key,
+                value: line["value"].clone(),
+            })
+        }))
+    }
+
+    /// An `Origin` holding only the given `(database, store)` pairs, for the
+    /// extractors that need just a few stores
+    pub fn origin(&self, dir: &Path, stores: &[(&str, &str)]) -> Result<Origin> {
+        let mut databases: BTreeMap<u64, Database> = BTreeMap::new();
+        for progress in &self.stores {
+            if !stores.contains(&(progress.database.as_str(), progress.store.as_str())) {
+                continue;
+            }
+            let records = self
+                .records(dir, &progress.database, &progress.store)?
+                .collect::<Result<Vec<_>>>()?;
+            let db = databases
+                .entry(progress.database_id)
+                .or_insert_with(|| Database {
+                    id: progress.database_id,
+                    name: progress.database.clone(),
+                    ..Database::default()
+                });
+            db.object_stores.insert(progress.store.clone(), records);
+        }
+        Ok(Origin {
+            databases: databases.into_values().collect(),
+            issues: self.issues.clone(),
+        })
+    }
+}
+
+/// Decodes every object store of the IndexedDB directory `src` into `dir`
+///
+/// Without `resume` an existing checkpoint is an error rather than being
+/// overwritten. `on_progress` sees the checkpoint when the decode starts or
+/// resumes and after each store.
+pub fn decode_directory(
+    src: &Path,
+    dir: &Path,
+    resume: bool,
+    chunk_records: usize,
+    mut on_progress: impl FnMut(&Checkpoint, Option<&StoreProgress>) -> Result<()>,
+) -> Result<Checkpoint> {
+    let now = || chrono::Utc::now().to_rfc3339();
+    let source = src.canonicalize()?;
+    let scan = raw::scan_directory(&source)?;
+    let mut checkpoint = match Checkpoint::load(dir)? {
+        Some(_) if !resume => bail!(
+            "{} holds an earlier decode; pass --resume to continue it or delete it",
+            dir.display()
+        ),
+        Some(checkpoint) if checkpoint.source != source => bail!(
+            "Checkpoint {} belongs to {}, not {}",
+            dir.display(),
+            checkpoint.source.display(),
+            source.display()
+        ),
+        Some(mut checkpoint) => {
+            checkpoint.resumes += 1;
+            checkpoint
+        }
+        None => {
+            std::fs::create_dir_all(dir)?;
+            Checkpoint {
+                source: source.clone(),
+                started_at: now(),
+                updated_at: now(),
+                stores: Vec::new(),
+                issues: scan.issues.clone(),
+                resumes: 0,
+                complete: false,
+            }
+        }
+    };
+    let entries = scan.latest();
+    drop(scan);
+    checkpoint.updated_at = now();
+    checkpoint.save(dir)?;
+    on_progress(&checkpoint, None)?;
+
+    // Names first: their keys don't necessarily sort before the data
+    let mut database_names = BTreeMap::new();
+    let mut store_names = BTreeMap::new();
+    for (raw_key, value) in &entries {
+        match decode_entry_key(raw_key) {
+            Some(EntryKey::DatabaseName { name, .. }) => {
+                if let Some(id) = key::decode_int(value) {
+                    database_names.insert(id, name);
+                }
+            }
+            Some(EntryKey::ObjectStoreMeta {
+                database_id,
+                object_store_id,
+                field: key::OBJECT_STORE_NAME,
+            }) => {
+                store_names.insert((database_id, object_store_id), key::decode_string(value));
+            }
+            _ => {}
+        }
+    }
+
+    // The records of one store share a key prefix and so are contiguous
+    let mut current: Option<StoreWriter> = None;
+    for (raw_key, value) in &entries {
+        let Some(EntryKey::ObjectStoreData {
+            database_id,
+            object_store_id,
+            key,
+        }) = decode_entry_key(raw_key)
+        else {
+            continue;
+        };
+        let ids = (database_id, object_store_id);
+        if current.as_ref().is_none_or(|w| w.ids != ids) {
+            if let Some(writer) = current.take() {
+                finish(writer, &mut checkpoint, dir, &mut on_progress)?;
+            }
+            let done = checkpoint
+                .stores
+                .iter()
+                .any(|s| (s.database_id, s.object_store_id) == ids);
+            if !done {
+                let progress = StoreProgress {
+                    database_id,
+                    database: database_names
+                        .get(&database_id)
+                        .cloned()
+                        .unwrap_or_else(|| format!("database-{}", database_id)),
+                    object_store_id,
+                    store: store_names
+                        .get(&ids)
+                        .cloned()
+                        .unwrap_or_else(|| format!("store-{}", object_store_id)),
+                    records: 0,
+                    file: format!("{}.{}.jsonl", database_id, object_store_id),
+                };
+                // A file without a checkpoint entry is from an interrupted run
+                let file = File::create(dir.join(&progress.file))?;
+                current = Some(StoreWriter {
+                    ids,
+                    progress,
+                    out: BufWriter::new(file),
+                    chunk: 0,
+                    chunk_records,
+                });
+            }
+        }
+        let Some(writer) = current.as_mut().filter(|w| w.ids == ids) else {
+            continue;
+        };
+        match decode_record_value(value) {
+            Ok((_, value)) => writer.write(&key, value)?,
+            Err(e) => checkpoint.issues.push(format!(
+                "{}/{} {}: {}",
+                writer.progress.database, writer.progress.store, key, e
+            )),
+        }
+    }
+    if let Some(writer) = current.take() {
+        finish(writer, &mut checkpoint, dir, &mut on_progress)?;
+    }
+
+    checkpoint.complete = true;
+    checkpoint.updated_at = now();
+    checkpoint.save(dir)?;
+    Ok(checkpoint)
+}
+
+/// Output of the store being decoded
+struct StoreWriter {
+    ids: (u64, u64),
+    progress: StoreProgress,
+    out: BufWriter<File>,
+    /// Records since the last flush
+    chunk: usize,
+    chunk_records: usize,
+}
+
+impl StoreWriter {
+    fn write(&mut self, key: &IdbKey, value: Value) -> Result<()> {
+        let mut encoded = Vec::new();
+        key.encode(&mut encoded);
+        serde_json::to_writer(
+            &mut self.out,
+            &json!({ "key": hex::encode(encoded), "value": value }),
+        )?;
+        self.out.write_all(b"\n")?;
+        self.progress.records += 1;
+        self.chunk += 1;
+        if self.chunk >= self.chunk_records {
+            self.out.flush()?;
+            self.chunk = 0;
+        }
+        Ok(())
+    }
+}
+
+fn finish(
+    writer: StoreWriter,
+    checkpoint: &mut Checkpoint,
+    dir: &Path,
+    on_progress: &mut impl FnMut(&Checkpoint, Option<&StoreProgress>) -> Result<()>,
+) -> Result<()> {
+    let file = writer.out.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    checkpoint.stores.push(writer.progress);
+    checkpoint.updated_at = chrono::Utc::now().to_rfc3339();
+    checkpoint.save(dir)?;
+    on_progress(checkpoint, checkpoint.stores.last())
+}
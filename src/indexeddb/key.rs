@@ -0,0 +1,357 @@
+//! IndexedDB LevelDB key encoding
+
+use crate::raw::{read_varint, write_varint};
+
+/// Index id marking object store records
+pub const OBJECT_STORE_DATA_INDEX_ID: u64 = 1;
+
+/// Type byte of database name entries in the global metadata
+pub const DATABASE_NAME_TYPE_BYTE: u8 = 201;
+/// Type byte of object store metadata entries in database metadata
+pub const OBJECT_STORE_META_DATA_TYPE_BYTE: u8 = 50;
+/// Type byte of object store name entries in database metadata
+pub const OBJECT_STORE_NAMES_TYPE_BYTE: u8 = 200;
+/// Database metadata field holding the origin
+pub const DATABASE_ORIGIN_NAME: u8 = 0;
+/// Database metadata field holding the database name
+pub const DATABASE_NAME: u8 = 1;
+/// Database metadata field holding the largest object store id
+pub const DATABASE_MAX_OBJECT_STORE_ID: u8 = 3;
+/// Object store metadata field holding the store name
+pub const OBJECT_STORE_NAME: u8 = 0;
+
+const NULL_TYPE_BYTE: u8 = 0;
+const STRING_TYPE_BYTE: u8 = 1;
+const DATE_TYPE_BYTE: u8 = 2;
+const NUMBER_TYPE_BYTE: u8 = 3;
+const ARRAY_TYPE_BYTE: u8 = 4;
+const MIN_KEY_TYPE_BYTE: u8 = 5;
+const BINARY_TYPE_BYTE: u8 = 6;
+
+/// Deepest array nesting accepted when decoding keys
+const MAX_KEY_DEPTH: usize = 64;
+
+/// Database / object store / index triple leading every key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPrefix {
+    pub database_id: u64,
+    pub object_store_id: u64,
+    pub index_id: u64,
+}
+
+impl KeyPrefix {
+    /// Prefix of global metadata keys
+    pub fn global() -> Self {
+        Self::new(0, 0, 0)
+    }
+
+    pub fn new(database_id: u64, object_store_id: u64, index_id: u64) -> Self {
+        KeyPrefix {
+            database_id,
+            object_store_id,
+            index_id,
+        }
+    }
+
+    /// Encodes the prefix: one byte of id widths followed by the ids
+    pub fn encode(&self) -> Vec<u8> {
+        let database_id = encode_int(self.database_id);
+        let object_store_id = encode_int(self.object_store_id);
+        let index_id = encode_int(self.index_id);
+        let widths = ((database_id.len() as u8 - 1) << 5)
+            | ((object_store_id.len() as u8 - 1) << 2)
+            | (index_id.len() as u8 - 1);
+
+        let mut out = vec![widths];
+        out.extend(database_id);
+        out.extend(object_store_id);
+        out.extend(index_id);
+        out
+    }
+
+    /// Decodes a prefix, returning it and the number of bytes consumed
+    pub fn decode(data: &[u8]) -> Option<(Self, usize)> {
+        let widths = *data.first()?;
+        let database_len = usize::from(widths >> 5) + 1;
+        let object_store_len = usize::from((widths >> 2) & 0x07) + 1;
+        let index_len = usize::from(widths & 0x03) + 1;
+
+        let mut pos = 1;
+        let mut take = |len: usize| {
+            let id = decode_int(data.get(pos..pos + len)?);
+            pos += len;
+            id
+        };
+        let prefix = KeyPrefix {
+            database_id: take(database_len)?,
+            object_store_id: take(object_store_len)?,
+            index_id: take(index_len)?,
+        };
+        Some((prefix, pos))
+    }
+}
+
+/// IndexedDB key value
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdbKey {
+    /// Placeholder type Chromium writes for absent index keys
+    Null,
+    MinKey,
+    String(String),
+    Date(f64),
+    Number(f64),
+    Array(Vec<IdbKey>),
+    Binary(Vec<u8>),
+}
+
+impl IdbKey {
+    /// Appends the key in Chromium's ordered key encoding
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            IdbKey::Null => out.push(NULL_TYPE_BYTE),
+            IdbKey::MinKey => out.push(MIN_KEY_TYPE_BYTE),
+            IdbKey::String(s) => {
+                out.push(STRING_TYPE_BYTE);
+                encode_string_with_length(s, out);
+            }
+            IdbKey::Date(ms) => {
+                out.push(DATE_TYPE_BYTE);
+                out.extend_from_slice(&ms.to_le_bytes());
+            }
+            IdbKey::Number(n) => {
+                out.push(NUMBER_TYPE_BYTE);
+                out.extend_from_slice(&n.to_le_bytes());
+            }
+            IdbKey::Array(items) => {
+                out.push(ARRAY_TYPE_BYTE);
+                write_varint(out, items.len() as u64);
+                for item in items {
+                    item.encode(out);
+                }
+            }
+            IdbKey::Binary(data) => {
+                out.push(BINARY_TYPE_BYTE);
+                write_varint(out, data.len() as u64);
+                out.extend_from_slice(data);
+            }
+        }
+    }
+}
+
+impl IdbKey {
+    /// Decodes a key, returning it and the number of bytes consumed
+    pub fn decode(data: &[u8]) -> Option<(Self, usize)> {
+        Self::decode_nested(data, 0)
+    }
+
+    fn decode_nested(data: &[u8], depth: usize) -> Option<(Self, usize)> {
+        let rest = data.get(1..)?;
+        match *data.first()? {
+            NULL_TYPE_BYTE => Some((IdbKey::Null, 1)),
+            MIN_KEY_TYPE_BYTE => Some((IdbKey::MinKey, 1)),
+            STRING_TYPE_BYTE => {
+                let (s, used) = decode_string_with_length(rest)?;
+                Some((IdbKey::String(s), 1 + used))
+            }
+            DATE_TYPE_BYTE => Some((IdbKey::Date(decode_f64(rest)?), 9)),
+            NUMBER_TYPE_BYTE => Some((IdbKey::Number(decode_f64(rest)?), 9)),
+            ARRAY_TYPE_BYTE if depth < MAX_KEY_DEPTH => {
+                let (len, mut pos) = read_varint(rest)?;
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    let (item, used) = Self::decode_nested(rest.get(pos..)?, depth + 1)?;
+                    items.push(item);
+                    pos += used;
+                }
+                Some((IdbKey::Array(items), 1 + pos))
+            }
+            BINARY_TYPE_BYTE => {
+                let (len, used) = read_varint(rest)?;
+                let end = used.checked_add(usize::try_from(len).ok()?)?;
+                Some((IdbKey::Binary(rest.get(used..end)?.to_vec()), 1 + end))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for IdbKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdbKey::Null => write!(f, "null"),
+            IdbKey::MinKey => write!(f, "-Infinity"),
+            IdbKey::String(s) => write!(f, "{}", s),
+            IdbKey::Date(ms) => write!(f, "Date({})", ms),
+            IdbKey::Number(n) => write!(f, "{}", n),
+            IdbKey::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            IdbKey::Binary(data) => write!(f, "0x{}", hex::encode(data)),
+        }
+    }
+}
+
+fn decode_f64(data: &[u8]) -> Option<f64> {
+    Some(f64::from_le_bytes(data.get(..8)?.try_into().ok()?))
+}
+
+/// Inverse of `encode_int`; accepts at most eight bytes
+pub fn decode_int(data: &[u8]) -> Option<u64> {
+    if data.is_empty() || data.len() > 8 {
+        return None;
+    }
+    let mut bytes = [0u8; 8];
+    bytes[..data.len()].copy_from_slice(data);
+    Some(u64::from_le_bytes(bytes))
+}
+
+/// Decodes a UTF-16BE string without a length, replacing unpaired surrogates
+pub fn decode_string(data: &[u8]) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Decodes a length-prefixed UTF-16BE string, returning it and the bytes consumed
+pub fn decode_string_with_length(data: &[u8]) -> Option<(String, usize)> {
+    let (units, used) = read_varint(data)?;
+    let end = usize::try_from(units)
+        .ok()?
+        .checked_mul(2)?
+        .checked_add(used)?;
+    Some((decode_string(data.get(used..end)?), end))
+}
+
+/// Little-endian integer using the fewest bytes (at least one)
+pub fn encode_int(value: u64) -> Vec<u8> {
+    let bytes = value.to_le_bytes();
+    let len = bytes.iter().rposition(|b| *b != 0).map_or(1, |i| i + 1);
+    bytes[..len].to_vec()
+}
+
+/// UTF-16BE string without a length
+pub fn encode_string(s: &str, out: &mut Vec<u8>) {
+    for unit in s.encode_utf16() {
+        out.extend_from_slice(&unit.to_be_bytes());
+    }
+}
+
+/// UTF-16BE string preceded by its length in code units
+pub fn encode_string_with_length(s: &str, out: &mut Vec<u8>) {
+    write_varint(out, s.encode_utf16().count() as u64);
+    encode_string(s, out);
+}
+
+/// Key of the global entry mapping (origin, name) to a database id
+pub fn database_name_key(origin: &str, name: &str) -> Vec<u8> {
+    let mut key = KeyPrefix::global().encode();
+    key.push(DATABASE_NAME_TYPE_BYTE);
+    encode_string_with_length(origin, &mut key);
+    encode_string_with_length(name, &mut key);
+    key
+}
+
+/// Key of a per-database metadata field
+pub fn database_meta_key(database_id: u64, field: u8) -> Vec<u8> {
+    let mut key = KeyPrefix::new(database_id, 0, 0).encode();
+    key.push(field);
+    key
+}
+
+/// Key of an object store metadata field
+pub fn object_store_meta_key(database_id: u64, object_store_id: u64, field: u8) -> Vec<u8> {
+    let mut key = KeyPrefix::new(database_id, 0, 0).encode();
+    key.push(OBJECT_STORE_META_DATA_TYPE_BYTE);
+    write_varint(&mut key, object_store_id);
+    key.push(field);
+    key
+}
+
+/// Key of the entry mapping an object store name to its id
+pub fn object_store_names_key(database_id: u64, name: &str) -> Vec<u8> {
+    let mut key = KeyPrefix::new(database_id, 0, 0).encode();
+    key.push(OBJECT_STORE_NAMES_TYPE_BYTE);
+    encode_string_with_length(name, &mut key);
+    key
+}
+
+/// Key of an object store record
+pub fn object_store_data_key(database_id: u64, object_store_id: u64, user_key: &IdbKey) -> Vec<u8> {
+    let mut key = KeyPrefix::new(database_id, object_store_id, OBJECT_STORE_DATA_INDEX_ID).encode();
+    user_key.encode(&mut key);
+    key
+}
+
+/// Meaning of a raw IndexedDB LevelDB key
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryKey {
+    /// Global entry mapping an origin and database name to a database id
+    DatabaseName { origin: String, name: String },
+    /// Per-database metadata field
+    DatabaseMeta { database_id: u64, field: u8 },
+    /// Object store metadata field
+    ObjectStoreMeta {
+        database_id: u64,
+        object_store_id: u64,
+        field: u8,
+    },
+    /// Entry mapping an object store name to its id
+    ObjectStoreName { database_id: u64, name: String },
+    /// Object store record
+    ObjectStoreData {
+        database_id: u64,
+        object_store_id: u64,
+        key: IdbKey,
+    },
+    /// Anything else (indexes, free lists, blob journals, ...)
+    Other(KeyPrefix),
+}
+
+/// Decodes a raw IndexedDB LevelDB key
+pub fn decode_entry_key(data: &[u8]) -> Option<EntryKey> {
+    let (prefix, used) = KeyPrefix::decode(data)?;
+    let rest = &data[used..];
+
+    let entry = match (prefix.database_id, prefix.object_store_id, prefix.index_id) {
+        (0, 0, 0) if rest.first() == Some(&DATABASE_NAME_TYPE_BYTE) => {
+            let (origin, a) = decode_string_with_length(&rest[1..])?;
+            let (name, _) = decode_string_with_length(&rest[1 + a..])?;
+            EntryKey::DatabaseName { origin, name }
+        }
+        (0, _, _) => EntryKey::Other(prefix),
+        (database_id, 0, 0) => match *rest.first()? {
+            OBJECT_STORE_META_DATA_TYPE_BYTE => {
+                let (object_store_id, a) = read_varint(&rest[1..])?;
+                EntryKey::ObjectStoreMeta {
+                    database_id,
+                    object_store_id,
+                    field: *rest.get(1 + a)?,
+                }
+            }
+            OBJECT_STORE_NAMES_TYPE_BYTE => EntryKey::ObjectStoreName {
+                database_id,
+                name: decode_string_with_length(&rest[1..])?.0,
+            },
+            field if field < OBJECT_STORE_META_DATA_TYPE_BYTE => {
+                EntryKey::DatabaseMeta { database_id, field }
+            }
+            _ => EntryKey::Other(prefix),
+        },
+        (database_id, object_store_id, OBJECT_STORE_DATA_INDEX_ID) => EntryKey::ObjectStoreData {
+            database_id,
+            object_store_id,
+            key: IdbKey::decode(rest)?.0,
+        },
+        _ => EntryKey::Other(prefix),
+    };
+    Some(entry)
+}
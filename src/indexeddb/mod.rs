@@ -0,0 +1,17 @@
+//! Chromium IndexedDB on-LevelDB encoding
+//!
+//! Chromium stores every IndexedDB database of an origin in one LevelDB
+//! (`IndexedDB/<origin>.indexeddb.leveldb`) using the `idb_cmp1` comparator.
+//! Keys start with a compact `KeyPrefix` (database, object store, index ids);
+//! object store values are Blink-wrapped V8 serialized JavaScript values.
+
+#[cfg(feature = "serde")]
+pub mod checkpoint;
+pub mod key;
+#[cfg(feature = "serde")]
+pub mod reader;
+#[cfg(feature = "serde")]
+pub mod value;
+
+/// Comparator name recorded in every IndexedDB LevelDB manifest
+pub const COMPARATOR_NAME: &str = "idb_cmp1";
@@ -0,0 +1,119 @@
+//! Whole-origin reader on top of the key and value decoders
+//!
+//! Resolves database and object store ids to their names and decodes every
+//! object store record, so callers can ask for "the `accountData` store of
+//! `matrix-js-sdk:riot-web-sync`" without knowing the key layout.
+
+use super::key::{self, decode_entry_key, EntryKey, IdbKey};
+use super::value::decode_record_value;
+use crate::raw;
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Single decoded object store record
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub key: IdbKey,
+    pub value: Value,
+}
+
+/// One IndexedDB database with its object stores
+#[derive(Debug, Clone, Default)]
+pub struct Database {
+    pub id: u64,
+    pub origin: String,
+    pub name: String,
+    pub object_stores: BTreeMap<String, Vec<Record>>,
+}
+
+/// Every database stored in one `*.indexeddb.leveldb` directory
+#[derive(Debug, Default)]
+pub struct Origin {
+    pub databases: Vec<Database>,
+    /// Records that could not be decoded, and raw scan problems
+    pub issues: Vec<String>,
+}
+
+impl Origin {
+    /// Reads a directory with the raw readers; `idb_cmp1` databases can't be
+    /// opened with the bytewise comparator
+    pub fn read_directory<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let scan = raw::scan_directory(dir)?;
+        let mut origin = Origin::from_entries(&scan.latest());
+        origin.issues.splice(0..0, scan.issues);
+        Ok(origin)
+    }
+
+    /// Decodes the live entries of an IndexedDB LevelDB
+    pub fn from_entries(entries: &BTreeMap<Vec<u8>, Vec<u8>>) -> Self {
+        let mut databases: BTreeMap<u64, Database> = BTreeMap::new();
+        let mut store_names: BTreeMap<(u64, u64), String> = BTreeMap::new();
+        let mut data = Vec::new();
+
+        for (raw_key, value) in entries {
+            match decode_entry_key(raw_key) {
+                Some(EntryKey::DatabaseName { origin, name }) => {
+                    if let Some(id) = key::decode_int(value) {
+                        let db = databases.entry(id).or_default();
+                        db.id = id;
+                        db.origin = origin;
+                        db.name = name;
+                    }
+                }
+                Some(EntryKey::ObjectStoreMeta {
+                    database_id,
+                    object_store_id,
+                    field: key::OBJECT_STORE_NAME,
+                }) => {
+                    store_names.insert((database_id, object_store_id), key::decode_string(value));
+                }
+                Some(EntryKey::ObjectStoreData {
+                    database_id,
+                    object_store_id,
+                    key,
+                }) => data.push((database_id, object_store_id, key, value)),
+                _ => {}
+            }
+        }
+
+        let mut issues = Vec::new();
+        for (database_id, object_store_id, key, value) in data {
+            let store = store_names
+                .get(&(database_id, object_store_id))
+                .cloned()
+                .unwrap_or_else(|| format!("store-{}", object_store_id));
+            let db = databases.entry(database_id).or_insert_with(|| Database {
+                id: database_id,
+                name: format!("database-{}", database_id),
+                ..Database::default()
+            });
+            match decode_record_value(value) {
+                Ok((_, value)) => db
+                    .object_stores
+                    .entry(store)
+                    .or_default()
+                    .push(Record { key, value }),
+                Err(e) => issues.push(format!("{}/{} {}: {}", db.name, store, key, e)),
+            }
+        }
+
+        Origin {
+            databases: databases.into_values().collect(),
+            issues,
+        }
+    }
+
+    /// Database by name
+    pub fn database(&self, name: &str) -> Option<&Database> {
+        self.databases.iter().find(|db| db.name == name)
+    }
+
+    /// Records of an object store; empty when the database or store is missing
+    pub fn records(&self, database: &str, store: &str) -> &[Record] {
+        self.database(database)
+            .and_then(|db| db.object_stores.get(store))
+            .map_or(&[], Vec::as_slice)
+    }
+}
@@ -0,0 +1,298 @@
+//! IndexedDB record values: Blink-wrapped V8 serialization
+//!
+//! A record value is a varint record version followed by the Blink envelope
+//! (`0xFF <blink version>`), the V8 envelope (`0xFF <v8 version>`) and the V8
+//! serialized value itself. Only the JSON-compatible subset of V8 tags is
+//! handled, which is everything matrix-js-sdk stores outside of blobs.
+
+use crate::raw::{read_varint, write_varint};
+use serde_json::{Map, Number, Value};
+
+/// Leading byte of a Blink or V8 version envelope
+pub const VERSION_TAG: u8 = 0xFF;
+/// Blink serialization version written by current Chromium
+pub const BLINK_VERSION: u8 = 0x14;
+/// Pseudo Blink version marking a value moved out into a blob file
+pub const REQUIRES_PROCESSING_VERSION: u8 = 0x11;
+/// Blink tag following the Blink version in versions >= 21: offset + size of a trailer
+pub const TAG_TRAILER_OFFSET: u8 = 0xFE;
+/// V8 serialization version written by current Chromium
+pub const V8_VERSION: u8 = 0x0F;
+
+pub const TAG_PADDING: u8 = 0x00;
+pub const TAG_NULL: u8 = b'0';
+pub const TAG_UNDEFINED: u8 = b'_';
+pub const TAG_TRUE: u8 = b'T';
+pub const TAG_FALSE: u8 = b'F';
+pub const TAG_INT32: u8 = b'I';
+pub const TAG_UINT32: u8 = b'U';
+pub const TAG_DOUBLE: u8 = b'N';
+pub const TAG_ONE_BYTE_STRING: u8 = b'"';
+pub const TAG_TWO_BYTE_STRING: u8 = b'c';
+pub const TAG_UTF8_STRING: u8 = b'S';
+pub const TAG_BEGIN_OBJECT: u8 = b'o';
+pub const TAG_END_OBJECT: u8 = b'{';
+pub const TAG_BEGIN_DENSE_ARRAY: u8 = b'A';
+pub const TAG_END_DENSE_ARRAY: u8 = b'$';
+pub const TAG_BEGIN_SPARSE_ARRAY: u8 = b'a';
+pub const TAG_END_SPARSE_ARRAY: u8 = b'@';
+pub const TAG_DATE: u8 = b'D';
+pub const TAG_ARRAY_BUFFER: u8 = b'B';
+pub const TAG_OBJECT_REFERENCE: u8 = b'^';
+
+/// Deepest object/array nesting accepted when decoding
+const MAX_DEPTH: usize = 256;
+
+/// Encodes a record value: record version, envelopes and V8 body
+pub fn encode_record_value(record_version: u64, value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_varint(&mut out, record_version);
+    out.extend_from_slice(&[VERSION_TAG, BLINK_VERSION, VERSION_TAG, V8_VERSION]);
+    encode_v8(value, &mut out);
+    out
+}
+
+/// Appends the V8 serialization of a JSON value
+pub fn encode_v8(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(TAG_NULL),
+        Value::Bool(true) => out.push(TAG_TRUE),
+        Value::Bool(false) => out.push(TAG_FALSE),
+        Value::Number(n) => match n.as_i64().and_then(|i| i32::try_from(i).ok()) {
+            Some(i) => {
+                out.push(TAG_INT32);
+                write_varint(out, u64::from(((i << 1) ^ (i >> 31)) as u32));
+            }
+            None => {
+                out.push(TAG_DOUBLE);
+                out.extend_from_slice(&n.as_f64().unwrap_or(f64::NAN).to_le_bytes());
+            }
+        },
+        Value::String(s) => encode_v8_string(s, out),
+        Value::Array(items) => {
+            out.push(TAG_BEGIN_DENSE_ARRAY);
+            write_varint(out, items.len() as u64);
+            for item in items {
+                encode_v8(item, out);
+            }
+            out.push(TAG_END_DENSE_ARRAY);
+            write_varint(out, 0);
+            write_varint(out, items.len() as u64);
+        }
+        Value::Object(map) => {
+            out.push(TAG_BEGIN_OBJECT);
+            for (key, item) in map {
+                encode_v8_string(key, out);
+                encode_v8(item, out);
+            }
+            out.push(TAG_END_OBJECT);
+            write_varint(out, map.len() as u64);
+        }
+    }
+}
+
+/// Latin-1 strings use the one-byte form, everything else UTF-16LE
+fn encode_v8_string(s: &str, out: &mut Vec<u8>) {
+    if s.chars().all(|c| (c as u32) < 0x100) {
+        out.push(TAG_ONE_BYTE_STRING);
+        write_varint(out, s.chars().count() as u64);
+        out.extend(s.chars().map(|c| c as u8));
+    } else {
+        let units: Vec<u16> = s.encode_utf16().collect();
+        out.push(TAG_TWO_BYTE_STRING);
+        write_varint(out, (units.len() * 2) as u64);
+        for unit in units {
+            out.extend_from_slice(&unit.to_le_bytes());
+        }
+    }
+}
+
+/// Decodes a record value into its record version and JSON form
+pub fn decode_record_value(data: &[u8]) -> Result<(u64, Value), String> {
+    let (record_version, used) = read_varint(data).ok_or("truncated record version")?;
+    let body = &data[used..];
+    if body.is_empty() {
+        // Tombstone-like record without a serialized value
+        return Ok((record_version, Value::Null));
+    }
+    Ok((record_version, decode_v8(body)?))
+}
+
+/// Decodes a Blink/V8 serialized value (with its version envelopes)
+pub fn decode_v8(data: &[u8]) -> Result<Value, String> {
+    let mut decoder = Decoder { data, pos: 0 };
+    decoder.skip_envelopes()?;
+    decoder.read_value(0)
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Decoder<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.data.get(self.pos).ok_or("unexpected end of value")?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&[u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or("length runs past end of value")?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let (value, used) = read_varint(&self.data[self.pos..]).ok_or("bad varint")?;
+        self.pos += used;
+        Ok(value)
+    }
+
+    fn length(&mut self) -> Result<usize, String> {
+        usize::try_from(self.varint()?).map_err(|_| "length overflow".to_string())
+    }
+
+    fn skip_envelopes(&mut self) -> Result<(), String> {
+        let mut first = true;
+        while self.data.get(self.pos) == Some(&VERSION_TAG) {
+            self.pos += 1;
+            let version = self.varint()?;
+            if first
+                && version == u64::from(REQUIRES_PROCESSING_VERSION)
+                && self.data.get(self.pos) == Some(&1)
+            {
+                return Err("value is stored in an external blob file".to_string());
+            }
+            if self.data.get(self.pos) == Some(&TAG_TRAILER_OFFSET) {
+                self.bytes(1 + 8 + 4)?;
+            }
+            first = false;
+        }
+        Ok(())
+    }
+
+    /// Reads the next tag, skipping alignment padding
+    fn tag(&mut self) -> Result<u8, String> {
+        loop {
+            match self.byte()? {
+                TAG_PADDING => continue,
+                tag => return Ok(tag),
+            }
+        }
+    }
+
+    fn read_value(&mut self, depth: usize) -> Result<Value, String> {
+        if depth > MAX_DEPTH {
+            return Err("value nested too deeply".to_string());
+        }
+        let tag = self.tag()?;
+        self.read_tagged(tag, depth)
+    }
+
+    fn read_tagged(&mut self, tag: u8, depth: usize) -> Result<Value, String> {
+        let value = match tag {
+            TAG_NULL | TAG_UNDEFINED => Value::Null,
+            TAG_TRUE => Value::Bool(true),
+            TAG_FALSE => Value::Bool(false),
+            TAG_INT32 => {
+                let zigzag = self.varint()? as u32;
+                let value = ((zigzag >> 1) as i32) ^ -((zigzag & 1) as i32);
+                Value::from(value)
+            }
+            TAG_UINT32 => Value::from(self.varint()? as u32),
+            TAG_DOUBLE | TAG_DATE => {
+                let raw = self.bytes(8)?;
+                let value = f64::from_le_bytes(raw.try_into().expect("8-byte slice"));
+                Number::from_f64(value).map_or(Value::Null, Value::Number)
+            }
+            TAG_ONE_BYTE_STRING | TAG_TWO_BYTE_STRING | TAG_UTF8_STRING => {
+                Value::String(self.read_string(tag)?)
+            }
+            TAG_BEGIN_OBJECT => Value::Object(self.read_properties(TAG_END_OBJECT, depth)?),
+            TAG_BEGIN_DENSE_ARRAY => {
+                let len = self.length()?;
+                let mut items = Vec::with_capacity(len.min(4096));
+                for _ in 0..len {
+                    items.push(self.read_value(depth + 1)?);
+                }
+                // Dense arrays may carry extra named properties before the end tag
+                let extra = self.read_properties(TAG_END_DENSE_ARRAY, depth)?;
+                self.varint()?;
+                if extra.is_empty() {
+                    Value::Array(items)
+                } else {
+                    let mut map: Map<String, Value> = items
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, v)| (i.to_string(), v))
+                        .collect();
+                    map.extend(extra);
+                    Value::Object(map)
+                }
+            }
+            TAG_BEGIN_SPARSE_ARRAY => {
+                self.varint()?;
+                let map = self.read_properties(TAG_END_SPARSE_ARRAY, depth)?;
+                self.varint()?;
+                Value::Object(map)
+            }
+            TAG_ARRAY_BUFFER => {
+                let len = self.length()?;
+                Value::String(format!("0x{}", hex::encode(self.bytes(len)?)))
+            }
+            TAG_OBJECT_REFERENCE => {
+                let id = self.varint()?;
+                serde_json::json!({ "$ref": id })
+            }
+            other => {
+                return Err(format!(
+                    "unsupported V8 tag 0x{:02x} at {}",
+                    other,
+                    self.pos - 1
+                ))
+            }
+        };
+        Ok(value)
+    }
+
+    fn read_string(&mut self, tag: u8) -> Result<String, String> {
+        let len = self.length()?;
+        let raw = self.bytes(len)?;
+        Ok(match tag {
+            TAG_ONE_BYTE_STRING => raw.iter().map(|b| char::from(*b)).collect(),
+            TAG_TWO_BYTE_STRING => {
+                let units: Vec<u16> = raw
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .collect();
+                String::from_utf16_lossy(&units)
+            }
+            _ => String::from_utf8_lossy(raw).into_owned(),
+        })
+    }
+
+    /// Reads key/value pairs until `end`, then the property count; array
+    /// callers still have to read the trailing length
+    fn read_properties(&mut self, end: u8, depth: usize) -> Result<Map<String, Value>, String> {
+        let mut map = Map::new();
+        loop {
+            let tag = self.tag()?;
+            if tag == end {
+                self.varint()?;
+                return Ok(map);
+            }
+            let key = match self.read_tagged(tag, depth + 1)? {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            let value = self.read_value(depth + 1)?;
+            map.insert(key, value);
+        }
+    }
+}
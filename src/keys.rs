@@ -0,0 +1,135 @@
+//! Typed classification of Element's Local Storage item names
+//!
+//! `classify_key` holds the heuristics `parse_metadata` uses to recognise the
+//! items it reports, so other tools can switch on an `ElementKey` instead of
+//! matching names themselves. Names are the decoded item names, like
+//! `Classification::Element` carries them.
+
+use crate::rooms::find_room_id;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// What an Element Local Storage item holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum ElementKey {
+    /// The logged in Matrix user ID
+    UserId,
+    DisplayName,
+    AvatarUrl,
+    /// The ID of this session's device (`mx_device_id`)
+    DeviceId,
+    /// `mx_hs_url`
+    HomeserverUrl,
+    /// `mx_is_url`
+    IdentityServerUrl,
+    /// `mx_access_token`, plaintext or encrypted with the pickle key
+    AccessToken,
+    /// `mx_refresh_token`, plaintext or encrypted with the pickle key
+    RefreshToken,
+    Theme,
+    Language,
+    Notifications,
+    /// Per-room state, such as a draft or right panel phases, by room ID
+    RoomState(String),
+    /// Pickle key bookkeeping for the crypto store
+    CryptoPickle,
+    /// A device-level `mx_setting_<name>`, by name
+    Setting(String),
+    /// Anything else, by item name
+    Unknown(String),
+}
+
+/// Broad group of an `ElementKey`, for summaries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum KeyCategory {
+    /// Account, session and homeserver
+    User,
+    Rooms,
+    Crypto,
+    Settings,
+    Other,
+}
+
+impl KeyCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KeyCategory::User => "user",
+            KeyCategory::Rooms => "rooms",
+            KeyCategory::Crypto => "crypto",
+            KeyCategory::Settings => "settings",
+            KeyCategory::Other => "other",
+        }
+    }
+}
+
+impl ElementKey {
+    pub fn category(&self) -> KeyCategory {
+        match self {
+            ElementKey::UserId
+            | ElementKey::DisplayName
+            | ElementKey::AvatarUrl
+            | ElementKey::DeviceId
+            | ElementKey::HomeserverUrl
+            | ElementKey::IdentityServerUrl
+            | ElementKey::AccessToken
+            | ElementKey::RefreshToken => KeyCategory::User,
+            ElementKey::RoomState(_) => KeyCategory::Rooms,
+            ElementKey::CryptoPickle => KeyCategory::Crypto,
+            ElementKey::Theme
+            | ElementKey::Language
+            | ElementKey::Notifications
+            | ElementKey::Setting(_) => KeyCategory::Settings,
+            ElementKey::Unknown(name)
+                if [
+                    "crypto",
+                    "olm",
+                    "secret_storage",
+                    "cross_signing",
+                    "key_backup",
+                ]
+                .iter()
+                .any(|part| name.contains(part)) =>
+            {
+                KeyCategory::Crypto
+            }
+            ElementKey::Unknown(name) if name.contains("room") => KeyCategory::Rooms,
+            ElementKey::Unknown(name) if name.contains("setting") => KeyCategory::Settings,
+            ElementKey::Unknown(_) => KeyCategory::Other,
+        }
+    }
+}
+
+/// Classifies an Element Local Storage item by name
+pub fn classify_key(name: &str) -> ElementKey {
+    match name {
+        // Loose matches first: these decide what `parse_metadata` reports
+        k if k.contains("user_id") || k.contains("userId") => ElementKey::UserId,
+        k if k.contains("display_name")
+            || k.contains("displayName")
+            || k.contains("displayname") =>
+        {
+            ElementKey::DisplayName
+        }
+        k if k.contains("avatar") => ElementKey::AvatarUrl,
+        k if k.contains("theme") => ElementKey::Theme,
+        k if k.contains("language") || k.contains("locale") => ElementKey::Language,
+        k if k.contains("notification") => ElementKey::Notifications,
+
+        "mx_device_id" => ElementKey::DeviceId,
+        "mx_hs_url" => ElementKey::HomeserverUrl,
+        "mx_is_url" => ElementKey::IdentityServerUrl,
+        "mx_access_token" => ElementKey::AccessToken,
+        "mx_refresh_token" => ElementKey::RefreshToken,
+        k if k.contains("pickle") => ElementKey::CryptoPickle,
+        k => match (find_room_id(k), k.strip_prefix("mx_setting_")) {
+            (Some(room_id), _) => ElementKey::RoomState(room_id.to_string()),
+            (None, Some(setting)) => ElementKey::Setting(setting.to_string()),
+            (None, None) => ElementKey::Unknown(k.to_string()),
+        },
+    }
+}
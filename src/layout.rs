@@ -0,0 +1,108 @@
+//! Element Desktop and Element Web profile layouts
+//!
+//! Desktop serves the app from `vector://vector` in a profile of its own;
+//! Element Web runs on an https origin inside a browser profile whose Local
+//! Storage it shares with every other site. Key names and IndexedDB database
+//! names are the same in both, so once the Element origin is known the
+//! extractors and the report do not depend on the variant.
+
+use crate::{localstorage, ELEMENT_DESKTOP_ORIGIN};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Local Storage entries only Element writes; used to find its origin
+pub(crate) const ELEMENT_KEYS: &[&str] = &[
+    "mx_hs_url",
+    "mx_is_url",
+    "mx_user_id",
+    "mx_device_id",
+    "mx_access_token",
+    "mx_local_settings",
+];
+
+/// Which Element build wrote a profile
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Variant {
+    #[default]
+    Desktop,
+    Web,
+}
+
+/// Where Element keeps its data in a profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Layout {
+    pub variant: Variant,
+    /// Web origin the app runs on, e.g. `vector://vector` or `https://app.element.io`
+    pub origin: String,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            variant: Variant::Desktop,
+            origin: ELEMENT_DESKTOP_ORIGIN.to_string(),
+        }
+    }
+}
+
+impl Layout {
+    /// Layout of an Element Web deployment at `origin`
+    pub fn web(origin: &str) -> Self {
+        Layout {
+            variant: Variant::Web,
+            origin: origin.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Finds the Element origin among raw Local Storage entries
+    ///
+    /// The Desktop origin wins when present; otherwise the origin with the
+    /// most Element keys. `None` when no origin has any.
+    pub fn detect<'a>(entries: impl IntoIterator<Item = (&'a [u8], &'a [u8])>) -> Option<Self> {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for (key, _) in entries {
+            if let Some((origin, name)) = localstorage::decode_key(key)
+                && ELEMENT_KEYS.contains(&name.as_str())
+            {
+                *counts.entry(origin).or_default() += 1;
+            }
+        }
+        Self::from_counts(counts)
+    }
+
+    /// The layout for the number of Element keys found under each origin
+    pub(crate) fn from_counts(counts: BTreeMap<String, usize>) -> Option<Self> {
+        if counts.contains_key(ELEMENT_DESKTOP_ORIGIN) {
+            return Some(Layout::default());
+        }
+        let (origin, _) = counts.into_iter().max_by_key(|(_, count)| *count)?;
+        Some(Layout::web(&origin))
+    }
+
+    /// Chromium's storage identifier of the origin: `<scheme>_<host>_<port>`,
+    /// with port 0 for the scheme's default, e.g. `https_app.element.io_0`
+    pub fn origin_id(&self) -> String {
+        let (scheme, rest) = self.origin.split_once("://").unwrap_or(("", &self.origin));
+        let (host, port) = match rest.rsplit_once(':') {
+            Some((host, port)) if port.parse::<u16>().is_ok() => (host, port),
+            _ => (rest, "0"),
+        };
+        format!("{}_{}_{}", scheme, host, port)
+    }
+
+    /// The origin's IndexedDB directory next to `<profile>/Local Storage/leveldb`
+    pub fn indexeddb_dir(&self, local_storage: &Path) -> Option<PathBuf> {
+        let profile = local_storage.parent()?.parent()?;
+        let dir = profile
+            .join("IndexedDB")
+            .join(format!("{}.indexeddb.leveldb", self.origin_id()));
+        dir.is_dir().then_some(dir)
+    }
+}
@@ -0,0 +1,134 @@
+//! Element Desktop LevelDB parsing as a library
+//!
+//! `ElementLevelDBParser` and its `ElementMetadata` report are the main entry
+//! point; the `element-desktop-leveldb` binary is a command line front end
+//! over this crate. The modules hold the raw LevelDB file readers/writers,
+//! Chromium Local Storage and IndexedDB decoding for Element Desktop and
+//! Element Web profiles, login history reconstruction, typed settings, key
+//! statistics, secret scanning, encryption posture analysis and the synthetic
+//! profile generator. The decoders are also the entry points of the fuzz
+//! targets in `fuzz/`.
+//!
+//! The exporters behind `export` (records, CSV, XML, CBOR and MessagePack,
+//! forensic timelines, `--query` and `--canonical`) are here too, as are the
+//! change sets of `diff` and `apply`, `clone`, `search`, the homeserver
+//! clients and the watchers; the binary only turns arguments into calls and
+//! prints the results.
+//!
+//! Library users who only need the parser can turn the default features off:
+//! `cli` is the binary and its dependencies, `serde` the serialization of the
+//! reports and everything read from JSON values (settings, the IndexedDB
+//! sync cache), `crypto` the crypto store readers, `parallel` the threaded
+//! raw reads and `async` the tokio API. `export`, `sqlite` and `parquet` are
+//! the export formats, `online` the homeserver clients, `live` the sync
+//! stream and `watch` the file watchers.
+
+#[cfg(feature = "serde")]
+pub mod assessment;
+#[cfg(feature = "async")]
+pub mod async_parser;
+#[cfg(feature = "serde")]
+pub mod audit;
+#[cfg(feature = "export")]
+pub mod binary;
+#[cfg(feature = "export")]
+pub mod canonical;
+#[cfg(feature = "serde")]
+pub mod changeset;
+#[cfg(feature = "serde")]
+pub mod clone;
+#[cfg(feature = "serde")]
+pub mod contacts;
+#[cfg(feature = "ruma")]
+pub mod convert;
+#[cfg(feature = "serde")]
+pub mod correlation;
+#[cfg(feature = "crypto")]
+pub mod crypto;
+#[cfg(feature = "export")]
+pub mod csv;
+pub mod decoders;
+pub mod devices;
+#[cfg(feature = "online")]
+pub mod enrich;
+pub mod entries;
+pub mod error;
+#[cfg(feature = "export")]
+pub mod export;
+#[cfg(feature = "online")]
+pub mod fetch;
+#[cfg(feature = "serde")]
+pub mod fields;
+pub mod findings;
+#[cfg(feature = "serde")]
+pub mod fixtures;
+pub mod history;
+#[cfg(feature = "online")]
+pub mod homeserver;
+pub mod indexeddb;
+pub mod keys;
+pub mod layout;
+#[cfg(feature = "live")]
+pub mod live;
+pub mod localstorage;
+pub mod media;
+#[cfg(feature = "watch")]
+pub mod monitor;
+pub mod openmetrics;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod parser;
+pub mod plugins;
+#[cfg(feature = "crypto")]
+pub mod posture;
+#[cfg(feature = "serde")]
+pub mod preview;
+pub mod progress;
+#[cfg(feature = "export")]
+pub mod query;
+pub mod raw;
+pub mod rooms;
+#[cfg(feature = "serde")]
+pub mod sarif;
+#[cfg(feature = "export")]
+pub mod search;
+pub mod settings;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+#[cfg(feature = "serde")]
+pub mod sync;
+#[cfg(feature = "export")]
+pub mod timeline;
+#[cfg(feature = "online")]
+pub mod verify;
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "export")]
+pub mod xml;
+
+#[cfg(feature = "async")]
+pub use async_parser::{AsyncElementLevelDBParser, EntryStream};
+pub use bytes::Bytes;
+pub use decoders::{DecoderRegistry, ValueDecoder};
+pub use entries::{Classification, Entries, Entry, EntryVisitor};
+pub use error::ParserError;
+pub use keys::{classify_key, ElementKey, KeyCategory};
+pub use parser::{
+    BinaryEncoding, ElementLevelDBParser, ElementMetadata, ParserBuilder, ParserOptions,
+    ParserSnapshot,
+};
+pub use plugins::{KeyParserPlugin, KeyParserPlugins};
+pub use progress::{Progress, ProgressCallback};
+
+/// Version of the `ElementMetadata` structure, written into every report as
+/// `schema_version`; raised when a field is removed or changes meaning
+pub const SCHEMA_VERSION: u32 = 1;
+/// Origin Element Desktop serves its web app from
+pub const ELEMENT_DESKTOP_ORIGIN: &str = "vector://vector";
+/// Chromium's identifier for the Element Desktop origin
+pub const ELEMENT_DESKTOP_ORIGIN_ID: &str = "vector_vector_0";
+/// Name of the matrix-js-sdk sync store database
+pub const SYNC_DATABASE: &str = "matrix-js-sdk:riot-web-sync";
+/// Name of the legacy matrix-js-sdk crypto store database
+pub const CRYPTO_DATABASE: &str = "matrix-js-sdk:crypto";
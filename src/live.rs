@@ -0,0 +1,249 @@
+//! Live extraction from a running Element Desktop over the Chrome DevTools Protocol
+//!
+//! Element Desktop started with `--remote-debugging-port=<port>` exposes its
+//! renderer over CDP. Reading storage through the renderer sidesteps the LOCK
+//! file held by the running app, and includes state that never reaches disk
+//! such as sessionStorage and writes still sitting in the memtable.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::net::TcpStream;
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+/// Host the DevTools endpoint is reached on; remote debugging only listens locally
+const DEVTOOLS_HOST: &str = "127.0.0.1";
+/// Records fetched per `IndexedDB.requestData` call
+const PAGE_SIZE: u64 = 100;
+/// How long to wait for a single CDP response
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Returns the receiver by value so objects come back as JSON
+const RETURN_THIS: &str = "function() { return this; }";
+
+/// Debuggable target listed by the `/json/list` endpoint
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Target {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    web_socket_debugger_url: Option<String>,
+}
+
+/// Storage captured from a running renderer
+#[derive(Debug, Serialize)]
+pub struct LiveDump {
+    pub origin: String,
+    pub target_url: String,
+    pub local_storage: BTreeMap<String, String>,
+    /// In-memory only, never written to disk
+    pub session_storage: BTreeMap<String, String>,
+    pub indexeddb: BTreeMap<String, LiveDatabase>,
+}
+
+/// One IndexedDB database as seen by the renderer
+#[derive(Debug, Serialize)]
+pub struct LiveDatabase {
+    pub version: f64,
+    pub object_stores: BTreeMap<String, Vec<LiveRecord>>,
+}
+
+/// Key and value of a single object store record
+#[derive(Debug, Serialize)]
+pub struct LiveRecord {
+    pub key: Value,
+    pub value: Value,
+}
+
+/// Minimal synchronous CDP client over a single target's WebSocket
+struct CdpClient {
+    socket: WebSocket<TcpStream>,
+    next_id: u64,
+}
+
+impl CdpClient {
+    fn connect(port: u16, url: &str) -> Result<Self> {
+        let stream = TcpStream::connect((DEVTOOLS_HOST, port))?;
+        stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+        let (socket, _) = tungstenite::client(url, stream)
+            .map_err(|e| anyhow!("WebSocket handshake with {} failed: {}", url, e))?;
+        Ok(CdpClient { socket, next_id: 1 })
+    }
+
+    /// Sends a command and waits for its response, skipping events
+    fn call(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({ "id": id, "method": method, "params": params });
+        self.socket.send(Message::text(request.to_string()))?;
+
+        loop {
+            let text = match self.socket.read()? {
+                Message::Text(text) => text,
+                Message::Close(_) => bail!("DevTools closed the connection during {}", method),
+                _ => continue,
+            };
+            let mut response: Value = serde_json::from_str(text.as_str())?;
+            if response["id"].as_u64() != Some(id) {
+                continue;
+            }
+            if let Some(error) = response.get("error") {
+                bail!("{} failed: {}", method, error["message"]);
+            }
+            return Ok(response["result"].take());
+        }
+    }
+
+    /// Resolves a `Runtime.RemoteObject` to its JSON value
+    fn remote_value(&mut self, object: &Value) -> Result<Value> {
+        match object["objectId"].as_str() {
+            Some(object_id) => {
+                let mut result = self.call(
+                    "Runtime.callFunctionOn",
+                    json!({
+                        "objectId": object_id,
+                        "functionDeclaration": RETURN_THIS,
+                        "returnByValue": true,
+                    }),
+                )?;
+                Ok(result["result"]["value"].take())
+            }
+            None => Ok(object.get("value").cloned().unwrap_or(Value::Null)),
+        }
+    }
+
+    fn dom_storage(&mut self, origin: &str, local: bool) -> Result<BTreeMap<String, String>> {
+        let result = self.call(
+            "DOMStorage.getDOMStorageItems",
+            json!({ "storageId": { "securityOrigin": origin, "isLocalStorage": local } }),
+        )?;
+        let entries = result["entries"].as_array().cloned().unwrap_or_default();
+        Ok(entries
+            .iter()
+            .filter_map(|entry| {
+                let key = entry[0].as_str()?;
+                let value = entry[1].as_str()?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect())
+    }
+
+    fn database(&mut self, origin: &str, name: &str) -> Result<LiveDatabase> {
+        let result = self.call(
+            "IndexedDB.requestDatabase",
+            json!({ "securityOrigin": origin, "databaseName": name }),
+        )?;
+        let info = &result["databaseWithObjectStores"];
+        let mut database = LiveDatabase {
+            version: info["version"].as_f64().unwrap_or_default(),
+            object_stores: BTreeMap::new(),
+        };
+
+        let stores: Vec<String> = info["objectStores"]
+            .as_array()
+            .map(|stores| {
+                stores
+                    .iter()
+                    .filter_map(|s| s["name"].as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        for store in stores {
+            let records = self.object_store(origin, name, &store)?;
+            database.object_stores.insert(store, records);
+        }
+        Ok(database)
+    }
+
+    fn object_store(
+        &mut self,
+        origin: &str,
+        database: &str,
+        store: &str,
+    ) -> Result<Vec<LiveRecord>> {
+        let mut records = Vec::new();
+        loop {
+            let page = self.call(
+                "IndexedDB.requestData",
+                json!({
+                    "securityOrigin": origin,
+                    "databaseName": database,
+                    "objectStoreName": store,
+                    "indexName": "",
+                    "skipCount": records.len(),
+                    "pageSize": PAGE_SIZE,
+                }),
+            )?;
+            let entries = page["objectStoreDataEntries"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            for entry in &entries {
+                records.push(LiveRecord {
+                    key: self.remote_value(&entry["primaryKey"])?,
+                    value: self.remote_value(&entry["value"])?,
+                });
+            }
+            if entries.is_empty() || !page["hasMore"].as_bool().unwrap_or(false) {
+                break;
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Finds the renderer serving `origin` among the debuggable targets
+fn find_target(port: u16, origin: &str) -> Result<Target> {
+    let url = format!("http://{}:{}/json/list", DEVTOOLS_HOST, port);
+    let body = ureq::get(&url)
+        .call()
+        .with_context(|| {
+            format!(
+                "No DevTools endpoint at {}; was Element started with --remote-debugging-port={}?",
+                url, port
+            )
+        })?
+        .body_mut()
+        .read_to_string()?;
+    let targets: Vec<Target> = serde_json::from_str(&body)?;
+    targets
+        .into_iter()
+        .find(|t| {
+            t.kind == "page" && t.url.starts_with(origin) && t.web_socket_debugger_url.is_some()
+        })
+        .ok_or_else(|| anyhow!("No page for {} among the DevTools targets", origin))
+}
+
+/// Dumps Local Storage, sessionStorage and IndexedDB of `origin` from a running client
+pub fn dump(port: u16, origin: &str) -> Result<LiveDump> {
+    let target = find_target(port, origin)?;
+    let ws_url = target
+        .web_socket_debugger_url
+        .as_deref()
+        .expect("find_target only returns targets with a WebSocket URL");
+    let mut client = CdpClient::connect(port, ws_url)?;
+
+    let local_storage = client.dom_storage(origin, true)?;
+    let session_storage = client.dom_storage(origin, false)?;
+
+    let names = client.call(
+        "IndexedDB.requestDatabaseNames",
+        json!({ "securityOrigin": origin }),
+    )?;
+    let mut indexeddb = BTreeMap::new();
+    for name in names["databaseNames"].as_array().into_iter().flatten() {
+        if let Some(name) = name.as_str() {
+            indexeddb.insert(name.to_string(), client.database(origin, name)?);
+        }
+    }
+
+    Ok(LiveDump {
+        origin: origin.to_string(),
+        target_url: target.url,
+        local_storage,
+        session_storage,
+        indexeddb,
+    })
+}
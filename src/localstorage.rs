@@ -0,0 +1,51 @@
+//! Chromium Local Storage key and value encoding
+//!
+//! Script-visible entries are stored as `_<origin>\0<name>`, where the name and
+//! the value are both prefixed with an encoding byte: `0x01` for Latin-1 or
+//! `0x00` for UTF-16LE. `META:<origin>` and `VERSION` are bookkeeping entries.
+
+/// Encodes a Local Storage entry key for `origin`
+pub fn encode_key(origin: &str, name: &str) -> Vec<u8> {
+    let mut key = format!("_{}\0", origin).into_bytes();
+    key.extend(encode_string(name));
+    key
+}
+
+/// Encodes a string the way Chromium does: Latin-1 when possible, UTF-16LE otherwise
+pub fn encode_string(value: &str) -> Vec<u8> {
+    if value.chars().all(|c| (c as u32) < 0x100) {
+        let mut out = vec![1];
+        out.extend(value.chars().map(|c| c as u8));
+        out
+    } else {
+        let mut out = vec![0];
+        for unit in value.encode_utf16() {
+            out.extend_from_slice(&unit.to_le_bytes());
+        }
+        out
+    }
+}
+
+/// Decodes a prefixed Local Storage string; `None` for unknown prefixes or odd UTF-16
+pub fn decode_string(data: &[u8]) -> Option<String> {
+    match data.split_first()? {
+        (1, rest) => Some(rest.iter().map(|&b| char::from(b)).collect()),
+        (0, rest) if rest.len() % 2 == 0 => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+        _ => None,
+    }
+}
+
+/// Splits an entry key into its origin and decoded name
+pub fn decode_key(key: &[u8]) -> Option<(String, String)> {
+    let rest = key.strip_prefix(b"_")?;
+    let sep = rest.iter().position(|&b| b == 0)?;
+    let origin = String::from_utf8(rest[..sep].to_vec()).ok()?;
+    let name = decode_string(&rest[sep + 1..])?;
+    Some((origin, name))
+}
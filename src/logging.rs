@@ -0,0 +1,37 @@
+//! Log output of the library's `tracing` spans and events
+//!
+//! Logs go to standard error, warnings only by default: each `-v` adds a
+//! level, `-q` leaves errors only and also hides the progress bar. With
+//! `--log-format json` every line is a JSON object for log collectors.
+
+use crate::cli::LogFormat;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::level_filters::LevelFilter;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Installs the subscriber for the whole run
+pub fn init(verbose: u8, quiet: bool, format: LogFormat) {
+    QUIET.store(quiet, Ordering::Relaxed);
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::WARN,
+        (false, 1) => LevelFilter::INFO,
+        (false, 2) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(level);
+    match format {
+        LogFormat::Text => builder
+            .with_ansi(std::io::IsTerminal::is_terminal(&std::io::stderr()))
+            .init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+/// Whether `--quiet` was given
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
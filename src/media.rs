@@ -0,0 +1,55 @@
+//! `mxc://` content URIs and their homeserver download endpoints
+
+use regex::Regex;
+#[cfg(feature = "serde")]
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::sync::LazyLock;
+
+static MXC_URI: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"mxc://[A-Za-z0-9.\-]+(?::[0-9]+)?/[A-Za-z0-9_\-]+").expect("valid regex")
+});
+
+/// Adds every `mxc://` URI found in `text` to `out`
+pub fn collect_from_text(text: &str, out: &mut BTreeSet<String>) {
+    out.extend(MXC_URI.find_iter(text).map(|m| m.as_str().to_string()));
+}
+
+/// Adds every `mxc://` URI found in the strings of a JSON value to `out`
+#[cfg(feature = "serde")]
+pub fn collect_from_json(value: &Value, out: &mut BTreeSet<String>) {
+    match value {
+        Value::String(s) => collect_from_text(s, out),
+        Value::Array(items) => items.iter().for_each(|v| collect_from_json(v, out)),
+        Value::Object(map) => map.values().for_each(|v| collect_from_json(v, out)),
+        _ => {}
+    }
+}
+
+/// Splits `mxc://<server>/<media id>` into its server name and media id
+pub fn parse_mxc(uri: &str) -> Option<(&str, &str)> {
+    let (server, media_id) = uri.strip_prefix("mxc://")?.split_once('/')?;
+    (!server.is_empty() && !media_id.is_empty() && !media_id.contains('/'))
+        .then_some((server, media_id))
+}
+
+/// Download endpoint for an `mxc://` URI on `homeserver`
+///
+/// Authenticated media (`/_matrix/client/v1/media`) needs an access token;
+/// the legacy unauthenticated endpoint is still served by many homeservers.
+pub fn download_url(homeserver: &str, uri: &str, authenticated: bool) -> Option<String> {
+    let (server, media_id) = parse_mxc(uri)?;
+    let base = homeserver.trim_end_matches('/');
+    let path = if authenticated {
+        "_matrix/client/v1/media/download"
+    } else {
+        "_matrix/media/v3/download"
+    };
+    Some(format!("{}/{}/{}/{}", base, path, server, media_id))
+}
+
+/// File name used when storing downloaded media: `<server>_<media id>`
+pub fn file_name(uri: &str) -> Option<String> {
+    let (server, media_id) = parse_mxc(uri)?;
+    Some(format!("{}_{}", server.replace(':', "_"), media_id))
+}
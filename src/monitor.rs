@@ -0,0 +1,226 @@
+//! Daemon reporting Element state changes as they happen
+//!
+//! Filesystem events on the Local Storage and IndexedDB directories trigger a
+//! fresh snapshot of the account state, read from the raw files so a running
+//! client is never disturbed; the snapshot is compared with the previous one
+//! and every difference that matters for endpoint monitoring becomes a
+//! notification. Access tokens are compared by fingerprint only.
+
+use crate::devices::DeviceIndex;
+use crate::indexeddb::reader::Origin;
+use crate::rooms::RoomIndex;
+use crate::settings::Settings;
+use crate::{crypto, history, raw, sync};
+use anyhow::Result;
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Quiet time after a filesystem event before the state is read again
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// The parts of the account state changes are reported for
+#[derive(Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub user_id: Option<String>,
+    pub device_id: Option<String>,
+    /// Fingerprint of the current access token
+    pub token_fingerprint: Option<String>,
+    /// The account's devices: display name by device ID
+    pub devices: BTreeMap<String, Option<String>>,
+    /// Joined rooms: name by room ID
+    pub joined_rooms: BTreeMap<String, Option<String>>,
+}
+
+impl Snapshot {
+    /// Reads the state of the profile whose Local Storage is `local_storage`
+    pub fn read(local_storage: &Path) -> Result<Self> {
+        let scan = raw::scan_directory(local_storage)?;
+        let entries = scan.latest();
+        let settings =
+            Settings::from_entries(entries.iter().map(|(key, value)| (&key[..], &value[..])));
+        let sessions = history::login_history(&scan);
+        let origin = settings
+            .layout()
+            .indexeddb_dir(local_storage)
+            .and_then(|dir| Origin::read_directory(dir).ok());
+        let user_id = settings.value("mx_user_id").map(str::to_string);
+
+        let mut devices = DeviceIndex::default();
+        devices.add_local_storage(&settings);
+        devices.add_sessions(&sessions);
+        let mut rooms = RoomIndex::default();
+        rooms.add_local_storage(&settings);
+        if let Some(origin) = &origin {
+            rooms.add_cached(&sync::cached_rooms(origin));
+            if let Some(user_id) = &user_id {
+                devices.add_cached(&crypto::cached_devices(origin), user_id);
+            }
+        }
+
+        Ok(Snapshot {
+            device_id: settings.value("mx_device_id").map(str::to_string),
+            token_fingerprint: settings.value("mx_access_token").and_then(|_| {
+                sessions
+                    .last()
+                    .and_then(|s| s.access_tokens.last().cloned())
+            }),
+            user_id,
+            devices: devices
+                .into_devices()
+                .into_iter()
+                .map(|d| (d.device_id, d.display_name))
+                .collect(),
+            joined_rooms: rooms
+                .into_rooms()
+                .into_iter()
+                .filter(|r| r.membership.as_deref() == Some("join"))
+                .map(|r| (r.room_id, r.name))
+                .collect(),
+        })
+    }
+}
+
+/// One state change, as posted to the webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    /// `device_added`, `token_changed`, `room_joined`, `room_left` or `logged_out`
+    pub event: &'static str,
+    /// Local Storage directory of the profile
+    pub profile: String,
+    /// When the change was noticed (RFC 3339)
+    pub observed_at: String,
+    pub user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room_id: Option<String>,
+    /// Device display name or room name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_fingerprint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_fingerprint: Option<String>,
+}
+
+/// Notifications for the differences between two snapshots of `profile`
+pub fn changes(profile: &str, old: &Snapshot, new: &Snapshot) -> Vec<Notification> {
+    let notification = |event| Notification {
+        event,
+        profile: profile.to_string(),
+        observed_at: chrono::Utc::now().to_rfc3339(),
+        user_id: new.user_id.clone().or_else(|| old.user_id.clone()),
+        device_id: None,
+        room_id: None,
+        name: None,
+        old_fingerprint: None,
+        new_fingerprint: None,
+    };
+
+    let mut notifications = Vec::new();
+    for (device_id, name) in &new.devices {
+        if !old.devices.contains_key(device_id) {
+            notifications.push(Notification {
+                device_id: Some(device_id.clone()),
+                name: name.clone(),
+                ..notification("device_added")
+            });
+        }
+    }
+    if old.token_fingerprint.is_some() && new.token_fingerprint.is_none() {
+        notifications.push(Notification {
+            device_id: old.device_id.clone(),
+            old_fingerprint: old.token_fingerprint.clone(),
+            ..notification("logged_out")
+        });
+    } else if new.token_fingerprint != old.token_fingerprint {
+        notifications.push(Notification {
+            device_id: new.device_id.clone(),
+            old_fingerprint: old.token_fingerprint.clone(),
+            new_fingerprint: new.token_fingerprint.clone(),
+            ..notification("token_changed")
+        });
+    }
+    for (room_id, name) in &new.joined_rooms {
+        if !old.joined_rooms.contains_key(room_id) {
+            notifications.push(Notification {
+                room_id: Some(room_id.clone()),
+                name: name.clone(),
+                ..notification("room_joined")
+            });
+        }
+    }
+    for (room_id, name) in &old.joined_rooms {
+        if !new.joined_rooms.contains_key(room_id) {
+            notifications.push(Notification {
+                room_id: Some(room_id.clone()),
+                name: name.clone(),
+                ..notification("room_left")
+            });
+        }
+    }
+    notifications
+}
+
+/// POSTs a notification as JSON
+pub fn post(webhook: &str, notification: &Notification) -> Result<()> {
+    ureq::post(webhook)
+        .header("Content-Type", "application/json")
+        .send(serde_json::to_string(notification)?)?;
+    Ok(())
+}
+
+/// Watches the profile and calls `on_change` with each batch of notifications
+///
+/// Events are collected until the directories have been quiet for a moment,
+/// so a sync that rewrites many files is read once. Snapshots that can't be
+/// read (e.g. mid-compaction) are reported and retried on the next event.
+pub fn monitor(
+    local_storage: &Path,
+    mut state: Snapshot,
+    mut on_change: impl FnMut(Vec<Notification>) -> Result<()>,
+    mut on_issue: impl FnMut(String),
+) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    for dir in watched_dirs(local_storage) {
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+    }
+    let profile = local_storage.display().to_string();
+
+    while let Ok(result) = rx.recv() {
+        let mut relevant = !matches!(result?.kind, EventKind::Access(_));
+        while let Ok(result) = rx.recv_timeout(SETTLE) {
+            relevant |= !matches!(result?.kind, EventKind::Access(_));
+        }
+        if !relevant {
+            continue;
+        }
+        match Snapshot::read(local_storage) {
+            Ok(snapshot) => {
+                let notifications = changes(&profile, &state, &snapshot);
+                state = snapshot;
+                if !notifications.is_empty() {
+                    on_change(notifications)?;
+                }
+            }
+            Err(e) => on_issue(format!("{}: {}", profile, e)),
+        }
+    }
+    Ok(())
+}
+
+/// The Local Storage directory and, when present, the Element IndexedDB one
+fn watched_dirs(local_storage: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![local_storage.to_path_buf()];
+    if let Ok(scan) = raw::scan_directory(local_storage) {
+        let entries = scan.latest();
+        let settings = Settings::from_entries(entries.iter().map(|(k, v)| (&k[..], &v[..])));
+        dirs.extend(settings.layout().indexeddb_dir(local_storage));
+    }
+    dirs
+}
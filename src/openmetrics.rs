@@ -0,0 +1,125 @@
+//! OpenMetrics text export of per-profile summaries
+//!
+//! One file covers any number of profiles, each series labelled with the
+//! profile path, so the output of fleet-wide parsing jobs can be picked up by
+//! a node exporter textfile collector or pushed to a Pushgateway and
+//! aggregated in Prometheus.
+
+use crate::findings::Severity;
+use crate::layout::{Layout, Variant};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const SEVERITIES: &[(Severity, &str)] = &[
+    (Severity::Low, "low"),
+    (Severity::Medium, "medium"),
+    (Severity::High, "high"),
+    (Severity::Critical, "critical"),
+];
+
+/// Gauge name (after `element_profile_`), help text and value
+type Gauge = (&'static str, &'static str, fn(&ProfileSummary) -> usize);
+
+const GAUGES: &[Gauge] = &[
+    ("entries", "Live Local Storage entries", |s| s.entries),
+    (
+        "rooms",
+        "Rooms seen in Local Storage or the sync cache",
+        |s| s.rooms,
+    ),
+    ("encrypted_rooms", "Rooms known to be encrypted", |s| {
+        s.encrypted_rooms
+    }),
+    ("devices", "Own devices, current and historical", |s| {
+        s.devices
+    }),
+    (
+        "login_sessions",
+        "Login sessions, including superseded ones",
+        |s| s.login_sessions,
+    ),
+    ("findings", "Secret-scanning findings", |s| {
+        s.findings.values().sum()
+    }),
+];
+
+/// Counts for one parsed profile
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProfileSummary {
+    /// Local Storage path the profile was read from
+    pub profile: String,
+    pub layout: Layout,
+    /// Live Local Storage entries
+    pub entries: usize,
+    pub rooms: usize,
+    pub encrypted_rooms: usize,
+    pub devices: usize,
+    pub login_sessions: usize,
+    pub findings: BTreeMap<Severity, usize>,
+}
+
+/// Renders the summaries as one OpenMetrics exposition, ending in `# EOF`
+pub fn render(summaries: &[ProfileSummary]) -> String {
+    let mut out = String::new();
+
+    // Info families are named without the `_info` suffix their samples carry
+    out.push_str("# TYPE element_profile info\n");
+    out.push_str("# HELP element_profile Element variant and origin of the profile\n");
+    for summary in summaries {
+        let variant = match summary.layout.variant {
+            Variant::Desktop => "desktop",
+            Variant::Web => "web",
+        };
+        let _ = writeln!(
+            out,
+            "element_profile_info{{profile=\"{}\",variant=\"{}\",origin=\"{}\"}} 1",
+            escape(&summary.profile),
+            variant,
+            escape(&summary.layout.origin)
+        );
+    }
+    for (name, help, value) in GAUGES {
+        family(&mut out, name, "gauge", help);
+        for summary in summaries {
+            let _ = writeln!(
+                out,
+                "element_profile_{}{{profile=\"{}\"}} {}",
+                name,
+                escape(&summary.profile),
+                value(summary)
+            );
+        }
+    }
+    family(
+        &mut out,
+        "findings_by_severity",
+        "gauge",
+        "Secret-scanning findings per severity",
+    );
+    for summary in summaries {
+        for (severity, label) in SEVERITIES {
+            let _ = writeln!(
+                out,
+                "element_profile_findings_by_severity{{profile=\"{}\",severity=\"{}\"}} {}",
+                escape(&summary.profile),
+                label,
+                summary.findings.get(severity).copied().unwrap_or(0)
+            );
+        }
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# TYPE element_profile_{} {}", name, kind);
+    let _ = writeln!(out, "# HELP element_profile_{} {}", name, help);
+}
+
+/// Escapes a label value: backslash, double quote and newline
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,181 @@
+//! Where reports go: standard output, or the file `--output` names
+//!
+//! A file is written atomically, like `metrics --out`: everything goes to
+//! `<file>.tmp`, gzip-compressed when the name ends in `.gz`, which is renamed
+//! over the target only once the command has succeeded, so a failed run never
+//! leaves a truncated report behind. Commands write with `outln!` and `out!`
+//! where they would use `println!` and `print!`. With `--query` their output
+//! is captured first, and only the query's results are written.
+
+use anyhow::{Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+enum Sink {
+    Stdout,
+    File {
+        target: PathBuf,
+        temp: PathBuf,
+        file: BufWriter<File>,
+    },
+    Gzip {
+        target: PathBuf,
+        temp: PathBuf,
+        file: GzEncoder<BufWriter<File>>,
+    },
+}
+
+static SINK: Mutex<Sink> = Mutex::new(Sink::Stdout);
+/// What commands wrote, held back for `--query`
+static CAPTURED: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+static NO_COLOR: AtomicBool = AtomicBool::new(false);
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+fn sink() -> MutexGuard<'static, Sink> {
+    SINK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn captured() -> MutexGuard<'static, Option<Vec<u8>>> {
+    CAPTURED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Holds back what commands write until `take_captured`
+pub fn capture() {
+    *captured() = Some(Vec::new());
+}
+
+/// Stops holding output back and returns what was written meanwhile
+pub fn take_captured() -> Vec<u8> {
+    captured().take().unwrap_or_default()
+}
+
+/// Sends reports to `path` from now on, through `<path>.tmp`
+pub fn init(path: Option<&Path>) -> Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let mut temp = path.to_path_buf().into_os_string();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let file =
+        File::create(&temp).with_context(|| format!("Failed to create {}", temp.display()))?;
+    let target = path.to_path_buf();
+    let file = BufWriter::new(file);
+    *sink() = if path.extension().is_some_and(|e| e == "gz") {
+        Sink::Gzip {
+            target,
+            temp,
+            file: GzEncoder::new(file, Compression::default()),
+        }
+    } else {
+        Sink::File { target, temp, file }
+    };
+    Ok(())
+}
+
+/// Completes the report: renames the file into place, or flushes standard output
+pub fn finish() -> Result<()> {
+    let (target, temp, file) = match std::mem::replace(&mut *sink(), Sink::Stdout) {
+        Sink::Stdout => return Ok(io::stdout().flush()?),
+        Sink::File { target, temp, file } => (target, temp, file),
+        Sink::Gzip { target, temp, file } => (target, temp, file.finish()?),
+    };
+    let file = file.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&temp, &target)
+        .with_context(|| format!("Failed to write {}", target.display()))?;
+    Ok(())
+}
+
+/// Removes the unfinished file after a failed command
+pub fn abandon() {
+    captured().take();
+    let temp = match std::mem::replace(&mut *sink(), Sink::Stdout) {
+        Sink::Stdout => return,
+        Sink::File { temp, .. } | Sink::Gzip { temp, .. } => temp,
+    };
+    let _ = std::fs::remove_file(temp);
+}
+
+/// Whether reports go straight to a terminal
+pub fn is_terminal() -> bool {
+    captured().is_none() && matches!(*sink(), Sink::Stdout) && io::stdout().is_terminal()
+}
+
+/// Turns colors off with `--no-color` or `NO_COLOR`, and table borders too
+/// with `--plain`
+pub fn set_style(no_color: bool, plain: bool) {
+    let no_color = no_color || plain || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    NO_COLOR.store(no_color, Ordering::Relaxed);
+    PLAIN.store(plain, Ordering::Relaxed);
+}
+
+/// Whether reports may be colored: they go to a terminal and colors are on
+pub fn color() -> bool {
+    !NO_COLOR.load(Ordering::Relaxed) && is_terminal()
+}
+
+/// Whether tables are drawn without borders
+pub fn plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// Writes to wherever reports go
+pub struct Writer;
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(captured) = &mut *captured() {
+            return captured.write(buf);
+        }
+        match &mut *sink() {
+            Sink::Stdout => io::stdout().write(buf),
+            Sink::File { file, .. } => file.write(buf),
+            Sink::Gzip { file, .. } => file.write(buf),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        if let Some(captured) = &mut *captured() {
+            return captured.write_all(buf);
+        }
+        match &mut *sink() {
+            Sink::Stdout => io::stdout().write_all(buf),
+            Sink::File { file, .. } => file.write_all(buf),
+            Sink::Gzip { file, .. } => file.write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut *sink() {
+            Sink::Stdout => io::stdout().flush(),
+            Sink::File { file, .. } => file.flush(),
+            Sink::Gzip { file, .. } => file.flush(),
+        }
+    }
+}
+
+/// `out!(...)`: `print!` to wherever reports go
+macro_rules! out {
+    ($($arg:tt)*) => {
+        std::io::Write::write_fmt(&mut $crate::output::Writer, format_args!($($arg)*))
+    };
+}
+
+/// `outln!(...)`: `println!` to wherever reports go
+macro_rules! outln {
+    () => {
+        $crate::output::out!("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::output::out!("{}\n", format_args!($($arg)*))
+    };
+}
+
+pub(crate) use {out, outln};
@@ -0,0 +1,92 @@
+//! `export --format parquet`: the entry table in columnar form
+//!
+//! One row per entry with the columns of the `entries` table of `export
+//! --format sqlite`: `key`, `kind`, `origin`, `name`, `category`, `value`
+//! and `size`. Columns are zstd-compressed and written in row groups of
+//! `BATCH` entries. The file's key-value metadata names the account
+//! (`element.user_id`), the origin (`element.origin`) and the tool version
+//! (`element.generator`), so files collected from many machines can be told
+//! apart once they sit together in a data lake.
+
+use crate::export::{self, Export};
+use ::parquet::arrow::ArrowWriter;
+use ::parquet::basic::{Compression, ZstdLevel};
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::format::KeyValue;
+use anyhow::{Context, Result};
+use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Entries per row group
+const BATCH: usize = 65_536;
+
+/// Writes the entries of `export` as a Parquet file at `path`; built in
+/// `<path>.tmp` and renamed once complete
+pub fn write(path: &Path, export: &Export) -> Result<()> {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("key", DataType::Utf8, false),
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("origin", DataType::Utf8, true),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("category", DataType::Utf8, false),
+        Field::new("value", DataType::Utf8, false),
+        Field::new("size", DataType::UInt64, false),
+    ]));
+    let metadata = &export.metadata;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .set_max_row_group_size(BATCH)
+        .set_key_value_metadata(Some(vec![
+            KeyValue::new("element.user_id".to_string(), metadata.user_id.clone()),
+            KeyValue::new("element.origin".to_string(), metadata.layout.origin.clone()),
+            KeyValue::new(
+                "element.generator".to_string(),
+                format!("element-desktop-leveldb {}", env!("CARGO_PKG_VERSION")),
+            ),
+        ]))
+        .build();
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = Path::new(&tmp);
+    let file = File::create(tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+    let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+    for batch in export.entries.chunks(BATCH) {
+        let parts: Vec<_> = batch
+            .iter()
+            .map(|entry| export::parts(&entry.classification, &metadata.layout.origin))
+            .collect();
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(
+                batch.iter().map(|entry| &entry.key),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                parts.iter().map(|(kind, _, _)| kind),
+            )),
+            Arc::new(StringArray::from_iter(
+                parts.iter().map(|(_, origin, _)| *origin),
+            )),
+            Arc::new(StringArray::from_iter(
+                parts.iter().map(|(_, _, name)| *name),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                batch
+                    .iter()
+                    .map(|entry| export::category(&entry.classification)),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                batch.iter().map(|entry| &entry.value),
+            )),
+            Arc::new(UInt64Array::from_iter_values(
+                batch.iter().map(|entry| entry.size as u64),
+            )),
+        ];
+        writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+    }
+    writer.close()?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
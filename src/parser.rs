@@ -0,0 +1,1197 @@
+//! The Local Storage parser behind the `parse` command
+//!
+//! `ElementLevelDBParser` opens the Local Storage LevelDB of an Element
+//! profile and gathers everything the other modules extract from it, and
+//! from the IndexedDB next to it, into one `ElementMetadata` report.
+
+#[cfg(feature = "crypto")]
+use crate::crypto;
+use crate::decoders::{DecoderRegistry, ValueDecoder};
+use crate::devices::{DeviceIndex, DeviceInfo};
+use crate::entries::{Classification, Entries, EntryVisitor};
+use crate::error::{ParserError, Result};
+use crate::history::{self, Session};
+#[cfg(feature = "serde")]
+use crate::indexeddb::reader::Origin;
+use crate::keys::{classify_key, ElementKey};
+use crate::layout::{Layout, ELEMENT_KEYS};
+use crate::localstorage;
+use crate::plugins::{KeyParserPlugin, KeyParserPlugins};
+use crate::progress::{ProgressCallback, ProgressHook, Stage};
+use crate::raw::{self, provenance::Provenance};
+use crate::rooms::{RoomIndex, RoomInfo};
+use crate::settings::{Appearance, MediaDevice, MediaSettings, Settings, UiState};
+#[cfg(feature = "serde")]
+use crate::sync;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use regex::Regex;
+use rusty_leveldb::{DBIterator, LdbIterator, Options, Snapshot, WriteBatch, DB};
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::{Bound, ControlFlow, RangeBounds};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+#[cfg(feature = "serde")]
+use std::sync::OnceLock;
+
+/// Element Desktop LevelDB metadata types
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ElementMetadata {
+    /// `SCHEMA_VERSION` of the report; 0 in reports from before versioning
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub schema_version: u32,
+
+    /// User ID and profile information
+    pub user_id: Option<String>,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+
+    /// Account settings
+    pub theme: Option<String>,
+    pub language: Option<String>,
+    pub notifications_enabled: Option<bool>,
+
+    /// Element Desktop or Element Web, and the origin the data was read from
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub layout: Layout,
+
+    /// Every room seen in Local Storage or the sync cache, by room ID
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rooms: Vec<RoomInfo>,
+
+    /// The account's devices: current, earlier logins and the device list
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub devices: Vec<DeviceInfo>,
+
+    /// Login sessions, oldest first, including superseded ones
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub login_history: Vec<Session>,
+
+    /// Last-viewed room, right panel and room list state
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ui_state: UiState,
+
+    /// Persisted microphone, camera and speaker choices
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub media_devices: Vec<MediaDevice>,
+
+    /// Font, layout and accessibility settings from `mx_local_settings`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub appearance: Appearance,
+
+    /// URL preview and media autoplay settings
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub media_settings: MediaSettings,
+
+    /// Raw metadata entries, by key
+    pub raw_entries: BTreeMap<String, String>,
+
+    /// File, level and offset of each raw entry; only with `--provenance`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub provenance: Option<BTreeMap<String, Provenance>>,
+
+    /// What `KeyParserPlugin`s extracted beyond the fields above, by name
+    #[cfg(feature = "serde")]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extensions: BTreeMap<String, serde_json::Value>,
+}
+
+/// How values that aren't UTF-8 are written to `raw_entries`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BinaryEncoding {
+    /// `0x` followed by lowercase hex
+    #[default]
+    Hex,
+    /// `base64:` followed by padded standard base64
+    Base64,
+}
+
+impl BinaryEncoding {
+    fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            BinaryEncoding::Hex => format!("0x{}", hex::encode(bytes)),
+            BinaryEncoding::Base64 => format!("base64:{}", STANDARD.encode(bytes)),
+        }
+    }
+}
+
+/// How the parser reads a database
+#[derive(Debug, Clone)]
+pub struct ParserOptions {
+    /// Collect every entry into `raw_entries`
+    pub raw_entries: bool,
+    /// Entries with longer values are skipped entirely
+    pub max_value_size: Option<usize>,
+    pub binary_encoding: BinaryEncoding,
+    /// Fail on a key that isn't UTF-8 instead of skipping it
+    pub fail_on_decode_error: bool,
+    /// Read the raw files on opening for the superseded sessions of
+    /// `login_history`; this reads the whole database
+    pub login_history: bool,
+    /// Read the table files on this many threads (0 for one per CPU) rather
+    /// than through a single LevelDB iterator
+    #[cfg(feature = "parallel")]
+    pub threads: Option<usize>,
+    /// Decoders applied to values before they reach the metadata
+    pub decoders: DecoderRegistry,
+    /// Extraction rules run after the built-in ones
+    pub plugins: KeyParserPlugins,
+    /// Called as `parse_metadata` works through the database
+    pub progress: ProgressHook,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            raw_entries: true,
+            max_value_size: None,
+            binary_encoding: BinaryEncoding::Hex,
+            fail_on_decode_error: false,
+            login_history: false,
+            #[cfg(feature = "parallel")]
+            threads: None,
+            decoders: DecoderRegistry::default(),
+            plugins: KeyParserPlugins::default(),
+            progress: ProgressHook::default(),
+        }
+    }
+}
+
+/// Configures and opens an `ElementLevelDBParser`
+#[derive(Debug, Clone, Default)]
+pub struct ParserBuilder {
+    options: ParserOptions,
+}
+
+impl ParserBuilder {
+    /// Replaces every option at once
+    pub fn options(mut self, options: ParserOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Whether to collect `raw_entries`; on by default
+    pub fn raw_entries(mut self, collect: bool) -> Self {
+        self.options.raw_entries = collect;
+        self
+    }
+
+    /// Skips entries whose value is longer than `bytes`
+    pub fn max_value_size(mut self, bytes: usize) -> Self {
+        self.options.max_value_size = Some(bytes);
+        self
+    }
+
+    /// Encoding of binary values in `raw_entries`; hex by default
+    pub fn binary_encoding(mut self, encoding: BinaryEncoding) -> Self {
+        self.options.binary_encoding = encoding;
+        self
+    }
+
+    /// Whether a key that can't be decoded fails the parse; skipped by default
+    pub fn fail_on_decode_error(mut self, fail: bool) -> Self {
+        self.options.fail_on_decode_error = fail;
+        self
+    }
+
+    /// Whether to read the raw files on opening for `login_history`; off by
+    /// default
+    ///
+    /// Earlier sessions survive only in superseded records, which opening the
+    /// database may compact away, so they are read from every `.log` and
+    /// `.ldb` file first. That costs a pass over the whole database, which
+    /// lookups and streaming `entries()` don't need. Damage found on the way
+    /// is in `issues`.
+    pub fn login_history(mut self, read: bool) -> Self {
+        self.options.login_history = read;
+        self
+    }
+
+    /// Reads the files of the database in parallel on `threads` threads, 0
+    /// for one per CPU
+    #[cfg(feature = "parallel")]
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.options.threads = Some(threads);
+        self
+    }
+
+    /// Decodes the values of the items whose name matches `pattern`
+    ///
+    /// Registered decoders are tried in order; see `decoders`.
+    pub fn decoder(mut self, pattern: Regex, decoder: impl ValueDecoder + 'static) -> Self {
+        self.options.decoders.register(pattern, decoder);
+        self
+    }
+
+    /// Adds an extraction rule run on the items it matches
+    pub fn plugin(mut self, plugin: impl KeyParserPlugin + 'static) -> Self {
+        self.options.plugins.register(plugin);
+        self
+    }
+
+    /// Calls `callback` with the progress of `parse_metadata`; see `progress`
+    pub fn progress(mut self, callback: impl ProgressCallback + 'static) -> Self {
+        self.options.progress.set(callback);
+        self
+    }
+
+    /// Opens the database at `path` with these options
+    pub fn open<P: AsRef<Path>>(self, path: P) -> Result<ElementLevelDBParser> {
+        ElementLevelDBParser::open_with(path.as_ref(), self.options)
+    }
+
+    /// Opens an in-memory database holding `entries`, raw keys to raw values
+    ///
+    /// Nothing touches the disk, so extraction logic can be tested on
+    /// entries built with `localstorage::encode_key` and `encode_string`.
+    /// There is no login history, IndexedDB or raw file access; `threads`
+    /// is ignored.
+    pub fn open_in_memory(
+        self,
+        entries: HashMap<Vec<u8>, Vec<u8>>,
+    ) -> Result<ElementLevelDBParser> {
+        ElementLevelDBParser::open_in_memory_with(entries, self.options)
+    }
+}
+
+/// Stands in for the path of an in-memory database in errors
+const MEMORY_PATH: &str = ":memory:";
+
+/// Parses Element Desktop LevelDB for metadata
+pub struct ElementLevelDBParser {
+    path: PathBuf,
+    database: Mutex<DB>,
+    options: ParserOptions,
+    login_history: Vec<Session>,
+    layout: Layout,
+    /// The profile's IndexedDB, read on first use
+    #[cfg(feature = "serde")]
+    indexeddb: OnceLock<Option<Origin>>,
+    /// Damaged data skipped in the raw files while opening
+    issues: Vec<String>,
+    /// Opened with `open_in_memory`, so there are no files to read
+    #[cfg(feature = "parallel")]
+    in_memory: bool,
+}
+
+impl ElementLevelDBParser {
+    /// Opens Element's LevelDB database with the default options
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::builder().open(path)
+    }
+
+    /// Opens an in-memory database holding `entries` with the default options
+    pub fn in_memory(entries: HashMap<Vec<u8>, Vec<u8>>) -> Result<Self> {
+        Self::builder().open_in_memory(entries)
+    }
+
+    /// Starts configuring a parser
+    pub fn builder() -> ParserBuilder {
+        ParserBuilder::default()
+    }
+
+    fn open_with(path: &Path, options: ParserOptions) -> Result<Self> {
+        let _span = tracing::info_span!("open", path = %path.display()).entered();
+        // LevelDB would otherwise create an empty database at a mistyped path
+        if !path.join("CURRENT").is_file() {
+            return Err(ParserError::NotElementDatabase(path.to_path_buf()));
+        }
+        // Superseded records must be read before opening, which may compact the log
+        let mut issues = Vec::new();
+        let mut login_history = Vec::new();
+        if options.login_history {
+            match raw::scan_directory(path) {
+                Ok(scan) => {
+                    for issue in &scan.issues {
+                        tracing::warn!(%issue, "skipped damaged data");
+                    }
+                    issues.clone_from(&scan.issues);
+                    login_history = history::login_history(&scan);
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "raw files unreadable, no login history");
+                    issues.push(e.to_string());
+                }
+            }
+        }
+        let mut db =
+            DB::open(path, Options::default()).map_err(|e| ParserError::leveldb(path, e))?;
+        let layout = detect_layout(&mut db, path)?.unwrap_or_default();
+        tracing::debug!(
+            origin = %layout.origin,
+            sessions = login_history.len(),
+            "profile located"
+        );
+        Ok(ElementLevelDBParser {
+            path: path.to_path_buf(),
+            database: Mutex::new(db),
+            options,
+            login_history,
+            layout,
+            #[cfg(feature = "serde")]
+            indexeddb: OnceLock::new(),
+            issues,
+            #[cfg(feature = "parallel")]
+            in_memory: false,
+        })
+    }
+
+    fn open_in_memory_with(
+        entries: HashMap<Vec<u8>, Vec<u8>>,
+        options: ParserOptions,
+    ) -> Result<Self> {
+        let path = Path::new(MEMORY_PATH);
+        let mut db = DB::open(path, rusty_leveldb::in_memory())
+            .map_err(|e| ParserError::leveldb(path, e))?;
+        let mut batch = WriteBatch::new();
+        for (key, value) in &entries {
+            batch.put(key, value);
+        }
+        db.write(batch, false)
+            .map_err(|e| ParserError::leveldb(path, e))?;
+        let layout = detect_layout(&mut db, path)?.unwrap_or_default();
+        Ok(ElementLevelDBParser {
+            path: path.to_path_buf(),
+            database: Mutex::new(db),
+            options,
+            login_history: Vec::new(),
+            layout,
+            #[cfg(feature = "serde")]
+            indexeddb: OnceLock::from(None),
+            issues: Vec::new(),
+            #[cfg(feature = "parallel")]
+            in_memory: true,
+        })
+    }
+
+    /// Extracts metadata from the LevelDB database
+    pub fn parse_metadata(&self) -> Result<ElementMetadata> {
+        self.parse_metadata_at(None)
+    }
+
+    fn parse_metadata_at(&self, at: Option<&Snapshot>) -> Result<ElementMetadata> {
+        let _span = tracing::info_span!("parse_metadata", path = %self.path.display()).entered();
+        let mut metadata = ElementMetadata {
+            schema_version: crate::SCHEMA_VERSION,
+            login_history: self.login_history.clone(),
+            layout: self.layout.clone(),
+            ..ElementMetadata::default()
+        };
+        let entries = self.read_entries(at)?;
+
+        let mut progress = self
+            .options
+            .progress
+            .tracker(Stage::Extracting, Some(entries.len() as u64));
+        for (key, value) in &entries {
+            progress.add(key.len() + value.len());
+            let key_str = match String::from_utf8(key.to_vec()) {
+                Ok(s) => s,
+                Err(_) if self.options.fail_on_decode_error => {
+                    return Err(ParserError::KeyDecode { key: key.to_vec() });
+                }
+                Err(_) => {
+                    tracing::debug!(key = %hex::encode(key), "skipped key that isn't UTF-8");
+                    continue;
+                }
+            };
+
+            let item = localstorage::decode_key(key);
+            let name = item.as_ref().map_or(key_str.as_str(), |(_, name)| name);
+            let value_str = match self.options.decoders.decode(name, value) {
+                Some(decoded) => decoded,
+                None => match std::str::from_utf8(value) {
+                    Ok(s) => s.to_string(),
+                    Err(_) => {
+                        tracing::trace!(key = %key_str, "binary value");
+                        if self.options.raw_entries {
+                            let encoded = self.options.binary_encoding.encode(value);
+                            metadata.raw_entries.insert(key_str, encoded);
+                        }
+                        continue;
+                    }
+                },
+            };
+
+            // Parse Element-specific keys; a browser profile holds other sites' too
+            if let Some((origin, name)) = &item
+                && *origin == self.layout.origin
+            {
+                self.parse_key_value(name, &value_str, &mut metadata);
+                self.options.plugins.apply(name, &value_str, &mut metadata);
+            }
+            if self.options.raw_entries {
+                metadata.raw_entries.insert(key_str, value_str);
+            }
+        }
+        progress.finish();
+        tracing::debug!(
+            entries = entries.len(),
+            raw_entries = metadata.raw_entries.len(),
+            "entries extracted"
+        );
+
+        let settings =
+            Settings::from_entries(entries.iter().map(|(key, value)| (&key[..], &value[..])));
+        #[cfg(feature = "serde")]
+        {
+            metadata.ui_state = settings.ui_state();
+            metadata.media_devices = settings.media_devices();
+            metadata.appearance = settings.appearance();
+            metadata.media_settings = settings.media_settings();
+        }
+
+        let mut rooms = RoomIndex::default();
+        rooms.add_local_storage(&settings);
+        #[cfg(feature = "serde")]
+        if let Some(origin) = self.indexeddb() {
+            rooms.add_cached(&sync::cached_rooms(origin));
+            rooms.add_notification_settings(&sync::notification_settings(origin));
+        }
+        metadata.rooms = rooms.into_rooms();
+
+        let mut devices = DeviceIndex::default();
+        devices.add_local_storage(&settings);
+        devices.add_sessions(&self.login_history);
+        #[cfg(feature = "crypto")]
+        if let (Some(origin), Some(user_id)) = (self.indexeddb(), settings.value("mx_user_id")) {
+            let cached = crypto::cached_devices(origin);
+            devices.add_cached(&cached, user_id);
+        }
+        metadata.devices = devices.into_devices();
+
+        Ok(metadata)
+    }
+
+    /// Iterates over the entries lazily, in key order
+    ///
+    /// Unlike `parse_metadata` nothing is collected: each entry is read when
+    /// the iterator reaches it. Entries over `max_value_size` are skipped.
+    pub fn entries(&self) -> Result<Entries> {
+        self.entries_at(None)
+    }
+
+    fn entries_at(&self, at: Option<&Snapshot>) -> Result<Entries> {
+        Ok(Entries::new(
+            self.iter_at(at)?,
+            self.layout.origin.clone(),
+            self.options.max_value_size,
+        ))
+    }
+
+    /// A LevelDB iterator over the live database or the snapshot `at`
+    fn iter_at(&self, at: Option<&Snapshot>) -> Result<DBIterator> {
+        let mut db = self.database.lock().map_err(|_| ParserError::Poisoned)?;
+        match at {
+            Some(snapshot) => db.new_iter_at(snapshot.clone()),
+            None => db.new_iter(),
+        }
+        .map_err(|e| ParserError::leveldb(&self.path, e))
+    }
+
+    /// Pins the current state of the database for a series of reads
+    ///
+    /// Reads through the snapshot all see the database as it was when the
+    /// snapshot was taken, whatever is written to it meanwhile.
+    pub fn snapshot(&self) -> Result<ParserSnapshot<'_>> {
+        let mut db = self.database.lock().map_err(|_| ParserError::Poisoned)?;
+        Ok(ParserSnapshot {
+            parser: self,
+            snapshot: db.get_snapshot(),
+        })
+    }
+
+    /// Iterates lazily over the entries from the last key to the first
+    pub fn iter_rev(&self) -> Result<Entries> {
+        Ok(self.entries()?.backwards())
+    }
+
+    /// Iterates lazily over the entries whose key starts with `prefix`
+    ///
+    /// The iterator seeks straight to the prefix and stops at the first key
+    /// past it, e.g. `localstorage::encode_key(origin, "mx_")` lists one
+    /// origin's Matrix items without reading the rest of the database.
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<Entries> {
+        Ok(self.entries()?.with_prefix(prefix))
+    }
+
+    /// Iterates lazily over the entries with keys in `range`, in key order
+    ///
+    /// Any range of byte strings works, with inclusive or exclusive ends:
+    /// `range(b"META:".as_slice()..b"VERSION".as_slice())`, `range(start..=end)`
+    /// or `(Bound::Excluded(a), Bound::Unbounded)`. The iterator seeks to the
+    /// start and stops at the end, reading nothing outside the range.
+    pub fn range<K: AsRef<[u8]>>(&self, range: impl RangeBounds<K>) -> Result<Entries> {
+        let (lower, upper) = owned_bounds(range);
+        Ok(self.entries()?.with_bounds(lower, upper))
+    }
+
+    /// Calls `visitor` with every entry in key order, until it breaks
+    ///
+    /// Returns `ControlFlow::Break` when the visitor stopped the walk early.
+    /// Entries over `max_value_size` are skipped.
+    pub fn parse_with_visitor(&self, visitor: &mut impl EntryVisitor) -> Result<ControlFlow<()>> {
+        self.visit_at(visitor, None)
+    }
+
+    fn visit_at(
+        &self,
+        visitor: &mut impl EntryVisitor,
+        at: Option<&Snapshot>,
+    ) -> Result<ControlFlow<()>> {
+        let mut iter = self.iter_at(at)?;
+        iter.seek_to_first();
+        while iter.valid() {
+            if let Some((key, value)) = iter.current()
+                && self
+                    .options
+                    .max_value_size
+                    .is_none_or(|max| value.len() <= max)
+            {
+                let classification = Classification::of(&key, &self.layout.origin);
+                if visitor.visit(&key, &value, &classification).is_break() {
+                    return Ok(ControlFlow::Break(()));
+                }
+            }
+            iter.advance();
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Every entry within `max_value_size`, in key order
+    ///
+    /// With `threads` the files are read concurrently by the raw readers and
+    /// merged by sequence number, instead of through the LevelDB iterator;
+    /// but never for a snapshot, which the files on disk may have moved past.
+    fn read_entries(&self, at: Option<&Snapshot>) -> Result<Vec<(Bytes, Bytes)>> {
+        let fits = |key: &[u8], value: &[u8]| {
+            let fits = self
+                .options
+                .max_value_size
+                .is_none_or(|max| value.len() <= max);
+            if !fits {
+                tracing::debug!(
+                    key = %String::from_utf8_lossy(key),
+                    size = value.len(),
+                    "skipped value over max_value_size"
+                );
+            }
+            fits
+        };
+        let mut progress = self.options.progress.tracker(Stage::Reading, None);
+        #[cfg(feature = "parallel")]
+        if let (Some(threads), None, false) = (self.options.threads, at, self.in_memory) {
+            let scan = raw::scan_directory_parallel(&self.path, threads)
+                .map_err(|e| ParserError::Io(std::io::Error::other(e)))?;
+            let entries = scan
+                .latest()
+                .into_iter()
+                .filter(|(key, value)| fits(key, value))
+                .map(|(key, value)| {
+                    progress.add(key.len() + value.len());
+                    (Bytes::from(key), Bytes::from(value))
+                })
+                .collect();
+            progress.finish();
+            return Ok(entries);
+        }
+
+        let mut iter = self.iter_at(at)?;
+        iter.seek_to_first();
+        let mut entries = Vec::new();
+        while iter.valid() {
+            if let Some((key, value)) = iter.current()
+                && fits(&key, &value)
+            {
+                progress.add(key.len() + value.len());
+                entries.push((key, value));
+            }
+            iter.advance();
+        }
+        progress.finish();
+        Ok(entries)
+    }
+
+    /// Parses individual key-value pairs for Element metadata
+    fn parse_key_value(&self, name: &str, value: &str, metadata: &mut ElementMetadata) {
+        // Clean LevelDB control characters
+        let clean_value = value.trim_start_matches('\u{0001}').to_string();
+
+        let key = classify_key(name);
+        tracing::trace!(name, key = ?key, "classified");
+        match key {
+            ElementKey::UserId => metadata.user_id = Some(clean_value),
+            ElementKey::DisplayName => metadata.display_name = Some(clean_value),
+            ElementKey::AvatarUrl => metadata.avatar_url = Some(clean_value),
+            ElementKey::Theme => metadata.theme = Some(clean_value),
+            ElementKey::Language => metadata.language = Some(clean_value),
+            ElementKey::Notifications => {
+                metadata.notifications_enabled = Some(clean_value.to_lowercase() == "true");
+            }
+            _ => {}
+        }
+    }
+
+    /// The options the parser was opened with
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    /// The profile's decoded IndexedDB, when the database sits in a full profile
+    ///
+    /// Read and decoded on the first call, by this or `parse_metadata`.
+    #[cfg(feature = "serde")]
+    pub fn indexeddb(&self) -> Option<&Origin> {
+        self.indexeddb
+            .get_or_init(|| {
+                let dir = self.layout.indexeddb_dir(&self.path)?;
+                let origin = Origin::read_directory(&dir);
+                if let Err(e) = &origin {
+                    tracing::warn!(dir = %dir.display(), error = %e, "IndexedDB unreadable");
+                }
+                origin.ok()
+            })
+            .as_ref()
+    }
+
+    /// The Element origin and variant found in the database
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// The directory the database was opened from; `:memory:` for in-memory
+    /// databases
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Damaged records and files skipped while reading the raw files on
+    /// opening, with `ParserBuilder::login_history`; metadata from them, such
+    /// as older sessions, may be missing
+    pub fn issues(&self) -> &[String] {
+        &self.issues
+    }
+
+    /// Exports metadata as JSON
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String> {
+        let metadata = self.parse_metadata()?;
+        Ok(serde_json::to_string_pretty(&metadata)?)
+    }
+
+    /// Gets a single value by raw key, without copying it
+    pub fn get_bytes(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        self.get_many_at(&[key], None)
+            .map(|mut values| values.remove(0))
+    }
+
+    /// Gets a single value by key, exactly as stored
+    pub fn get_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.get_bytes(key)?.map(|value| value.to_vec()))
+    }
+
+    /// Deserializes the JSON value of the Element item `name`, such as
+    /// `mx_local_settings`
+    ///
+    /// A value that isn't JSON is taken as a string; one that doesn't
+    /// deserialize into `T` is a `ParserError::Json`.
+    #[cfg(feature = "serde")]
+    pub fn get_as<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>> {
+        self.get_as_at(name, None)
+    }
+
+    #[cfg(feature = "serde")]
+    fn get_as_at<T: DeserializeOwned>(
+        &self,
+        name: &str,
+        at: Option<&Snapshot>,
+    ) -> Result<Option<T>> {
+        let key = localstorage::encode_key(&self.layout.origin, name);
+        let Some(value) = self.get_many_at(&[&key], at)?.remove(0) else {
+            return Ok(None);
+        };
+        Ok(Some(serde_json::from_value(item_value(&value))?))
+    }
+
+    /// Deserializes all Element items into `T`, as one object by item name
+    ///
+    /// Values that are JSON are taken as such and others as strings, so a
+    /// struct with `mx_user_id: String` and `mx_local_settings: MySettings`
+    /// fields reads both.
+    #[cfg(feature = "serde")]
+    pub fn parse_into<T: DeserializeOwned>(&self) -> Result<T> {
+        self.parse_into_at(None)
+    }
+
+    #[cfg(feature = "serde")]
+    fn parse_into_at<T: DeserializeOwned>(&self, at: Option<&Snapshot>) -> Result<T> {
+        let mut items = serde_json::Map::new();
+        for (key, value) in self.read_entries(at)? {
+            let Some((origin, name)) = localstorage::decode_key(&key) else {
+                continue;
+            };
+            if origin != self.layout.origin {
+                continue;
+            }
+            items.insert(name, item_value(&value));
+        }
+        Ok(serde_json::from_value(Value::Object(items))?)
+    }
+
+    /// Gets several values by key, taking the database lock once
+    ///
+    /// Keys are raw LevelDB keys like `get_value_bytes` takes; every key is
+    /// in the result, with `None` when it is absent.
+    pub fn get_many(&self, keys: &[&str]) -> Result<HashMap<String, Option<Vec<u8>>>> {
+        many(keys, |keys| self.get_many_at(keys, None))
+    }
+
+    /// Values of `keys`, in order, under one lock
+    fn get_many_at(&self, keys: &[&[u8]], at: Option<&Snapshot>) -> Result<Vec<Option<Bytes>>> {
+        let mut db = self.database.lock().map_err(|_| ParserError::Poisoned)?;
+        keys.iter()
+            .map(|key| match at {
+                Some(snapshot) => db
+                    .get_at(snapshot, key)
+                    .map_err(|e| ParserError::leveldb(&self.path, e)),
+                None => Ok(db.get(key)),
+            })
+            .collect()
+    }
+
+    /// Gets a single value by key as text, with invalid UTF-8 replaced by U+FFFD
+    ///
+    /// Binary values such as pickled Olm data are damaged by the replacement;
+    /// `get_value_bytes` returns them intact.
+    pub fn get_value_lossy(&self, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .get_bytes(key.as_bytes())?
+            .map(|value| String::from_utf8_lossy(&value).into_owned()))
+    }
+
+    /// Gets a single value by key
+    #[deprecated(note = "lossy for binary values; use get_value_bytes or get_value_lossy")]
+    pub fn get_value(&self, key: &str) -> Result<Option<String>> {
+        self.get_value_lossy(key)
+    }
+}
+
+/// The layout of a database holding `entries`, checking it is Element's
+///
+/// A fresh profile has only Chromium's bookkeeping keys; another site's
+/// storage or an IndexedDB has no Element ones.
+fn detect_layout(db: &mut DB, path: &Path) -> Result<Option<Layout>> {
+    let mut iter = db.new_iter().map_err(|e| ParserError::leveldb(path, e))?;
+    let mut counts = BTreeMap::new();
+    let (mut element, mut data) = (false, false);
+    // A few seeks per origin rather than a pass over every entry
+    iter.seek_to_first();
+    while let Some((key, _)) = iter.current().filter(|_| iter.valid()) {
+        let origin = key
+            .strip_prefix(b"_")
+            .and_then(|rest| rest.iter().position(|&b| b == 0).map(|end| &rest[..end]));
+        let Some(origin) = origin else {
+            data |= key.as_ref() != b"VERSION" && !key.starts_with(b"META:");
+            iter.advance();
+            continue;
+        };
+        data = true;
+        if let Ok(origin) = std::str::from_utf8(origin) {
+            let has = |iter: &mut DBIterator, name: &str, exact: bool| {
+                let wanted = localstorage::encode_key(origin, name);
+                iter.seek(&wanted);
+                iter.current()
+                    .filter(|_| iter.valid())
+                    .is_some_and(|(key, _)| {
+                        if exact {
+                            key.as_ref() == wanted
+                        } else {
+                            key.starts_with(&wanted)
+                        }
+                    })
+            };
+            element |= has(&mut iter, "mx_", false);
+            let found = ELEMENT_KEYS
+                .iter()
+                .filter(|name| has(&mut iter, name, true))
+                .count();
+            if found > 0 {
+                counts.insert(origin.to_string(), found);
+            }
+        }
+        // Past every key of this origin, whose names follow a 0 byte
+        let mut next = key[..origin.len() + 1].to_vec();
+        next.push(1);
+        iter.seek(&next);
+    }
+    if data && !element {
+        return Err(ParserError::NotElementDatabase(path.to_path_buf()));
+    }
+    Ok(Layout::from_counts(counts))
+}
+
+/// Reads of an `ElementLevelDBParser` pinned to one point in time
+///
+/// From `ElementLevelDBParser::snapshot`. Parsing, lookups and scans all see
+/// the same state of the database, so follow-up `get_value_bytes` calls
+/// agree with the metadata parsed before them.
+pub struct ParserSnapshot<'a> {
+    parser: &'a ElementLevelDBParser,
+    snapshot: Snapshot,
+}
+
+impl ParserSnapshot<'_> {
+    /// `ElementLevelDBParser::parse_metadata` at the snapshot; `threads` is
+    /// ignored, since the raw files may already be newer than the snapshot
+    pub fn parse_metadata(&self) -> Result<ElementMetadata> {
+        self.parser.parse_metadata_at(Some(&self.snapshot))
+    }
+
+    /// `ElementLevelDBParser::entries` at the snapshot
+    pub fn entries(&self) -> Result<Entries> {
+        self.parser.entries_at(Some(&self.snapshot))
+    }
+
+    /// `ElementLevelDBParser::iter_rev` at the snapshot
+    pub fn iter_rev(&self) -> Result<Entries> {
+        Ok(self.entries()?.backwards())
+    }
+
+    /// `ElementLevelDBParser::scan_prefix` at the snapshot
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<Entries> {
+        Ok(self.entries()?.with_prefix(prefix))
+    }
+
+    /// `ElementLevelDBParser::range` at the snapshot
+    pub fn range<K: AsRef<[u8]>>(&self, range: impl RangeBounds<K>) -> Result<Entries> {
+        let (lower, upper) = owned_bounds(range);
+        Ok(self.entries()?.with_bounds(lower, upper))
+    }
+
+    /// `ElementLevelDBParser::parse_with_visitor` at the snapshot
+    pub fn parse_with_visitor(&self, visitor: &mut impl EntryVisitor) -> Result<ControlFlow<()>> {
+        self.parser.visit_at(visitor, Some(&self.snapshot))
+    }
+
+    /// `ElementLevelDBParser::get_bytes` at the snapshot
+    pub fn get_bytes(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        self.parser
+            .get_many_at(&[key], Some(&self.snapshot))
+            .map(|mut values| values.remove(0))
+    }
+
+    /// `ElementLevelDBParser::get_value_bytes` at the snapshot
+    pub fn get_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.get_bytes(key)?.map(|value| value.to_vec()))
+    }
+
+    /// `ElementLevelDBParser::get_value_lossy` at the snapshot
+    pub fn get_value_lossy(&self, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .get_bytes(key.as_bytes())?
+            .map(|value| String::from_utf8_lossy(&value).into_owned()))
+    }
+
+    /// `ElementLevelDBParser::get_as` at the snapshot
+    #[cfg(feature = "serde")]
+    pub fn get_as<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>> {
+        self.parser.get_as_at(name, Some(&self.snapshot))
+    }
+
+    /// `ElementLevelDBParser::parse_into` at the snapshot
+    #[cfg(feature = "serde")]
+    pub fn parse_into<T: DeserializeOwned>(&self) -> Result<T> {
+        self.parser.parse_into_at(Some(&self.snapshot))
+    }
+
+    /// `ElementLevelDBParser::get_many` at the snapshot
+    pub fn get_many(&self, keys: &[&str]) -> Result<HashMap<String, Option<Vec<u8>>>> {
+        many(keys, |keys| {
+            self.parser.get_many_at(keys, Some(&self.snapshot))
+        })
+    }
+}
+
+/// A Local Storage value as JSON, or as a JSON string when it isn't JSON
+#[cfg(feature = "serde")]
+fn item_value(value: &[u8]) -> Value {
+    let text = localstorage::decode_string(value)
+        .unwrap_or_else(|| String::from_utf8_lossy(value).into_owned());
+    serde_json::from_str(&text).unwrap_or(Value::String(text))
+}
+
+fn owned_bounds<K: AsRef<[u8]>>(range: impl RangeBounds<K>) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+    let owned = |bound: Bound<&K>| bound.map(|key| key.as_ref().to_vec());
+    (owned(range.start_bound()), owned(range.end_bound()))
+}
+
+/// `get_many` on top of a lookup of raw keys
+fn many(
+    keys: &[&str],
+    get: impl FnOnce(&[&[u8]]) -> Result<Vec<Option<Bytes>>>,
+) -> Result<HashMap<String, Option<Vec<u8>>>> {
+    let raw: Vec<&[u8]> = keys.iter().map(|key| key.as_bytes()).collect();
+    let values = get(&raw)?;
+    Ok(keys
+        .iter()
+        .zip(values)
+        .map(|(key, value)| (key.to_string(), value.map(|v| v.to_vec())))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entries::Entry;
+    use crate::localstorage::{encode_key, encode_string};
+
+    const ORIGIN: &str = "vector://vector";
+
+    fn item(name: &str, value: &str) -> (Vec<u8>, Vec<u8>) {
+        (encode_key(ORIGIN, name), encode_string(value))
+    }
+
+    fn profile() -> HashMap<Vec<u8>, Vec<u8>> {
+        HashMap::from([
+            item("mx_user_id", "@alice:example.org"),
+            item("mx_profile_displayname", "Alice"),
+            item("theme", "dark"),
+            (b"VERSION".to_vec(), b"1".to_vec()),
+            (b"META:vector://vector".to_vec(), vec![0xff, 0x01]),
+        ])
+    }
+
+    #[test]
+    fn metadata_of_an_in_memory_profile() {
+        let parser = ElementLevelDBParser::in_memory(profile()).unwrap();
+        assert_eq!(parser.layout().origin, ORIGIN);
+        let metadata = parser.parse_metadata().unwrap();
+        assert_eq!(metadata.user_id.as_deref(), Some("@alice:example.org"));
+        assert_eq!(metadata.display_name.as_deref(), Some("Alice"));
+        assert_eq!(metadata.theme.as_deref(), Some("dark"));
+        assert!(metadata.login_history.is_empty());
+        assert_eq!(metadata.raw_entries["VERSION"], "1");
+        assert_eq!(metadata.raw_entries["META:vector://vector"], "0xff01");
+    }
+
+    #[test]
+    fn values_are_read_as_stored() {
+        let parser = ElementLevelDBParser::in_memory(profile()).unwrap();
+        let key = encode_key(ORIGIN, "mx_user_id");
+        assert_eq!(
+            parser.get_value_bytes(&key).unwrap(),
+            Some(encode_string("@alice:example.org"))
+        );
+        assert_eq!(parser.get_value_bytes(b"absent").unwrap(), None);
+    }
+
+    #[test]
+    fn entries_are_classified_in_key_order() {
+        let parser = ElementLevelDBParser::in_memory(profile()).unwrap();
+        let entries: Vec<Entry> = parser.entries().unwrap().collect();
+        let keys: Vec<&[u8]> = entries.iter().map(|e| e.key.as_ref()).collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+        assert_eq!(entries.len(), 5);
+        assert_eq!(
+            entries[0].classification,
+            Classification::Meta {
+                origin: ORIGIN.to_string()
+            }
+        );
+        assert_eq!(entries[1].classification, Classification::Version);
+        assert!(entries.iter().any(|e| e.classification
+            == Classification::Element {
+                name: "theme".to_string()
+            }));
+    }
+
+    #[test]
+    fn web_origins_and_foreign_databases() {
+        let origin = "https://app.element.io";
+        let parser = ElementLevelDBParser::in_memory(HashMap::from([
+            (
+                encode_key(origin, "mx_user_id"),
+                encode_string("@bob:example.org"),
+            ),
+            (
+                encode_key("https://other.example", "cart"),
+                encode_string("[]"),
+            ),
+        ]))
+        .unwrap();
+        assert_eq!(parser.layout().origin, origin);
+        let metadata = parser.parse_metadata().unwrap();
+        assert_eq!(metadata.user_id.as_deref(), Some("@bob:example.org"));
+
+        let foreign = HashMap::from([(encode_key("https://other.example", "cart"), vec![1])]);
+        assert!(matches!(
+            ElementLevelDBParser::in_memory(foreign),
+            Err(ParserError::NotElementDatabase(_))
+        ));
+    }
+
+    #[test]
+    fn visitors_stop_when_they_break() {
+        let parser = ElementLevelDBParser::in_memory(profile()).unwrap();
+        let mut seen = Vec::new();
+        let flow = parser
+            .parse_with_visitor(&mut |key: &[u8], _: &[u8], _: &Classification| {
+                seen.push(key.to_vec());
+                if seen.len() == 2 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        assert_eq!(flow, ControlFlow::Break(()));
+        assert_eq!(
+            seen,
+            [b"META:vector://vector".to_vec(), b"VERSION".to_vec()]
+        );
+
+        let mut count = 0;
+        let flow = parser
+            .parse_with_visitor(&mut |_: &[u8], _: &[u8], _: &Classification| {
+                count += 1;
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!((flow, count), (ControlFlow::Continue(()), 5));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn threads_read_the_same_metadata() {
+        let parser = ElementLevelDBParser::builder()
+            .threads(4)
+            .open_in_memory(profile())
+            .unwrap();
+        let metadata = parser.parse_metadata().unwrap();
+        assert_eq!(metadata.user_id.as_deref(), Some("@alice:example.org"));
+        assert_eq!(metadata.raw_entries.len(), 5);
+    }
+
+    #[test]
+    fn get_many_has_every_key() {
+        let parser = ElementLevelDBParser::in_memory(profile()).unwrap();
+        let values = parser.get_many(&["VERSION", "absent"]).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values["VERSION"], Some(b"1".to_vec()));
+        assert_eq!(values["absent"], None);
+    }
+
+    #[test]
+    fn snapshots_ignore_later_writes() {
+        let parser = ElementLevelDBParser::in_memory(profile()).unwrap();
+        let snapshot = parser.snapshot().unwrap();
+        let (key, value) = item("mx_user_id", "@mallory:example.org");
+        {
+            let mut db = parser.database.lock().unwrap();
+            db.put(&key, &value).unwrap();
+            db.put(b"added", b"later").unwrap();
+        }
+
+        let metadata = snapshot.parse_metadata().unwrap();
+        assert_eq!(metadata.user_id.as_deref(), Some("@alice:example.org"));
+        assert_eq!(
+            snapshot.get_value_bytes(&key).unwrap(),
+            Some(encode_string("@alice:example.org"))
+        );
+        assert_eq!(snapshot.get_value_bytes(b"added").unwrap(), None);
+        assert_eq!(snapshot.entries().unwrap().count(), 5);
+        assert_eq!(snapshot.get_many(&["added"]).unwrap()["added"], None);
+
+        let metadata = parser.parse_metadata().unwrap();
+        assert_eq!(metadata.user_id.as_deref(), Some("@mallory:example.org"));
+        assert_eq!(parser.entries().unwrap().count(), 6);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn items_deserialize_by_name() {
+        #[derive(Deserialize)]
+        struct Items {
+            mx_user_id: String,
+            mx_local_settings: BTreeMap<String, String>,
+        }
+
+        let mut entries = profile();
+        entries.extend([item("mx_local_settings", r#"{"language":"de"}"#)]);
+        let parser = ElementLevelDBParser::in_memory(entries).unwrap();
+        assert_eq!(
+            parser.get_as::<String>("mx_user_id").unwrap().as_deref(),
+            Some("@alice:example.org")
+        );
+        assert_eq!(
+            parser.get_as::<Value>("mx_local_settings").unwrap(),
+            Some(serde_json::json!({"language": "de"}))
+        );
+        assert_eq!(parser.get_as::<String>("mx_absent").unwrap(), None);
+        assert!(matches!(
+            parser.get_as::<u32>("mx_user_id"),
+            Err(ParserError::Json(_))
+        ));
+
+        let items: Items = parser.parse_into().unwrap();
+        assert_eq!(items.mx_user_id, "@alice:example.org");
+        assert_eq!(items.mx_local_settings["language"], "de");
+    }
+
+    #[test]
+    fn decoders_and_plugins_see_matching_items() {
+        struct Shout;
+
+        impl KeyParserPlugin for Shout {
+            fn matches(&self, key: &str) -> bool {
+                key == "theme"
+            }
+
+            fn apply(&self, _key: &str, _value: &str, metadata: &mut ElementMetadata) {
+                let theme = metadata.theme.as_deref().unwrap_or_default();
+                metadata.theme = Some(theme.to_uppercase());
+            }
+        }
+
+        let parser = ElementLevelDBParser::builder()
+            .decoder(Regex::new("^mx_profile_").unwrap(), |_: &str, _: &[u8]| {
+                Some("Decoded".to_string())
+            })
+            .plugin(Shout)
+            .open_in_memory(profile())
+            .unwrap();
+        let metadata = parser.parse_metadata().unwrap();
+        assert_eq!(metadata.display_name.as_deref(), Some("Decoded"));
+        assert_eq!(metadata.user_id.as_deref(), Some("@alice:example.org"));
+        // Plugins run after the built-in rules
+        assert_eq!(metadata.theme.as_deref(), Some("DARK"));
+    }
+
+    #[test]
+    fn large_values_are_skipped() {
+        let mut entries = profile();
+        entries.extend([item("mx_local_settings", &"x".repeat(100))]);
+        let parser = ElementLevelDBParser::builder()
+            .max_value_size(64)
+            .open_in_memory(entries)
+            .unwrap();
+        assert_eq!(parser.entries().unwrap().count(), 5);
+        let metadata = parser.parse_metadata().unwrap();
+        assert!(!metadata
+            .raw_entries
+            .keys()
+            .any(|key| key.contains("mx_local_settings")));
+    }
+}
@@ -0,0 +1,46 @@
+//! Custom extraction rules for `parse_metadata`
+//!
+//! A `KeyParserPlugin` registered on the `ParserBuilder` sees every Element
+//! item whose name it matches, after the built-in rules, and may fill in or
+//! override `ElementMetadata` fields. Data the metadata has no field for goes
+//! into `ElementMetadata::extensions`, so the rules of a custom Element fork
+//! can ship as a plugin instead of a patch to the parser.
+
+use crate::parser::ElementMetadata;
+use std::fmt;
+use std::sync::Arc;
+
+/// An extraction rule for Element items
+pub trait KeyParserPlugin: Send + Sync {
+    /// Whether the item `key` is one this plugin reads
+    fn matches(&self, key: &str) -> bool;
+
+    /// Reads the item `key` with the (decoded) text `value` into `metadata`
+    fn apply(&self, key: &str, value: &str, metadata: &mut ElementMetadata);
+}
+
+/// Plugins in the order they were registered
+#[derive(Clone, Default)]
+pub struct KeyParserPlugins {
+    plugins: Vec<Arc<dyn KeyParserPlugin>>,
+}
+
+impl KeyParserPlugins {
+    /// Adds `plugin` after the ones registered so far
+    pub fn register(&mut self, plugin: impl KeyParserPlugin + 'static) {
+        self.plugins.push(Arc::new(plugin));
+    }
+
+    /// Runs every plugin matching `key`, in order
+    pub fn apply(&self, key: &str, value: &str, metadata: &mut ElementMetadata) {
+        for plugin in self.plugins.iter().filter(|p| p.matches(key)) {
+            plugin.apply(key, value, metadata);
+        }
+    }
+}
+
+impl fmt::Debug for KeyParserPlugins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} plugins", self.plugins.len())
+    }
+}
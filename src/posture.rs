@@ -0,0 +1,362 @@
+//! End-to-end encryption posture of a profile
+//!
+//! Combines the legacy crypto store, secret storage (SSSS) and key backup
+//! account data, and the cached device lists to answer what a locked-out user
+//! wants to know: which rooms can be read from this profile, which devices are
+//! trusted, and whether message history can be recovered at all.
+
+use crate::crypto::{self, CachedDevice, KeyRequest};
+use crate::indexeddb::reader::Origin;
+use crate::settings::Settings;
+use crate::sync::{self, QueuedToDevice};
+use crate::{CRYPTO_DATABASE, SYNC_DATABASE};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// IndexedDB database of the Rust crypto stack (Element 1.11.x and later)
+pub const RUST_CRYPTO_DATABASE: &str = "matrix-js-sdk::matrix-sdk-crypto";
+/// Account data type and secret name of the key backup
+const BACKUP_SECRET: &str = "m.megolm_backup.v1";
+const CROSS_SIGNING_SECRETS: &[&str] = &[
+    "m.cross_signing.master",
+    "m.cross_signing.self_signing",
+    "m.cross_signing.user_signing",
+];
+
+/// Everything `analyze` found
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CryptoPosture {
+    /// Crypto databases present in the profile
+    pub stores: Vec<String>,
+    /// `mx_has_pickle_key`: pickles are encrypted with a key kept in the OS keychain
+    pub pickle_key: Option<bool>,
+    pub secret_storage: SecretStorage,
+    pub key_backup: KeyBackup,
+    pub devices: Vec<DeviceTrust>,
+    pub rooms: Vec<RoomDecryption>,
+    /// Room keys this device asked other devices for and has not received;
+    /// each one stands for messages it failed to decrypt
+    pub pending_key_requests: Vec<KeyRequest>,
+    /// To-device messages (key requests, key shares) not yet sent
+    pub to_device_queue: Vec<QueuedToDevice>,
+    /// What became of the room keys this device asked for
+    pub key_request_history: Vec<KeyRequestOutcome>,
+    pub recovery: Recovery,
+    /// Parts that could not be examined and why
+    pub limitations: Vec<String>,
+}
+
+/// Secret storage (SSSS) configuration from account data
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SecretStorage {
+    pub default_key: Option<String>,
+    pub algorithm: Option<String>,
+    /// The key can be derived from a passphrase, not only the recovery key
+    pub passphrase: bool,
+    /// Secrets encrypted with the default key
+    pub secrets: Vec<String>,
+}
+
+/// Server-side key backup as far as the profile shows it
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct KeyBackup {
+    /// The backup decryption key is kept in secret storage
+    pub key_in_secret_storage: bool,
+    /// The backup decryption key is cached in the crypto store
+    pub key_cached_locally: bool,
+    /// Sessions not yet uploaded when the client last ran
+    pub sessions_pending: usize,
+}
+
+/// Trust of one cached device
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DeviceTrust {
+    pub user_id: String,
+    pub device_id: String,
+    pub display_name: Option<String>,
+    /// `verified`, `unverified`, `blocked` or `unknown`
+    pub trust: &'static str,
+    /// The profile's own account
+    pub own: bool,
+    /// The device this profile belongs to
+    pub current: bool,
+}
+
+/// How much of an encrypted room's cached timeline this profile can read
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RoomDecryption {
+    pub room_id: String,
+    pub name: Option<String>,
+    /// Stored Megolm sessions for the room
+    pub sessions: usize,
+    /// Encrypted events in the cached timeline
+    pub encrypted_events: usize,
+    /// Those whose session is stored locally
+    pub decryptable_events: usize,
+}
+
+/// Outcome of one room key request
+///
+/// The crypto store deletes a request once it is answered, so fulfilled
+/// requests are inferred from sessions received by forwarding and refusals
+/// from withheld notices.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct KeyRequestOutcome {
+    pub room_id: Option<String>,
+    pub session_id: String,
+    /// `pending`, `cancelled`, `fulfilled` or `withheld`
+    pub outcome: &'static str,
+    /// Who answered or why the key was refused
+    pub detail: Option<String>,
+}
+
+/// Whether and how message history can be recovered
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Recovery {
+    pub possible: bool,
+    /// Ways to recover, most convenient first
+    pub paths: Vec<String>,
+}
+
+/// Analyzes a profile's IndexedDB origin and, when available, its Local Storage
+pub fn analyze(origin: &Origin, local_storage: Option<&Settings>) -> CryptoPosture {
+    let mut stores: Vec<String> = [CRYPTO_DATABASE, RUST_CRYPTO_DATABASE]
+        .into_iter()
+        .filter(|name| origin.database(name).is_some())
+        .map(str::to_string)
+        .collect();
+    stores.sort();
+    let mut limitations = Vec::new();
+    if origin.database(RUST_CRYPTO_DATABASE).is_some() {
+        limitations.push(format!(
+            "{} is encrypted with the pickle key and was not examined",
+            RUST_CRYPTO_DATABASE
+        ));
+    }
+    if origin.database(CRYPTO_DATABASE).is_none() {
+        limitations.push(format!("no {} database", CRYPTO_DATABASE));
+    }
+
+    let pickle_key = local_storage
+        .and_then(|s| s.value("mx_has_pickle_key"))
+        .map(|v| v == "true");
+    let user_id = local_storage.and_then(|s| s.value("mx_user_id"));
+    let device_id = local_storage.and_then(|s| s.value("mx_device_id"));
+    if local_storage.is_none() {
+        limitations.push("Local Storage not available: own devices and pickle key unknown".into());
+    }
+
+    let account_data: BTreeMap<&str, &Value> = origin
+        .records(SYNC_DATABASE, "accountData")
+        .iter()
+        .filter_map(|r| Some((r.value["type"].as_str()?, &r.value["content"])))
+        .collect();
+    let secret_storage = secret_storage(&account_data);
+    let cached = crypto::account_entries(origin);
+    let key_backup = KeyBackup {
+        key_in_secret_storage: secret_storage.secrets.iter().any(|s| s == BACKUP_SECRET),
+        key_cached_locally: cached
+            .iter()
+            .any(|name| name == &format!("ssss_cache:{}", BACKUP_SECRET)),
+        sessions_pending: origin
+            .records(CRYPTO_DATABASE, "sessions_needing_backup")
+            .len(),
+    };
+
+    let devices = crypto::cached_devices(origin)
+        .iter()
+        .map(|d| device_trust(d, user_id, device_id))
+        .collect();
+
+    let sessions = crypto::inbound_sessions(origin);
+    let rooms = room_decryption(origin, &sessions);
+    let key_requests = crypto::key_requests(origin);
+    let key_request_history = key_request_history(origin, &key_requests, &sessions);
+
+    let mut recovery = Recovery::default();
+    let keychain = if pickle_key == Some(true) {
+        " once the pickle key is taken from the OS keychain of the same user"
+    } else {
+        ""
+    };
+    if !sessions.is_empty() {
+        recovery.paths.push(format!(
+            "{} Megolm sessions in this profile can be exported{}",
+            sessions.len(),
+            keychain
+        ));
+    }
+    if key_backup.key_cached_locally {
+        recovery.paths.push(format!(
+            "the key backup can be restored with the backup key cached in this profile{}",
+            keychain
+        ));
+    }
+    if key_backup.key_in_secret_storage {
+        recovery.paths.push(format!(
+            "the key backup can be restored after unlocking secret storage with the recovery key{}",
+            if secret_storage.passphrase {
+                " or passphrase"
+            } else {
+                ""
+            }
+        ));
+    }
+    recovery.possible = !recovery.paths.is_empty();
+
+    CryptoPosture {
+        stores,
+        pickle_key,
+        secret_storage,
+        key_backup,
+        devices,
+        rooms,
+        pending_key_requests: key_requests,
+        to_device_queue: sync::to_device_queue(origin),
+        key_request_history,
+        recovery,
+        limitations,
+    }
+}
+
+fn secret_storage(account_data: &BTreeMap<&str, &Value>) -> SecretStorage {
+    let default_key = account_data
+        .get("m.secret_storage.default_key")
+        .and_then(|c| c["key"].as_str())
+        .map(str::to_string);
+    let Some(key) = &default_key else {
+        return SecretStorage::default();
+    };
+    let description = account_data
+        .get(format!("m.secret_storage.key.{}", key).as_str())
+        .copied();
+    let secrets = CROSS_SIGNING_SECRETS
+        .iter()
+        .chain([&BACKUP_SECRET])
+        .filter(|name| {
+            account_data
+                .get(**name)
+                .is_some_and(|c| c["encrypted"].get(key).is_some())
+        })
+        .map(|name| name.to_string())
+        .collect();
+    SecretStorage {
+        algorithm: description.and_then(|d| d["algorithm"].as_str().map(str::to_string)),
+        passphrase: description.is_some_and(|d| d.get("passphrase").is_some()),
+        default_key,
+        secrets,
+    }
+}
+
+fn device_trust(
+    device: &CachedDevice,
+    user_id: Option<&str>,
+    device_id: Option<&str>,
+) -> DeviceTrust {
+    let own = Some(device.user_id.as_str()) == user_id;
+    DeviceTrust {
+        user_id: device.user_id.clone(),
+        device_id: device.device_id.clone(),
+        display_name: device.display_name.clone(),
+        // matrix-js-sdk `DeviceVerification`
+        trust: match device.verified {
+            Some(1) => "verified",
+            Some(0) => "unverified",
+            Some(-1) => "blocked",
+            _ => "unknown",
+        },
+        own,
+        current: own && Some(device.device_id.as_str()) == device_id,
+    }
+}
+
+fn room_decryption(origin: &Origin, sessions: &[crypto::InboundSession]) -> Vec<RoomDecryption> {
+    let stored: BTreeSet<(&str, &str)> = sessions
+        .iter()
+        .filter_map(|s| Some((s.room_id.as_deref()?, s.session_id.as_str())))
+        .collect();
+    let Some(snapshot) = sync::snapshot(origin) else {
+        return Vec::new();
+    };
+
+    let mut rooms = Vec::new();
+    for room in sync::cached_rooms(origin)
+        .into_iter()
+        .filter(|r| r.encrypted)
+    {
+        let data = &snapshot["roomsData"][&room.membership][&room.room_id];
+        let encrypted: Vec<&str> = data["timeline"]["events"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|e| e["type"] == "m.room.encrypted")
+            .map(|e| e["content"]["session_id"].as_str().unwrap_or_default())
+            .collect();
+        rooms.push(RoomDecryption {
+            sessions: stored.iter().filter(|(r, _)| *r == room.room_id).count(),
+            encrypted_events: encrypted.len(),
+            decryptable_events: encrypted
+                .iter()
+                .filter(|id| stored.contains(&(room.room_id.as_str(), **id)))
+                .count(),
+            room_id: room.room_id,
+            name: room.name,
+        });
+    }
+    rooms
+}
+
+fn key_request_history(
+    origin: &Origin,
+    requests: &[KeyRequest],
+    sessions: &[crypto::InboundSession],
+) -> Vec<KeyRequestOutcome> {
+    let mut history = Vec::new();
+    for request in requests {
+        let Some(session_id) = &request.session_id else {
+            continue;
+        };
+        history.push(KeyRequestOutcome {
+            room_id: request.room_id.clone(),
+            session_id: session_id.clone(),
+            // A cancellation is sent once the key arrived or the user dismissed the request
+            outcome: match request.state.as_str() {
+                "cancellation_pending" | "cancellation_pending_and_will_resend" => "cancelled",
+                _ => "pending",
+            },
+            detail: Some(format!("asked {}", request.recipients.join(", "))),
+        });
+    }
+    for session in sessions.iter().filter(|s| s.forwarded) {
+        history.push(KeyRequestOutcome {
+            room_id: session.room_id.clone(),
+            session_id: session.session_id.clone(),
+            outcome: "fulfilled",
+            detail: session
+                .forwarding_chain
+                .last()
+                .map(|key| format!("forwarded by device key {}", key)),
+        });
+    }
+    for withheld in crypto::withheld_sessions(origin) {
+        history.push(KeyRequestOutcome {
+            room_id: withheld.room_id,
+            session_id: withheld.session_id,
+            outcome: "withheld",
+            detail: withheld.code.map(|code| match withheld.reason {
+                Some(reason) => format!("{}: {}", code, reason),
+                None => code,
+            }),
+        });
+    }
+    history
+}
@@ -0,0 +1,129 @@
+//! Values as a person reads them: decoded text, indented JSON or a hex dump
+//!
+//! Local Storage values carry their encoding in a prefix byte; other values
+//! are tried as UTF-8, then as UTF-16LE text, and are otherwise binary.
+
+use crate::{Classification, Entry};
+use serde_json::Value;
+
+/// The encoding a value appears to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// Local Storage, prefix byte 1
+    Latin1,
+    /// Local Storage prefix byte 0, or a value that reads cleanly as UTF-16LE
+    Utf16Le,
+    Utf8,
+    Binary,
+}
+
+impl TextEncoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TextEncoding::Latin1 => "latin-1",
+            TextEncoding::Utf16Le => "utf-16le",
+            TextEncoding::Utf8 => "utf-8",
+            TextEncoding::Binary => "binary",
+        }
+    }
+}
+
+/// A value decoded for display
+#[derive(Debug)]
+pub struct Decoded {
+    pub encoding: TextEncoding,
+    /// Whether the text is a JSON object or array
+    pub json: bool,
+    /// The text, JSON indented; a hex dump for binary values
+    pub body: String,
+}
+
+impl Decoded {
+    /// The guessed encoding, e.g. `utf-16le, JSON`
+    pub fn label(&self) -> String {
+        if self.json {
+            format!("{}, JSON", self.encoding.as_str())
+        } else {
+            self.encoding.as_str().to_string()
+        }
+    }
+}
+
+/// Decodes the value of `entry`
+pub fn decode(entry: &Entry) -> Decoded {
+    let local_storage = matches!(
+        entry.classification,
+        Classification::Element { .. } | Classification::OtherOrigin { .. }
+    );
+    let (encoding, text) = match entry.text() {
+        Some(text) if local_storage => match entry.value.first() {
+            Some(1) => (TextEncoding::Latin1, text),
+            _ => (TextEncoding::Utf16Le, text),
+        },
+        Some(text) => (TextEncoding::Utf8, text),
+        None => match utf16le(&entry.value) {
+            Some(text) => (TextEncoding::Utf16Le, text),
+            None => {
+                return Decoded {
+                    encoding: TextEncoding::Binary,
+                    json: false,
+                    body: hexdump(&entry.value),
+                };
+            }
+        },
+    };
+    match serde_json::from_str::<Value>(&text) {
+        Ok(json) if json.is_object() || json.is_array() => Decoded {
+            encoding,
+            json: true,
+            body: serde_json::to_string_pretty(&json).unwrap_or(text),
+        },
+        _ => Decoded {
+            encoding,
+            json: false,
+            body: text,
+        },
+    }
+}
+
+/// `bytes` as UTF-16LE text, if they decode without errors to printable text
+fn utf16le(bytes: &[u8]) -> Option<String> {
+    if bytes.is_empty() || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let text = String::from_utf16(&units).ok()?;
+    text.chars()
+        .all(|c| !c.is_control() || c.is_whitespace())
+        .then_some(text)
+}
+
+/// Offset, hex and ASCII columns, 16 bytes a line, like `hexdump -C`
+pub fn hexdump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        let mut hex = String::new();
+        for (i, byte) in chunk.iter().enumerate() {
+            if i == 8 {
+                hex.push(' ');
+            }
+            hex.push_str(&format!("{:02x} ", byte));
+        }
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        out.push_str(&format!("{:08x}  {:<49} |{}|\n", line * 16, hex, ascii));
+    }
+    out.push_str(&format!("{:08x}\n", bytes.len()));
+    out
+}
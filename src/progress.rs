@@ -0,0 +1,109 @@
+//! Progress of `parse_metadata` through a large database
+//!
+//! A `ProgressCallback` registered on the builder is called as entries are
+//! read and again as metadata is extracted from them, so a GUI or the CLI's
+//! progress bar can show how far a multi-gigabyte profile has got. Closures
+//! taking a `&Progress` are callbacks too.
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Entries between two calls of the callback
+pub const REPORT_EVERY: u64 = 1000;
+
+/// What `parse_metadata` is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading entries from the database; the total isn't known yet
+    Reading,
+    /// Extracting metadata from the entries read
+    Extracting,
+}
+
+/// How far `parse_metadata` has got
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub stage: Stage,
+    /// Entries done in this stage
+    pub entries: u64,
+    /// Key and value bytes of those entries
+    pub bytes: u64,
+    /// Entries the stage will process, when known
+    pub total_entries: Option<u64>,
+}
+
+/// Called with the progress of `parse_metadata`
+pub trait ProgressCallback: Send + Sync {
+    fn progress(&self, progress: &Progress);
+}
+
+impl<F: Fn(&Progress) + Send + Sync> ProgressCallback for F {
+    fn progress(&self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// The callback of a parser, if any
+#[derive(Clone, Default)]
+pub struct ProgressHook {
+    callback: Option<Arc<dyn ProgressCallback>>,
+}
+
+impl ProgressHook {
+    /// Replaces the callback
+    pub fn set(&mut self, callback: impl ProgressCallback + 'static) {
+        self.callback = Some(Arc::new(callback));
+    }
+
+    /// Counts entries of one stage and calls the callback every
+    /// `REPORT_EVERY` entries and at the end
+    pub(crate) fn tracker(&self, stage: Stage, total_entries: Option<u64>) -> Tracker<'_> {
+        Tracker {
+            hook: self,
+            progress: Progress {
+                stage,
+                entries: 0,
+                bytes: 0,
+                total_entries,
+            },
+        }
+    }
+}
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.callback.is_some() {
+            "set"
+        } else {
+            "none"
+        };
+        write!(f, "progress callback {}", state)
+    }
+}
+
+pub(crate) struct Tracker<'a> {
+    hook: &'a ProgressHook,
+    progress: Progress,
+}
+
+impl Tracker<'_> {
+    /// Counts one entry of `bytes` key and value bytes
+    pub(crate) fn add(&mut self, bytes: usize) {
+        self.progress.entries += 1;
+        self.progress.bytes += bytes as u64;
+        if self.progress.entries.is_multiple_of(REPORT_EVERY) {
+            self.report();
+        }
+    }
+
+    /// Reports the stage as done
+    pub(crate) fn finish(self) {
+        self.report();
+    }
+
+    fn report(&self) {
+        if let Some(callback) = &self.hook.callback {
+            callback.progress(&self.progress);
+        }
+    }
+}
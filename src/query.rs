@@ -0,0 +1,532 @@
+//! `--query EXPR`: picks values out of a command's JSON, jq style
+//!
+//! The command's output is captured and read as one JSON document or as JSON
+//! lines; the expression is applied to each document in turn. Only a small part
+//! of jq is understood, enough to do without it on a locked-down workstation:
+//!
+//! - `.`, `.name`, `."odd name"`, `.[0]`, `.[-1]` and `.[]`, chained as in
+//!   `.rooms[].name`; a leading name needs no dot, as in `user_id`
+//! - `length`, `keys`, `values`, `first` and `last`
+//! - `select(COND)` keeping the values for which `COND`, a query or a query
+//!   compared with `==`, `!=`, `<`, `<=`, `>` or `>=` to a JSON literal, has a
+//!   result other than `false` and `null`, as in
+//!   `.rooms[] | select(.member_count > 2) | .name`
+//! - `|` feeding every result of the left side into the right
+//!
+//! Strings are printed without quotes, like `jq -r`; other values as JSON.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// One step of a path
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Field(String),
+    Index(i64),
+    Iterate,
+}
+
+/// Operator of a `select` condition
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Path(Vec<Step>),
+    Length,
+    Keys,
+    Values,
+    First,
+    Last,
+    Select(Query, Option<(Comparison, Value)>),
+}
+
+/// A parsed `--query` expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    filters: Vec<Filter>,
+}
+
+impl Query {
+    pub fn parse(expr: &str) -> Result<Self> {
+        parse_query(expr).map_err(|e| anyhow!("Invalid query {:?}: {}", expr, e))
+    }
+
+    /// Every result of the query on `input`
+    pub fn apply(&self, input: Value) -> Result<Vec<Value>> {
+        let mut values = vec![input];
+        for filter in &self.filters {
+            let mut next = Vec::new();
+            for value in values {
+                apply_filter(filter, value, &mut next)?;
+            }
+            values = next;
+        }
+        Ok(values)
+    }
+
+    /// Applies the query to each JSON document in `output` and formats the
+    /// results, one per line
+    pub fn run(&self, output: &[u8]) -> Result<String> {
+        let mut results = String::new();
+        for document in serde_json::Deserializer::from_slice(output).into_iter::<Value>() {
+            let document =
+                document.map_err(|_| anyhow!("--query needs JSON output; try --format json"))?;
+            for value in self.apply(document)? {
+                match value {
+                    Value::String(text) => results.push_str(&text),
+                    value => results.push_str(&serde_json::to_string_pretty(&value)?),
+                }
+                results.push('\n');
+            }
+        }
+        Ok(results)
+    }
+}
+
+fn parse_query(expr: &str) -> Result<Query> {
+    let filters = split_pipes(expr)
+        .into_iter()
+        .map(|part| parse_filter(part.trim()))
+        .collect::<Result<_>>()?;
+    Ok(Query { filters })
+}
+
+/// Byte offsets of the characters of `expr` outside string literals, with
+/// their depth of parentheses
+fn outside_strings(expr: &str) -> Vec<(usize, char, usize)> {
+    let mut chars = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in expr.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ => chars.push((i, c, depth)),
+        }
+    }
+    chars
+}
+
+/// The parts of `expr` between `|`s outside string literals and parentheses
+fn split_pipes(expr: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (i, c, depth) in outside_strings(expr) {
+        if c == '|' && depth == 0 {
+            parts.push(&expr[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&expr[start..]);
+    parts
+}
+
+/// The comparison of a `select` condition: the query left of it, the
+/// operator and the literal right of it
+fn parse_condition(condition: &str) -> Result<Filter> {
+    let found = outside_strings(condition)
+        .into_iter()
+        .filter(|&(_, _, depth)| depth == 0)
+        .find_map(|(i, _, _)| {
+            let rest = &condition[i..];
+            [
+                ("==", Comparison::Eq),
+                ("!=", Comparison::Ne),
+                ("<=", Comparison::Le),
+                (">=", Comparison::Ge),
+                ("<", Comparison::Lt),
+                (">", Comparison::Gt),
+            ]
+            .into_iter()
+            .find(|(op, _)| rest.starts_with(op))
+            .map(|(op, comparison)| (i, op.len(), comparison))
+        });
+    let Some((at, len, comparison)) = found else {
+        return Ok(Filter::Select(parse_query(condition)?, None));
+    };
+    let literal = condition[at + len..].trim();
+    let literal = serde_json::from_str(literal).map_err(|_| {
+        anyhow!(
+            "expected a JSON literal after the comparison, not {:?}",
+            literal
+        )
+    })?;
+    Ok(Filter::Select(
+        parse_query(&condition[..at])?,
+        Some((comparison, literal)),
+    ))
+}
+
+fn parse_filter(text: &str) -> Result<Filter> {
+    match text {
+        "length" => return Ok(Filter::Length),
+        "keys" => return Ok(Filter::Keys),
+        "values" => return Ok(Filter::Values),
+        "first" => return Ok(Filter::First),
+        "last" => return Ok(Filter::Last),
+        "" => bail!("empty filter"),
+        _ => {}
+    }
+    if let Some(condition) = text.strip_prefix("select(") {
+        let condition = condition
+            .strip_suffix(')')
+            .ok_or_else(|| anyhow!("select( without a closing )"))?;
+        return parse_condition(condition);
+    }
+    let mut chars = text.chars().peekable();
+    let mut steps = Vec::new();
+    // `user_id` for `.user_id`
+    let mut dot = !text.starts_with(['.', '[']);
+    loop {
+        match chars.peek() {
+            None => break,
+            Some('.') if !dot => {
+                chars.next();
+                dot = true;
+            }
+            Some('[') => {
+                chars.next();
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some(c) => inner.push(c),
+                        None => bail!("missing ]"),
+                    }
+                }
+                let inner = inner.trim();
+                steps.push(if inner.is_empty() {
+                    Step::Iterate
+                } else if let Some(name) = quoted(inner) {
+                    Step::Field(name)
+                } else {
+                    Step::Index(
+                        inner
+                            .parse()
+                            .map_err(|_| anyhow!("bad index [{}]", inner))?,
+                    )
+                });
+                dot = false;
+            }
+            Some('"') if dot => {
+                let mut name = String::from('"');
+                chars.next();
+                for c in chars.by_ref() {
+                    name.push(c);
+                    if c == '"' {
+                        break;
+                    }
+                }
+                steps.push(Step::Field(
+                    quoted(&name).ok_or_else(|| anyhow!("unterminated string {}", name))?,
+                ));
+                dot = false;
+            }
+            Some(&c) if dot && (c.is_alphanumeric() || c == '_') => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '-') {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                steps.push(Step::Field(name));
+                dot = false;
+            }
+            Some(&c) => bail!("unexpected {:?}", c),
+        }
+    }
+    Ok(Filter::Path(steps))
+}
+
+/// The text of a JSON string literal
+fn quoted(text: &str) -> Option<String> {
+    match serde_json::from_str(text) {
+        Ok(Value::String(text)) => Some(text),
+        _ => None,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Whether `left` and `right` compare as `comparison`; only numbers and
+/// strings are ordered
+fn compare(left: &Value, comparison: Comparison, right: &Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .zip(b.as_f64())
+            .and_then(|(a, b)| a.partial_cmp(&b)),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ if left == right => Some(Ordering::Equal),
+        _ => None,
+    };
+    match comparison {
+        Comparison::Eq => ordering == Some(Ordering::Equal),
+        Comparison::Ne => ordering != Some(Ordering::Equal),
+        Comparison::Lt => ordering == Some(Ordering::Less),
+        Comparison::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        Comparison::Gt => ordering == Some(Ordering::Greater),
+        Comparison::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+    }
+}
+
+fn apply_filter(filter: &Filter, value: Value, out: &mut Vec<Value>) -> Result<()> {
+    match filter {
+        Filter::Path(steps) => apply_path(steps, value, out)?,
+        Filter::Length => out.push(Value::from(match &value {
+            Value::Null => 0,
+            Value::String(text) => text.chars().count(),
+            Value::Array(items) => items.len(),
+            Value::Object(fields) => fields.len(),
+            other => bail!("{} has no length", type_name(other)),
+        })),
+        Filter::Keys => out.push(match value {
+            Value::Object(fields) => fields.keys().cloned().map(Value::String).collect(),
+            Value::Array(items) => (0..items.len()).map(Value::from).collect(),
+            other => bail!("{} has no keys", type_name(&other)),
+        }),
+        Filter::Values => out.push(match value {
+            Value::Object(fields) => fields.into_iter().map(|(_, v)| v).collect(),
+            Value::Array(items) => Value::Array(items),
+            other => bail!("{} has no values", type_name(&other)),
+        }),
+        Filter::First | Filter::Last => match value {
+            Value::Array(mut items) => out.push(if *filter == Filter::First {
+                items.into_iter().next().unwrap_or_default()
+            } else {
+                items.pop().unwrap_or_default()
+            }),
+            other => bail!("{} is not an array", type_name(&other)),
+        },
+        Filter::Select(condition, comparison) => {
+            let keep = condition
+                .apply(value.clone())?
+                .iter()
+                .any(|result| match comparison {
+                    Some((comparison, literal)) => compare(result, *comparison, literal),
+                    None => !matches!(result, Value::Null | Value::Bool(false)),
+                });
+            if keep {
+                out.push(value);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn apply_path(steps: &[Step], value: Value, out: &mut Vec<Value>) -> Result<()> {
+    let Some((step, rest)) = steps.split_first() else {
+        out.push(value);
+        return Ok(());
+    };
+    match (step, value) {
+        (Step::Field(name), Value::Object(mut fields)) => {
+            apply_path(rest, fields.remove(name).unwrap_or_default(), out)
+        }
+        (Step::Field(_) | Step::Index(_), Value::Null) => apply_path(rest, Value::Null, out),
+        (Step::Index(index), Value::Array(mut items)) => {
+            let index = if *index < 0 {
+                items.len().checked_sub(index.unsigned_abs() as usize)
+            } else {
+                Some(*index as usize)
+            };
+            let item = match index {
+                Some(index) if index < items.len() => items.swap_remove(index),
+                _ => Value::Null,
+            };
+            apply_path(rest, item, out)
+        }
+        (Step::Iterate, Value::Array(items)) => {
+            for item in items {
+                apply_path(rest, item, out)?;
+            }
+            Ok(())
+        }
+        (Step::Iterate, Value::Object(fields)) => {
+            for (_, item) in fields {
+                apply_path(rest, item, out)?;
+            }
+            Ok(())
+        }
+        (Step::Field(name), other) => bail!("Cannot get .{} of {}", name, type_name(&other)),
+        (Step::Index(index), other) => bail!("Cannot get [{}] of {}", index, type_name(&other)),
+        (Step::Iterate, other) => bail!("Cannot iterate over {}", type_name(&other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn query(expr: &str, input: Value) -> Vec<Value> {
+        Query::parse(expr).unwrap().apply(input).unwrap()
+    }
+
+    fn field(name: &str) -> Step {
+        Step::Field(name.to_string())
+    }
+
+    #[test]
+    fn parses_paths() {
+        let path = |expr| Query::parse(expr).unwrap().filters;
+        assert_eq!(
+            path(".a.b"),
+            vec![Filter::Path(vec![field("a"), field("b")])]
+        );
+        assert_eq!(path("user_id"), vec![Filter::Path(vec![field("user_id")])]);
+        assert_eq!(path("."), vec![Filter::Path(vec![])]);
+        assert_eq!(path(".[]"), vec![Filter::Path(vec![Step::Iterate])]);
+        assert_eq!(path(".[2]"), vec![Filter::Path(vec![Step::Index(2)])]);
+        assert_eq!(path(".[-1]"), vec![Filter::Path(vec![Step::Index(-1)])]);
+        assert_eq!(
+            path(r#".rooms[]."odd name"["x|y"]"#),
+            vec![Filter::Path(vec![
+                field("rooms"),
+                Step::Iterate,
+                field("odd name"),
+                field("x|y"),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parses_pipes_and_select() {
+        assert_eq!(
+            Query::parse(".rooms | length").unwrap().filters,
+            vec![Filter::Path(vec![field("rooms")]), Filter::Length]
+        );
+        assert_eq!(
+            Query::parse(r#".[] | select(.name == "a|b") | .id"#)
+                .unwrap()
+                .filters,
+            vec![
+                Filter::Path(vec![Step::Iterate]),
+                Filter::Select(
+                    Query {
+                        filters: vec![Filter::Path(vec![field("name")])]
+                    },
+                    Some((Comparison::Eq, json!("a|b"))),
+                ),
+                Filter::Path(vec![field("id")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn evaluates_paths() {
+        let input = json!({"a": {"b": 1}, "list": [10, 20, 30]});
+        assert_eq!(query(".a.b", input.clone()), vec![json!(1)]);
+        assert_eq!(query(".a.missing", input.clone()), vec![Value::Null]);
+        assert_eq!(query(".list[1]", input.clone()), vec![json!(20)]);
+        assert_eq!(query(".list[-1]", input.clone()), vec![json!(30)]);
+        assert_eq!(query(".list[5]", input.clone()), vec![Value::Null]);
+        assert_eq!(
+            query(".list[]", input.clone()),
+            vec![json!(10), json!(20), json!(30)]
+        );
+        assert_eq!(query(".a[]", input), vec![json!(1)]);
+    }
+
+    #[test]
+    fn evaluates_pipes() {
+        let input = json!({"rooms": [{"name": "x"}, {"name": "y"}], "room_ids": ["!a", "!b"]});
+        assert_eq!(
+            query(".rooms[] | .name", input.clone()),
+            vec![json!("x"), json!("y")]
+        );
+        assert_eq!(query("room_ids | length", input.clone()), vec![json!(2)]);
+        assert_eq!(
+            query(".rooms | last | .name", input.clone()),
+            vec![json!("y")]
+        );
+        assert_eq!(query("keys", input), vec![json!(["room_ids", "rooms"])]);
+    }
+
+    #[test]
+    fn evaluates_select() {
+        let input = json!([
+            {"name": "a", "members": 1, "encrypted": true},
+            {"name": "b", "members": 3, "encrypted": false},
+            {"name": "c", "members": 5},
+        ]);
+        let names = |expr: &str| {
+            query(&format!(".[] | {} | .name", expr), input.clone())
+                .into_iter()
+                .map(|v| v.as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("select(.members > 1)"), ["b", "c"]);
+        assert_eq!(names("select(.members <= 3)"), ["a", "b"]);
+        assert_eq!(names("select(.members == 3.0)"), ["b"]);
+        assert_eq!(names(r#"select(.name != "a")"#), ["b", "c"]);
+        assert_eq!(names("select(.encrypted)"), ["a"]);
+        assert_eq!(names("select(.encrypted == null)"), ["c"]);
+        assert_eq!(names("select(.name | length >= 1)"), ["a", "b", "c"]);
+        assert_eq!(names(r#"select(.members > "1")"#), Vec::<String>::new());
+    }
+
+    #[test]
+    fn syntax_errors_are_errors() {
+        for expr in [
+            "",
+            ".a |",
+            "| .a",
+            ".[",
+            ".[x]",
+            ".a b",
+            r#"."unterminated"#,
+            "..a",
+            "select(.a",
+            "select(.a == )",
+            "select(.a == nope)",
+            "select()",
+            "select(.a > 1) extra",
+        ] {
+            assert!(Query::parse(expr).is_err(), "{:?} parsed", expr);
+        }
+    }
+
+    #[test]
+    fn type_errors_are_errors() {
+        for expr in [".a", ".[0]", ".[]", "keys", "first", "select(.a)"] {
+            let query = Query::parse(expr).unwrap();
+            assert!(query.apply(json!(1)).is_err(), "{:?} applied", expr);
+        }
+    }
+
+    #[test]
+    fn prints_strings_raw() {
+        let query = Query::parse(".name").unwrap();
+        assert_eq!(
+            query.run(b"{\"name\":\"x\"}\n{\"name\":[1]}").unwrap(),
+            "x\n[\n  1\n]\n"
+        );
+        assert!(query.run(b"text").is_err());
+    }
+}
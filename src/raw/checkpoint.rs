@@ -0,0 +1,204 @@
+//! Raw directory scans that can be interrupted and resumed
+//!
+//! Records recovered from each finished file are appended to a spool, and the
+//! checkpoint (files done, spool length) is rewritten atomically after every
+//! file. Resuming truncates the spool to the checkpointed length, dropping
+//! whatever a crash left half-written, and skips the files already done.
+
+use super::{
+    database_files, file_name, read_length_prefixed, read_varint, write_length_prefixed,
+    write_varint, RawRecord, RawScan, ValueType,
+};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Checkpoint metadata inside the checkpoint directory
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+/// Encoded records of the finished files
+pub const SPOOL_FILE: &str = "records.spool";
+
+/// Progress of an interrupted or running scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub source: PathBuf,
+    /// RFC 3339 time the scan first started
+    pub started_at: String,
+    /// RFC 3339 time of the last update
+    pub updated_at: String,
+    /// Files fully processed, in scan order
+    pub files: Vec<FileProgress>,
+    /// Valid length of the spool; anything after it is discarded on resume
+    pub spool_offset: u64,
+    pub issues: Vec<String>,
+    /// How many times the scan was resumed
+    #[serde(default)]
+    pub resumes: u32,
+}
+
+/// One fully processed file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileProgress {
+    pub name: String,
+    /// Size when read; a resumed scan refuses to continue if it changed
+    pub size: u64,
+    pub records: usize,
+    /// Spool length after this file's records
+    pub spool_offset: u64,
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint of `dir`, if there is one
+    pub fn load(dir: &Path) -> Result<Option<Checkpoint>> {
+        let path = dir.join(CHECKPOINT_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = std::fs::read(&path)?;
+        let checkpoint = serde_json::from_slice(&data)
+            .with_context(|| format!("Unreadable checkpoint {}", path.display()))?;
+        Ok(Some(checkpoint))
+    }
+
+    /// Writes the checkpoint through a temporary file and a rename
+    fn save(&self, dir: &Path) -> Result<()> {
+        let tmp = dir.join(format!("{}.tmp", CHECKPOINT_FILE));
+        let mut file = File::create(&tmp)?;
+        file.write_all(&serde_json::to_vec_pretty(self)?)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, dir.join(CHECKPOINT_FILE))?;
+        Ok(())
+    }
+}
+
+/// Scans `src` like `scan_directory`, checkpointing into `dir` after every file
+///
+/// Without `resume` an existing checkpoint is an error rather than being
+/// overwritten. `on_progress` sees the checkpoint when the scan starts or
+/// resumes and after each file. The checkpoint directory is left in place;
+/// remove it once the scan's output is safely written.
+pub fn scan_directory(
+    src: &Path,
+    dir: &Path,
+    resume: bool,
+    mut on_progress: impl FnMut(&Checkpoint, Option<&FileProgress>) -> Result<()>,
+) -> Result<RawScan> {
+    let now = || chrono::Utc::now().to_rfc3339();
+    let source = src.canonicalize()?;
+    let mut checkpoint = match Checkpoint::load(dir)? {
+        Some(_) if !resume => bail!(
+            "{} holds an unfinished scan; pass --resume to continue it or delete it",
+            dir.display()
+        ),
+        Some(checkpoint) if checkpoint.source != source => bail!(
+            "Checkpoint {} belongs to {}, not {}",
+            dir.display(),
+            checkpoint.source.display(),
+            source.display()
+        ),
+        Some(mut checkpoint) => {
+            checkpoint.resumes += 1;
+            checkpoint
+        }
+        None => {
+            std::fs::create_dir_all(dir)?;
+            Checkpoint {
+                source: source.clone(),
+                started_at: now(),
+                updated_at: now(),
+                files: Vec::new(),
+                spool_offset: 0,
+                issues: Vec::new(),
+                resumes: 0,
+            }
+        }
+    };
+
+    let mut spool = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(SPOOL_FILE))?;
+    spool.set_len(checkpoint.spool_offset)?;
+    checkpoint.updated_at = now();
+    checkpoint.save(dir)?;
+    on_progress(&checkpoint, None)?;
+
+    for path in database_files(&source)? {
+        let name = file_name(&path);
+        let size = std::fs::metadata(&path)?.len();
+        if let Some(done) = checkpoint.files.iter().find(|f| f.name == name) {
+            if done.size != size {
+                bail!(
+                    "{} changed size since it was checkpointed ({} -> {} bytes)",
+                    name,
+                    done.size,
+                    size
+                );
+            }
+            continue;
+        }
+
+        let (records, issues) = match super::read_file(&path) {
+            Ok(read) => read,
+            Err(e) => (Vec::new(), vec![e.to_string()]),
+        };
+        let mut encoded = Vec::new();
+        for record in &records {
+            encode_record(&mut encoded, record);
+        }
+        spool.write_all(&encoded)?;
+        spool.sync_data()?;
+
+        checkpoint.spool_offset += encoded.len() as u64;
+        checkpoint
+            .issues
+            .extend(issues.into_iter().map(|i| format!("{}: {}", name, i)));
+        checkpoint.files.push(FileProgress {
+            name,
+            size,
+            records: records.len(),
+            spool_offset: checkpoint.spool_offset,
+        });
+        checkpoint.updated_at = now();
+        checkpoint.save(dir)?;
+        on_progress(&checkpoint, checkpoint.files.last())?;
+    }
+
+    let spool = std::fs::read(dir.join(SPOOL_FILE))?;
+    let records =
+        decode_records(&spool).with_context(|| format!("Corrupt spool in {}", dir.display()))?;
+    Ok(RawScan {
+        records,
+        files_read: checkpoint.files.len(),
+        issues: checkpoint.issues,
+    })
+}
+
+fn encode_record(out: &mut Vec<u8>, record: &RawRecord) {
+    write_length_prefixed(out, &record.key);
+    write_varint(out, record.sequence);
+    out.push(record.value_type.tag());
+    write_length_prefixed(out, &record.value);
+}
+
+fn decode_records(mut data: &[u8]) -> Option<Vec<RawRecord>> {
+    let mut records = Vec::new();
+    while !data.is_empty() {
+        let (key, n) = read_length_prefixed(data)?;
+        data = &data[n..];
+        let (sequence, n) = read_varint(data)?;
+        let value_type = ValueType::from_tag(*data.get(n)?)?;
+        data = &data[n + 1..];
+        let (value, n) = read_length_prefixed(data)?;
+        records.push(RawRecord {
+            key: key.to_vec(),
+            sequence,
+            value_type,
+            value: value.to_vec(),
+        });
+        data = &data[n..];
+    }
+    Some(records)
+}
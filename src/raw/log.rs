@@ -0,0 +1,387 @@
+//! Write-ahead log reader
+//!
+//! The log is a sequence of 32 KiB blocks holding checksummed fragments; a
+//! logical record is either one FULL fragment or FIRST, MIDDLE..., LAST.
+//! Each logical record in a database log is a serialized write batch.
+
+use super::{
+    mask_crc, read_length_prefixed, unmask_crc, write_length_prefixed, RawRecord, ValueType,
+};
+
+const BLOCK_SIZE: usize = 32 * 1024;
+const HEADER_SIZE: usize = 7;
+
+const FULL: u8 = 1;
+const FIRST: u8 = 2;
+const MIDDLE: u8 = 3;
+const LAST: u8 = 4;
+
+/// Splits a log file into logical records, skipping damaged fragments
+///
+/// A checksum or framing error drops the rest of the current block and any
+/// partially assembled record, which matches what LevelDB does on recovery.
+pub fn read_records(data: &[u8]) -> (Vec<Vec<u8>>, Vec<String>) {
+    let (records, issues) = read_records_at(data);
+    (records.into_iter().map(|(_, r)| r).collect(), issues)
+}
+
+/// Like `read_records`, with the file offset of each record's first fragment
+pub fn read_records_at(data: &[u8]) -> (Vec<(u64, Vec<u8>)>, Vec<String>) {
+    let mut records = Vec::new();
+    let mut issues = Vec::new();
+    let mut pending: Option<(u64, Vec<u8>)> = None;
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let block_end = (offset - offset % BLOCK_SIZE + BLOCK_SIZE).min(data.len());
+        if block_end - offset < HEADER_SIZE {
+            offset = block_end;
+            continue;
+        }
+
+        let header = &data[offset..offset + HEADER_SIZE];
+        let crc = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let length = usize::from(u16::from_le_bytes([header[4], header[5]]));
+        let kind = header[6];
+
+        // Zero-filled preallocated space at the tail of a log
+        if kind == 0 && length == 0 {
+            offset = block_end;
+            continue;
+        }
+
+        let start = offset + HEADER_SIZE;
+        if start + length > block_end {
+            issues.push(format!("fragment at offset {} overruns its block", offset));
+            pending = None;
+            offset = block_end;
+            continue;
+        }
+
+        let payload = &data[start..start + length];
+        let fragment = offset as u64;
+        let actual = crc32c::crc32c_append(crc32c::crc32c(&[kind]), payload);
+        if actual != unmask_crc(crc) {
+            issues.push(format!("checksum mismatch at offset {}", offset));
+            pending = None;
+            offset = block_end;
+            continue;
+        }
+        offset = start + length;
+
+        match kind {
+            FULL => {
+                if pending.take().is_some() {
+                    issues.push(format!("unterminated record before offset {}", offset));
+                }
+                records.push((fragment, payload.to_vec()));
+            }
+            FIRST => {
+                if pending.replace((fragment, payload.to_vec())).is_some() {
+                    issues.push(format!("unterminated record before offset {}", offset));
+                }
+            }
+            MIDDLE | LAST => match pending.as_mut() {
+                Some((_, buf)) => {
+                    buf.extend_from_slice(payload);
+                    if kind == LAST {
+                        records.extend(pending.take());
+                    }
+                }
+                None => issues.push(format!("orphan fragment at offset {}", offset)),
+            },
+            other => issues.push(format!(
+                "unknown fragment type {} at offset {}",
+                other, offset
+            )),
+        }
+    }
+
+    if pending.is_some() {
+        issues.push("log ends inside a record".to_string());
+    }
+
+    (records, issues)
+}
+
+/// Decodes a serialized write batch into its individual records
+///
+/// Decoding stops at the first malformed record; the records before it are
+/// still returned alongside the error.
+pub fn parse_write_batch(batch: &[u8]) -> (Vec<RawRecord>, Option<String>) {
+    let mut records = Vec::new();
+    if batch.len() < 12 {
+        let issue = format!("write batch too short ({} bytes)", batch.len());
+        return (records, Some(issue));
+    }
+    let base_sequence = u64::from_le_bytes(batch[..8].try_into().expect("8-byte slice"));
+    let count = u32::from_le_bytes(batch[8..12].try_into().expect("4-byte slice"));
+
+    let mut rest = &batch[12..];
+    while !rest.is_empty() {
+        let Some(value_type) = ValueType::from_tag(rest[0]) else {
+            return (records, Some(format!("unknown record tag {}", rest[0])));
+        };
+        rest = &rest[1..];
+
+        let Some((key, used)) = read_length_prefixed(rest) else {
+            return (records, Some("truncated key".to_string()));
+        };
+        rest = &rest[used..];
+
+        let value = if value_type == ValueType::Value {
+            let Some((value, used)) = read_length_prefixed(rest) else {
+                return (records, Some("truncated value".to_string()));
+            };
+            rest = &rest[used..];
+            value.to_vec()
+        } else {
+            Vec::new()
+        };
+
+        records.push(RawRecord {
+            key: key.to_vec(),
+            sequence: base_sequence + records.len() as u64,
+            value_type,
+            value,
+        });
+    }
+
+    let issue = (records.len() != count as usize).then(|| {
+        format!(
+            "write batch declares {} records but holds {}",
+            count,
+            records.len()
+        )
+    });
+    (records, issue)
+}
+
+/// Reads every write batch in a database log
+///
+/// Batches that fail to decode are reported; records decoded before the
+/// damage are kept.
+pub fn read_write_batches(data: &[u8]) -> (Vec<RawRecord>, Vec<String>) {
+    let (batches, issues) = read_write_batches_at(data);
+    (batches.into_iter().flat_map(|(_, r)| r).collect(), issues)
+}
+
+/// Like `read_write_batches`, keeping the records of each batch together
+/// with the file offset the batch starts at
+pub fn read_write_batches_at(data: &[u8]) -> (Vec<(u64, Vec<RawRecord>)>, Vec<String>) {
+    let (batches, mut issues) = read_records_at(data);
+    let mut records = Vec::new();
+    for (offset, batch) in batches {
+        let (decoded, issue) = parse_write_batch(&batch);
+        records.push((offset, decoded));
+        issues.extend(issue);
+    }
+    (records, issues)
+}
+
+/// Builds a log file in memory using the standard block framing
+#[derive(Debug, Default)]
+pub struct LogWriter {
+    buf: Vec<u8>,
+}
+
+impl LogWriter {
+    /// Appends one logical record, fragmenting it across blocks as needed
+    pub fn add_record(&mut self, mut record: &[u8]) {
+        let mut first = true;
+        loop {
+            let left_in_block = BLOCK_SIZE - self.buf.len() % BLOCK_SIZE;
+            if left_in_block < HEADER_SIZE {
+                self.buf.resize(self.buf.len() + left_in_block, 0);
+                continue;
+            }
+
+            let room = left_in_block - HEADER_SIZE;
+            let fragment = &record[..record.len().min(room)];
+            let last = fragment.len() == record.len();
+            let kind = match (first, last) {
+                (true, true) => FULL,
+                (true, false) => FIRST,
+                (false, false) => MIDDLE,
+                (false, true) => LAST,
+            };
+
+            let crc = crc32c::crc32c_append(crc32c::crc32c(&[kind]), fragment);
+            self.buf.extend_from_slice(&mask_crc(crc).to_le_bytes());
+            self.buf
+                .extend_from_slice(&(fragment.len() as u16).to_le_bytes());
+            self.buf.push(kind);
+            self.buf.extend_from_slice(fragment);
+
+            record = &record[fragment.len()..];
+            first = false;
+            if last {
+                return;
+            }
+        }
+    }
+
+    /// Returns the finished log file contents
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Serializes a write batch whose records get consecutive sequence numbers
+pub fn encode_write_batch(base_sequence: u64, records: &[(ValueType, &[u8], &[u8])]) -> Vec<u8> {
+    let mut batch = Vec::new();
+    batch.extend_from_slice(&base_sequence.to_le_bytes());
+    batch.extend_from_slice(&(records.len() as u32).to_le_bytes());
+    for (value_type, key, value) in records {
+        batch.push(value_type.tag());
+        write_length_prefixed(&mut batch, key);
+        if *value_type == ValueType::Value {
+            write_length_prefixed(&mut batch, value);
+        }
+    }
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(len: usize, seed: u8) -> Vec<u8> {
+        (0..len)
+            .map(|i| (i as u8).wrapping_mul(31) ^ seed)
+            .collect()
+    }
+
+    #[test]
+    fn reassembles_fragments_across_blocks() {
+        let big = record(2 * BLOCK_SIZE + 100, 1);
+        let small = record(10, 2);
+        let mut writer = LogWriter::default();
+        writer.add_record(&big);
+        writer.add_record(&small);
+        let data = writer.finish();
+
+        // FIRST in block 0, MIDDLE filling block 1, LAST and the FULL in block 2
+        assert_eq!(data[6], FIRST);
+        assert_eq!(data[BLOCK_SIZE + 6], MIDDLE);
+        assert_eq!(data[2 * BLOCK_SIZE + 6], LAST);
+
+        let (records, issues) = read_records_at(&data);
+        assert!(issues.is_empty(), "{:?}", issues);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], (0, big));
+        assert_eq!(records[1].1, small);
+        assert!(records[1].0 > 2 * BLOCK_SIZE as u64);
+    }
+
+    #[test]
+    fn bad_checksum_drops_the_rest_of_its_block() {
+        // The first record fills block 0 exactly; the second starts block 1
+        let first = record(BLOCK_SIZE - HEADER_SIZE, 3);
+        let second = record(20, 4);
+        let mut writer = LogWriter::default();
+        writer.add_record(&first);
+        writer.add_record(&second);
+        let mut data = writer.finish();
+        data[HEADER_SIZE + 5] ^= 0xff;
+
+        let (records, issues) = read_records(&data);
+        assert_eq!(records, vec![second]);
+        assert_eq!(issues, vec!["checksum mismatch at offset 0"]);
+    }
+
+    #[test]
+    fn bad_checksum_in_a_last_fragment_drops_the_record() {
+        let big = record(BLOCK_SIZE + 100, 5);
+        let next = record(20, 6);
+        let mut writer = LogWriter::default();
+        writer.add_record(&big);
+        writer.add_record(&next);
+        let mut data = writer.finish();
+        data[BLOCK_SIZE + HEADER_SIZE] ^= 0xff;
+
+        let (records, issues) = read_records(&data);
+        assert!(records.is_empty());
+        assert_eq!(
+            issues[0],
+            format!("checksum mismatch at offset {}", BLOCK_SIZE)
+        );
+    }
+
+    #[test]
+    fn truncated_log_reports_the_unfinished_record() {
+        let small = record(10, 7);
+        let big = record(BLOCK_SIZE + 100, 8);
+        let mut writer = LogWriter::default();
+        writer.add_record(&small);
+        writer.add_record(&big);
+        let data = writer.finish();
+
+        // Cut inside the LAST fragment's payload
+        let (records, issues) = read_records(&data[..data.len() - 50]);
+        assert_eq!(records, vec![small.clone()]);
+        assert_eq!(
+            issues,
+            vec![format!(
+                "fragment at offset {} overruns its block",
+                BLOCK_SIZE
+            )]
+        );
+
+        // Cut at the block boundary, after the FIRST fragment
+        let (records, issues) = read_records(&data[..BLOCK_SIZE]);
+        assert_eq!(records, vec![small]);
+        assert_eq!(issues, vec!["log ends inside a record"]);
+    }
+
+    #[test]
+    fn orphan_fragment_is_reported() {
+        let mut writer = LogWriter::default();
+        writer.add_record(&record(BLOCK_SIZE + 100, 9));
+        let data = writer.finish();
+
+        let (records, issues) = read_records(&data[BLOCK_SIZE..]);
+        assert!(records.is_empty());
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].starts_with("orphan fragment"), "{}", issues[0]);
+    }
+
+    #[test]
+    fn write_batch_round_trip() {
+        let batch = encode_write_batch(
+            41,
+            &[
+                (ValueType::Value, b"a", b"1"),
+                (ValueType::Deletion, b"b", b""),
+            ],
+        );
+        let (records, issue) = parse_write_batch(&batch);
+        assert_eq!(issue, None);
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            (records[0].key.as_slice(), records[0].sequence),
+            (&b"a"[..], 41)
+        );
+        assert_eq!(records[0].value, b"1");
+        assert_eq!(records[1].value_type, ValueType::Deletion);
+        assert_eq!(records[1].sequence, 42);
+    }
+
+    #[test]
+    fn truncated_write_batch_keeps_the_records_before() {
+        let batch = encode_write_batch(
+            1,
+            &[
+                (ValueType::Value, b"a", b"1"),
+                (ValueType::Value, b"b", b"22"),
+            ],
+        );
+        let (records, issue) = parse_write_batch(&batch[..batch.len() - 1]);
+        assert_eq!(records.len(), 1);
+        assert_eq!(issue.as_deref(), Some("truncated value"));
+
+        let (records, issue) = parse_write_batch(&batch[..8]);
+        assert!(records.is_empty());
+        assert_eq!(issue.as_deref(), Some("write batch too short (8 bytes)"));
+    }
+}
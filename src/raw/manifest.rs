@@ -0,0 +1,123 @@
+//! MANIFEST (version edit) encoding and replay
+//!
+//! The MANIFEST is a log-format file of version edits describing which table
+//! files make up each level of the database.
+
+use super::{log, read_length_prefixed, read_varint, write_length_prefixed, write_varint};
+use std::collections::BTreeMap;
+
+const TAG_COMPARATOR: u64 = 1;
+const TAG_LOG_NUMBER: u64 = 2;
+const TAG_NEXT_FILE_NUMBER: u64 = 3;
+const TAG_LAST_SEQUENCE: u64 = 4;
+const TAG_COMPACT_POINTER: u64 = 5;
+const TAG_DELETED_FILE: u64 = 6;
+const TAG_NEW_FILE: u64 = 7;
+const TAG_PREV_LOG_NUMBER: u64 = 9;
+
+/// Table file added to a level by a version edit
+#[derive(Debug, Clone)]
+pub struct FileMetaData {
+    pub level: u32,
+    pub number: u64,
+    pub size: u64,
+    pub smallest: Vec<u8>,
+    pub largest: Vec<u8>,
+}
+
+/// Subset of a version edit needed to describe a fresh database
+#[derive(Debug, Clone, Default)]
+pub struct VersionEdit {
+    pub comparator: Option<String>,
+    pub log_number: Option<u64>,
+    pub next_file_number: Option<u64>,
+    pub last_sequence: Option<u64>,
+    pub new_files: Vec<FileMetaData>,
+}
+
+impl VersionEdit {
+    /// Serializes the edit as stored in a MANIFEST record
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if let Some(name) = &self.comparator {
+            write_varint(&mut out, TAG_COMPARATOR);
+            write_length_prefixed(&mut out, name.as_bytes());
+        }
+        for (tag, value) in [
+            (TAG_LOG_NUMBER, self.log_number),
+            (TAG_NEXT_FILE_NUMBER, self.next_file_number),
+            (TAG_LAST_SEQUENCE, self.last_sequence),
+        ] {
+            if let Some(value) = value {
+                write_varint(&mut out, tag);
+                write_varint(&mut out, value);
+            }
+        }
+        for file in &self.new_files {
+            write_varint(&mut out, TAG_NEW_FILE);
+            write_varint(&mut out, u64::from(file.level));
+            write_varint(&mut out, file.number);
+            write_varint(&mut out, file.size);
+            write_length_prefixed(&mut out, &file.smallest);
+            write_length_prefixed(&mut out, &file.largest);
+        }
+        out
+    }
+}
+
+/// Replays every version edit of a MANIFEST, giving the level of each live
+/// table by file number
+///
+/// An edit that can't be decoded is reported and replay continues with the
+/// next one, so a damaged MANIFEST still yields what it can.
+pub fn table_levels(manifest: &[u8]) -> (BTreeMap<u64, u32>, Vec<String>) {
+    let (edits, mut issues) = log::read_records(manifest);
+    let mut levels = BTreeMap::new();
+    for edit in edits {
+        if let Err(issue) = replay_edit(&edit, &mut levels) {
+            issues.push(issue);
+        }
+    }
+    (levels, issues)
+}
+
+fn replay_edit(mut edit: &[u8], levels: &mut BTreeMap<u64, u32>) -> Result<(), String> {
+    let truncated = || "truncated version edit".to_string();
+    let varint = |data: &mut &[u8]| {
+        let (value, used) = read_varint(data).ok_or_else(truncated)?;
+        *data = &data[used..];
+        Ok::<_, String>(value)
+    };
+    let skip_bytes = |data: &mut &[u8]| {
+        let (_, used) = read_length_prefixed(data).ok_or_else(truncated)?;
+        *data = &data[used..];
+        Ok::<_, String>(())
+    };
+    while !edit.is_empty() {
+        match varint(&mut edit)? {
+            TAG_COMPARATOR => skip_bytes(&mut edit)?,
+            TAG_LOG_NUMBER | TAG_NEXT_FILE_NUMBER | TAG_LAST_SEQUENCE | TAG_PREV_LOG_NUMBER => {
+                varint(&mut edit)?;
+            }
+            TAG_COMPACT_POINTER => {
+                varint(&mut edit)?;
+                skip_bytes(&mut edit)?;
+            }
+            TAG_DELETED_FILE => {
+                varint(&mut edit)?;
+                let number = varint(&mut edit)?;
+                levels.remove(&number);
+            }
+            TAG_NEW_FILE => {
+                let level = varint(&mut edit)?;
+                let number = varint(&mut edit)?;
+                varint(&mut edit)?;
+                skip_bytes(&mut edit)?;
+                skip_bytes(&mut edit)?;
+                levels.insert(number, level as u32);
+            }
+            tag => return Err(format!("unknown version edit tag {}", tag)),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Where each live entry physically comes from
+//!
+//! For every key the newest record is located in its file: the write-ahead
+//! log (offset of the write batch) or a table (offset of the data block),
+//! with the table's level from the MANIFEST that `CURRENT` names. Tables the
+//! MANIFEST doesn't list, e.g. left over from an interrupted compaction, have
+//! no level.
+
+use super::{database_files, file_name, log, manifest, table, ValueType};
+use anyhow::Result;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Kind of file an entry was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum FileKind {
+    /// Write-ahead log: written since the last compaction
+    Log,
+    /// Sorted table
+    Table,
+}
+
+/// Physical origin of an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Provenance {
+    /// File name inside the database directory, e.g. `000005.ldb`
+    pub file: String,
+    pub kind: FileKind,
+    /// LevelDB level of the table; `None` for the log and unlisted tables
+    pub level: Option<u32>,
+    /// Byte offset of the write batch or data block holding the record
+    pub offset: u64,
+    pub sequence: u64,
+}
+
+/// Provenance by raw key
+pub type ProvenanceMap = BTreeMap<Vec<u8>, Provenance>;
+
+/// Provenance of every live key in the LevelDB directory `dir`, with the
+/// problems met while reading
+pub fn live_provenance<P: AsRef<Path>>(dir: P) -> Result<(ProvenanceMap, Vec<String>)> {
+    let dir = dir.as_ref();
+    let mut issues = Vec::new();
+    let levels = match current_manifest(dir) {
+        Some(data) => {
+            let (levels, manifest_issues) = manifest::table_levels(&data);
+            issues.extend(
+                manifest_issues
+                    .into_iter()
+                    .map(|i| format!("MANIFEST: {}", i)),
+            );
+            levels
+        }
+        None => {
+            issues.push("no readable MANIFEST; table levels unknown".to_string());
+            BTreeMap::new()
+        }
+    };
+
+    // Newest record per key, with whether it is a deletion
+    let mut newest: BTreeMap<Vec<u8>, (Provenance, ValueType)> = BTreeMap::new();
+    for path in database_files(dir)? {
+        let name = file_name(&path);
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                issues.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+        let (kind, (groups, file_issues)) = match path.extension().and_then(|e| e.to_str()) {
+            Some("log") => (FileKind::Log, log::read_write_batches_at(&data)),
+            _ => (FileKind::Table, table::read_table_blocks(&data)),
+        };
+        issues.extend(file_issues.into_iter().map(|i| format!("{}: {}", name, i)));
+        let level = match kind {
+            FileKind::Log => None,
+            FileKind::Table => file_number(&name).and_then(|n| levels.get(&n).copied()),
+        };
+        for (offset, records) in groups {
+            for record in records {
+                if newest
+                    .get(&record.key)
+                    .is_some_and(|(p, _)| p.sequence >= record.sequence)
+                {
+                    continue;
+                }
+                let provenance = Provenance {
+                    file: name.clone(),
+                    kind,
+                    level,
+                    offset,
+                    sequence: record.sequence,
+                };
+                newest.insert(record.key, (provenance, record.value_type));
+            }
+        }
+    }
+
+    let live = newest
+        .into_iter()
+        .filter(|(_, (_, value_type))| *value_type == ValueType::Value)
+        .map(|(key, (provenance, _))| (key, provenance))
+        .collect();
+    Ok((live, issues))
+}
+
+fn current_manifest(dir: &Path) -> Option<Vec<u8>> {
+    let current = std::fs::read_to_string(dir.join("CURRENT")).ok()?;
+    std::fs::read(dir.join(current.trim())).ok()
+}
+
+/// `000005` of `000005.ldb`
+fn file_number(name: &str) -> Option<u64> {
+    name.split('.').next()?.parse().ok()
+}
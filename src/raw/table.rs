@@ -0,0 +1,455 @@
+//! Sorted table (`.ldb`/`.sst`) reader
+//!
+//! A table ends with a fixed 48-byte footer pointing at the index block; the
+//! index maps separator keys to data blocks, and each data block holds
+//! prefix-compressed internal keys (user key + 8-byte sequence/type trailer).
+
+use super::{mask_crc, read_varint, unmask_crc, write_varint, RawRecord, ValueType};
+
+const FOOTER_SIZE: usize = 48;
+const BLOCK_TRAILER_SIZE: usize = 5;
+const TABLE_MAGIC: u64 = 0xdb47_7524_8b80_fb57;
+
+const NO_COMPRESSION: u8 = 0;
+const SNAPPY_COMPRESSION: u8 = 1;
+
+/// Key/value pair stored in a block
+pub type BlockEntry = (Vec<u8>, Vec<u8>);
+
+/// Location of a block inside a table file
+#[derive(Debug, Clone, Copy)]
+pub struct BlockHandle {
+    pub offset: u64,
+    pub size: u64,
+}
+
+impl BlockHandle {
+    fn decode(data: &[u8]) -> Option<(Self, usize)> {
+        let (offset, a) = read_varint(data)?;
+        let (size, b) = read_varint(&data[a..])?;
+        Some((BlockHandle { offset, size }, a + b))
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.offset);
+        write_varint(out, self.size);
+    }
+}
+
+/// Reads and decompresses one block
+///
+/// A checksum mismatch is reported but the block is still decoded, since
+/// partially damaged blocks often hold intact entries.
+pub fn read_block(file: &[u8], handle: BlockHandle) -> Result<(Vec<u8>, Option<String>), String> {
+    let start = usize::try_from(handle.offset).map_err(|_| "block offset overflow")?;
+    let size = usize::try_from(handle.size).map_err(|_| "block size overflow")?;
+    let end = start
+        .checked_add(size)
+        .filter(|end| end + BLOCK_TRAILER_SIZE <= file.len())
+        .ok_or_else(|| format!("block at offset {} lies outside the file", start))?;
+
+    let contents = &file[start..end];
+    let compression = file[end];
+    let crc = u32::from_le_bytes(file[end + 1..end + 5].try_into().expect("4-byte slice"));
+    let actual = crc32c::crc32c_append(crc32c::crc32c(contents), &[compression]);
+    let issue = (actual != unmask_crc(crc))
+        .then(|| format!("checksum mismatch in block at offset {}", start));
+
+    let block = match compression {
+        NO_COMPRESSION => contents.to_vec(),
+        SNAPPY_COMPRESSION => snap::raw::Decoder::new()
+            .decompress_vec(contents)
+            .map_err(|e| format!("snappy error in block at offset {}: {}", start, e))?,
+        other => {
+            return Err(format!(
+                "unsupported compression {} in block at offset {}",
+                other, start
+            ));
+        }
+    };
+    Ok((block, issue))
+}
+
+/// Decodes the prefix-compressed entries of a block
+///
+/// Returns the entries decoded before any malformed one, plus the problem found.
+pub fn block_entries(block: &[u8]) -> (Vec<BlockEntry>, Option<String>) {
+    let mut entries = Vec::new();
+    if block.len() < 4 {
+        return (entries, Some("block too short".to_string()));
+    }
+    let num_restarts =
+        u32::from_le_bytes(block[block.len() - 4..].try_into().expect("4-byte slice"));
+    let Some(data_end) = (num_restarts as usize)
+        .checked_mul(4)
+        .and_then(|n| block.len().checked_sub(n + 4))
+    else {
+        return (entries, Some("restart array larger than block".to_string()));
+    };
+
+    let mut key: Vec<u8> = Vec::new();
+    let mut pos = 0;
+    while pos < data_end {
+        let data = &block[pos..data_end];
+        let Some((shared, a)) = read_varint(data) else {
+            return (entries, Some(format!("bad entry header at {}", pos)));
+        };
+        let Some((non_shared, b)) = read_varint(&data[a..]) else {
+            return (entries, Some(format!("bad entry header at {}", pos)));
+        };
+        let Some((value_len, c)) = read_varint(&data[a + b..]) else {
+            return (entries, Some(format!("bad entry header at {}", pos)));
+        };
+
+        let header = a + b + c;
+        let (shared, non_shared, value_len) =
+            (shared as usize, non_shared as usize, value_len as usize);
+        let Some(value_end) = header
+            .checked_add(non_shared)
+            .and_then(|n| n.checked_add(value_len))
+            .filter(|end| *end <= data.len())
+        else {
+            return (entries, Some(format!("entry at {} overruns block", pos)));
+        };
+        if shared > key.len() {
+            return (
+                entries,
+                Some(format!(
+                    "entry at {} shares more than the previous key",
+                    pos
+                )),
+            );
+        }
+
+        key.truncate(shared);
+        key.extend_from_slice(&data[header..header + non_shared]);
+        entries.push((key.clone(), data[header + non_shared..value_end].to_vec()));
+        pos += value_end;
+    }
+
+    (entries, None)
+}
+
+/// Splits an internal key into user key, sequence number and value type
+pub fn parse_internal_key(key: &[u8]) -> Option<(&[u8], u64, ValueType)> {
+    let split = key.len().checked_sub(8)?;
+    let trailer = u64::from_le_bytes(key[split..].try_into().ok()?);
+    let value_type = ValueType::from_tag((trailer & 0xff) as u8)?;
+    Some((&key[..split], trailer >> 8, value_type))
+}
+
+/// Builds an internal key from a user key, sequence number and value type
+pub fn internal_key(user_key: &[u8], sequence: u64, value_type: ValueType) -> Vec<u8> {
+    let mut key = user_key.to_vec();
+    key.extend_from_slice(&((sequence << 8) | u64::from(value_type.tag())).to_le_bytes());
+    key
+}
+
+/// Reads all records of a table, skipping blocks that cannot be decoded
+pub fn read_table(file: &[u8]) -> (Vec<RawRecord>, Vec<String>) {
+    let (blocks, issues) = read_table_blocks(file);
+    (blocks.into_iter().flat_map(|(_, r)| r).collect(), issues)
+}
+
+/// Like `read_table`, keeping the records of each data block together with
+/// the block's file offset
+pub fn read_table_blocks(file: &[u8]) -> (Vec<(u64, Vec<RawRecord>)>, Vec<String>) {
+    let mut blocks = Vec::new();
+    let mut issues = Vec::new();
+
+    if file.len() < FOOTER_SIZE {
+        issues.push("file too short for a table footer".to_string());
+        return (blocks, issues);
+    }
+    let footer = &file[file.len() - FOOTER_SIZE..];
+    let magic = u64::from_le_bytes(footer[40..].try_into().expect("8-byte slice"));
+    if magic != TABLE_MAGIC {
+        issues.push("bad table magic number".to_string());
+        return (blocks, issues);
+    }
+
+    let index_handle = BlockHandle::decode(footer)
+        .and_then(|(_, used)| BlockHandle::decode(&footer[used..]))
+        .map(|(handle, _)| handle);
+    let Some(index_handle) = index_handle else {
+        issues.push("unreadable footer block handles".to_string());
+        return (blocks, issues);
+    };
+
+    let index = match read_block(file, index_handle) {
+        Ok((block, issue)) => {
+            issues.extend(issue);
+            block
+        }
+        Err(e) => {
+            issues.push(format!("index block: {}", e));
+            return (blocks, issues);
+        }
+    };
+    let (index_entries, issue) = block_entries(&index);
+    issues.extend(issue.map(|i| format!("index block: {}", i)));
+
+    for (_, handle) in index_entries {
+        let Some((handle, _)) = BlockHandle::decode(&handle) else {
+            issues.push("unreadable data block handle".to_string());
+            continue;
+        };
+        let block = match read_block(file, handle) {
+            Ok((block, issue)) => {
+                issues.extend(issue);
+                block
+            }
+            Err(e) => {
+                issues.push(e);
+                continue;
+            }
+        };
+
+        let (entries, issue) = block_entries(&block);
+        issues.extend(issue.map(|i| format!("data block at offset {}: {}", handle.offset, i)));
+        let mut records = Vec::new();
+        for (internal_key, value) in entries {
+            match parse_internal_key(&internal_key) {
+                Some((key, sequence, value_type)) => records.push(RawRecord {
+                    key: key.to_vec(),
+                    sequence,
+                    value_type,
+                    value,
+                }),
+                None => issues.push("malformed internal key".to_string()),
+            }
+        }
+        blocks.push((handle.offset, records));
+    }
+
+    (blocks, issues)
+}
+
+const RESTART_INTERVAL: usize = 16;
+const TARGET_BLOCK_SIZE: usize = 4096;
+
+/// Accumulates prefix-compressed entries of one block
+#[derive(Debug, Default)]
+struct BlockBuilder {
+    buf: Vec<u8>,
+    restarts: Vec<u32>,
+    counter: usize,
+    last_key: Vec<u8>,
+}
+
+impl BlockBuilder {
+    fn add(&mut self, key: &[u8], value: &[u8]) {
+        let shared = if self.counter.is_multiple_of(RESTART_INTERVAL) {
+            self.restarts.push(self.buf.len() as u32);
+            0
+        } else {
+            key.iter()
+                .zip(&self.last_key)
+                .take_while(|(a, b)| a == b)
+                .count()
+        };
+
+        write_varint(&mut self.buf, shared as u64);
+        write_varint(&mut self.buf, (key.len() - shared) as u64);
+        write_varint(&mut self.buf, value.len() as u64);
+        self.buf.extend_from_slice(&key[shared..]);
+        self.buf.extend_from_slice(value);
+        self.last_key = key.to_vec();
+        self.counter += 1;
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        let mut block = std::mem::take(&mut self.buf);
+        let restarts = std::mem::take(&mut self.restarts);
+        let restarts = if restarts.is_empty() {
+            vec![0]
+        } else {
+            restarts
+        };
+        for restart in &restarts {
+            block.extend_from_slice(&restart.to_le_bytes());
+        }
+        block.extend_from_slice(&(restarts.len() as u32).to_le_bytes());
+        self.counter = 0;
+        block
+    }
+}
+
+/// Builds an uncompressed table file in memory
+///
+/// Entries must be added in internal-key order.
+#[derive(Debug, Default)]
+pub struct TableBuilder {
+    file: Vec<u8>,
+    data_block: BlockBuilder,
+    index_block: BlockBuilder,
+}
+
+impl TableBuilder {
+    /// Appends an entry keyed by an internal key
+    pub fn add(&mut self, internal_key: &[u8], value: &[u8]) {
+        self.data_block.add(internal_key, value);
+        if self.data_block.buf.len() >= TARGET_BLOCK_SIZE {
+            self.flush_data_block();
+        }
+    }
+
+    fn flush_data_block(&mut self) {
+        if self.data_block.counter == 0 {
+            return;
+        }
+        let last_key = self.data_block.last_key.clone();
+        let block = self.data_block.finish();
+        let handle = self.write_block(&block);
+        let mut encoded = Vec::new();
+        handle.encode(&mut encoded);
+        self.index_block.add(&last_key, &encoded);
+    }
+
+    fn write_block(&mut self, block: &[u8]) -> BlockHandle {
+        let handle = BlockHandle {
+            offset: self.file.len() as u64,
+            size: block.len() as u64,
+        };
+        let crc = crc32c::crc32c_append(crc32c::crc32c(block), &[NO_COMPRESSION]);
+        self.file.extend_from_slice(block);
+        self.file.push(NO_COMPRESSION);
+        self.file.extend_from_slice(&mask_crc(crc).to_le_bytes());
+        handle
+    }
+
+    /// Writes the index, metaindex and footer and returns the table contents
+    pub fn finish(mut self) -> Vec<u8> {
+        self.flush_data_block();
+        let metaindex = BlockBuilder::default().finish();
+        let metaindex_handle = self.write_block(&metaindex);
+        let index = self.index_block.finish();
+        let index_handle = self.write_block(&index);
+
+        let mut footer = Vec::with_capacity(FOOTER_SIZE);
+        metaindex_handle.encode(&mut footer);
+        index_handle.encode(&mut footer);
+        footer.resize(FOOTER_SIZE - 8, 0);
+        footer.extend_from_slice(&TABLE_MAGIC.to_le_bytes());
+        self.file.extend_from_slice(&footer);
+        self.file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(i: usize) -> Vec<u8> {
+        format!("_vector://vector\0\x01mx_key_{:04}", i).into_bytes()
+    }
+
+    fn table(count: usize) -> Vec<u8> {
+        let mut builder = TableBuilder::default();
+        for i in 0..count {
+            let value = vec![b'v'; 100];
+            builder.add(
+                &internal_key(&key(i), i as u64 + 1, ValueType::Value),
+                &value,
+            );
+        }
+        builder.finish()
+    }
+
+    #[test]
+    fn decodes_entries_from_restart_points() {
+        let mut builder = BlockBuilder::default();
+        for i in 0..40 {
+            builder.add(&key(i), &[i as u8]);
+        }
+        let block = builder.finish();
+
+        let count = u32::from_le_bytes(block[block.len() - 4..].try_into().unwrap()) as usize;
+        assert_eq!(count, 3);
+        let array = block.len() - 4 - count * 4;
+        let restarts: Vec<usize> = block[array..block.len() - 4]
+            .chunks(4)
+            .map(|r| u32::from_le_bytes(r.try_into().unwrap()) as usize)
+            .collect();
+        assert_eq!(restarts[0], 0);
+        for (n, &restart) in restarts.iter().enumerate() {
+            // A restart entry shares nothing with the key before it, so
+            // decoding can start there
+            assert_eq!(
+                read_varint(&block[restart..]).map(|(shared, _)| shared),
+                Some(0)
+            );
+            let mut tail = block[restart..array].to_vec();
+            tail.extend_from_slice(&0u32.to_le_bytes());
+            tail.extend_from_slice(&1u32.to_le_bytes());
+            let (entries, issue) = block_entries(&tail);
+            assert_eq!(issue, None);
+            assert_eq!(entries[0].0, key(n * RESTART_INTERVAL));
+            assert_eq!(entries.len(), 40 - n * RESTART_INTERVAL);
+        }
+
+        let (entries, issue) = block_entries(&block);
+        assert_eq!(issue, None);
+        assert_eq!(entries.len(), 40);
+        for (i, (k, v)) in entries.iter().enumerate() {
+            assert_eq!(k, &key(i));
+            assert_eq!(v, &[i as u8]);
+        }
+    }
+
+    #[test]
+    fn restart_array_larger_than_block() {
+        let (entries, issue) = block_entries(&[0, 0, 0, 0, 9, 0, 0, 0]);
+        assert!(entries.is_empty());
+        assert_eq!(issue.as_deref(), Some("restart array larger than block"));
+    }
+
+    #[test]
+    fn table_round_trip_across_blocks() {
+        let (blocks, issues) = read_table_blocks(&table(200));
+        assert!(issues.is_empty(), "{:?}", issues);
+        assert!(blocks.len() > 1);
+        let records: Vec<RawRecord> = blocks.into_iter().flat_map(|(_, r)| r).collect();
+        assert_eq!(records.len(), 200);
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(record.key, key(i));
+            assert_eq!(record.sequence, i as u64 + 1);
+            assert_eq!(record.value_type, ValueType::Value);
+        }
+    }
+
+    #[test]
+    fn truncated_trailers() {
+        let file = table(10);
+        let (records, issues) = read_table(&file[..file.len() - 1]);
+        assert!(records.is_empty());
+        assert_eq!(issues, vec!["bad table magic number"]);
+
+        let (records, issues) = read_table(&file[..FOOTER_SIZE - 1]);
+        assert!(records.is_empty());
+        assert_eq!(issues, vec!["file too short for a table footer"]);
+
+        // A block reaching up to the footer, its trailer one byte short
+        let handle = BlockHandle {
+            offset: 0,
+            size: (file.len() - FOOTER_SIZE) as u64,
+        };
+        let cut = file.len() - FOOTER_SIZE + BLOCK_TRAILER_SIZE - 1;
+        assert_eq!(
+            read_block(&file[..cut], handle).unwrap_err(),
+            "block at offset 0 lies outside the file"
+        );
+    }
+
+    #[test]
+    fn bad_block_checksum_is_reported_and_decoded() {
+        let mut file = table(10);
+        // A byte of the first value
+        let at = file.windows(4).position(|w| w == b"vvvv").unwrap();
+        file[at] = b'w';
+        let (records, issues) = read_table(&file);
+        assert_eq!(records.len(), 10);
+        assert_eq!(issues, vec!["checksum mismatch in block at offset 0"]);
+        assert_eq!(records[0].value[0], b'w');
+    }
+}
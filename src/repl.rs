@@ -0,0 +1,199 @@
+//! `repl`: interactive exploration of one database
+//!
+//! The database is opened once and every line is run against it, so looking
+//! at a few items does not mean reopening the profile for each. Lines are
+//! kept in a history file in the home directory, and Tab completes commands
+//! and the key names known when the REPL started.
+
+use crate::i18n::tr;
+use anyhow::{bail, Result};
+use element_desktop_leveldb::export::{self, EntryRecord, Export};
+use element_desktop_leveldb::{classify_key, Classification, ElementKey, ElementLevelDBParser};
+use element_desktop_leveldb::{Entry, KeyCategory};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+const COMMANDS: &[&str] = &["get", "scan", "classify", "export", "help", "quit"];
+
+/// What `classify <key>` prints
+#[derive(Debug, Serialize)]
+struct ClassifyRecord {
+    key: String,
+    classification: Classification,
+    /// What Element keeps in the item; Local Storage items only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    element_key: Option<ElementKey>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<KeyCategory>,
+}
+
+/// Completes command names, then key names and `export` formats
+struct ReplHelper {
+    keys: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+        let start = line.rfind(' ').map_or(0, |i| i + 1);
+        let word = &line[start..];
+        let candidates: Vec<&str> = match line[..start].split_whitespace().collect::<Vec<_>>()[..] {
+            [] => COMMANDS.to_vec(),
+            ["get" | "scan" | "classify"] => self.keys.iter().map(String::as_str).collect(),
+            ["export"] => vec!["json"],
+            _ => Vec::new(),
+        };
+        let pairs = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .map(|candidate| Pair {
+                display: candidate.to_string(),
+                replacement: candidate.to_string(),
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Reads commands for the database at `path` until `quit` or end of input
+pub fn run(path: &Path) -> Result<()> {
+    let parser = ElementLevelDBParser::open(path)?;
+    let mut keys: Vec<String> = parser.entries()?.map(|e| export::item_name(&e)).collect();
+    keys.sort();
+    keys.dedup();
+
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(ReplHelper { keys }));
+    let history = history_path();
+    if let Some(history) = &history {
+        // There is no history before the first session
+        let _ = editor.load_history(history);
+    }
+
+    eprintln!("{}", tr!("repl-welcome", path = path.display().to_string()));
+    loop {
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line.as_str())?;
+        match words[..] {
+            ["quit" | "exit"] => break,
+            ["help"] => println!("{}", tr!("repl-help")),
+            _ => {
+                if let Err(e) = command(&parser, &words) {
+                    eprintln!("{}", e);
+                }
+            }
+        }
+    }
+
+    if let Some(history) = &history
+        && let Err(e) = editor.save_history(history)
+    {
+        eprintln!(
+            "{}",
+            tr!("warning", message = format!("{}: {}", history.display(), e))
+        );
+    }
+    Ok(())
+}
+
+/// Runs one line other than `help` and `quit`
+fn command(parser: &ElementLevelDBParser, words: &[&str]) -> Result<()> {
+    match words {
+        ["get", key] => {
+            println!("{}", EntryRecord::from(&find(parser, key)?).value);
+        }
+        ["scan"] => scan(parser, "")?,
+        ["scan", prefix] => scan(parser, prefix)?,
+        ["classify", key] => {
+            let entry = find(parser, key)?;
+            let element_key = match &entry.classification {
+                Classification::Element { name } => Some(classify_key(name)),
+                _ => None,
+            };
+            let record = ClassifyRecord {
+                key: export::key_label(&entry.key),
+                classification: entry.classification.clone(),
+                category: element_key.as_ref().map(ElementKey::category),
+                element_key,
+            };
+            println!("{}", serde_json::to_string_pretty(&record)?);
+        }
+        ["export", "json", rest @ ..] if rest.len() <= 1 => {
+            let export = Export {
+                metadata: parser.parse_metadata()?,
+                entries: parser.entries()?.map(|e| EntryRecord::from(&e)).collect(),
+            };
+            let json = serde_json::to_string_pretty(&export)?;
+            match rest {
+                [file] => std::fs::write(file, json)?,
+                _ => println!("{}", json),
+            }
+        }
+        [name, ..] if COMMANDS.contains(name) => bail!("Wrong arguments to {}; try help", name),
+        [name, ..] => bail!("Unknown command {}; try help", name),
+        [] => {}
+    }
+    Ok(())
+}
+
+/// The entry whose item name or raw key is `key`
+fn find(parser: &ElementLevelDBParser, key: &str) -> Result<Entry> {
+    match parser.entries()?.find(|e| export::is_key(e, key)) {
+        Some(entry) => Ok(entry),
+        None => bail!("No item {}", key),
+    }
+}
+
+/// Prints size, type and label of each key whose item name starts with `prefix`
+fn scan(parser: &ElementLevelDBParser, prefix: &str) -> Result<()> {
+    for entry in parser
+        .entries()?
+        .filter(|e| export::item_name(e).starts_with(prefix))
+    {
+        println!(
+            "{:>9}  {:<6}  {}",
+            entry.value.len(),
+            export::value_type(&entry),
+            export::key_label(&entry.key)
+        );
+    }
+    Ok(())
+}
+
+/// `~/.element-desktop-leveldb_history`, when there is a home directory
+fn history_path() -> Option<PathBuf> {
+    std::env::home_dir().map(|home| home.join(".element-desktop-leveldb_history"))
+}
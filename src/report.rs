@@ -0,0 +1,193 @@
+//! `report`: one document to attach to an investigation write-up
+//!
+//! The report holds the account, devices, rooms and settings tables `parse`
+//! shows at a terminal, the crypto material of the profile, notable findings
+//! (secrets `scan` finds and crypto posture issues), and every raw entry. The
+//! HTML report is a single file: styles and the search script are
+//! inline and nothing is loaded from elsewhere, so it opens offline from a
+//! case folder. Raw entries are folded away until opened. Headings follow
+//! `--lang`; nothing in the report depends on when it was made, so two reports
+//! of the same database are identical. The Markdown report is the summary for
+//! pasting into a ticket or wiki page: identity, devices, rooms and findings.
+
+use crate::i18n::{self, tr};
+use crate::table::{self, Table};
+use anyhow::{Context, Result};
+use element_desktop_leveldb::export::{self, EntryRecord};
+use element_desktop_leveldb::keys::KeyCategory;
+use element_desktop_leveldb::posture::CryptoPosture;
+use element_desktop_leveldb::sarif::Issue;
+use element_desktop_leveldb::{Classification, ElementMetadata};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tera::Tera;
+
+const HTML: &str = include_str!("../templates/report.html");
+
+/// Everything a report shows
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub database: String,
+    /// Account, devices, rooms, settings, crypto and crypto items
+    pub tables: Vec<Table>,
+    pub findings: Table,
+    pub entries: Table,
+}
+
+fn flag(value: Option<bool>) -> String {
+    match value {
+        Some(true) => tr!("table-yes"),
+        Some(false) => tr!("table-no"),
+        None => "-".to_string(),
+    }
+}
+
+fn list(items: &[String]) -> String {
+    if items.is_empty() {
+        "-".to_string()
+    } else {
+        items.join("; ")
+    }
+}
+
+/// The report of the database at `path`; `posture` when its IndexedDB was read
+pub fn build(
+    path: &Path,
+    metadata: &ElementMetadata,
+    entries: &[EntryRecord],
+    posture: Option<&CryptoPosture>,
+    issues: &[Issue],
+) -> Report {
+    let mut tables = table::metadata(metadata);
+
+    let mut crypto = Table::new(tr!("report-crypto"), Vec::new());
+    match posture {
+        Some(posture) => {
+            let storage = &posture.secret_storage;
+            for (label, value) in [
+                ("report-crypto-stores", list(&posture.stores)),
+                ("report-pickle-key", flag(posture.pickle_key)),
+                (
+                    "report-secret-storage",
+                    storage
+                        .default_key
+                        .clone()
+                        .unwrap_or_else(|| "-".to_string()),
+                ),
+                ("report-passphrase", flag(Some(storage.passphrase))),
+                (
+                    "report-backup-key",
+                    flag(Some(posture.key_backup.key_cached_locally)),
+                ),
+                (
+                    "report-megolm-sessions",
+                    posture
+                        .rooms
+                        .iter()
+                        .map(|room| room.sessions)
+                        .sum::<usize>()
+                        .to_string(),
+                ),
+                ("report-recovery", list(&posture.recovery.paths)),
+            ] {
+                crypto.row(vec![i18n::message(label, None), value]);
+            }
+        }
+        None => crypto.row(vec![tr!("report-no-indexeddb")]),
+    }
+    tables.push(crypto);
+
+    let mut items = Table::new(
+        tr!("report-crypto-items"),
+        vec![tr!("table-name"), tr!("report-size")],
+    );
+    for entry in entries {
+        if let Classification::Element { name } = &entry.classification
+            && export::category(&entry.classification) == KeyCategory::Crypto.as_str()
+        {
+            items.row(vec![name.clone(), entry.size.to_string()]);
+        }
+    }
+    tables.push(items);
+
+    let mut findings = Table::new(
+        tr!("report-findings"),
+        vec![
+            tr!("report-level"),
+            tr!("report-rule"),
+            tr!("report-location"),
+            tr!("report-message"),
+        ],
+    );
+    for issue in issues {
+        findings.row(vec![
+            issue.level.to_string(),
+            issue.rule.clone(),
+            issue.location.clone(),
+            issue.message.clone(),
+        ]);
+    }
+
+    let mut raw = Table::new(
+        tr!("report-raw-entries"),
+        vec![
+            tr!("report-key"),
+            tr!("report-category"),
+            tr!("report-size"),
+            tr!("report-value"),
+        ],
+    );
+    for entry in entries {
+        raw.row(vec![
+            entry.key.clone(),
+            export::category(&entry.classification).to_string(),
+            entry.size.to_string(),
+            entry.value.clone(),
+        ]);
+    }
+
+    Report {
+        database: path.display().to_string(),
+        tables,
+        findings,
+        entries: raw,
+    }
+}
+
+/// `report` as a self-contained HTML page
+pub fn html(report: &Report) -> Result<String> {
+    let labels: BTreeMap<&str, String> = [
+        ("title", "report-title"),
+        ("database", "report-database"),
+        ("search", "report-search"),
+        ("empty", "table-empty"),
+    ]
+    .into_iter()
+    .map(|(name, id)| (name, i18n::message(id, None)))
+    .collect();
+
+    let mut tera = Tera::default();
+    tera.add_raw_template("report.html", HTML)
+        .context("Invalid built-in report template")?;
+    let mut context = tera::Context::from_serialize(report)?;
+    context.insert("labels", &labels);
+    context.insert("version", env!("CARGO_PKG_VERSION"));
+    Ok(tera.render("report.html", &context)?)
+}
+
+/// `report` as Markdown: identity, devices, rooms and findings
+pub fn markdown(report: &Report) -> String {
+    let mut out = format!(
+        "# {}\n\n{}: `{}`\n",
+        tr!("report-title"),
+        tr!("report-database"),
+        report.database
+    );
+    // `table::metadata` puts the account, devices and rooms first
+    for table in report.tables.iter().take(3).chain([&report.findings]) {
+        out.push('\n');
+        out.push_str(&table.markdown());
+    }
+    out
+}
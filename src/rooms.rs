@@ -0,0 +1,119 @@
+//! One consolidated view of every room the profile mentions
+//!
+//! Local Storage only knows rooms by ID, in key names (per-room settings,
+//! drafts) or values (`mx_last_room_id`); the IndexedDB sync cache adds names,
+//! encryption, tags and unread counts. Both feed the same `RoomInfo`, so a
+//! room seen in several places appears once with all of its sources.
+
+use crate::settings::Settings;
+#[cfg(feature = "serde")]
+use crate::sync::{CachedRoom, NotificationCounts, NotificationSettings};
+use regex::Regex;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::LazyLock;
+
+/// `!opaque:server.name[:port]`
+static ROOM_ID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"![A-Za-z0-9._~=+/\-]+:[A-Za-z0-9.\-]+(?::\d+)?").expect("valid regex")
+});
+
+/// Everything known about one room
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RoomInfo {
+    pub room_id: String,
+    pub name: Option<String>,
+    pub canonical_alias: Option<String>,
+    /// `join`, `invite` or `leave`, from the sync cache
+    pub membership: Option<String>,
+    /// `None` when no source says either way
+    pub encrypted: Option<bool>,
+    pub tags: Vec<String>,
+    #[cfg(feature = "serde")]
+    pub notification_counts: Option<NotificationCounts>,
+    /// Notification overrides; `None` when the room uses the account defaults
+    #[cfg(feature = "serde")]
+    pub notifications: Option<NotificationSettings>,
+    /// Where the room was seen: `sync`, or Local Storage key names with the
+    /// room ID removed, e.g. `mx_cider_state_`
+    pub sources: BTreeSet<String>,
+}
+
+/// Rooms being collected, by room ID
+#[derive(Debug, Clone, Default)]
+pub struct RoomIndex {
+    rooms: BTreeMap<String, RoomInfo>,
+}
+
+impl RoomIndex {
+    /// The room with `room_id`, created if new, with `source` recorded
+    pub fn room(&mut self, room_id: &str, source: &str) -> &mut RoomInfo {
+        let room = self
+            .rooms
+            .entry(room_id.to_string())
+            .or_insert_with(|| RoomInfo {
+                room_id: room_id.to_string(),
+                ..RoomInfo::default()
+            });
+        room.sources.insert(source.to_string());
+        room
+    }
+
+    /// Records rooms named in Local Storage keys, or in the values of `*room*id*` keys
+    ///
+    /// A `true` value under a key naming a room and containing `encrypted`
+    /// marks that room as encrypted.
+    pub fn add_local_storage(&mut self, settings: &Settings) {
+        for (name, value) in settings.entries() {
+            if let Some(room_id) = find_room_id(name) {
+                let source = name.replacen(room_id, "", 1);
+                let room = self.room(room_id, &source);
+                if name.contains("encrypted") && value.eq_ignore_ascii_case("true") {
+                    room.encrypted = Some(true);
+                }
+            } else if name.contains("room")
+                && name.contains("id")
+                && let Some(room_id) = find_room_id(value)
+            {
+                self.room(room_id, name);
+            }
+        }
+    }
+
+    /// Merges the rooms of the sync cache
+    #[cfg(feature = "serde")]
+    pub fn add_cached(&mut self, rooms: &[CachedRoom]) {
+        for cached in rooms {
+            let room = self.room(&cached.room_id, "sync");
+            room.name = room.name.take().or_else(|| cached.name.clone());
+            room.canonical_alias = room
+                .canonical_alias
+                .take()
+                .or_else(|| cached.canonical_alias.clone());
+            room.membership = Some(cached.membership.clone());
+            room.encrypted = Some(cached.encrypted || room.encrypted == Some(true));
+            room.tags = cached.tags.clone();
+            room.notification_counts = cached.notification_counts;
+        }
+    }
+
+    /// Attaches per-room notification overrides
+    #[cfg(feature = "serde")]
+    pub fn add_notification_settings(&mut self, settings: &BTreeMap<String, NotificationSettings>) {
+        for (room_id, settings) in settings {
+            self.room(room_id, "notifications").notifications = Some(settings.clone());
+        }
+    }
+
+    pub fn into_rooms(self) -> Vec<RoomInfo> {
+        self.rooms.into_values().collect()
+    }
+}
+
+/// First room ID in `text`
+pub fn find_room_id(text: &str) -> Option<&str> {
+    ROOM_ID.find(text).map(|m| m.as_str())
+}
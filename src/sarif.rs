@@ -0,0 +1,216 @@
+//! SARIF 2.1.0 output for secret-scanning and crypto posture findings
+//!
+//! Every result points at the profile directory as its artifact and names the
+//! Local Storage key, room or device it concerns as a logical location. The
+//! `elementFinding/v1` partial fingerprint leaves out the profile path, so
+//! repeated scans of copies of the same profile deduplicate.
+
+use crate::findings::{Finding, RuleSpec, Severity};
+#[cfg(feature = "crypto")]
+use crate::posture::CryptoPosture;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Crypto posture rules: id, SARIF level and description
+const POSTURE_RULES: &[(&str, &str, &str)] = &[
+    (
+        "crypto/no-recovery",
+        "error",
+        "Message history cannot be recovered from this profile or its key backup",
+    ),
+    (
+        "crypto/undecryptable-events",
+        "warning",
+        "Cached encrypted events whose Megolm session is not stored",
+    ),
+    (
+        "crypto/unverified-own-device",
+        "warning",
+        "Another device of the account is not verified",
+    ),
+    (
+        "crypto/backup-pending",
+        "note",
+        "Megolm sessions not yet uploaded to the key backup",
+    ),
+    (
+        "crypto/key-withheld",
+        "note",
+        "A sender refused to share a room key",
+    ),
+];
+
+/// One result before it is placed in the log
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub rule: String,
+    /// `error`, `warning` or `note`
+    pub level: &'static str,
+    pub message: String,
+    /// Key, room or device the result concerns
+    pub location: String,
+    /// Kind of the location: `member` for keys, `room`, `device`, `session`
+    /// or `profile`
+    pub location_kind: &'static str,
+}
+
+/// SARIF level of a finding's severity
+pub fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Low => "note",
+        Severity::Medium => "warning",
+        Severity::High | Severity::Critical => "error",
+    }
+}
+
+/// Results for secret-scanning findings
+pub fn finding_issues(findings: &[Finding]) -> Vec<Issue> {
+    findings
+        .iter()
+        .map(|f| Issue {
+            rule: f.rule.clone(),
+            level: level(f.severity),
+            message: format!("{} ({})", f.description, f.excerpt),
+            location: f.key.clone(),
+            location_kind: "member",
+        })
+        .collect()
+}
+
+/// Results for weaknesses in the encryption posture
+#[cfg(feature = "crypto")]
+pub fn posture_issues(posture: &CryptoPosture) -> Vec<Issue> {
+    let issue = |rule: &str, message: String, location: &str, location_kind| Issue {
+        rule: rule.to_string(),
+        level: POSTURE_RULES
+            .iter()
+            .find(|(id, _, _)| *id == rule)
+            .map_or("warning", |(_, level, _)| level),
+        message,
+        location: location.to_string(),
+        location_kind,
+    };
+
+    let mut issues = Vec::new();
+    if !posture.recovery.possible {
+        issues.push(issue(
+            "crypto/no-recovery",
+            "No local sessions, no cached backup key and no key backup in secret storage".into(),
+            "recovery",
+            "profile",
+        ));
+    }
+    for room in &posture.rooms {
+        if room.decryptable_events < room.encrypted_events {
+            issues.push(issue(
+                "crypto/undecryptable-events",
+                format!(
+                    "{} of {} cached encrypted events in {} cannot be decrypted",
+                    room.encrypted_events - room.decryptable_events,
+                    room.encrypted_events,
+                    room.name.as_deref().unwrap_or(&room.room_id)
+                ),
+                &room.room_id,
+                "room",
+            ));
+        }
+    }
+    for device in &posture.devices {
+        if device.own && !device.current && device.trust != "verified" {
+            issues.push(issue(
+                "crypto/unverified-own-device",
+                format!("Own device {} is {}", device.device_id, device.trust),
+                &device.device_id,
+                "device",
+            ));
+        }
+    }
+    if posture.key_backup.sessions_pending > 0 {
+        issues.push(issue(
+            "crypto/backup-pending",
+            format!(
+                "{} sessions were waiting for backup",
+                posture.key_backup.sessions_pending
+            ),
+            "key_backup",
+            "profile",
+        ));
+    }
+    for entry in &posture.key_request_history {
+        if entry.outcome == "withheld" {
+            issues.push(issue(
+                "crypto/key-withheld",
+                format!(
+                    "Key for a session in {} was withheld: {}",
+                    entry.room_id.as_deref().unwrap_or("an unknown room"),
+                    entry.detail.as_deref().unwrap_or("no reason given")
+                ),
+                &entry.session_id,
+                "session",
+            ));
+        }
+    }
+    issues
+}
+
+/// Builds a SARIF log with one run for `profile`
+///
+/// `rules` describes the secret-scanning rules; crypto posture rules are added
+/// when any posture issue is present.
+pub fn log(profile: &str, rules: &[RuleSpec], issues: &[Issue]) -> Value {
+    let mut descriptors: Vec<Value> = rules
+        .iter()
+        .map(|r| {
+            json!({
+                "id": r.id,
+                "shortDescription": { "text": r.description },
+                "defaultConfiguration": { "level": level(r.severity) },
+            })
+        })
+        .collect();
+    if issues.iter().any(|i| i.rule.starts_with("crypto/")) {
+        descriptors.extend(POSTURE_RULES.iter().map(|(id, level, description)| {
+            json!({
+                "id": id,
+                "shortDescription": { "text": description },
+                "defaultConfiguration": { "level": level },
+            })
+        }));
+    }
+
+    let uri = profile.replace('\\', "/").replace(' ', "%20");
+    let results: Vec<Value> = issues
+        .iter()
+        .map(|issue| {
+            let fingerprint = Sha256::digest(format!("{}\0{}", issue.rule, issue.location));
+            json!({
+                "ruleId": issue.rule,
+                "level": issue.level,
+                "message": { "text": issue.message },
+                "locations": [{
+                    "physicalLocation": { "artifactLocation": { "uri": uri } },
+                    "logicalLocations": [{
+                        "fullyQualifiedName": issue.location,
+                        "kind": issue.location_kind,
+                    }],
+                }],
+                "partialFingerprints": { "elementFinding/v1": hex::encode(fingerprint) },
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": { "driver": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+                "rules": descriptors,
+            }},
+            "results": results,
+        }],
+    })
+}
@@ -0,0 +1,238 @@
+//! `search`: where a text or pattern appears in the keys and values
+//!
+//! Keys are searched by their label, `origin/name` for Local Storage items,
+//! and values by their text. Every occurrence is reported with some context
+//! on either side, so a user or room ID can be seen in the JSON around it.
+//!
+//! With `--binary` values are also searched as UTF-8, invalid sequences
+//! replaced, and as UTF-16LE at either byte alignment, in that order; the
+//! first decoding with an occurrence is reported. Strings inside binary
+//! values, such as UTF-16 text in a serialized blob, are found this way.
+//! There is no separate pass over the raw bytes: anything the pattern
+//! matches there is valid UTF-8 and so already in the first decoding.
+
+use crate::export::key_label;
+use crate::{Classification, Entry};
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+/// How a value was read where the pattern was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decoding {
+    /// The value's text, decoded for Local Storage items
+    Text,
+    /// UTF-8, invalid sequences replaced
+    Utf8,
+    /// UTF-16LE from the first byte
+    Utf16Le,
+    /// UTF-16LE from the second byte
+    Utf16LeOdd,
+}
+
+/// A search pattern, and whether binary values are decoded to search them
+pub struct Matcher {
+    text: Regex,
+    binary: bool,
+}
+
+impl Matcher {
+    /// `pattern` as a regular expression, or a literal unless `regex`
+    pub fn new(pattern: &str, regex: bool, ignore_case: bool, binary: bool) -> Result<Self> {
+        let pattern = if regex {
+            pattern.to_string()
+        } else {
+            regex::escape(pattern)
+        };
+        let text = RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .build()?;
+        Ok(Matcher { text, binary })
+    }
+}
+
+/// Where an occurrence was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    Key,
+    Value,
+}
+
+/// One occurrence of the pattern
+#[derive(Debug, Serialize)]
+pub struct Match {
+    pub field: Field,
+    /// How the value was read; keys are always their label
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoding: Option<Decoding>,
+    /// Byte offsets of the occurrence in the key label or the decoded value
+    pub start: usize,
+    pub end: usize,
+    /// Text before the occurrence, at most `context` characters
+    pub before: String,
+    #[serde(rename = "match")]
+    pub matched: String,
+    /// Text after the occurrence, at most `context` characters
+    pub after: String,
+    /// Whether there is more text before `before` or after `after`
+    #[serde(skip)]
+    pub cut: (bool, bool),
+}
+
+/// An entry with at least one occurrence
+#[derive(Debug, Serialize)]
+pub struct Hit {
+    pub key: String,
+    pub classification: Classification,
+    pub matches: Vec<Match>,
+}
+
+/// The occurrences of `matcher` in `entry`, with `context` characters around
+/// each, or `None` when there are none
+pub fn search(entry: &Entry, matcher: &Matcher, context: usize) -> Option<Hit> {
+    let key = key_label(&entry.key);
+    let mut matches = occurrences(Field::Key, None, &key, &matcher.text, context);
+    matches.extend(value_occurrences(
+        &entry.text(),
+        &entry.value,
+        matcher,
+        context,
+    ));
+    (!matches.is_empty()).then(|| Hit {
+        key,
+        classification: entry.classification.clone(),
+        matches,
+    })
+}
+
+/// Occurrences in a value of the first decoding that has any
+fn value_occurrences(
+    text: &Option<String>,
+    value: &[u8],
+    matcher: &Matcher,
+    context: usize,
+) -> Vec<Match> {
+    let found = |decoding, text: &str| {
+        occurrences(Field::Value, Some(decoding), text, &matcher.text, context)
+    };
+    if let Some(text) = text {
+        let matches = found(Decoding::Text, text);
+        if !matches.is_empty() {
+            return matches;
+        }
+    }
+    if !matcher.binary {
+        return Vec::new();
+    }
+
+    let decodings = [
+        (Decoding::Utf8, String::from_utf8_lossy(value).into_owned()),
+        (Decoding::Utf16Le, utf16le(value)),
+        (
+            Decoding::Utf16LeOdd,
+            utf16le(value.get(1..).unwrap_or_default()),
+        ),
+    ];
+    decodings
+        .into_iter()
+        .map(|(decoding, text)| found(decoding, &text))
+        .find(|matches| !matches.is_empty())
+        .unwrap_or_default()
+}
+
+/// `bytes` as UTF-16LE, unpaired surrogates replaced and an odd byte dropped
+fn utf16le(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn occurrences(
+    field: Field,
+    decoding: Option<Decoding>,
+    text: &str,
+    pattern: &Regex,
+    context: usize,
+) -> Vec<Match> {
+    pattern
+        .find_iter(text)
+        .filter(|m| !m.is_empty())
+        .map(|m| {
+            let before = tail(&text[..m.start()], context);
+            let after = head(&text[m.end()..], context);
+            Match {
+                field,
+                decoding,
+                start: m.start(),
+                end: m.end(),
+                before: before.to_string(),
+                matched: m.as_str().to_string(),
+                after: after.to_string(),
+                cut: (before.len() < m.start(), after.len() < text.len() - m.end()),
+            }
+        })
+        .collect()
+}
+
+/// The last `chars` characters of `text`
+fn tail(text: &str, chars: usize) -> &str {
+    match text.char_indices().rev().nth(chars) {
+        Some((i, c)) => &text[i + c.len_utf8()..],
+        None => text,
+    }
+}
+
+/// The first `chars` characters of `text`
+fn head(text: &str, chars: usize) -> &str {
+    match text.char_indices().nth(chars) {
+        Some((i, _)) => &text[..i],
+        None => text,
+    }
+}
+
+/// A hit as text: the key, then one line of context per occurrence in the
+/// value, naming decodings other than the text; occurrences are bracketed,
+/// or with `color` highlighted for a terminal
+pub fn render(hit: &Hit, color: bool) -> String {
+    let highlight = |text: &str| {
+        let text = text.escape_debug();
+        if color {
+            format!("\x1b[1;31m{}\x1b[0m", text)
+        } else {
+            format!("[{}]", text)
+        }
+    };
+
+    let mut out = String::new();
+    let mut last = 0;
+    for m in hit.matches.iter().filter(|m| m.field == Field::Key) {
+        out.push_str(&hit.key[last..m.start]);
+        out.push_str(&highlight(&m.matched));
+        last = m.end;
+    }
+    out.push_str(&hit.key[last..]);
+
+    let ellipsis = |cut: bool| if cut { "…" } else { "" };
+    for m in hit.matches.iter().filter(|m| m.field == Field::Value) {
+        let decoding = match m.decoding {
+            Some(Decoding::Utf8) => "(utf-8) ",
+            Some(Decoding::Utf16Le) => "(utf-16le) ",
+            Some(Decoding::Utf16LeOdd) => "(utf-16le, odd offset) ",
+            Some(Decoding::Text) | None => "",
+        };
+        out.push_str(&format!(
+            "\n    {}{}{}{}{}{}",
+            decoding,
+            ellipsis(m.cut.0),
+            m.before.escape_debug(),
+            highlight(&m.matched),
+            m.after.escape_debug(),
+            ellipsis(m.cut.1),
+        ));
+    }
+    out
+}
@@ -0,0 +1,396 @@
+//! Typed views of the settings Element persists in Local Storage
+//!
+//! Device-level settings live as one JSON object in `mx_local_settings`,
+//! per-room device settings as `mx_setting_<name>_<room id>`, and a few stores
+//! keep their state under their own `mx_` keys.
+//!
+//! The typed views read those values as JSON and need the `serde` feature;
+//! without it only the raw entries are available.
+
+use crate::layout::Layout;
+use crate::localstorage;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Element's Local Storage entries, by decoded name
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    layout: Layout,
+    values: BTreeMap<String, String>,
+    #[cfg(feature = "serde")]
+    local: Map<String, Value>,
+}
+
+/// What the user was looking at when the client last ran
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UiState {
+    /// Room open when the client last ran (`mx_last_room_id`)
+    pub last_room_id: Option<String>,
+    /// Space selected in the space panel (`mx_active_space`)
+    pub active_space: Option<String>,
+    /// Right panel state, the global one (no room ID) first
+    pub right_panel: Vec<RightPanelState>,
+    pub room_list: RoomListState,
+    /// Width of the left panel in pixels (`mx_lhs_size`)
+    pub left_panel_width: Option<u64>,
+}
+
+/// Right panel state for one room, or for rooms without their own
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RightPanelState {
+    pub room_id: Option<String>,
+    pub is_open: Option<bool>,
+    /// Phase on top of the panel history, e.g. `RoomSummary` or `Timeline`
+    pub phase: Option<String>,
+}
+
+/// Room list sorting and filtering
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RoomListState {
+    /// Sort algorithm per tag (`mx_tagSort_<tag>`)
+    pub sort: BTreeMap<String, String>,
+    /// List ordering per tag (`mx_listOrder_<tag>`)
+    pub order: BTreeMap<String, String>,
+    /// `RoomList.*` device settings, e.g. `RoomList.showMessagePreview`
+    #[cfg(feature = "serde")]
+    pub settings: BTreeMap<String, Value>,
+}
+
+/// A persisted microphone, camera or speaker choice
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MediaDevice {
+    /// `audioinput`, `videoinput` or `audiooutput`
+    pub kind: String,
+    /// Browser media device ID, or `default`
+    pub device_id: String,
+    /// Device label such as `Logitech BRIO (046d:085e)`, when stored
+    pub label: Option<String>,
+    /// `element` for `webrtc_*` settings, `element-call` for the embedded call widget
+    pub source: String,
+}
+
+/// Appearance and accessibility settings; together with theme and language a
+/// fairly stable fingerprint of the person configuring the client
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Appearance {
+    pub theme: Option<String>,
+    /// Follow the OS light/dark preference (`use_system_theme`)
+    pub use_system_theme: Option<bool>,
+    pub language: Option<String>,
+    /// Base font size in pixels (`baseFontSizeV2`, older `baseFontSize`)
+    pub font_size: Option<f64>,
+    /// Offset from the browser default font size (`fontSizeDelta`)
+    pub font_size_delta: Option<i64>,
+    pub use_system_font: Option<bool>,
+    /// Custom font family list (`systemFont`)
+    pub system_font: Option<String>,
+    pub use_bundled_emoji_font: Option<bool>,
+    /// Timeline layout: `group`, `irc` or `bubble`
+    pub layout: Option<String>,
+    pub compact_layout: Option<bool>,
+    pub accessibility: Accessibility,
+}
+
+/// Accessibility-related settings
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Accessibility {
+    /// A high contrast theme is selected
+    pub high_contrast: bool,
+    /// Ctrl+Enter sends instead of Enter (`MessageComposerInput.ctrlEnterToSend`)
+    pub ctrl_enter_to_send: Option<bool>,
+    /// Ctrl+F searches the room instead of the page (`ctrlFForSearch`)
+    pub ctrl_f_for_search: Option<bool>,
+    /// Timestamps are always shown, not only on hover (`alwaysShowTimestamps`)
+    pub always_show_timestamps: Option<bool>,
+    /// Timestamps use 12-hour format (`showTwelveHourTimestamps`)
+    pub twelve_hour_timestamps: Option<bool>,
+}
+
+/// URL preview and inline media settings
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MediaSettings {
+    /// URL previews in unencrypted rooms (`urlPreviewsEnabled`)
+    pub url_previews: Option<bool>,
+    /// URL previews in encrypted rooms (`urlPreviewsEnabled_e2ee`)
+    pub url_previews_e2ee: Option<bool>,
+    /// Per-room overrides (`mx_setting_urlPreviewsEnabled[_e2ee]_<room id>`)
+    pub room_url_previews: Vec<RoomUrlPreviews>,
+    pub autoplay_gifs: Option<bool>,
+    pub autoplay_videos: Option<bool>,
+    /// Image and video thumbnails are shown (`showImages`)
+    pub show_images: Option<bool>,
+}
+
+/// URL preview override for one room
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RoomUrlPreviews {
+    pub room_id: String,
+    pub enabled: bool,
+    /// The override is the encrypted-room setting
+    pub e2ee: bool,
+}
+
+/// Setting names of Element's and Element Call's device choices, by kind
+#[cfg(feature = "serde")]
+const MEDIA_DEVICE_SETTINGS: &[(&str, &str, &str)] = &[
+    (
+        "audioinput",
+        "webrtc_audioinput",
+        "matrix-setting-audio-input",
+    ),
+    (
+        "videoinput",
+        "webrtc_videoinput",
+        "matrix-setting-video-input",
+    ),
+    (
+        "audiooutput",
+        "webrtc_audiooutput",
+        "matrix-setting-audio-output",
+    ),
+];
+
+impl Settings {
+    /// Collects the Element origin's entries from raw Local Storage key/value
+    /// pairs, for Element Desktop and Element Web alike
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = (&'a [u8], &'a [u8])>) -> Self {
+        let entries: Vec<_> = entries.into_iter().collect();
+        let layout = Layout::detect(entries.iter().copied()).unwrap_or_default();
+        let values = entries
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let (origin, name) = localstorage::decode_key(key)?;
+                (origin == layout.origin)
+                    .then(|| Some((name, localstorage::decode_string(value)?)))?
+            })
+            .collect();
+        Settings {
+            layout,
+            ..Self::from_values(values)
+        }
+    }
+
+    /// Wraps already decoded entries of an Element Desktop profile
+    pub fn from_values(values: BTreeMap<String, String>) -> Self {
+        Settings {
+            layout: Layout::default(),
+            #[cfg(feature = "serde")]
+            local: values
+                .get("mx_local_settings")
+                .and_then(|s| serde_json::from_str(s).ok())
+                .unwrap_or_default(),
+            values,
+        }
+    }
+
+    /// Where the entries came from
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Decoded value of an entry
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// All entries as `(name, value)`, by name
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Settings {
+    /// Device-level setting from `mx_local_settings`
+    pub fn local(&self, name: &str) -> Option<&Value> {
+        self.local.get(name)
+    }
+
+    /// Entries whose name starts with `prefix`, with the prefix removed
+    fn with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.values
+            .range(prefix.to_string()..)
+            .map_while(move |(k, v)| Some((k.strip_prefix(prefix)?, v.as_str())))
+    }
+
+    /// Last-viewed room, right panel and room list state
+    pub fn ui_state(&self) -> UiState {
+        let mut right_panel: Vec<RightPanelState> = self
+            .local("RightPanel.phasesGlobal")
+            .map(|v| right_panel_state(None, v))
+            .into_iter()
+            .collect();
+        for (room_id, value) in self.with_prefix("mx_setting_RightPanel.phases_") {
+            if let Ok(value) = serde_json::from_str(value) {
+                right_panel.push(right_panel_state(Some(room_id), &value));
+            }
+        }
+
+        UiState {
+            last_room_id: self.value("mx_last_room_id").map(str::to_string),
+            active_space: self.value("mx_active_space").map(str::to_string),
+            right_panel,
+            room_list: RoomListState {
+                sort: collect_strings(self.with_prefix("mx_tagSort_")),
+                order: collect_strings(self.with_prefix("mx_listOrder_")),
+                settings: self
+                    .local
+                    .iter()
+                    .filter(|(k, _)| k.starts_with("RoomList."))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+            },
+            left_panel_width: self.value("mx_lhs_size").and_then(|v| v.parse().ok()),
+        }
+    }
+
+    /// Font, layout, theme, language and accessibility settings
+    pub fn appearance(&self) -> Appearance {
+        let text = |name| self.local(name).and_then(Value::as_str).map(str::to_string);
+        let flag = |name| self.local(name).and_then(Value::as_bool);
+        let theme = text("theme");
+        Appearance {
+            use_system_theme: flag("use_system_theme"),
+            language: text("language"),
+            font_size: self
+                .local("baseFontSizeV2")
+                .or_else(|| self.local("baseFontSize"))
+                .and_then(Value::as_f64),
+            font_size_delta: self.local("fontSizeDelta").and_then(Value::as_i64),
+            use_system_font: flag("useSystemFont"),
+            system_font: text("systemFont").filter(|f| !f.is_empty()),
+            use_bundled_emoji_font: flag("useBundledEmojiFont"),
+            layout: text("layout"),
+            compact_layout: flag("useCompactLayout"),
+            accessibility: Accessibility {
+                high_contrast: theme
+                    .as_deref()
+                    .is_some_and(|t| t.contains("high-contrast")),
+                ctrl_enter_to_send: flag("MessageComposerInput.ctrlEnterToSend"),
+                ctrl_f_for_search: flag("ctrlFForSearch"),
+                always_show_timestamps: flag("alwaysShowTimestamps"),
+                twelve_hour_timestamps: flag("showTwelveHourTimestamps"),
+            },
+            theme,
+        }
+    }
+
+    /// URL preview (global and per room) and media autoplay settings
+    pub fn media_settings(&self) -> MediaSettings {
+        let flag = |name| self.local(name).and_then(Value::as_bool);
+        let mut room_url_previews = Vec::new();
+        for (rest, value) in self.with_prefix("mx_setting_urlPreviewsEnabled_") {
+            let Ok(enabled) = serde_json::from_str(value) else {
+                continue;
+            };
+            let (room_id, e2ee) = match rest.strip_prefix("e2ee_") {
+                Some(room_id) => (room_id, true),
+                None => (rest, false),
+            };
+            room_url_previews.push(RoomUrlPreviews {
+                room_id: room_id.to_string(),
+                enabled,
+                e2ee,
+            });
+        }
+        MediaSettings {
+            url_previews: flag("urlPreviewsEnabled"),
+            url_previews_e2ee: flag("urlPreviewsEnabled_e2ee"),
+            room_url_previews,
+            autoplay_gifs: flag("autoplayGifs"),
+            autoplay_videos: flag("autoplayVideo"),
+            show_images: flag("showImages"),
+        }
+    }
+
+    /// Microphone, camera and speaker choices of Element and Element Call
+    ///
+    /// Element Call runs as a widget on the same origin, so its
+    /// `matrix-setting-*` entries (JSON-encoded) sit next to Element's own.
+    pub fn media_devices(&self) -> Vec<MediaDevice> {
+        let mut devices = Vec::new();
+        for (kind, element, call) in MEDIA_DEVICE_SETTINGS {
+            if let Some(device) = self
+                .local(element)
+                .and_then(|v| media_device(kind, "element", v))
+            {
+                devices.push(device);
+            }
+            if let Some(device) = self
+                .value(call)
+                .and_then(|v| serde_json::from_str(v).ok())
+                .and_then(|v| media_device(kind, "element-call", &v))
+            {
+                devices.push(device);
+            }
+        }
+        devices
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Reads a device choice stored either as a bare ID or as `{deviceId, label}`
+fn media_device(kind: &str, source: &str, value: &Value) -> Option<MediaDevice> {
+    let (device_id, label) = match value {
+        Value::String(id) => (id.clone(), None),
+        Value::Object(device) => (
+            device
+                .get("deviceId")
+                .or_else(|| device.get("id"))?
+                .as_str()?
+                .to_string(),
+            device
+                .get("label")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        ),
+        _ => return None,
+    };
+    (!device_id.is_empty()).then(|| MediaDevice {
+        kind: kind.to_string(),
+        device_id,
+        label: label.filter(|l| !l.is_empty()),
+        source: source.to_string(),
+    })
+}
+
+#[cfg(feature = "serde")]
+fn right_panel_state(room_id: Option<&str>, value: &Value) -> RightPanelState {
+    RightPanelState {
+        room_id: room_id.map(str::to_string),
+        is_open: value["isOpen"].as_bool(),
+        phase: value["history"]
+            .as_array()
+            .and_then(|h| h.last())
+            .and_then(|card| card["phase"].as_str())
+            .map(str::to_string),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn collect_strings<'a>(
+    entries: impl Iterator<Item = (&'a str, &'a str)>,
+) -> BTreeMap<String, String> {
+    entries
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
@@ -0,0 +1,307 @@
+//! `export --format sqlite`: the export as a database for ad-hoc SQL
+//!
+//! Every list of the export gets its own table, with lists inside a record
+//! split out into tables keyed by the record's ID:
+//!
+//! | Table | Rows |
+//! |---|---|
+//! | `account` | the user, profile and app |
+//! | `entries` | every entry: key label, kind, origin, item name, category, value and size |
+//! | `rooms`, `room_tags`, `room_sources` | rooms |
+//! | `devices`, `device_sources` | the account's devices |
+//! | `sessions`, `session_logins` | login history, with each `mx_last_login_ts` |
+//! | `settings` | appearance, media and UI settings as section, name and JSON value |
+//! | `crypto_devices`, `crypto_sessions` | the crypto store's device list and Megolm sessions, when IndexedDB is next to the database |
+//!
+//! Times are milliseconds since the epoch, booleans 0 or 1.
+
+use crate::crypto;
+use crate::export::{self, Export};
+use crate::indexeddb::reader::Origin;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, Transaction};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE account (
+    user_id TEXT,
+    display_name TEXT,
+    avatar_url TEXT,
+    theme TEXT,
+    language TEXT,
+    notifications_enabled INTEGER,
+    variant TEXT,
+    origin TEXT
+);
+CREATE TABLE entries (
+    id INTEGER PRIMARY KEY,
+    key TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    origin TEXT,
+    name TEXT,
+    category TEXT NOT NULL,
+    value TEXT NOT NULL,
+    size INTEGER NOT NULL
+);
+CREATE INDEX entries_key ON entries (key);
+CREATE INDEX entries_category ON entries (category);
+CREATE INDEX entries_name ON entries (name);
+CREATE TABLE rooms (
+    room_id TEXT PRIMARY KEY,
+    name TEXT,
+    canonical_alias TEXT,
+    membership TEXT,
+    encrypted INTEGER
+);
+CREATE TABLE room_tags (
+    room_id TEXT NOT NULL REFERENCES rooms,
+    tag TEXT NOT NULL
+);
+CREATE INDEX room_tags_room ON room_tags (room_id);
+CREATE TABLE room_sources (
+    room_id TEXT NOT NULL REFERENCES rooms,
+    source TEXT NOT NULL
+);
+CREATE INDEX room_sources_room ON room_sources (room_id);
+CREATE TABLE devices (
+    device_id TEXT PRIMARY KEY,
+    user_id TEXT,
+    display_name TEXT,
+    current INTEGER NOT NULL,
+    first_seen_sequence INTEGER,
+    first_seen INTEGER,
+    last_seen_sequence INTEGER,
+    last_seen INTEGER,
+    curve25519_key TEXT,
+    ed25519_key TEXT
+);
+CREATE TABLE device_sources (
+    device_id TEXT NOT NULL REFERENCES devices,
+    source TEXT NOT NULL
+);
+CREATE INDEX device_sources_device ON device_sources (device_id);
+CREATE TABLE sessions (
+    id INTEGER PRIMARY KEY,
+    user_id TEXT,
+    device_id TEXT,
+    first_sequence INTEGER NOT NULL,
+    last_sequence INTEGER NOT NULL,
+    soft_logout INTEGER NOT NULL,
+    logged_out INTEGER NOT NULL
+);
+CREATE INDEX sessions_device ON sessions (device_id);
+CREATE TABLE session_logins (
+    session_id INTEGER NOT NULL REFERENCES sessions,
+    timestamp INTEGER NOT NULL
+);
+CREATE INDEX session_logins_session ON session_logins (session_id);
+CREATE TABLE settings (
+    section TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (section, name)
+);
+CREATE TABLE crypto_devices (
+    user_id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    display_name TEXT,
+    verified INTEGER,
+    curve25519_key TEXT,
+    ed25519_key TEXT
+);
+CREATE INDEX crypto_devices_user ON crypto_devices (user_id, device_id);
+CREATE TABLE crypto_sessions (
+    session_id TEXT NOT NULL,
+    room_id TEXT,
+    sender_key TEXT NOT NULL,
+    forwarded INTEGER NOT NULL
+);
+CREATE INDEX crypto_sessions_room ON crypto_sessions (room_id);
+";
+
+/// Writes `export`, and the crypto store of `indexeddb`, as a new database
+/// at `path`; built in `<path>.tmp` and renamed once complete
+pub fn write(path: &Path, export: &Export, indexeddb: Option<&Origin>) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = Path::new(&tmp);
+    if tmp.exists() {
+        std::fs::remove_file(tmp)?;
+    }
+    let mut db =
+        Connection::open(tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+    db.execute_batch(SCHEMA)?;
+    let transaction = db.transaction()?;
+    insert_account(&transaction, export)?;
+    insert_entries(&transaction, export)?;
+    insert_rooms(&transaction, export)?;
+    insert_devices(&transaction, export)?;
+    insert_sessions(&transaction, export)?;
+    insert_settings(&transaction, export)?;
+    if let Some(origin) = indexeddb {
+        insert_crypto(&transaction, origin)?;
+    }
+    transaction.commit()?;
+    db.close().map_err(|(_, e)| e)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+fn insert_account(db: &Transaction, export: &Export) -> Result<()> {
+    let metadata = &export.metadata;
+    db.execute(
+        "INSERT INTO account VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            metadata.user_id,
+            metadata.display_name,
+            metadata.avatar_url,
+            metadata.theme,
+            metadata.language,
+            metadata.notifications_enabled,
+            serde_json::to_value(metadata.layout.variant)?.as_str(),
+            metadata.layout.origin,
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_entries(db: &Transaction, export: &Export) -> Result<()> {
+    let mut insert = db.prepare(
+        "INSERT INTO entries (key, kind, origin, name, category, value, size)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for entry in &export.entries {
+        let (kind, origin, name) =
+            export::parts(&entry.classification, &export.metadata.layout.origin);
+        insert.execute(params![
+            entry.key,
+            kind,
+            origin,
+            name,
+            export::category(&entry.classification),
+            entry.value,
+            entry.size as i64
+        ])?;
+    }
+    Ok(())
+}
+
+fn insert_rooms(db: &Transaction, export: &Export) -> Result<()> {
+    let mut room = db.prepare("INSERT INTO rooms VALUES (?1, ?2, ?3, ?4, ?5)")?;
+    let mut tag = db.prepare("INSERT INTO room_tags VALUES (?1, ?2)")?;
+    let mut source = db.prepare("INSERT INTO room_sources VALUES (?1, ?2)")?;
+    for info in &export.metadata.rooms {
+        room.execute(params![
+            info.room_id,
+            info.name,
+            info.canonical_alias,
+            info.membership,
+            info.encrypted
+        ])?;
+        for name in &info.tags {
+            tag.execute(params![info.room_id, name])?;
+        }
+        for name in &info.sources {
+            source.execute(params![info.room_id, name])?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_devices(db: &Transaction, export: &Export) -> Result<()> {
+    let mut device =
+        db.prepare("INSERT INTO devices VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)")?;
+    let mut source = db.prepare("INSERT INTO device_sources VALUES (?1, ?2)")?;
+    for info in &export.metadata.devices {
+        device.execute(params![
+            info.device_id,
+            info.user_id,
+            info.display_name,
+            info.current,
+            info.first_seen.map(|seen| seen.sequence as i64),
+            info.first_seen
+                .and_then(|seen| seen.timestamp)
+                .map(|ts| ts as i64),
+            info.last_seen.map(|seen| seen.sequence as i64),
+            info.last_seen
+                .and_then(|seen| seen.timestamp)
+                .map(|ts| ts as i64),
+            info.curve25519_key,
+            info.ed25519_key,
+        ])?;
+        for name in &info.sources {
+            source.execute(params![info.device_id, name])?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_sessions(db: &Transaction, export: &Export) -> Result<()> {
+    let mut session = db.prepare(
+        "INSERT INTO sessions (user_id, device_id, first_sequence, last_sequence, soft_logout, logged_out)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    let mut login = db.prepare("INSERT INTO session_logins VALUES (?1, ?2)")?;
+    for info in &export.metadata.login_history {
+        session.execute(params![
+            info.user_id,
+            info.device_id,
+            info.first_sequence as i64,
+            info.last_sequence as i64,
+            info.soft_logout,
+            info.logged_out,
+        ])?;
+        let id = db.last_insert_rowid();
+        for ts in &info.login_timestamps {
+            login.execute(params![id, *ts as i64])?;
+        }
+    }
+    Ok(())
+}
+
+/// One row per field of each settings section, the value as JSON
+fn insert_settings(db: &Transaction, export: &Export) -> Result<()> {
+    let metadata = &export.metadata;
+    let mut setting = db.prepare("INSERT INTO settings VALUES (?1, ?2, ?3)")?;
+    for (section, value) in [
+        ("appearance", serde_json::to_value(&metadata.appearance)?),
+        (
+            "media_settings",
+            serde_json::to_value(&metadata.media_settings)?,
+        ),
+        ("ui_state", serde_json::to_value(&metadata.ui_state)?),
+    ] {
+        let serde_json::Value::Object(fields) = value else {
+            continue;
+        };
+        for (name, value) in fields {
+            setting.execute(params![section, name, value.to_string()])?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_crypto(db: &Transaction, origin: &Origin) -> Result<()> {
+    let mut device = db.prepare("INSERT INTO crypto_devices VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+    for info in crypto::cached_devices(origin) {
+        let key = |algorithm: &str| info.keys.get(&format!("{}:{}", algorithm, info.device_id));
+        device.execute(params![
+            info.user_id,
+            info.device_id,
+            info.display_name,
+            info.verified,
+            key("curve25519"),
+            key("ed25519"),
+        ])?;
+    }
+    let mut session = db.prepare("INSERT INTO crypto_sessions VALUES (?1, ?2, ?3, ?4)")?;
+    for info in crypto::inbound_sessions(origin) {
+        session.execute(params![
+            info.session_id,
+            info.room_id,
+            info.sender_key,
+            info.forwarded
+        ])?;
+    }
+    Ok(())
+}
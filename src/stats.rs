@@ -0,0 +1,132 @@
+//! Database statistics
+//!
+//! Entry counts and sizes, the largest values, and counts per key namespace,
+//! origin and category, for triage of a profile before a full parse.
+//! Computed from any set of key/value pairs, so embedding tools can render
+//! their own dashboards; the `stats` command is just one presentation.
+
+use crate::keys::{classify_key, KeyCategory};
+use crate::localstorage;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Aggregate figures for a set of entries
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Statistics {
+    pub entries: usize,
+    pub key_bytes: usize,
+    pub value_bytes: usize,
+    /// Values that are not valid UTF-8
+    pub binary_values: usize,
+    /// Entry count per key namespace (see [`key_namespace`])
+    pub namespaces: BTreeMap<String, usize>,
+    /// Entry count per origin, for Local Storage and `META:` keys
+    pub origins: BTreeMap<String, usize>,
+    /// Value count per size bucket, keyed by the bucket's upper bound in bytes
+    /// (the value length rounded up to a power of two)
+    pub value_sizes: BTreeMap<usize, usize>,
+    /// The largest values, largest first, at most [`LARGEST_VALUES`]
+    pub largest_values: Vec<ValueSize>,
+    /// Local Storage item count per [`KeyCategory`] of its name
+    pub categories: BTreeMap<KeyCategory, usize>,
+}
+
+/// Number of values `Statistics::largest_values` keeps
+pub const LARGEST_VALUES: usize = 10;
+
+/// A key and the size of its value
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ValueSize {
+    /// `origin/name` for Local Storage items, else the key, lossily decoded
+    pub key: String,
+    pub bytes: usize,
+}
+
+impl Statistics {
+    /// Computes statistics over key/value pairs
+    pub fn compute<'a, I>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a [u8], &'a [u8])>,
+    {
+        let mut stats = Statistics::default();
+        for (key, value) in entries {
+            stats.entries += 1;
+            stats.key_bytes += key.len();
+            stats.value_bytes += value.len();
+            if std::str::from_utf8(value).is_err() {
+                stats.binary_values += 1;
+            }
+            *stats.namespaces.entry(key_namespace(key)).or_default() += 1;
+            if let Some(origin) = key_origin(key) {
+                *stats.origins.entry(origin).or_default() += 1;
+            }
+            *stats
+                .value_sizes
+                .entry(value.len().max(1).next_power_of_two())
+                .or_default() += 1;
+            if let Some((_, name)) = localstorage::decode_key(key) {
+                *stats
+                    .categories
+                    .entry(classify_key(&name).category())
+                    .or_default() += 1;
+            }
+            stats.add_largest(key, value.len());
+        }
+        stats
+    }
+
+    fn add_largest(&mut self, key: &[u8], bytes: usize) {
+        if self.largest_values.len() == LARGEST_VALUES
+            && self.largest_values.last().is_some_and(|v| v.bytes >= bytes)
+        {
+            return;
+        }
+        let key = match localstorage::decode_key(key) {
+            Some((origin, name)) => format!("{}/{}", origin, name),
+            None => String::from_utf8_lossy(key).into_owned(),
+        };
+        let at = self.largest_values.partition_point(|v| v.bytes >= bytes);
+        self.largest_values.insert(at, ValueSize { key, bytes });
+        self.largest_values.truncate(LARGEST_VALUES);
+    }
+
+    /// Bytes of all keys and values together
+    pub fn total_bytes(&self) -> usize {
+        self.key_bytes + self.value_bytes
+    }
+
+    /// Share of values that are binary, between 0 and 1
+    pub fn binary_ratio(&self) -> f64 {
+        if self.entries == 0 {
+            0.0
+        } else {
+            self.binary_values as f64 / self.entries as f64
+        }
+    }
+}
+
+/// Namespace of a key
+///
+/// Local Storage names are cut to their first two `_`-separated segments
+/// (`mx_setting_RightPanel.phases_!room` becomes `mx_setting`); other keys
+/// to the text before the first `:`, or `<binary>` when not UTF-8.
+pub fn key_namespace(key: &[u8]) -> String {
+    if let Some((_, name)) = localstorage::decode_key(key) {
+        return name.splitn(3, '_').take(2).collect::<Vec<_>>().join("_");
+    }
+    match std::str::from_utf8(key) {
+        Ok(text) => text.split(':').next().unwrap_or_default().to_string(),
+        Err(_) => "<binary>".to_string(),
+    }
+}
+
+fn key_origin(key: &[u8]) -> Option<String> {
+    if let Some((origin, _)) = localstorage::decode_key(key) {
+        return Some(origin);
+    }
+    let origin = key.strip_prefix(b"META:")?;
+    String::from_utf8(origin.to_vec()).ok()
+}
@@ -0,0 +1,59 @@
+//! Exit status of the binary, so automation can tell outcomes apart
+//!
+//! | Code | Meaning |
+//! |---|---|
+//! | 0 | parsed, Element data found |
+//! | 1 | parsed, but the database holds no Element data |
+//! | 2 | the database could not be read, or the command failed |
+//! | 3 | parsed, but damaged data was skipped |
+//!
+//! Commands that parse a database note what they found with `found` and
+//! `damaged`; clap's usage errors exit with 2 on their own.
+
+use element_desktop_leveldb::{ElementMetadata, ParserError};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const EMPTY: u8 = 1;
+const FAILED: u8 = 2;
+const PARTIAL: u8 = 3;
+
+static NO_DATA: AtomicBool = AtomicBool::new(false);
+static DAMAGED: AtomicBool = AtomicBool::new(false);
+
+/// Notes whether `metadata` holds an account, rooms, devices or sessions
+pub fn found(metadata: &ElementMetadata) {
+    let empty = metadata.user_id.is_none()
+        && metadata.rooms.is_empty()
+        && metadata.devices.is_empty()
+        && metadata.login_history.is_empty();
+    if empty {
+        NO_DATA.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Notes damaged data that was skipped, if `issues` has any
+pub fn damaged<T>(issues: &[T]) {
+    if !issues.is_empty() {
+        DAMAGED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The exit status of a command that ended with `result`
+pub fn code(result: &anyhow::Result<()>) -> ExitCode {
+    match result {
+        // A LevelDB database was read, but none of its keys are Element's
+        Err(e)
+            if matches!(
+                e.downcast_ref::<ParserError>(),
+                Some(ParserError::NotElementDatabase(path)) if path.join("CURRENT").is_file()
+            ) =>
+        {
+            ExitCode::from(EMPTY)
+        }
+        Err(_) => ExitCode::from(FAILED),
+        Ok(()) if DAMAGED.load(Ordering::Relaxed) => ExitCode::from(PARTIAL),
+        Ok(()) if NO_DATA.load(Ordering::Relaxed) => ExitCode::from(EMPTY),
+        Ok(()) => ExitCode::SUCCESS,
+    }
+}
@@ -0,0 +1,296 @@
+//! Accessors for the matrix-js-sdk sync store cache
+//!
+//! The `sync` object store of `matrix-js-sdk:riot-web-sync` holds the last
+//! accumulated `/sync` response (`roomsData`, `accountData`, `nextBatch`), from
+//! which rooms, their state and a partial timeline can be read offline.
+//! `to_device_queue` holds to-device messages not yet sent to the server.
+
+use crate::indexeddb::reader::Origin;
+use crate::SYNC_DATABASE;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Memberships `roomsData` is keyed by
+pub const MEMBERSHIPS: &[&str] = &["join", "invite", "leave"];
+
+/// Room as recorded in the cached sync response
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CachedRoom {
+    pub room_id: String,
+    pub membership: String,
+    pub name: Option<String>,
+    pub canonical_alias: Option<String>,
+    pub encrypted: bool,
+    /// Room tags such as `m.favourite` or `m.lowpriority`
+    pub tags: Vec<String>,
+    pub notification_counts: Option<NotificationCounts>,
+}
+
+/// Unread counts from the room's `unread_notifications`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NotificationCounts {
+    pub notification_count: u64,
+    pub highlight_count: u64,
+}
+
+/// What the cached timelines are, for reports that show them
+pub const PARTIAL_CACHE_NOTE: &str = "partial local cache: only the most recent events of each room as last synced, not the full history";
+
+/// The cached timeline window of one room
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CachedTimeline {
+    pub room_id: String,
+    pub membership: String,
+    pub name: Option<String>,
+    /// The server left out older events, so there is a gap before the first one
+    pub limited: bool,
+    /// Pagination token for the events before the cached window
+    pub prev_batch: Option<String>,
+    pub events: Vec<CachedEvent>,
+}
+
+/// Event from a cached timeline
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CachedEvent {
+    pub event_id: Option<String>,
+    pub sender: Option<String>,
+    pub event_type: String,
+    /// Milliseconds since the Unix epoch
+    pub origin_server_ts: Option<u64>,
+    pub state_key: Option<String>,
+    pub encrypted: bool,
+    /// Plaintext content; `None` for encrypted events
+    pub content: Option<Value>,
+}
+
+/// Room account data event Element stores a custom notification sound in
+pub const NOTIFICATION_SOUND_EVENT: &str = "uk.half-shot.notification.sound";
+
+/// How the user set up notifications for one room
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NotificationSettings {
+    /// `mute`, `mentions_only`, `all_messages_loud` or `all_messages`;
+    /// `None` when the room follows the account default
+    pub level: Option<String>,
+    /// Name (or `mxc://` URL) of a custom notification sound
+    pub sound: Option<String>,
+    /// Push rule kinds naming the room, e.g. `override` or `room`
+    pub push_rules: Vec<String>,
+}
+
+/// Batch of to-device messages waiting to be sent
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct QueuedToDevice {
+    pub event_type: String,
+    pub txn_id: Option<String>,
+    /// Devices the messages are addressed to, as `<user id>/<device id>`
+    pub targets: Vec<String>,
+}
+
+/// The cached sync response, if the store has one
+pub fn snapshot(origin: &Origin) -> Option<&Value> {
+    origin
+        .records(SYNC_DATABASE, "sync")
+        .iter()
+        .map(|r| &r.value)
+        .find(|v| v.get("roomsData").is_some())
+}
+
+/// Content of the newest state event of `event_type` in a cached room
+pub fn room_state<'a>(room: &'a Value, event_type: &str) -> Option<&'a Value> {
+    ["state", "timeline"]
+        .iter()
+        .filter_map(|section| room[section]["events"].as_array())
+        .flatten()
+        .rfind(|e| e["type"] == event_type && e.get("state_key").is_some())
+        .map(|e| &e["content"])
+}
+
+/// Every room in the cached sync response
+pub fn cached_rooms(origin: &Origin) -> Vec<CachedRoom> {
+    let Some(snapshot) = snapshot(origin) else {
+        return Vec::new();
+    };
+    let mut rooms = Vec::new();
+    for membership in MEMBERSHIPS {
+        let Some(section) = snapshot["roomsData"][membership].as_object() else {
+            continue;
+        };
+        for (room_id, room) in section {
+            let text = |event_type, field: &str| {
+                room_state(room, event_type)
+                    .and_then(|c| c[field].as_str())
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+            };
+            rooms.push(CachedRoom {
+                room_id: room_id.clone(),
+                membership: membership.to_string(),
+                name: text("m.room.name", "name"),
+                canonical_alias: text("m.room.canonical_alias", "alias"),
+                encrypted: room_state(room, "m.room.encryption").is_some(),
+                tags: room["account_data"]["events"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter(|e| e["type"] == "m.tag")
+                    .filter_map(|e| e["content"]["tags"].as_object())
+                    .flat_map(|tags| tags.keys().cloned())
+                    .collect(),
+                notification_counts: room["unread_notifications"].as_object().map(|counts| {
+                    let count = |field| counts.get(field).and_then(Value::as_u64).unwrap_or(0);
+                    NotificationCounts {
+                        notification_count: count("notification_count"),
+                        highlight_count: count("highlight_count"),
+                    }
+                }),
+            });
+        }
+    }
+    rooms
+}
+
+/// Cached timeline events of every room, oldest first within each room
+pub fn cached_timelines(origin: &Origin) -> Vec<CachedTimeline> {
+    let Some(snapshot) = snapshot(origin) else {
+        return Vec::new();
+    };
+    let mut timelines = Vec::new();
+    for membership in MEMBERSHIPS {
+        let Some(section) = snapshot["roomsData"][membership].as_object() else {
+            continue;
+        };
+        for (room_id, room) in section {
+            let text = |value: &Value| value.as_str().map(str::to_string);
+            let events = room["timeline"]["events"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|event| {
+                    let event_type = text(&event["type"])?;
+                    let encrypted = event_type == "m.room.encrypted";
+                    Some(CachedEvent {
+                        event_id: text(&event["event_id"]),
+                        sender: text(&event["sender"]),
+                        // IndexedDB stores JavaScript numbers as doubles
+                        origin_server_ts: event["origin_server_ts"].as_f64().map(|ts| ts as u64),
+                        state_key: text(&event["state_key"]),
+                        content: (!encrypted).then(|| event["content"].clone()),
+                        encrypted,
+                        event_type,
+                    })
+                })
+                .collect();
+            timelines.push(CachedTimeline {
+                room_id: room_id.clone(),
+                membership: membership.to_string(),
+                name: room_state(room, "m.room.name").and_then(|c| text(&c["name"])),
+                limited: room["timeline"]["limited"].as_bool().unwrap_or(false),
+                prev_batch: text(&room["timeline"]["prev_batch"]),
+                events,
+            });
+        }
+    }
+    timelines
+}
+
+/// Per-room notification overrides from the `m.push_rules` account data and
+/// each cached room's account data, for rooms that have any
+pub fn notification_settings(origin: &Origin) -> BTreeMap<String, NotificationSettings> {
+    let mut settings: BTreeMap<String, NotificationSettings> = BTreeMap::new();
+    let push_rules = origin
+        .records(SYNC_DATABASE, "accountData")
+        .iter()
+        .find(|r| r.value["type"] == "m.push_rules")
+        .map(|r| &r.value["content"]["global"]);
+    for kind in ["override", "room"] {
+        let rules = push_rules.and_then(|g| g[kind].as_array());
+        for rule in rules.into_iter().flatten() {
+            let Some(room_id) = rule["rule_id"].as_str().filter(|id| id.starts_with('!')) else {
+                continue;
+            };
+            if rule["enabled"] == false {
+                continue;
+            }
+            let actions = rule["actions"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let notify = actions.iter().any(|a| a == "notify");
+            let sound = actions
+                .iter()
+                .any(|a| a.get("set_tweak") == Some(&"sound".into()));
+            let room = settings.entry(room_id.to_string()).or_default();
+            room.push_rules.push(kind.to_string());
+            // Matches Element's RoomNotifs: an override wins over a room rule
+            let level = match (kind, notify, sound) {
+                ("override", false, _) => "mute",
+                ("override", true, _) => continue,
+                (_, false, _) => "mentions_only",
+                (_, true, true) => "all_messages_loud",
+                (_, true, false) => "all_messages",
+            };
+            if room.level.as_deref() != Some("mute") {
+                room.level = Some(level.to_string());
+            }
+        }
+    }
+
+    let Some(snapshot) = snapshot(origin) else {
+        return settings;
+    };
+    for membership in MEMBERSHIPS {
+        let Some(section) = snapshot["roomsData"][membership].as_object() else {
+            continue;
+        };
+        for (room_id, room) in section {
+            let sound = room["account_data"]["events"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .rfind(|e| e["type"] == NOTIFICATION_SOUND_EVENT)
+                .map(|e| &e["content"]);
+            let Some(name) = sound.and_then(|c| c["name"].as_str().or(c["url"].as_str())) else {
+                continue;
+            };
+            settings.entry(room_id.clone()).or_default().sound = Some(name.to_string());
+        }
+    }
+    settings
+}
+
+/// Every queued to-device batch, oldest first
+pub fn to_device_queue(origin: &Origin) -> Vec<QueuedToDevice> {
+    origin
+        .records(SYNC_DATABASE, "to_device_queue")
+        .iter()
+        .filter_map(|r| {
+            Some(QueuedToDevice {
+                event_type: r.value["eventType"].as_str()?.to_string(),
+                txn_id: r.value["txnId"].as_str().map(str::to_string),
+                targets: r.value["batch"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|m| {
+                        Some(format!(
+                            "{}/{}",
+                            m["userId"].as_str()?,
+                            m["deviceId"].as_str()?
+                        ))
+                    })
+                    .collect(),
+            })
+        })
+        .collect()
+}
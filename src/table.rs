@@ -0,0 +1,297 @@
+//! Aligned tables for people triaging at a terminal
+//!
+//! `parse` shows the account, devices, rooms and settings this way when its
+//! output is a terminal. Headings are bold and borders dim unless colors are
+//! off (`--no-color`, `NO_COLOR` or not a terminal); `--plain` leaves out the
+//! borders too and separates columns with spaces only.
+
+use crate::i18n::{self, tr};
+use crate::output;
+use element_desktop_leveldb::devices::Seen;
+use element_desktop_leveldb::ElementMetadata;
+use serde::Serialize;
+use std::fmt::Write;
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// A titled table; without headers, a list of label and value pairs
+#[derive(Debug, Serialize)]
+pub struct Table {
+    title: String,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(title: String, headers: Vec<String>) -> Self {
+        Table {
+            title,
+            headers,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows
+            .push(cells.into_iter().map(|cell| single_line(&cell)).collect());
+    }
+
+    /// The table in the style `--no-color` and `--plain` select
+    pub fn render(&self) -> String {
+        let color = output::color();
+        let plain = output::plain();
+        let paint = |code: &str, text: &str| {
+            if color {
+                format!("{}{}{}", code, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+
+        let columns = self
+            .rows
+            .iter()
+            .map(Vec::len)
+            .chain([self.headers.len()])
+            .max()
+            .unwrap_or_default();
+        let mut widths = vec![0; columns];
+        for row in self.rows.iter().chain([&self.headers]) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let line = |cells: &[String], header: bool| {
+            let cells: Vec<String> = widths
+                .iter()
+                .enumerate()
+                .map(|(i, width)| {
+                    let cell = cells.get(i).map_or("", String::as_str);
+                    let padded = format!("{:<w$}", cell, w = width);
+                    // Labels of a list are headings too
+                    if header || (self.headers.is_empty() && i == 0) {
+                        paint(BOLD, &padded)
+                    } else {
+                        padded
+                    }
+                })
+                .collect();
+            if plain {
+                cells.join("  ").trim_end().to_string()
+            } else {
+                let bar = paint(DIM, "│");
+                format!("{} {} {}", bar, cells.join(&format!(" {} ", bar)), bar)
+            }
+        };
+        let border = |left: &str, middle: &str, right: &str| {
+            let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+            paint(DIM, &format!("{}{}{}", left, segments.join(middle), right))
+        };
+
+        let mut out = String::new();
+        let _ = writeln!(out, "{}", paint(BOLD, &self.title));
+        if self.rows.is_empty() {
+            let _ = writeln!(out, "  {}", tr!("table-empty"));
+            return out;
+        }
+        if !plain {
+            let _ = writeln!(out, "{}", border("┌", "┬", "┐"));
+        }
+        if !self.headers.is_empty() {
+            let _ = writeln!(out, "{}", line(&self.headers, true));
+            if !plain {
+                let _ = writeln!(out, "{}", border("├", "┼", "┤"));
+            }
+        }
+        for row in &self.rows {
+            let _ = writeln!(out, "{}", line(row, false));
+        }
+        if !plain {
+            let _ = writeln!(out, "{}", border("└", "┴", "┘"));
+        }
+        out
+    }
+
+    /// The table as Markdown under a `##` heading; a list as bullet points.
+    /// Line breaks become `<br>` in a cell and spaces in a list item, so a
+    /// multi-line value stays on its row
+    pub fn markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "## {}\n", self.title);
+        if self.rows.is_empty() {
+            let _ = writeln!(out, "{}", tr!("table-empty"));
+            return out;
+        }
+        if self.headers.is_empty() {
+            for row in &self.rows {
+                if let Some((label, values)) = row.split_first() {
+                    let values = values.join(" ").replace(['\r', '\n'], " ");
+                    let _ = writeln!(out, "- **{}**: {}", label, values);
+                }
+            }
+            return out;
+        }
+        let line = |cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .map(|cell| {
+                    cell.replace('\\', "\\\\")
+                        .replace('|', "\\|")
+                        .replace("\r\n", "<br>")
+                        .replace(['\r', '\n'], "<br>")
+                })
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let _ = writeln!(out, "{}", line(&self.headers));
+        let _ = writeln!(out, "|{}", "---|".repeat(self.headers.len()));
+        for row in &self.rows {
+            let _ = writeln!(out, "{}", line(row));
+        }
+        out
+    }
+}
+
+/// Control characters, such as newlines in a room name, as spaces
+fn single_line(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+fn text(value: Option<&str>) -> String {
+    value.unwrap_or("-").to_string()
+}
+
+fn flag(value: Option<bool>) -> String {
+    match value {
+        Some(true) => tr!("table-yes"),
+        Some(false) => tr!("table-no"),
+        None => "-".to_string(),
+    }
+}
+
+/// The time of a sighting, or its sequence number when no time was recorded
+fn seen(seen: Option<&Seen>) -> String {
+    match seen {
+        Some(seen) => seen
+            .timestamp
+            .and_then(|ts| i18n::datetime(ts as i64))
+            .unwrap_or_else(|| format!("#{}", seen.sequence)),
+        None => "-".to_string(),
+    }
+}
+
+/// Account, devices, rooms and settings of `metadata`
+pub fn metadata(metadata: &ElementMetadata) -> Vec<Table> {
+    let mut account = Table::new(tr!("table-account"), Vec::new());
+    let current = metadata.devices.iter().find(|d| d.current);
+    for (label, value) in [
+        ("table-user-id", text(metadata.user_id.as_deref())),
+        ("table-display-name", text(metadata.display_name.as_deref())),
+        ("table-avatar", text(metadata.avatar_url.as_deref())),
+        ("table-device", text(current.map(|d| d.device_id.as_str()))),
+        (
+            "table-app",
+            format!("{:?} ({})", metadata.layout.variant, metadata.layout.origin),
+        ),
+        ("table-sessions", metadata.login_history.len().to_string()),
+    ] {
+        account.row(vec![i18n::message(label, None), value]);
+    }
+
+    let mut devices = Table::new(
+        tr!("table-devices"),
+        [
+            "table-device-id",
+            "table-name",
+            "table-current",
+            "table-first-seen",
+            "table-last-seen",
+            "table-sources",
+        ]
+        .map(|id| i18n::message(id, None))
+        .to_vec(),
+    );
+    for device in &metadata.devices {
+        devices.row(vec![
+            device.device_id.clone(),
+            text(device.display_name.as_deref()),
+            flag(Some(device.current)),
+            seen(device.first_seen.as_ref()),
+            seen(device.last_seen.as_ref()),
+            device
+                .sources
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", "),
+        ]);
+    }
+
+    let mut rooms = Table::new(
+        tr!("table-rooms"),
+        [
+            "table-room-id",
+            "table-name",
+            "table-alias",
+            "table-membership",
+            "table-encrypted",
+            "table-tags",
+        ]
+        .map(|id| i18n::message(id, None))
+        .to_vec(),
+    );
+    for room in &metadata.rooms {
+        rooms.row(vec![
+            room.room_id.clone(),
+            text(room.name.as_deref()),
+            text(room.canonical_alias.as_deref()),
+            text(room.membership.as_deref()),
+            flag(room.encrypted),
+            room.tags.join(", "),
+        ]);
+    }
+
+    let mut settings = Table::new(tr!("table-settings"), Vec::new());
+    let appearance = &metadata.appearance;
+    let media = &metadata.media_settings;
+    for (label, value) in [
+        (
+            "table-theme",
+            text(metadata.theme.as_deref().or(appearance.theme.as_deref())),
+        ),
+        (
+            "table-language",
+            text(
+                metadata
+                    .language
+                    .as_deref()
+                    .or(appearance.language.as_deref()),
+            ),
+        ),
+        ("table-notifications", flag(metadata.notifications_enabled)),
+        ("table-layout", text(appearance.layout.as_deref())),
+        (
+            "table-font-size",
+            appearance
+                .font_size
+                .map_or_else(|| "-".to_string(), |size| size.to_string()),
+        ),
+        (
+            "table-high-contrast",
+            flag(Some(appearance.accessibility.high_contrast)),
+        ),
+        ("table-url-previews", flag(media.url_previews)),
+        ("table-url-previews-e2ee", flag(media.url_previews_e2ee)),
+        ("table-autoplay-gifs", flag(media.autoplay_gifs)),
+        ("table-autoplay-videos", flag(media.autoplay_videos)),
+        ("table-show-images", flag(media.show_images)),
+    ] {
+        settings.row(vec![i18n::message(label, None), value]);
+    }
+
+    vec![account, devices, rooms, settings]
+}
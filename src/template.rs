@@ -0,0 +1,43 @@
+//! `--template FILE`: reports in the user's own layout
+//!
+//! The file is a [Tera](https://keats.github.io/tera/docs/) template. Its
+//! variables are the top-level fields of the JSON the command would print
+//! otherwise, e.g. `{{ user_id }}` or `{% for room in rooms %}`. Templates
+//! whose name ends in `.html` or `.xml` escape values. The `datetime` filter
+//! formats a millisecond timestamp in the `--timezone` and `--lang` of the run.
+
+use crate::i18n;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use tera::Tera;
+
+/// Renders the template at `path` with the fields of `report`
+pub fn render(path: &Path, report: &impl Serialize) -> Result<String> {
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let name = path
+        .file_name()
+        .map_or_else(|| "template".into(), |name| name.to_string_lossy());
+    let mut tera = Tera::default();
+    tera.autoescape_on(vec![".html", ".htm", ".xml"]);
+    tera.register_filter("datetime", datetime);
+    tera.add_raw_template(&name, &source)
+        .with_context(|| format!("Invalid template {}", path.display()))?;
+    let context = tera::Context::from_serialize(report)?;
+    let report = tera
+        .render(&name, &context)
+        .with_context(|| format!("Failed to render {}", path.display()))?;
+    Ok(report)
+}
+
+/// `{{ ts | datetime }}`: a timestamp in milliseconds as the report's date
+fn datetime(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    value
+        .as_i64()
+        .and_then(i18n::datetime)
+        .map(Value::String)
+        .ok_or_else(|| tera::Error::msg(format!("datetime needs a timestamp, got {}", value)))
+}
@@ -0,0 +1,327 @@
+//! Timeline events for `export --format l2tcsv|bodyfile|timesketch`, to merge
+//! Element artifacts into a master forensic timeline
+//!
+//! Every artifact with a time becomes an event, oldest first:
+//!
+//! | Artifact | Time | Description |
+//! |---|---|---|
+//! | Each entry | modification time of the table or log file holding it | `Content Modification Time` |
+//! | Login sessions | each `mx_last_login_ts` | `Last Login Time` |
+//! | Events of the IndexedDB sync cache | `origin_server_ts` | `Origin Server Time` |
+//!
+//! Compaction rewrites LevelDB files, so an entry's file time is the latest
+//! its record can have been written, not when it was; the sequence number in
+//! the event orders records within the database. File times are read before
+//! the database is opened, since opening can compact the log away. An entry
+//! whose file time can't be read is still an event, `Not a time`, after the
+//! timed ones. Times are in UTC.
+//!
+//! In the Sleuth Kit body format each time goes to the field of its MACB
+//! letter, as in l2tcsv: an entry's file time is its `mtime`, a login its
+//! `atime` and a cached event its `crtime`. The body format has whole seconds
+//! only; an entry without a time has all four as `0`.
+//!
+//! A Timesketch line has the `message`, `datetime`, `timestamp` in
+//! microseconds and `timestamp_desc` Timesketch requires, the source as
+//! `source_short` and `source_long` like Plaso's output, the user and file,
+//! and each extra as a field of its own. Timesketch needs a time on every
+//! line, so an entry without one is at the Unix epoch, `Not a time`.
+
+use crate::csv::Csv;
+use crate::export::key_label;
+use crate::indexeddb::reader::Origin;
+use crate::raw::provenance::ProvenanceMap;
+use crate::{sync, ElementMetadata, Entry};
+use chrono::{DateTime, SecondsFormat};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FILE_MODIFIED: &str = "Content Modification Time";
+const LAST_LOGIN: &str = "Last Login Time";
+const ORIGIN_SERVER: &str = "Origin Server Time";
+const NOT_A_TIME: &str = "Not a time";
+
+/// Characters of a value shown in an event message
+const EXCERPT: usize = 200;
+
+/// One timed artifact
+#[derive(Debug)]
+pub struct Event {
+    /// Milliseconds since the Unix epoch; `None` when unknown
+    pub timestamp: Option<i64>,
+    /// What the time means, as Plaso's `timestamp_desc`
+    pub description: &'static str,
+    /// `Element Local Storage` or `Element sync cache`
+    pub source: &'static str,
+    pub user: Option<String>,
+    /// File or directory the artifact was read from
+    pub file: String,
+    pub message: String,
+    /// Key, sequence number, room, event ID and the like
+    pub extra: Vec<(&'static str, String)>,
+}
+
+fn excerpt(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Modification time of each file of `provenance` in the database at
+/// `db_path`; read before the database is opened
+pub fn file_times(db_path: &Path, provenance: &ProvenanceMap) -> HashMap<String, SystemTime> {
+    let mut times = HashMap::new();
+    for record in provenance.values() {
+        if times.contains_key(&record.file) {
+            continue;
+        }
+        let path = db_path.join(&record.file);
+        if let Ok(time) = std::fs::metadata(path).and_then(|m| m.modified()) {
+            times.insert(record.file.clone(), time);
+        }
+    }
+    times
+}
+
+/// The events of a database at `db_path`: its `entries`, with `provenance`
+/// giving the file of each and `file_times` the modification times of the
+/// files, the login sessions of `metadata`, and the sync cache of
+/// `indexeddb` with the directory it was read from
+pub fn events(
+    db_path: &Path,
+    metadata: &ElementMetadata,
+    entries: &[Entry],
+    provenance: &ProvenanceMap,
+    file_times: &HashMap<String, SystemTime>,
+    indexeddb: Option<(&Path, &Origin)>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+    let user = metadata.user_id.clone();
+
+    for entry in entries {
+        let record = provenance.get(entry.key.as_ref());
+        let timestamp = record
+            .and_then(|record| file_times.get(&record.file))
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_millis() as i64);
+        let key = key_label(&entry.key);
+        let value = entry
+            .text()
+            .unwrap_or_else(|| format!("0x{}", hex::encode(&entry.value)));
+        let mut extra = vec![("key", key.clone())];
+        if let Some(record) = record {
+            extra.push(("sequence", record.sequence.to_string()));
+        }
+        events.push(Event {
+            timestamp,
+            description: if timestamp.is_some() {
+                FILE_MODIFIED
+            } else {
+                NOT_A_TIME
+            },
+            source: "Element Local Storage",
+            user: user.clone(),
+            file: match record {
+                Some(record) => db_path.join(&record.file).display().to_string(),
+                None => db_path.display().to_string(),
+            },
+            message: format!("{}: {}", key, excerpt(&value, EXCERPT)),
+            extra,
+        });
+    }
+
+    for session in &metadata.login_history {
+        let device = session.device_id.as_deref().unwrap_or("-");
+        let mut message = format!(
+            "Login of {} as device {}",
+            session.user_id.as_deref().unwrap_or("-"),
+            device
+        );
+        if session.logged_out {
+            message.push_str(", later logged out");
+        } else if session.soft_logout {
+            message.push_str(", later soft-logged out");
+        }
+        for &ts in &session.login_timestamps {
+            events.push(Event {
+                timestamp: Some(ts as i64),
+                description: LAST_LOGIN,
+                source: "Element Local Storage",
+                user: session.user_id.clone().or_else(|| user.clone()),
+                file: db_path.display().to_string(),
+                message: message.clone(),
+                extra: vec![
+                    ("device_id", device.to_string()),
+                    ("sequence", session.first_sequence.to_string()),
+                ],
+            });
+        }
+    }
+
+    if let Some((dir, origin)) = indexeddb {
+        for timeline in sync::cached_timelines(origin) {
+            let room = timeline.name.as_deref().unwrap_or(&timeline.room_id);
+            for event in &timeline.events {
+                let Some(ts) = event.origin_server_ts else {
+                    continue;
+                };
+                let sender = event.sender.as_deref().unwrap_or("-");
+                let mut message = format!("{} from {} in {}", event.event_type, sender, room);
+                if event.encrypted {
+                    message.push_str(" (encrypted)");
+                } else if let Some(body) = event
+                    .content
+                    .as_ref()
+                    .and_then(|content| content["body"].as_str())
+                {
+                    message = format!("{}: {}", message, excerpt(body, EXCERPT));
+                }
+                let mut extra = vec![("room_id", timeline.room_id.clone())];
+                if let Some(id) = &event.event_id {
+                    extra.push(("event_id", id.clone()));
+                }
+                events.push(Event {
+                    timestamp: Some(ts as i64),
+                    description: ORIGIN_SERVER,
+                    source: "Element sync cache",
+                    user: user.clone(),
+                    file: dir.display().to_string(),
+                    message,
+                    extra,
+                });
+            }
+        }
+    }
+
+    // Unknown times last
+    events.sort_by(|a, b| {
+        let key = |e: &Event| (e.timestamp.is_none(), e.timestamp);
+        (key(a), &a.file, &a.message).cmp(&(key(b), &b.file, &b.message))
+    });
+    events
+}
+
+/// The MACB letters of Plaso for what a time means: a login is an access and
+/// a server event a creation
+fn macb(description: &str) -> &'static str {
+    match description {
+        FILE_MODIFIED => "M...",
+        LAST_LOGIN => ".A..",
+        ORIGIN_SERVER => "...B",
+        _ => "....",
+    }
+}
+
+/// The events as log2timeline CSV (`l2tcsv`)
+pub fn l2tcsv(events: &[Event]) -> String {
+    let mut csv = Csv::new(&[
+        "date",
+        "time",
+        "timezone",
+        "MACB",
+        "source",
+        "sourcetype",
+        "type",
+        "user",
+        "host",
+        "short",
+        "desc",
+        "version",
+        "filename",
+        "inode",
+        "notes",
+        "format",
+        "extra",
+    ]);
+    for event in events {
+        // Plaso's l2tcsv for a time it doesn't know
+        let (date, time) = match event.timestamp.and_then(DateTime::from_timestamp_millis) {
+            Some(time) => (
+                time.format("%m/%d/%Y").to_string(),
+                time.format("%H:%M:%S").to_string(),
+            ),
+            None => ("00/00/0000".to_string(), "--:--:--".to_string()),
+        };
+        let extra: Vec<String> = event
+            .extra
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect();
+        csv.row([
+            date,
+            time,
+            "UTC".to_string(),
+            macb(event.description).to_string(),
+            "WEBHIST".to_string(),
+            event.source.to_string(),
+            event.description.to_string(),
+            event.user.clone().unwrap_or_else(|| "-".to_string()),
+            "-".to_string(),
+            excerpt(&event.message, 80),
+            event.message.clone(),
+            "2".to_string(),
+            event.file.clone(),
+            "-".to_string(),
+            "-".to_string(),
+            "element-desktop-leveldb".to_string(),
+            extra.join("; "),
+        ]);
+    }
+    csv.text
+}
+
+/// The events in the Sleuth Kit body format, a line of
+/// `MD5|name|inode|mode|UID|GID|size|atime|mtime|ctime|crtime` each
+pub fn bodyfile(events: &[Event]) -> String {
+    let mut out = String::new();
+    for event in events {
+        let seconds = event.timestamp.map_or(0, |ms| ms.div_euclid(1000));
+        let macb = macb(event.description).as_bytes();
+        let time = |i: usize| if macb[i] == b'.' { 0 } else { seconds };
+        // `|` separates fields and lines end records, so neither can be in a name
+        let name = format!("{}: {} ({})", event.source, event.message, event.file)
+            .replace('|', "/")
+            .replace(['\r', '\n'], " ");
+        out.push_str(&format!(
+            "0|{}|0|0|0|0|0|{}|{}|{}|{}\n",
+            name,
+            time(1),
+            time(0),
+            time(2),
+            time(3)
+        ));
+    }
+    out
+}
+
+/// The events as Timesketch JSON lines
+pub fn timesketch(events: &[Event]) -> String {
+    let mut out = String::new();
+    for event in events {
+        let time = event
+            .timestamp
+            .and_then(DateTime::from_timestamp_millis)
+            .unwrap_or_default();
+        let mut line = json!({
+            "message": event.message,
+            "datetime": time.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "timestamp": time.timestamp_micros(),
+            "timestamp_desc": event.description,
+            "source_short": "WEBHIST",
+            "source_long": event.source,
+            "username": event.user,
+            "filename": event.file,
+        });
+        if let Value::Object(fields) = &mut line {
+            for (name, value) in &event.extra {
+                fields.insert(name.to_string(), Value::String(value.clone()));
+            }
+        }
+        out.push_str(&line.to_string());
+        out.push('\n');
+    }
+    out
+}
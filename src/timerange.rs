@@ -0,0 +1,421 @@
+//! `--since` and `--until`: a report scoped to the incident window
+//!
+//! A bound is a time or a LevelDB sequence number (`#N`, as the tables show
+//! records without a time). Login sessions and devices are kept when the span
+//! they were seen in overlaps the window; entries have sequence numbers only,
+//! so time bounds leave them alone. Timeline events, whether an entry's file
+//! time, a login or a cached event's `origin_server_ts`, are kept when their
+//! time is in the window; they have no sequence number of this database, so
+//! sequence bounds leave them alone. Whatever can't be placed against a bound,
+//! such as a device with no recorded login time, stays in the report: the
+//! window only drops what is known to fall outside it. Rooms and settings are
+//! current state without a time and are never filtered.
+
+use crate::i18n;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use element_desktop_leveldb::devices::Seen;
+use element_desktop_leveldb::history::Session;
+use element_desktop_leveldb::timeline::Event;
+use element_desktop_leveldb::ElementMetadata;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+/// Smallest number read as milliseconds since the epoch, in 1973; a smaller
+/// one is more likely a date such as 20240501 or seconds since the epoch
+const MIN_MILLIS: i64 = 100_000_000_000;
+
+/// A bound as given on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instant {
+    /// `#N`
+    Sequence(u64),
+    /// Milliseconds since the epoch, or a time with its offset
+    Millis(i64),
+    /// A time of day in the `--timezone` of the run
+    Local(NaiveDateTime),
+    /// A whole day in the `--timezone` of the run
+    Date(NaiveDate),
+}
+
+impl FromStr for Instant {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        if let Some(sequence) = text.strip_prefix('#') {
+            return sequence
+                .parse()
+                .map(Instant::Sequence)
+                .map_err(|_| anyhow!("bad sequence number {}", text));
+        }
+        if let Ok(millis) = text.parse::<i64>() {
+            if millis < MIN_MILLIS {
+                bail!(
+                    "{} is too small for milliseconds since the epoch; write a date as \
+                     2024-05-01",
+                    text
+                );
+            }
+            return Ok(Instant::Millis(millis));
+        }
+        if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+            return Ok(Instant::Millis(time.timestamp_millis()));
+        }
+        for pattern in [
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%dT%H:%M",
+            "%Y-%m-%d %H:%M",
+        ] {
+            if let Ok(time) = NaiveDateTime::parse_from_str(text, pattern) {
+                return Ok(Instant::Local(time));
+            }
+        }
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .map(Instant::Date)
+            .map_err(|_| {
+                anyhow!(
+                    "expected a date such as 2024-05-01, a time such as 2024-05-01T09:30 or \
+                     2024-05-01T09:30:00Z, milliseconds since the epoch, or #N for a sequence number"
+                )
+            })
+    }
+}
+
+/// A resolved bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Point {
+    Sequence(u64),
+    Millis(i64),
+}
+
+impl Point {
+    /// Where `seen` lies relative to the point, if that is known
+    fn compare(self, seen: &Seen) -> Option<Ordering> {
+        match self {
+            Point::Sequence(sequence) => Some(seen.sequence.cmp(&sequence)),
+            Point::Millis(millis) => seen.timestamp.map(|ts| (ts as i64).cmp(&millis)),
+        }
+    }
+}
+
+/// The window between `--since` and `--until`, both inclusive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeRange {
+    since: Option<Point>,
+    until: Option<Point>,
+}
+
+impl TimeRange {
+    /// Resolves the bounds in the selected time zone; a date as `--until`
+    /// includes the whole day
+    pub fn new(since: Option<Instant>, until: Option<Instant>) -> Result<Self> {
+        Self::in_zone(since, until, i18n::timestamp)
+    }
+
+    /// Like `new`, with `timestamp` giving the milliseconds of a local time
+    fn in_zone(
+        since: Option<Instant>,
+        until: Option<Instant>,
+        timestamp: impl Fn(NaiveDateTime) -> Option<i64>,
+    ) -> Result<Self> {
+        let resolve = |instant: Instant, end: bool| {
+            let millis = match instant {
+                Instant::Sequence(sequence) => return Ok(Point::Sequence(sequence)),
+                Instant::Millis(millis) => Some(millis),
+                Instant::Local(time) => timestamp(time),
+                Instant::Date(date) if end => date
+                    .succ_opt()
+                    .and_then(|next| timestamp(next.and_time(NaiveTime::MIN)))
+                    .map(|next| next - 1),
+                Instant::Date(date) => timestamp(date.and_time(NaiveTime::MIN)),
+            };
+            millis
+                .map(Point::Millis)
+                .ok_or_else(|| anyhow!("--since or --until names a time the selected zone skips"))
+        };
+        Ok(TimeRange {
+            since: since.map(|s| resolve(s, false)).transpose()?,
+            until: until.map(|u| resolve(u, true)).transpose()?,
+        })
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.since.is_some() || self.until.is_some()
+    }
+
+    /// Whether either bound is a sequence number
+    pub fn has_sequence(&self) -> bool {
+        [self.since, self.until]
+            .iter()
+            .any(|point| matches!(point, Some(Point::Sequence(_))))
+    }
+
+    /// Whether something seen from `first` to `last` may overlap the window
+    pub fn overlaps(&self, first: &Seen, last: &Seen) -> bool {
+        let ended_before = self
+            .since
+            .is_some_and(|since| since.compare(last) == Some(Ordering::Less));
+        let began_after = self
+            .until
+            .is_some_and(|until| until.compare(first) == Some(Ordering::Greater));
+        !(ended_before || began_after)
+    }
+
+    /// Whether a record written with `sequence` may be in the window
+    pub fn contains_sequence(&self, sequence: Option<u64>) -> bool {
+        match sequence {
+            Some(sequence) => {
+                let seen = Seen {
+                    sequence,
+                    timestamp: None,
+                };
+                self.overlaps(&seen, &seen)
+            }
+            None => true,
+        }
+    }
+
+    /// Whether something at `millis` since the epoch may be in the window;
+    /// sequence bounds can't place it
+    pub fn contains_time(&self, millis: Option<i64>) -> bool {
+        let Some(millis) = millis else {
+            return true;
+        };
+        let after_since = match self.since {
+            Some(Point::Millis(since)) => millis >= since,
+            _ => true,
+        };
+        let before_until = match self.until {
+            Some(Point::Millis(until)) => millis <= until,
+            _ => true,
+        };
+        after_since && before_until
+    }
+
+    /// Drops the timeline events whose time is outside the window
+    pub fn retain_events(&self, events: &mut Vec<Event>) {
+        events.retain(|event| self.contains_time(event.timestamp));
+    }
+
+    /// Drops the login sessions and devices of `metadata` seen only outside
+    /// the window
+    pub fn retain(&self, metadata: &mut ElementMetadata) {
+        metadata.login_history.retain(|session| {
+            let (first, last) = span(session);
+            self.overlaps(&first, &last)
+        });
+        metadata.devices.retain(|device| {
+            match (
+                device.first_seen.or(device.last_seen),
+                device.last_seen.or(device.first_seen),
+            ) {
+                (Some(first), Some(last)) => self.overlaps(&first, &last),
+                _ => true,
+            }
+        });
+    }
+}
+
+/// First and last sighting of a login session
+fn span(session: &Session) -> (Seen, Seen) {
+    let first = Seen {
+        sequence: session.first_sequence,
+        timestamp: session.login_timestamps.iter().min().copied(),
+    };
+    let last = Seen {
+        sequence: session.last_sequence,
+        timestamp: session.login_timestamps.iter().max().copied(),
+    };
+    (first, last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use element_desktop_leveldb::devices::DeviceInfo;
+
+    fn utc(time: NaiveDateTime) -> Option<i64> {
+        Some(time.and_utc().timestamp_millis())
+    }
+
+    fn millis(text: &str) -> i64 {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    fn range(since: Option<&str>, until: Option<&str>) -> TimeRange {
+        let parse = |text: &str| text.parse().unwrap();
+        TimeRange::in_zone(since.map(parse), until.map(parse), utc).unwrap()
+    }
+
+    fn seen(sequence: u64, timestamp: Option<&str>) -> Seen {
+        Seen {
+            sequence,
+            timestamp: timestamp.map(|t| millis(t) as u64),
+        }
+    }
+
+    #[test]
+    fn parses_instants() {
+        let parse = |text: &str| text.parse::<Instant>();
+        assert_eq!(parse("#42").unwrap(), Instant::Sequence(42));
+        assert_eq!(
+            parse("1714550400000").unwrap(),
+            Instant::Millis(1_714_550_400_000)
+        );
+        assert_eq!(
+            parse("2024-05-01T09:30:00+02:00").unwrap(),
+            Instant::Millis(millis("2024-05-01T07:30:00Z"))
+        );
+        assert_eq!(
+            parse("2024-05-01 09:30").unwrap(),
+            Instant::Local(
+                NaiveDate::from_ymd_opt(2024, 5, 1)
+                    .unwrap()
+                    .and_hms_opt(9, 30, 0)
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            parse("2024-05-01").unwrap(),
+            Instant::Date(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap())
+        );
+        for bad in ["#", "#x", "#-1", "2024-13-01", "yesterday", ""] {
+            assert!(parse(bad).is_err(), "{:?} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn rejects_bare_numbers_too_small_for_millis() {
+        for bad in ["20240501", "1714550400", "0", "-5"] {
+            let error = bad.parse::<Instant>().unwrap_err().to_string();
+            assert!(error.contains("too small"), "{}: {}", bad, error);
+        }
+    }
+
+    #[test]
+    fn until_date_covers_the_whole_day() {
+        let range = range(Some("2024-05-01"), Some("2024-05-01"));
+        assert_eq!(
+            range.since,
+            Some(Point::Millis(millis("2024-05-01T00:00:00Z")))
+        );
+        assert_eq!(
+            range.until,
+            Some(Point::Millis(millis("2024-05-02T00:00:00Z") - 1))
+        );
+        let at = |time| seen(1, Some(time));
+        let inside = at("2024-05-01T23:59:59.999Z");
+        assert!(range.overlaps(&inside, &inside));
+        let after = at("2024-05-02T00:00:00Z");
+        assert!(!range.overlaps(&after, &after));
+        let before = at("2024-04-30T23:59:59.999Z");
+        assert!(!range.overlaps(&before, &before));
+    }
+
+    #[test]
+    fn sequence_bounds() {
+        let range = range(Some("#10"), Some("#20"));
+        assert!(range.has_sequence());
+        assert!(!range.contains_sequence(Some(9)));
+        assert!(range.contains_sequence(Some(10)));
+        assert!(range.contains_sequence(Some(20)));
+        assert!(!range.contains_sequence(Some(21)));
+        assert!(range.contains_sequence(None));
+        // Seen from before the window into it
+        assert!(range.overlaps(&seen(5, None), &seen(15, None)));
+        assert!(!range.overlaps(&seen(1, None), &seen(9, None)));
+
+        assert!(!TimeRange::default().has_sequence());
+        assert!(!self::range(Some("2024-05-01"), None).has_sequence());
+    }
+
+    #[test]
+    fn skipped_local_time_is_an_error() {
+        // A zone whose clocks skip from 02:00 to 03:00
+        let skipping = |time: NaiveDateTime| {
+            let skipped = time.format("%H").to_string() == "02";
+            (!skipped).then(|| time.and_utc().timestamp_millis())
+        };
+        let instant = |text: &str| Some(text.parse::<Instant>().unwrap());
+        assert!(TimeRange::in_zone(instant("2024-03-31T02:30"), None, skipping).is_err());
+        assert!(TimeRange::in_zone(None, instant("2024-03-31T02:30"), skipping).is_err());
+        assert!(TimeRange::in_zone(instant("2024-03-31T03:30"), None, skipping).is_ok());
+    }
+
+    #[test]
+    fn events_outside_the_window_are_dropped() {
+        let event = |timestamp: Option<&str>, message: &str| Event {
+            timestamp: timestamp.map(millis),
+            description: "",
+            source: "",
+            user: None,
+            file: String::new(),
+            message: message.to_string(),
+            extra: Vec::new(),
+        };
+        let mut events = vec![
+            event(Some("2024-04-30T23:59:59.999Z"), "before"),
+            event(Some("2024-05-01T00:00:00Z"), "first"),
+            event(Some("2024-05-01T23:59:59.999Z"), "last"),
+            event(Some("2024-05-02T00:00:00Z"), "after"),
+            event(None, "untimed"),
+        ];
+        range(Some("2024-05-01"), Some("2024-05-01")).retain_events(&mut events);
+        let kept: Vec<&str> = events.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(kept, ["first", "last", "untimed"]);
+
+        // Only one bound
+        let time = Some(millis("2024-05-01T12:00:00Z"));
+        assert!(range(Some("2024-05-01"), None).contains_time(time));
+        assert!(!range(Some("2024-05-02"), None).contains_time(time));
+        assert!(!range(None, Some("2024-04-30")).contains_time(time));
+
+        // Sequence bounds can't place a time
+        assert!(range(Some("#10"), Some("#20")).contains_time(time));
+    }
+
+    #[test]
+    fn retain_keeps_what_it_cannot_place() {
+        let device = |id: &str, first: Option<Seen>, last: Option<Seen>| DeviceInfo {
+            device_id: id.to_string(),
+            first_seen: first,
+            last_seen: last,
+            ..Default::default()
+        };
+        let session = |timestamps: &[&str]| Session {
+            login_timestamps: timestamps.iter().map(|t| millis(t) as u64).collect(),
+            ..Default::default()
+        };
+        let mut metadata = ElementMetadata {
+            devices: vec![
+                device("NEVER", None, None),
+                device("UNTIMED", Some(seen(3, None)), Some(seen(4, None))),
+                device("BEFORE", Some(seen(1, Some("2024-04-01T00:00:00Z"))), None),
+                device("DURING", None, Some(seen(2, Some("2024-05-01T12:00:00Z")))),
+            ],
+            login_history: vec![
+                session(&[]),
+                session(&["2024-04-01T00:00:00Z"]),
+                session(&["2024-04-01T00:00:00Z", "2024-05-01T12:00:00Z"]),
+            ],
+            ..Default::default()
+        };
+        range(Some("2024-05-01"), Some("2024-05-01")).retain(&mut metadata);
+
+        let ids: Vec<&str> = metadata
+            .devices
+            .iter()
+            .map(|d| d.device_id.as_str())
+            .collect();
+        assert_eq!(ids, ["NEVER", "UNTIMED", "DURING"]);
+        let logins: Vec<usize> = metadata
+            .login_history
+            .iter()
+            .map(|s| s.login_timestamps.len())
+            .collect();
+        assert_eq!(logins, [0, 2]);
+    }
+}
@@ -0,0 +1,115 @@
+//! Human-readable unified diffs of a change set
+//!
+//! JSON-valued entries (most of Element's settings) are pretty-printed with
+//! sorted keys before diffing, so a change deep inside `mx_local_settings`
+//! shows up as one changed line with its surrounding context. `summary`
+//! lists just the changed keys with the hashes of their values.
+
+use crate::i18n::tr;
+use element_desktop_leveldb::changeset::{decode_bytes, Change, ChangeKind, ChangeSet};
+use element_desktop_leveldb::localstorage;
+use similar::TextDiff;
+
+/// Renders every change as a unified diff with `context` lines around each hunk
+pub fn render(set: &ChangeSet, context: usize) -> String {
+    let mut out = String::new();
+    for change in &set.changes {
+        let (label, local_storage) = label(change);
+
+        let old = change
+            .old_value
+            .as_deref()
+            .map(|v| display_value(v, local_storage))
+            .unwrap_or_default();
+        let new = change
+            .new_value
+            .as_deref()
+            .map(|v| display_value(v, local_storage))
+            .unwrap_or_default();
+        let old_header = match change.old_value {
+            Some(_) => format!("a/{}", label),
+            None => "/dev/null".to_string(),
+        };
+        let new_header = match change.new_value {
+            Some(_) => format!("b/{}", label),
+            None => "/dev/null".to_string(),
+        };
+
+        let diff = TextDiff::from_lines(&old, &new);
+        out.push_str(
+            &diff
+                .unified_diff()
+                .context_radius(context)
+                .header(&old_header, &new_header)
+                .to_string(),
+        );
+    }
+    out
+}
+
+/// One line per changed key, `+` added, `-` removed and `~` changed, with
+/// the first 12 hex digits of the values' SHA-256, then the counts
+pub fn summary(set: &ChangeSet) -> String {
+    let short = |hash: &Option<String>| {
+        hash.as_deref()
+            .map_or("-", |h| h.get(..12).unwrap_or(h))
+            .to_string()
+    };
+    let mut out = String::new();
+    let mut counts = [0; 3];
+    for change in &set.changes {
+        let (label, _) = label(change);
+        let (marker, hashes, count) = match change.kind {
+            ChangeKind::Added => ('+', short(&change.new_sha256), &mut counts[0]),
+            ChangeKind::Removed => ('-', short(&change.old_sha256), &mut counts[1]),
+            ChangeKind::Changed => (
+                '~',
+                format!(
+                    "{} -> {}",
+                    short(&change.old_sha256),
+                    short(&change.new_sha256)
+                ),
+                &mut counts[2],
+            ),
+        };
+        *count += 1;
+        out.push_str(&format!("{} {}  {}\n", marker, label, hashes));
+    }
+    let [added, removed, changed] = counts;
+    out.push_str(&tr!(
+        "diff-summary",
+        added = added,
+        removed = removed,
+        changed = changed
+    ));
+    out.push('\n');
+    out
+}
+
+/// `origin/name` of a Local Storage key, else the key escaped, and whether
+/// it is a Local Storage key
+fn label(change: &Change) -> (String, bool) {
+    let key = decode_bytes(&change.key).unwrap_or_else(|_| change.key.clone().into_bytes());
+    match localstorage::decode_key(&key) {
+        Some((origin, name)) => (format!("{}/{}", origin, name), true),
+        None => (change.key.escape_debug().to_string(), false),
+    }
+}
+
+/// Text shown for a value: Local Storage strings decoded, JSON pretty-printed
+fn display_value(value: &str, local_storage: bool) -> String {
+    let text = match decode_bytes(value) {
+        Ok(bytes) if local_storage => {
+            localstorage::decode_string(&bytes).unwrap_or_else(|| value.to_string())
+        }
+        _ => value.to_string(),
+    };
+    let mut text = match serde_json::from_str::<serde_json::Value>(&text) {
+        Ok(json) if json.is_object() || json.is_array() => {
+            serde_json::to_string_pretty(&json).unwrap_or(text)
+        }
+        _ => text,
+    };
+    text.push('\n');
+    text
+}
@@ -0,0 +1,110 @@
+use crate::crypto::CachedDevice;
+use crate::homeserver::Homeserver;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+/// Outcome of comparing one device's keys with the homeserver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStatus {
+    /// Every locally stored key matches the published one
+    Match,
+    /// At least one key differs: tampering, or another device reusing the ID
+    Mismatch,
+    /// The homeserver no longer publishes the device (logged out or deleted)
+    MissingOnServer,
+    /// Published by the homeserver but not in the local device list
+    OnlyOnServer,
+}
+
+/// Comparison result for one device
+#[derive(Debug, Serialize)]
+pub struct KeyCheck {
+    pub user_id: String,
+    pub device_id: String,
+    pub status: KeyStatus,
+    /// Key IDs whose values differ
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mismatched_keys: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_display_name: Option<String>,
+}
+
+/// Queries `/keys/query` for every user in `devices` and cross-checks the keys
+///
+/// Returns the checks, problems first, and the users the homeserver could not
+/// answer for (`failures` in the response).
+pub fn verify_keys(
+    homeserver: &Homeserver,
+    devices: &[CachedDevice],
+) -> Result<(Vec<KeyCheck>, Vec<String>)> {
+    let mut users: BTreeMap<&str, Vec<&CachedDevice>> = BTreeMap::new();
+    for device in devices {
+        users.entry(&device.user_id).or_default().push(device);
+    }
+    let query: Map<String, Value> = users.keys().map(|u| (u.to_string(), json!([]))).collect();
+    let response = homeserver.post_json(
+        "/_matrix/client/v3/keys/query",
+        &json!({ "device_keys": query, "timeout": 10000 }),
+    )?;
+    let failures = response["failures"]
+        .as_object()
+        .map(|f| f.keys().cloned().collect())
+        .unwrap_or_default();
+
+    let mut checks = Vec::new();
+    for (user_id, local_devices) in &users {
+        let published = response["device_keys"][*user_id].as_object();
+        for device in local_devices {
+            let server = published.and_then(|p| p.get(&device.device_id));
+            let mut check = KeyCheck {
+                user_id: user_id.to_string(),
+                device_id: device.device_id.clone(),
+                status: KeyStatus::MissingOnServer,
+                mismatched_keys: Vec::new(),
+                local_display_name: device.display_name.clone(),
+                server_display_name: None,
+            };
+            if let Some(server) = server {
+                check.server_display_name = server["unsigned"]["device_display_name"]
+                    .as_str()
+                    .map(str::to_string);
+                check.mismatched_keys = device
+                    .keys
+                    .iter()
+                    .filter(|(id, key)| server["keys"][id.as_str()].as_str() != Some(key.as_str()))
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                check.status = if check.mismatched_keys.is_empty() {
+                    KeyStatus::Match
+                } else {
+                    KeyStatus::Mismatch
+                };
+            }
+            checks.push(check);
+        }
+
+        for (device_id, server) in published.into_iter().flatten() {
+            if local_devices.iter().any(|d| &d.device_id == device_id) {
+                continue;
+            }
+            checks.push(KeyCheck {
+                user_id: user_id.to_string(),
+                device_id: device_id.clone(),
+                status: KeyStatus::OnlyOnServer,
+                mismatched_keys: Vec::new(),
+                local_display_name: None,
+                server_display_name: server["unsigned"]["device_display_name"]
+                    .as_str()
+                    .map(str::to_string),
+            });
+        }
+    }
+
+    checks.sort_by_key(|c| c.status == KeyStatus::Match);
+    Ok((checks, failures))
+}
@@ -0,0 +1,294 @@
+//! Near-real-time monitoring of a LevelDB directory
+//!
+//! Only the files a filesystem event names are re-read. Write-ahead logs grow
+//! in place, so for each log the number of records already seen is remembered
+//! and only newer ones are processed; tables are immutable and are read once
+//! they read cleanly. Records that compaction copies into new tables carry sequence numbers that
+//! were already seen and produce no events.
+//!
+//! With `--metadata` the whole directory is re-parsed instead, from the raw
+//! files so the running client's LOCK is never taken, after each burst of
+//! writes to a log or table; the extracted metadata is compared with the
+//! previous one and every field that differs is reported.
+
+use crate::changeset::{encode_bytes, Change, ChangeKind};
+use crate::raw::{self, log, table, RawRecord, ValueType};
+use crate::{history, ElementLevelDBParser, ElementMetadata};
+use anyhow::Result;
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Quiet time after a filesystem event before the metadata is parsed again
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// Change observed in a single file, in the change-set format
+#[derive(Debug, Serialize)]
+pub struct WatchEvent {
+    pub file: String,
+    pub sequence: u64,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+/// A metadata field whose value changed between two parses
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MetadataDelta {
+    /// JSON Pointer to the field, e.g. `/rooms/2/name`
+    pub path: String,
+    /// The earlier value; `null` when the field is new
+    pub old: Value,
+    /// The current value; `null` when the field is gone
+    pub new: Value,
+}
+
+/// What has been read so far from a database directory
+#[derive(Debug, Default)]
+pub struct IncrementalState {
+    /// Records already consumed from each file
+    consumed: HashMap<PathBuf, usize>,
+    /// Newest sequence and value per key; `None` once deleted
+    latest: HashMap<Vec<u8>, (u64, Option<Vec<u8>>)>,
+}
+
+impl IncrementalState {
+    /// Reads every existing file without emitting events
+    pub fn baseline(dir: &Path) -> Result<(Self, Vec<String>)> {
+        let mut state = IncrementalState::default();
+        let mut issues = Vec::new();
+        let mut paths: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let (_, file_issues) = state.update_file(&path)?;
+            issues.extend(file_issues);
+        }
+        Ok((state, issues))
+    }
+
+    /// Number of live keys
+    pub fn live_keys(&self) -> usize {
+        self.latest.values().filter(|(_, v)| v.is_some()).count()
+    }
+
+    /// Processes records of `path` that have not been seen yet
+    pub fn update_file(&mut self, path: &Path) -> Result<(Vec<WatchEvent>, Vec<String>)> {
+        let is_log = match path.extension().and_then(|e| e.to_str()) {
+            Some("log") => true,
+            Some("ldb") | Some("sst") => false,
+            _ => return Ok((Vec::new(), Vec::new())),
+        };
+        if !is_log && self.consumed.contains_key(path) {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            // Logs and tables are removed after compaction
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.consumed.remove(path);
+                return Ok((Vec::new(), Vec::new()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let (records, issues) = if is_log {
+            log::read_write_batches(&data)
+        } else {
+            table::read_table(&data)
+        };
+        let file = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // A log shorter than before was recycled; start over
+        let seen = match self.consumed.get(path) {
+            Some(&seen) if seen <= records.len() => seen,
+            _ => 0,
+        };
+        // A table that fails to read may still be being written; retry on the next event
+        if is_log || issues.is_empty() {
+            self.consumed.insert(path.to_path_buf(), records.len());
+        }
+
+        let events = records[seen..]
+            .iter()
+            .filter_map(|record| self.apply(record, &file))
+            .collect();
+        let issues = issues
+            .into_iter()
+            .map(|i| format!("{}: {}", file, i))
+            .collect();
+        Ok((events, issues))
+    }
+
+    fn apply(&mut self, record: &RawRecord, file: &str) -> Option<WatchEvent> {
+        let new_value = match record.value_type {
+            ValueType::Value => Some(record.value.clone()),
+            ValueType::Deletion => None,
+        };
+        let old_value = match self.latest.get(&record.key) {
+            Some((sequence, _)) if *sequence >= record.sequence => return None,
+            Some((_, value)) => value.clone(),
+            None => None,
+        };
+        self.latest
+            .insert(record.key.clone(), (record.sequence, new_value.clone()));
+
+        let kind = match (&old_value, &new_value) {
+            (None, None) => return None,
+            (None, Some(_)) => ChangeKind::Added,
+            (Some(_), None) => ChangeKind::Removed,
+            (Some(old), Some(new)) if old == new => return None,
+            (Some(_), Some(_)) => ChangeKind::Changed,
+        };
+        Some(WatchEvent {
+            file: file.to_string(),
+            sequence: record.sequence,
+            change: Change {
+                kind,
+                key: encode_bytes(&record.key),
+                old_value: old_value.as_deref().map(encode_bytes),
+                old_sha256: None,
+                new_value: new_value.as_deref().map(encode_bytes),
+                new_sha256: None,
+            },
+        })
+    }
+}
+
+/// Watches `dir` and calls `on_event` for every change until the watcher fails
+pub fn watch(
+    dir: &Path,
+    mut state: IncrementalState,
+    mut on_event: impl FnMut(WatchEvent) -> Result<()>,
+    mut on_issue: impl FnMut(String),
+) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    for result in rx {
+        let event = result?;
+        if matches!(event.kind, EventKind::Access(_)) {
+            continue;
+        }
+        for path in event.paths {
+            let (events, issues) = state.update_file(&path)?;
+            issues.into_iter().for_each(&mut on_issue);
+            for event in events {
+                on_event(event)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The metadata of the database in `dir`, read from its raw files
+///
+/// Raw entries are left out: their changes are what plain `watch` reports.
+pub fn metadata(dir: &Path) -> Result<ElementMetadata> {
+    let scan = raw::scan_directory(dir)?;
+    let parser = ElementLevelDBParser::builder()
+        .raw_entries(false)
+        .open_in_memory(scan.latest().into_iter().collect())?;
+    let mut metadata = parser.parse_metadata()?;
+    metadata.login_history = history::login_history(&scan);
+    Ok(metadata)
+}
+
+/// The fields that differ between `old` and `new`, leaves only
+pub fn metadata_delta(old: &ElementMetadata, new: &ElementMetadata) -> Result<Vec<MetadataDelta>> {
+    let mut deltas = Vec::new();
+    diff_values(
+        String::new(),
+        &serde_json::to_value(old)?,
+        &serde_json::to_value(new)?,
+        &mut deltas,
+    );
+    Ok(deltas)
+}
+
+fn diff_values(path: String, old: &Value, new: &Value, deltas: &mut Vec<MetadataDelta>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut keys: Vec<_> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                let null = Value::Null;
+                diff_values(
+                    path,
+                    old.get(key).unwrap_or(&null),
+                    new.get(key).unwrap_or(&null),
+                    deltas,
+                );
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for i in 0..old.len().max(new.len()) {
+                let null = Value::Null;
+                diff_values(
+                    format!("{}/{}", path, i),
+                    old.get(i).unwrap_or(&null),
+                    new.get(i).unwrap_or(&null),
+                    deltas,
+                );
+            }
+        }
+        _ if old != new => deltas.push(MetadataDelta {
+            path,
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Watches `dir` and, after each burst of writes to a log or table, parses
+/// it again and calls `on_delta` with what changed since the previous parse
+pub fn watch_metadata(
+    dir: &Path,
+    mut state: ElementMetadata,
+    mut on_delta: impl FnMut(Vec<MetadataDelta>) -> Result<()>,
+    mut on_issue: impl FnMut(String),
+) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    let relevant = |event: notify::Event| {
+        !matches!(event.kind, EventKind::Access(_))
+            && event.paths.iter().any(|path| {
+                matches!(
+                    path.extension().and_then(|e| e.to_str()),
+                    Some("log" | "ldb" | "sst")
+                )
+            })
+    };
+    while let Ok(result) = rx.recv() {
+        let mut changed = relevant(result?);
+        while let Ok(result) = rx.recv_timeout(SETTLE) {
+            changed |= relevant(result?);
+        }
+        if !changed {
+            continue;
+        }
+        match metadata(dir) {
+            Ok(metadata) => {
+                let deltas = metadata_delta(&state, &metadata)?;
+                state = metadata;
+                if !deltas.is_empty() {
+                    on_delta(deltas)?;
+                }
+            }
+            Err(e) => on_issue(format!("{}: {}", dir.display(), e)),
+        }
+    }
+    Ok(())
+}
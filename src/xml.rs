@@ -0,0 +1,161 @@
+//! `export --format xml`: the export for case tools that only read XML
+//!
+//! The structure is versioned by the `version` attribute of the root element
+//! and changes only with it. Version 1:
+//!
+//! ```xml
+//! <?xml version="1.0" encoding="UTF-8"?>
+//! <element-export version="1" generator="element-desktop-leveldb 0.1.0">
+//!   <metadata>
+//!     <display_name>Alice</display_name>
+//!     <layout><origin>vector://vector</origin><variant>desktop</variant></layout>
+//!     <rooms>
+//!       <item><encrypted>true</encrypted>...<room_id>!abc:example.org</room_id></item>
+//!     </rooms>
+//!     <raw_entries>
+//!       <entry key="mx_user_id">@alice:example.org</entry>
+//!     </raw_entries>
+//!     ...
+//!     <user_id>@alice:example.org</user_id>
+//!   </metadata>
+//!   <entries>
+//!     <entry key="vector://vector/mx_user_id" category="user" size="18">@alice:example.org</entry>
+//!   </entries>
+//! </element-export>
+//! ```
+//!
+//! `<metadata>` mirrors `ElementMetadata` as `export --format json` writes
+//! it: a field is an element of the same name, with the fields of each level
+//! in alphabetical order; a list holds an `<item>` per element, and a field
+//! without a value is left out. The maps `raw_entries`, `provenance` and
+//! `extensions`, and any object whose keys aren't XML names (such as room IDs),
+//! hold an `<entry key="...">` per key. Characters XML can't carry become
+//! U+FFFD, except in entry values: those are written as base64 of their UTF-8
+//! text, marked with `encoding="base64"`. `--fields` leaves out what it leaves
+//! out of the JSON, and `<entries>` when it drops the entries.
+
+use crate::export::{self, EntryRecord};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::Value;
+use std::fmt::Write;
+
+/// Version of the element structure
+pub const VERSION: u32 = 1;
+
+/// Fields of `ElementMetadata` keyed by arbitrary strings
+const MAPS: [&str; 3] = ["raw_entries", "provenance", "extensions"];
+
+/// Whether XML 1.0 can carry `c`
+fn allowed(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}')
+        || c >= '\u{10000}'
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            c if allowed(c) => out.push(c),
+            _ => out.push('\u{FFFD}'),
+        }
+    }
+    out
+}
+
+/// Whether `name` can be an element name as it is
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && !name.to_ascii_lowercase().starts_with("xml")
+}
+
+/// `value` as the element `open`, closed with `close`
+fn element(out: &mut String, open: &str, close: &str, value: &Value, map: bool) {
+    match value {
+        Value::Null => {}
+        Value::Bool(b) => {
+            let _ = write!(out, "<{}>{}</{}>", open, b, close);
+        }
+        Value::Number(n) => {
+            let _ = write!(out, "<{}>{}</{}>", open, n, close);
+        }
+        Value::String(s) => {
+            let _ = write!(out, "<{}>{}</{}>", open, escape(s), close);
+        }
+        Value::Array(items) => {
+            let _ = write!(out, "<{}>", open);
+            for item in items {
+                element(out, "item", "item", item, map);
+            }
+            let _ = write!(out, "</{}>", close);
+        }
+        Value::Object(fields) => {
+            let _ = write!(out, "<{}>", open);
+            for (name, value) in fields {
+                if map || !is_name(name) {
+                    let open = format!("entry key=\"{}\"", escape(name));
+                    element(out, &open, "entry", value, false);
+                } else {
+                    element(out, name, name, value, false);
+                }
+            }
+            let _ = write!(out, "</{}>", close);
+        }
+    }
+}
+
+/// The XML document of `export`, from its metadata as JSON; `entries` is
+/// `None` when `--fields` drops them
+pub fn document(metadata: &Value, entries: Option<&[EntryRecord]>) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<element-export version=\"{}\" generator=\"element-desktop-leveldb {}\">",
+        VERSION,
+        env!("CARGO_PKG_VERSION")
+    );
+
+    out.push_str("<metadata>\n");
+    if let Value::Object(fields) = metadata {
+        for (name, value) in fields {
+            element(&mut out, name, name, value, MAPS.contains(&name.as_str()));
+            if !value.is_null() {
+                out.push('\n');
+            }
+        }
+    }
+    out.push_str("</metadata>\n");
+    let Some(entries) = entries else {
+        out.push_str("</element-export>\n");
+        return out;
+    };
+    out.push_str("<entries>\n");
+    for entry in entries {
+        let _ = write!(
+            out,
+            "<entry key=\"{}\" category=\"{}\" size=\"{}\"",
+            escape(&entry.key),
+            export::category(&entry.classification),
+            entry.size
+        );
+        if entry.value.chars().all(allowed) {
+            let _ = writeln!(out, ">{}</entry>", escape(&entry.value));
+        } else {
+            let _ = writeln!(
+                out,
+                " encoding=\"base64\">{}</entry>",
+                STANDARD.encode(&entry.value)
+            );
+        }
+    }
+    out.push_str("</entries>\n</element-export>\n");
+    out
+}
@@ -0,0 +1,107 @@
+//! Golden-file regression tests
+//!
+//! Every directory in `tests/fixtures` is a LevelDB database whose `parse`
+//! output must match `tests/golden/<name>.json`. Run with `BLESS=1` to accept
+//! the current output as the new golden files after an intended change.
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn tests_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+/// Copies a fixture so opening it never touches the checked-in files
+fn copy_dir(src: &Path, dst: &Path) {
+    std::fs::create_dir_all(dst).unwrap();
+    for entry in std::fs::read_dir(src).unwrap() {
+        let path = entry.unwrap().path();
+        std::fs::copy(&path, dst.join(path.file_name().unwrap())).unwrap();
+    }
+}
+
+fn parse_fixture(fixture: &Path) -> Value {
+    let scratch = tempfile::tempdir().unwrap();
+    let db = scratch.path().join("leveldb");
+    copy_dir(fixture, &db);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_element-desktop-leveldb"))
+        .arg("parse")
+        .arg(&db)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "parse failed for {}: {}",
+        fixture.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+/// Lists the JSON paths at which two values differ
+fn differences(expected: &Value, actual: &Value, path: &str, out: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(a), Value::Object(b)) => {
+            for key in a.keys().chain(b.keys().filter(|k| !a.contains_key(*k))) {
+                let child = format!("{}.{}", path, key);
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => differences(x, y, &child, out),
+                    (Some(_), None) => out.push(format!("{}: missing", child)),
+                    (None, Some(_)) => out.push(format!("{}: unexpected", child)),
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (x, y)) in a.iter().zip(b).enumerate() {
+                differences(x, y, &format!("{}[{}]", path, i), out);
+            }
+        }
+        _ if expected != actual => {
+            out.push(format!("{}: expected {}, got {}", path, expected, actual))
+        }
+        _ => {}
+    }
+}
+
+#[test]
+fn parse_output_matches_golden_files() {
+    let bless = std::env::var_os("BLESS").is_some();
+    let mut fixtures: Vec<_> = std::fs::read_dir(tests_dir().join("fixtures"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures found");
+
+    let mut failures = Vec::new();
+    for fixture in fixtures {
+        let name = fixture.file_name().unwrap().to_string_lossy().to_string();
+        let golden_path = tests_dir().join("golden").join(format!("{}.json", name));
+        let actual = parse_fixture(&fixture);
+
+        if bless {
+            let text = serde_json::to_string_pretty(&actual).unwrap();
+            std::fs::write(&golden_path, text + "\n").unwrap();
+            continue;
+        }
+
+        let Ok(text) = std::fs::read_to_string(&golden_path) else {
+            failures.push(format!("{}: no golden file (run with BLESS=1)", name));
+            continue;
+        };
+        let expected: Value = serde_json::from_str(&text).unwrap();
+        let mut diffs = Vec::new();
+        differences(&expected, &actual, "$", &mut diffs);
+        failures.extend(diffs.into_iter().map(|d| format!("{}: {}", name, d)));
+    }
+
+    assert!(
+        failures.is_empty(),
+        "parse output drifted from golden files (BLESS=1 to accept):\n  {}",
+        failures.join("\n  ")
+    );
+}